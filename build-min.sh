@@ -0,0 +1,3 @@
+# build-min.sh
+
+wasm-pack build crates/wasm --target web --out-dir ../../pkg --out-name nextjlc --no-default-features --profile wasm-min
@@ -1,3 +1,3 @@
 # build.sh
 
-wasm-pack build --target web --out-dir pkg --features wasm
\ No newline at end of file
+wasm-pack build crates/wasm --target web --out-dir ../../pkg --out-name nextjlc
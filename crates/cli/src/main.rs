@@ -0,0 +1,168 @@
+/* src/main.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::compare::{self, Primitive, PrimitiveKind};
+use nextjlc::file_type::identify_software;
+use nextjlc::gerber;
+use nextjlc::prelude::*;
+use nextjlc::provider::{DirectoryProvider, ZipProvider};
+use nextjlc::rename;
+use nextjlc::sink::{DirectorySink, ZipSink};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: nextjlc <input directory or .zip> [output directory or .zip]
+       nextjlc diff <old.gbr> <new.gbr>
+       nextjlc explain <file> [--eda <id>]";
+
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+fn run(input: &Path, output: Option<&Path>) -> Result<bool, String> {
+    let pipeline = Pipeline::new(ConvertOptions::default());
+    let result = if is_zip(input) {
+        let data = std::fs::read(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+        pipeline.run_provider(&ZipProvider::new(&data).map_err(|e| e.to_string())?)
+    } else {
+        pipeline.run_provider(&DirectoryProvider::new(input))
+    }
+    .map_err(|e| e.to_string())?;
+
+    for diagnostic in result.diagnostics() {
+        eprintln!("{}", diagnostic);
+    }
+    if result.report.is_err() {
+        return Ok(false);
+    }
+
+    // Without an output, the zip is named by the bundle template
+    let bundle = result.bundle_name();
+    let output = output.unwrap_or_else(|| Path::new(&bundle));
+    let count = if is_zip(output) {
+        let file = File::create(output).map_err(|e| format!("{}: {}", output.display(), e))?;
+        let mut sink = ZipSink::new(BufWriter::new(file));
+        let count = result
+            .set
+            .write_outputs(&mut sink)
+            .map_err(|e| e.to_string())?;
+        if let Some(mut writer) = sink.into_inner() {
+            writer.flush().map_err(|e| e.to_string())?;
+        }
+        count
+    } else {
+        result
+            .set
+            .write_outputs(&mut DirectorySink::new(output))
+            .map_err(|e| e.to_string())?
+    };
+    println!("{} files written to {}", count, output.display());
+    Ok(true)
+}
+
+fn read_layer(path: &Path) -> Result<gerber::GerberLayer, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(gerber::parse_gerber(&content))
+}
+
+fn describe(primitive: &Primitive) -> String {
+    let kind = match primitive.kind {
+        PrimitiveKind::Flash => "flash",
+        PrimitiveKind::Line => "line",
+        PrimitiveKind::Arc => "arc",
+        PrimitiveKind::Region => "region",
+    };
+    let at = format!(
+        "{} at ({:.3}, {:.3})",
+        kind, primitive.position.x, primitive.position.y
+    );
+    match &primitive.aperture {
+        Some(aperture) => format!("{} with {}", at, aperture),
+        None => at,
+    }
+}
+
+// Prints how `new` differs from `old`; true when they are the same
+fn diff(old: &Path, new: &Path) -> Result<bool, String> {
+    let diff = compare::layers(&read_layer(old)?, &read_layer(new)?);
+    println!("XOR area: {:.4} mm²", diff.xor_area);
+    println!("Similarity: {:.2}%", diff.similarity * 100.0);
+    for primitive in &diff.missing_in_b {
+        println!("- {}", describe(primitive));
+    }
+    for primitive in &diff.extra_in_b {
+        println!("+ {}", describe(primitive));
+    }
+    Ok(diff.is_identical())
+}
+
+// Prints how every rename rule judges `file`, with the other files of its
+// directory as siblings. Without `eda`, the rules follow the file's software.
+fn explain(file: &Path, eda: Option<&str>) -> Result<bool, String> {
+    let eda_type = match eda {
+        Some(id) => EdaType::from_id(id).ok_or_else(|| format!("unknown EDA '{}'", id))?,
+        None => {
+            let content = std::fs::read(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            identify_software(&String::from_utf8_lossy(&content))
+                .and_then(EdaType::from_software)
+                .ok_or_else(|| {
+                    format!(
+                        "{}: cannot tell the EDA from the file; pass --eda",
+                        file.display()
+                    )
+                })?
+        }
+    };
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("{}: not a file", file.display()))?;
+    let directory = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let siblings: Vec<String> = std::fs::read_dir(directory.unwrap_or(Path::new(".")))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_file())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    println!("{} with the {} rules", name, eda_type.id());
+    for trace in rename::explain_in_set(&name, &siblings, eda_type) {
+        println!("{}", trace);
+    }
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["diff", old, new] => diff(Path::new(old), Path::new(new)),
+        ["explain", file] => explain(Path::new(file), None),
+        ["explain", file, "--eda", eda] => explain(Path::new(file), Some(eda)),
+        [input] | [input, _] if input != "diff" && input != "explain" => {
+            run(Path::new(input), args.get(1).map(Path::new))
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
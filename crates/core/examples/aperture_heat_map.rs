@@ -0,0 +1,35 @@
+/* examples/aperture_heat_map.rs */
+
+use nextjlc::gerber::parse_gerber;
+use nextjlc::render::{RenderMode, aperture_usage, render_svg};
+
+fn main() {
+    let layer = parse_gerber(
+        r#"%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,0.250000*%
+%ADD11R,1.500000X1.000000*%
+%ADD12O,1.000000X2.000000*%
+D10*
+X0Y0D02*
+X10000000Y0D01*
+X10000000Y5000000D01*
+D11*
+X2000000Y2000000D03*
+X4000000Y2000000D03*
+D12*
+X8000000Y2500000D03*
+M02*"#,
+    );
+
+    println!("--- Aperture usage ---");
+    for usage in aperture_usage(&layer) {
+        println!(
+            "D{:<4} flashes: {:<3} draws: {}",
+            usage.code, usage.flashes, usage.draws
+        );
+    }
+    println!();
+    println!("--- Heat map SVG ---");
+    print!("{}", render_svg(&layer, RenderMode::ApertureHeatMap));
+}
@@ -0,0 +1,37 @@
+/* examples/board_size.rs */
+
+use nextjlc::gerber::layer_stats;
+use nextjlc::outline::{compute_board_size, find_outline_file};
+
+fn main() {
+    let files = vec![
+        "Gerber_TopLayer.GTL".to_string(),
+        "Gerber_MechanicalLayer1.GM1".to_string(),
+        "Gerber_KeepOutLayer.GKO".to_string(),
+    ];
+
+    // The keep-out layer is preferred over mechanical layer 1.
+    println!("Outline file: {:?}\n", find_outline_file(&files));
+
+    // A 50 x 30 mm rectangle with one rounded corner, drawn with a 0.1 mm line.
+    let outline = r#"%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,0.100000*%
+D10*
+X0Y0D02*
+X50000000Y0D01*
+X50000000Y25000000D01*
+G75*
+G03X45000000Y30000000I-5000000J0D01*
+G01*
+X0Y30000000D01*
+X0Y0D01*
+M02*"#;
+
+    if let Some(size) = compute_board_size(outline) {
+        println!("Board size: {:.2} x {:.2} mm", size.width, size.height);
+    }
+
+    let stats = layer_stats(outline);
+    println!("Layer stats: {:#?}", stats);
+}
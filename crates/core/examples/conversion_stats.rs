@@ -0,0 +1,55 @@
+/* examples/conversion_stats.rs */
+
+use nextjlc::prelude::*;
+
+const KICAD_LAYER: &str = "%TF.GenerationSoftware,KiCad,Pcbnew,8.0.6*%
+%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,0.100000*%
+D10*
+X0Y0D02*
+X50000000Y0D01*
+M02*
+";
+
+fn main() {
+    let files = vec![
+        ("board-Edge_Cuts.gbr".to_string(), KICAD_LAYER.to_string()),
+        ("board-F_Cu.gbr".to_string(), KICAD_LAYER.to_string()),
+        ("board-B_Cu.gbr".to_string(), KICAD_LAYER.to_string()),
+    ];
+
+    let result = Pipeline::new(ConvertOptions::default()).run(&files);
+    let stats = &result.stats;
+
+    println!("EDA detected: {:?}", stats.eda_detected);
+    println!("Layer count: {:?}", stats.layer_count);
+    println!(
+        "Files: {} in, {} out",
+        stats.input_file_count, stats.output_file_count
+    );
+    println!(
+        "Warnings: {}, errors: {}",
+        stats.warning_count, stats.error_count
+    );
+    for timing in &stats.stage_timings {
+        println!(
+            "{:<12} {:>8.3} ms {:>8} -> {:>8} bytes",
+            timing.stage, timing.milliseconds, timing.input_bytes, timing.output_bytes
+        );
+        for file in &timing.files {
+            println!(
+                "    {:<36} {:>8} -> {:>8} bytes",
+                file.file, file.input_bytes, file.output_bytes
+            );
+        }
+    }
+    println!("{:<12} {:>8.3} ms", "total", stats.total_milliseconds());
+
+    for diagnostic in result.diagnostics() {
+        println!("{}", diagnostic);
+    }
+
+    println!();
+    println!("{}", result.summary_table());
+}
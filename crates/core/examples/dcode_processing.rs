@@ -1,6 +1,6 @@
 /* examples/dcode_processing.rs */
 
-use nextjlc::dcode::{GerberFlavor, process_d_codes};
+use nextjlc::dcode::{GerberFlavor, decide, process_d_codes};
 
 fn main() {
     let gerber_sample = r#"
@@ -33,5 +33,12 @@ M02*"#;
     let processed_data = process_d_codes(gerber_sample.to_string(), GerberFlavor::KiCad);
 
     println!("--- Processed Gerber Data ---");
-    println!("{}", processed_data);
+    println!("{}\n", processed_data);
+
+    // Which rule of the decision table decided each line
+    println!("--- Decisions ---");
+    for line in gerber_sample.lines().filter(|l| !l.is_empty()) {
+        let rule = decide(line, GerberFlavor::KiCad);
+        println!("{:<24} {:<20} {}", line, rule.id, rule.description);
+    }
 }
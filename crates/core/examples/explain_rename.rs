@@ -0,0 +1,47 @@
+/* examples/explain_rename.rs */
+
+use nextjlc::rename::{EdaType, RuleOutcome, explain, explain_in_set};
+
+// Prints every rule tried on a file, skipping the numbered inner and plane
+// layer rules that do not match, to keep the listing short
+fn print_trace(title: &str, traces: &[nextjlc::rename::RuleTrace]) {
+    println!("--- {} ---", title);
+    for trace in traces {
+        let numbered = trace.logical_name.starts_with("Gerber_InnerLayer")
+            || trace.logical_name.starts_with("Gerber_PlaneLayer");
+        if numbered && trace.outcome == RuleOutcome::NoMatch {
+            continue;
+        }
+        println!("{}", trace);
+    }
+    println!();
+}
+
+fn main() {
+    // Usage: cargo run --example explain_rename -- <file> [eda type] [sibling files...]
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(file) = args.first() {
+        let eda_type = args
+            .get(1)
+            .and_then(|id| EdaType::from_id(id))
+            .unwrap_or(EdaType::Ad);
+        let siblings: Vec<String> = args.iter().skip(2).cloned().collect();
+        print_trace(file, &explain_in_set(file, &siblings, eda_type));
+        return;
+    }
+
+    // A mechanical layer is the outline of an Altium set...
+    print_trace("board.GM1 (Altium)", &explain("board.GM1", EdaType::Ad));
+
+    // ...unless the set has a keep-out layer
+    let files = vec!["board.GM1".to_string(), "board.GKO".to_string()];
+    print_trace(
+        "board.GM1 next to board.GKO (Altium)",
+        &explain_in_set("board.GM1", &files, EdaType::Ad),
+    );
+
+    print_trace(
+        "board-Edge_Cuts.gbr (KiCad)",
+        &explain("board-Edge_Cuts.gbr", EdaType::KiCad),
+    );
+}
@@ -0,0 +1,38 @@
+/* examples/layer_diff.rs */
+
+use nextjlc::compare;
+use nextjlc::gerber::parse_gerber;
+
+fn main() {
+    let original = r#"%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,1.000000*%
+%ADD11R,2.000000X2.000000*%
+D10*
+X0Y0D02*
+X10000000Y0D01*
+D11*
+X5000000Y5000000D03*
+X15000000Y5000000D03*
+M02*"#;
+
+    // The converted layer lost one pad.
+    let converted = r#"%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,1.000000*%
+%ADD11R,2.000000X2.000000*%
+D10*
+X0Y0D02*
+X10000000Y0D01*
+D11*
+X5000000Y5000000D03*
+M02*"#;
+
+    let diff = compare::layers(&parse_gerber(original), &parse_gerber(converted));
+    println!("Area A: {:.3} mm²", diff.area_a);
+    println!("Area B: {:.3} mm²", diff.area_b);
+    println!("XOR area: {:.3} mm²", diff.xor_area);
+    println!("Similarity: {:.4}", diff.similarity);
+    println!("Missing in B: {:#?}", diff.missing_in_b);
+    println!("Extra in B: {:#?}", diff.extra_in_b);
+}
@@ -0,0 +1,59 @@
+/* examples/layer_set_pipeline.rs */
+
+use nextjlc::layer_set::LayerSet;
+
+const KICAD_LAYER: &str = "%TF.GenerationSoftware,KiCad,Pcbnew,8.0.6*%
+%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,0.100000*%
+D10*
+X0Y0D02*
+X50000000Y0D01*
+M02*
+";
+
+const KICAD_DRILL: &str = "M48
+; DRILL file {KiCad 8.0.6} date 2024-11-23
+FMAT,2
+METRIC
+T1C0.300
+%
+G90
+G05
+T1
+X10.0Y5.0
+M30
+";
+
+fn main() {
+    let files = vec![
+        ("board-Edge_Cuts.gbr".to_string(), KICAD_LAYER.to_string()),
+        ("board-F_Cu.gbr".to_string(), KICAD_LAYER.to_string()),
+        ("board-F_Mask.gbr".to_string(), KICAD_LAYER.to_string()),
+        ("board-PTH.drl".to_string(), KICAD_DRILL.to_string()),
+    ];
+
+    let mut set = LayerSet::from_files(&files);
+    println!("Primary software: {:?}", set.metadata.primary_software);
+
+    if let Some(eda) = set.eda_type() {
+        set.rename(eda);
+    }
+    set.add_headers();
+    set.process_d_codes();
+    set.add_fingerprints(false);
+    set.process_drills();
+
+    match set.validate() {
+        Ok(report) => println!("Valid {}-layer board", report.layer_count),
+        Err(errors) => println!("Validation failed: {:?}", errors),
+    }
+    set.add_order_guide();
+
+    for warning in &set.metadata.warnings {
+        println!("- {}", warning);
+    }
+    for (name, content) in set.into_outputs() {
+        println!("{:<40} {:>6} bytes", name, content.len());
+    }
+}
@@ -0,0 +1,183 @@
+/* examples/rename_files.rs */
+
+use nextjlc::rename::{
+    EdaType, OutlineSelection, RenameResult, map_filenames_detailed, map_filenames_with_outline,
+};
+
+fn print_results(title: &str, mapping: &RenameResult) {
+    println!("--- {} ---", title);
+    println!(
+        "{:<40} -> {:<40} {:<10}",
+        "Original Name", "New Name", "Confidence"
+    );
+    println!("{:-<95}", "");
+    for entry in mapping.iter() {
+        println!(
+            "{:<40} -> {:<40} {}",
+            entry.original,
+            entry.new_name,
+            entry.confidence.id()
+        );
+    }
+    println!(); // Add a blank line for spacing
+}
+
+fn main() {
+    // --- Test case 1: Altium Designer Gerber Files ---
+    let ad_files = vec![
+        "Gerber_MechanicalLayer1.GM1".to_string(),
+        "Gerber_TopLayer.GTL".to_string(),
+        "Gerber_TopSilkscreenLayer.GTO".to_string(),
+        "Gerber_TopSolderMaskLayer.GTS".to_string(),
+    ];
+
+    // --- Test case 2: KiCad Gerber Files ---
+    let kicad_files = vec![
+        "KICAD-B_Cu.gbr".to_string(),
+        "KICAD-B_Mask.gbr".to_string(),
+        "KICAD-B_Paste.gbr".to_string(),
+        "KICAD-B_Silkscreen.gbr".to_string(),
+        "KICAD-drl_map.gbr".to_string(),
+        "KICAD-Edge_Cuts.gbr".to_string(),
+        "KICAD-F_Cu.gbr".to_string(),
+        "KICAD-F_Mask.gbr".to_string(),
+        "KICAD-F_Paste.gbr".to_string(),
+        "KICAD-F_Silkscreen.gbr".to_string(),
+        "KICAD-In1_Cu.gbr".to_string(),
+        "KICAD-In2_Cu.gbr".to_string(),
+        "KICAD-job.gbrjob".to_string(), // This should not be renamed
+        "KICAD.drl".to_string(),        // This is the main PTH drill file
+    ];
+
+    // --- Test case 2b: KiCad 5 names, dot-separated with "SilkS" ---
+    let kicad5_files = vec![
+        "board-B.Cu.gbr".to_string(),
+        "board-B.SilkS.gbr".to_string(),
+        "board-Edge.Cuts.gbr".to_string(),
+        "board-F.Cu.gbr".to_string(),
+        "board-F.Mask.gbr".to_string(),
+        "board-F.SilkS.gbr".to_string(),
+        "board-In1.Cu.gbr".to_string(),
+    ];
+
+    // --- Test case 3: EAGLE / Fusion 360 CAM outputs, new and legacy names ---
+    let eagle_files = vec![
+        "copper_top.gbr".to_string(),
+        "copper_bottom.gbr".to_string(),
+        "soldermask_top.gbr".to_string(),
+        "silkscreen_top.gbr".to_string(),
+        "profile.gbr".to_string(),
+        "board.cmp".to_string(),
+        "board.sol".to_string(),
+        "board.plc".to_string(),
+        "board.stc".to_string(),
+    ];
+
+    // --- Test case 4: PADS Layout photoplot and NC drill outputs ---
+    let pads_files = vec![
+        "top.pho".to_string(),
+        "bot.pho".to_string(),
+        "smt.pho".to_string(),
+        "sst.pho".to_string(),
+        "brd.pho".to_string(),
+        "drilldrawing.pho".to_string(),
+        "drl001.drl".to_string(),
+    ];
+
+    // --- Test case 5: Proteus ARES CADCAM outputs ---
+    let proteus_files = vec![
+        "Board - CADCAM Top Copper.TXT".to_string(),
+        "Board - CADCAM Bottom Copper.TXT".to_string(),
+        "Board - CADCAM Top Silk Screen.TXT".to_string(),
+        "Board - CADCAM Bottom Solder Resist.TXT".to_string(),
+        "Board - CADCAM Mechanical 1.TXT".to_string(),
+        "Board - CADCAM Drill.TXT".to_string(),
+    ];
+
+    // --- Test case 6: LibrePCB fabrication outputs ---
+    let librepcb_files = vec![
+        "board_v1_COPPER-TOP.gbr".to_string(),
+        "board_v1_COPPER-BOTTOM.gbr".to_string(),
+        "board_v1_SOLDERMASK-TOP.gbr".to_string(),
+        "board_v1_SILKSCREEN-TOP.gbr".to_string(),
+        "board_v1_OUTLINES.gbr".to_string(),
+        "board_v1_DRILLS-PTH.drl".to_string(),
+        "board_v1_DRILLS-NPTH.drl".to_string(),
+    ];
+
+    // --- Test case 7: Horizon EDA fabrication outputs ---
+    let horizon_files = vec![
+        "board.gtl".to_string(),
+        "board.gbl".to_string(),
+        "board.g1".to_string(),
+        "board.gts".to_string(),
+        "board.gko".to_string(),
+        "board_pth.drl".to_string(),
+    ];
+
+    // --- Test case 8: DipTrace Gerber and N/C drill exports ---
+    let diptrace_files = vec![
+        "Top.gbr".to_string(),
+        "TopSilk.gbr".to_string(),
+        "TopMask.gbr".to_string(),
+        "TopAssy.gbr".to_string(),
+        "Bottom.gbr".to_string(),
+        "BoardOutline.gbr".to_string(),
+        "Through.drl".to_string(),
+    ];
+
+    // --- Test case 9: Altium project with the outline on mechanical layer 4 ---
+    let ad_mech_files = vec![
+        "board.GM1".to_string(),
+        "board.GM4".to_string(),
+        "board.GTL".to_string(),
+        "board.GTS".to_string(),
+    ];
+
+    // Process and print results for Altium Designer
+    let ad_map = map_filenames_detailed(&ad_files, EdaType::Ad);
+    print_results("Altium Designer Renaming Plan", &ad_map);
+
+    // Process and print results for KiCad
+    let kicad_map = map_filenames_detailed(&kicad_files, EdaType::KiCad);
+    print_results("KiCad Renaming Plan", &kicad_map);
+
+    // Process and print results for KiCad 5
+    let kicad5_map = map_filenames_detailed(&kicad5_files, EdaType::KiCad);
+    print_results("KiCad 5 Renaming Plan", &kicad5_map);
+
+    // Process and print results for EAGLE
+    let eagle_map = map_filenames_detailed(&eagle_files, EdaType::Eagle);
+    print_results("EAGLE Renaming Plan", &eagle_map);
+
+    // Process and print results for PADS
+    let pads_map = map_filenames_detailed(&pads_files, EdaType::Pads);
+    print_results("PADS Renaming Plan", &pads_map);
+
+    // Process and print results for Proteus
+    let proteus_map = map_filenames_detailed(&proteus_files, EdaType::Proteus);
+    print_results("Proteus Renaming Plan", &proteus_map);
+
+    // Process and print results for LibrePCB
+    let librepcb_map = map_filenames_detailed(&librepcb_files, EdaType::LibrePcb);
+    print_results("LibrePCB Renaming Plan", &librepcb_map);
+
+    // Process and print results for Horizon EDA
+    let horizon_map = map_filenames_detailed(&horizon_files, EdaType::Horizon);
+    print_results("Horizon EDA Renaming Plan", &horizon_map);
+
+    // Process and print results for DipTrace
+    let diptrace_map = map_filenames_detailed(&diptrace_files, EdaType::DipTrace);
+    print_results("DipTrace Renaming Plan", &diptrace_map);
+
+    // Process and print results for the selected Altium outline layer
+    let ad_mech_map = map_filenames_with_outline(
+        &ad_mech_files,
+        EdaType::Ad,
+        &OutlineSelection::Mechanical(4),
+    );
+    print_results(
+        "Altium Designer Renaming Plan (outline on GM4)",
+        &ad_mech_map,
+    );
+}
@@ -0,0 +1,36 @@
+/* examples/report_bundle.rs */
+
+use nextjlc::layer_set::LayerSet;
+use nextjlc::support::make_report_bundle;
+
+const KICAD_LAYER: &str = "%TF.GenerationSoftware,KiCad,Pcbnew,8.0.6*%
+%TF.ProjectId,secret-board,00000000-0000-0000-0000-000000000000,rev?*%
+G04 Created by KiCad (PCBNEW 8.0.6) from /home/user/projects/secret-board.kicad_pcb*
+%FSLAX46Y46*%
+%MOMM*%
+%ADD10C,0.100000*%
+D10*
+X0Y0D02*
+X50000000Y0D01*
+M02*
+";
+
+fn main() {
+    let inputs = vec![
+        ("board-Edge_Cuts.gbr".to_string(), KICAD_LAYER.to_string()),
+        ("board-F_Cu.gbr".to_string(), KICAD_LAYER.to_string()),
+    ];
+
+    let mut set = LayerSet::from_files(&inputs);
+    if let Some(eda) = set.eda_type() {
+        set.rename(eda);
+    }
+    set.add_headers();
+    set.process_d_codes();
+    let report = set.validate();
+
+    let bundle = make_report_bundle(&inputs, &set, &report);
+    let path = std::env::temp_dir().join("nextjlc-report.zip");
+    std::fs::write(&path, &bundle).expect("Failed to write report bundle");
+    println!("Wrote {} bytes to {}", bundle.len(), path.display());
+}
@@ -0,0 +1,468 @@
+/* src/autofix.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::{BoundingBox, Point};
+use crate::gerber::{self, Aperture, Command, GerberLayer, Interpolation, Polarity, Unit};
+use crate::json::JsonValue;
+use crate::outline;
+use crate::transform;
+use crate::writer::{self, WriterOptions};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+// This regex matches a circle or rectangle aperture whose first size is zero, e.g. "%ADD10C,0*%".
+static ZERO_APERTURE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"%ADD(\d+)([CRO]),0*(?:\.0*)?([X*])").expect("Invalid zero aperture regex")
+});
+
+// Replacement sizes for zero-size apertures: 1 µm, far below any fab's resolution.
+const MIN_APERTURE_MM: &str = "0.001";
+const MIN_APERTURE_INCH: &str = "0.00004";
+
+// Line width of a synthesized outline, and its distance from the copper, in mm
+const OUTLINE_WIDTH_MM: f64 = 0.1;
+const OUTLINE_MARGIN_MM: f64 = 1.0;
+
+/// Default solder mask expansion per side for regenerated masks, in mm
+pub const MASK_EXPANSION_MM: f64 = 0.05;
+
+/// Distance past the board contour beyond which outline and document geometry
+/// is an off-board annotation, in mm
+pub const OFF_BOARD_MARGIN_MM: f64 = 5.0;
+
+// Copper may reach this far past the contour, e.g. on castellated edges, in mm
+const COPPER_OVERHANG_MM: f64 = 1.0;
+
+/// How much the pipeline may change the design on its own
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FixLevel {
+    /// Only report problems
+    None,
+    /// Fixes that cannot change the fabricated board: zero-size aperture
+    /// repair, drill unit unification, removal of off-board annotations,
+    /// conversion of negative planes and end-of-file marker repair
+    #[default]
+    Safe,
+    /// Also synthesize a missing outline and regenerate missing solder masks,
+    /// which are guesses the user must review
+    Aggressive,
+}
+
+/// A change made by an automatic fix, listed for the user to review
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFix {
+    /// Short stable id of the pass, e.g. "zero-size-aperture"
+    pub pass: &'static str,
+    pub file: Option<String>,
+    pub description: String,
+}
+
+/// Ids of every fix pass, in the order they run
+pub const PASSES: &[&str] = &[
+    "off-board-geometry",
+    "zero-size-aperture",
+    "negative-plane",
+    "outline-synthesis",
+    "mask-regeneration",
+    "drill-units",
+    "end-marker",
+];
+
+impl AppliedFix {
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("pass".to_string(), self.pass.into()),
+            ("file".to_string(), self.file.as_deref().into()),
+            ("description".to_string(), self.description.as_str().into()),
+        ])
+    }
+
+    /// Restores a fix serialized by [`AppliedFix::to_json`]; `None` for an
+    /// unknown pass.
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let pass = value.get("pass")?.as_str()?;
+        Some(AppliedFix {
+            pass: PASSES.iter().find(|p| **p == pass)?,
+            file: value
+                .get("file")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string),
+            description: value.get("description")?.as_str()?.to_string(),
+        })
+    }
+}
+
+/// Gives zero-size C, R and O apertures a 1 µm size.
+/// Returns the new content and the number of apertures changed.
+pub(crate) fn repair_zero_size_apertures(content: &str) -> (String, usize) {
+    if !ZERO_APERTURE_REGEX.is_match(content) {
+        return (content.to_string(), 0);
+    }
+    let size = match gerber::parse_gerber(content).unit {
+        Unit::Millimeter => MIN_APERTURE_MM,
+        Unit::Inch => MIN_APERTURE_INCH,
+    };
+    let mut count = 0;
+    let repaired = ZERO_APERTURE_REGEX.replace_all(content, |caps: &regex::Captures| {
+        count += 1;
+        format!("%ADD{}{},{}{}", &caps[1], &caps[2], size, &caps[3])
+    });
+    (repaired.into_owned(), count)
+}
+
+/// End-of-file marker of a Gerber layer
+pub const GERBER_END_MARKER: &str = "M02*";
+/// End-of-file marker of an Excellon drill file
+pub const DRILL_END_MARKER: &str = "M30";
+
+/// How the end-of-file marker of a file is out of place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndMarkerIssue {
+    Missing,
+    /// The marker appears this many times, as in files concatenated together
+    Repeated(usize),
+    /// Content follows the only marker
+    NotLast,
+}
+
+impl EndMarkerIssue {
+    pub fn describe(&self, marker: &str) -> String {
+        match self {
+            EndMarkerIssue::Missing => format!("no {} end marker", marker),
+            EndMarkerIssue::Repeated(count) => format!("{} {} end markers", count, marker),
+            EndMarkerIssue::NotLast => format!("content after the {} end marker", marker),
+        }
+    }
+}
+
+// Byte ranges of the end markers of a file: "M02*" statements of a Gerber
+// layer, which may share a line with other statements, or "M30" lines of a
+// drill file
+fn end_markers(content: &str, drill: bool) -> Vec<(usize, usize)> {
+    let mut markers = Vec::new();
+    if drill {
+        let mut start = 0;
+        for line in content.split_inclusive('\n') {
+            if line.trim() == DRILL_END_MARKER {
+                markers.push((start, start + line.trim_end().len()));
+            }
+            start += line.len();
+        }
+    } else {
+        let mut start = 0;
+        for statement in content.split_inclusive('*') {
+            // The % closing an extended command belongs to the statement before
+            let code = statement.trim_start_matches(|c: char| c.is_whitespace() || c == '%');
+            if code == GERBER_END_MARKER {
+                markers.push((
+                    start + statement.len() - code.len(),
+                    start + statement.len(),
+                ));
+            }
+            start += statement.len();
+        }
+    }
+    markers
+}
+
+/// Checks that a Gerber layer (or, with `drill`, an Excellon file) ends with
+/// exactly one end-of-file marker.
+pub fn check_end_marker(content: &str, drill: bool) -> Option<EndMarkerIssue> {
+    let markers = end_markers(content, drill);
+    match markers.as_slice() {
+        [] => Some(EndMarkerIssue::Missing),
+        [(_, end)] if !content[*end..].trim().is_empty() => Some(EndMarkerIssue::NotLast),
+        [_] => None,
+        _ => Some(EndMarkerIssue::Repeated(markers.len())),
+    }
+}
+
+/// Removes every end-of-file marker of a file and writes one at its end.
+/// Returns the repaired content and what was wrong, or `None` when the
+/// file already ends with exactly one marker.
+pub fn repair_end_marker(content: &str, drill: bool) -> Option<(String, EndMarkerIssue)> {
+    let issue = check_end_marker(content, drill)?;
+    let mut repaired = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, mut end) in end_markers(content, drill) {
+        // A marker alone on its line takes the line with it
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let rest = &content[end..];
+        let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let start =
+            if content[line_start..start].trim().is_empty() && rest[..line_end].trim().is_empty() {
+                end += line_end;
+                line_start
+            } else {
+                start
+            };
+        repaired.push_str(&content[last..start]);
+        last = end;
+    }
+    repaired.push_str(&content[last..]);
+
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut repaired = repaired.trim_end().to_string();
+    if !repaired.is_empty() {
+        repaired.push_str(newline);
+    }
+    repaired.push_str(if drill {
+        DRILL_END_MARKER
+    } else {
+        GERBER_END_MARKER
+    });
+    repaired.push_str(newline);
+    Some((repaired, issue))
+}
+
+/// Draws a rectangular outline 1 mm around the union of the given copper layers.
+/// Returns the outline Gerber and its extents, or `None` without any copper geometry.
+pub(crate) fn synthesize_outline(copper_layers: &[&str]) -> Option<(String, BoundingBox)> {
+    let mut bounds: Option<BoundingBox> = None;
+    for content in copper_layers {
+        if let Some(b) = gerber::parse_gerber(content).image_bounds() {
+            match &mut bounds {
+                Some(existing) => existing.include_box(&b),
+                None => bounds = Some(b),
+            }
+        }
+    }
+    let bounds = bounds?.expanded(OUTLINE_MARGIN_MM);
+
+    let corners = [
+        Point::new(bounds.min_x, bounds.min_y),
+        Point::new(bounds.max_x, bounds.min_y),
+        Point::new(bounds.max_x, bounds.max_y),
+        Point::new(bounds.min_x, bounds.max_y),
+    ];
+    let mut commands = vec![Command::SelectAperture(10), Command::Move(corners[0])];
+    for (i, to) in corners.iter().cycle().skip(1).take(4).enumerate() {
+        commands.push(Command::Draw {
+            from: corners[i],
+            to: *to,
+            interpolation: Interpolation::Linear,
+            center: None,
+        });
+    }
+
+    let mut apertures = BTreeMap::new();
+    apertures.insert(
+        10,
+        Aperture {
+            code: 10,
+            template: "C".to_string(),
+            parameters: vec![OUTLINE_WIDTH_MM],
+            attributes: Vec::new(),
+        },
+    );
+    let layer = new_layer(apertures, commands, "TF.FileFunction,Profile,NP");
+    Some((
+        writer::write_gerber(&layer, &WriterOptions::default()),
+        bounds,
+    ))
+}
+
+/// Builds a solder mask from the pads (flashes) of a copper layer, grown by
+/// `expansion_mm` per side. Pads drawn as regions or with macro apertures are
+/// not covered, so the result is a starting point, not a replacement for the
+/// mask from the design.
+///
+/// Returns `None` when the copper layer has no flashed pads.
+pub(crate) fn regenerate_mask(copper: &str, expansion_mm: f64, top: bool) -> Option<String> {
+    let source = gerber::parse_gerber(copper);
+
+    let mut apertures = BTreeMap::new();
+    for aperture in source.apertures.values().filter(|a| a.is_standard()) {
+        let mut grown = aperture.clone();
+        grown.attributes.clear();
+        let sizes = match grown.template.as_str() {
+            "C" | "P" => 1,
+            _ => 2,
+        };
+        for size in grown.parameters.iter_mut().take(sizes) {
+            *size += 2.0 * expansion_mm;
+        }
+        apertures.insert(grown.code, grown);
+    }
+
+    let mut commands = Vec::new();
+    let mut current = None;
+    let mut selected = None;
+    for command in &source.commands {
+        match command {
+            Command::SelectAperture(code) => current = Some(*code),
+            Command::Flash(p) => {
+                let Some(code) = current.filter(|c| apertures.contains_key(c)) else {
+                    continue;
+                };
+                if selected != Some(code) {
+                    commands.push(Command::SelectAperture(code));
+                    selected = Some(code);
+                }
+                commands.push(Command::Flash(*p));
+            }
+            _ => {}
+        }
+    }
+    if commands.is_empty() {
+        return None;
+    }
+
+    apertures.retain(|code, _| commands.contains(&Command::SelectAperture(*code)));
+    let function = if top {
+        "TF.FileFunction,Soldermask,Top"
+    } else {
+        "TF.FileFunction,Soldermask,Bot"
+    };
+    let mut layer = new_layer(apertures, commands, function);
+    // The image shows the openings, as in masks exported by EDA tools
+    layer
+        .file_attributes
+        .push("TF.FilePolarity,Negative".to_string());
+    Some(writer::write_gerber(&layer, &WriterOptions::default()))
+}
+
+/// Whether a layer is a negative image, by `%IPNEG` or
+/// `%TF.FilePolarity,Negative`.
+pub(crate) fn is_negative_image(content: &str) -> bool {
+    content.contains("%IPNEG")
+        || gerber::parse_gerber(content)
+            .file_attribute("FilePolarity")
+            .is_some_and(|fields| fields.first() == Some(&"Negative"))
+}
+
+/// Turns a negative copper image, such as an Altium internal plane, into a
+/// positive one: a dark rectangle over `board` (or the layer's own extents)
+/// with every object of the plane drawn clear on top, polarities swapped.
+/// JLC reads inner layers as positive, so a negative plane would be made
+/// inverted. Returns the new layer and the filled area, or `None` for an
+/// empty layer without a board.
+pub(crate) fn invert_negative_plane(
+    content: &str,
+    board: Option<BoundingBox>,
+) -> Option<(String, BoundingBox)> {
+    let source = gerber::parse_gerber(content);
+    let area = board.or_else(|| source.image_bounds())?;
+
+    let corners = [
+        Point::new(area.min_x, area.min_y),
+        Point::new(area.max_x, area.min_y),
+        Point::new(area.max_x, area.max_y),
+        Point::new(area.min_x, area.max_y),
+    ];
+    let mut commands = vec![
+        Command::SetPolarity(Polarity::Dark),
+        Command::RegionStart,
+        Command::Move(corners[0]),
+    ];
+    for (i, to) in corners.iter().cycle().skip(1).take(4).enumerate() {
+        commands.push(Command::Draw {
+            from: corners[i],
+            to: *to,
+            interpolation: Interpolation::Linear,
+            center: None,
+        });
+    }
+    commands.push(Command::RegionEnd);
+    commands.push(Command::SetPolarity(Polarity::Clear));
+    for command in &source.commands {
+        commands.push(match command {
+            Command::SetPolarity(Polarity::Dark) => Command::SetPolarity(Polarity::Clear),
+            Command::SetPolarity(Polarity::Clear) => Command::SetPolarity(Polarity::Dark),
+            other => other.clone(),
+        });
+    }
+
+    let mut file_attributes: Vec<String> = source
+        .file_attributes
+        .iter()
+        .filter(|a| !a.starts_with("TF.FilePolarity"))
+        .cloned()
+        .collect();
+    file_attributes.push("TF.FilePolarity,Positive".to_string());
+    let layer = GerberLayer {
+        file_attributes,
+        commands,
+        ..source
+    };
+    Some((
+        writer::write_gerber(&layer, &WriterOptions::default()),
+        area,
+    ))
+}
+
+/// Picks the board contour among the closed contours of an outline layer: the
+/// smallest one around all of `copper`, or the largest when there is no copper.
+/// Title block frames are closed too, which is why the copper decides.
+pub(crate) fn board_contour(
+    outline_content: &str,
+    copper: Option<BoundingBox>,
+) -> Option<BoundingBox> {
+    let contours = outline::closed_contours(outline_content);
+    match copper {
+        Some(copper) => contours
+            .into_iter()
+            .filter(|c| {
+                let grown = c.expanded(COPPER_OVERHANG_MM);
+                grown.contains(Point::new(copper.min_x, copper.min_y))
+                    && grown.contains(Point::new(copper.max_x, copper.max_y))
+            })
+            .min_by(|a, b| a.area().total_cmp(&b.area())),
+        None => contours
+            .into_iter()
+            .max_by(|a, b| a.area().total_cmp(&b.area())),
+    }
+}
+
+/// Crops a layer to `board` grown by [`OFF_BOARD_MARGIN_MM`], removing title
+/// blocks and dimensions drawn away from the board.
+/// Returns the new content with the extents before and after, or `None` when
+/// nothing lies outside.
+pub(crate) fn strip_off_board(
+    content: &str,
+    board: &BoundingBox,
+) -> Option<(String, BoundingBox, BoundingBox)> {
+    let layer = gerber::parse_gerber(content);
+    let before = layer.bounds()?;
+    let keep = board.expanded(OFF_BOARD_MARGIN_MM);
+    if keep.contains(Point::new(before.min_x, before.min_y))
+        && keep.contains(Point::new(before.max_x, before.max_y))
+    {
+        return None;
+    }
+    let cropped = transform::crop(&layer, &keep);
+    let after = cropped.bounds()?;
+    Some((
+        writer::write_gerber(&cropped, &WriterOptions::default()),
+        before,
+        after,
+    ))
+}
+
+fn new_layer(
+    apertures: BTreeMap<u32, Aperture>,
+    commands: Vec<Command>,
+    file_function: &str,
+) -> GerberLayer {
+    GerberLayer {
+        unit: Unit::Millimeter,
+        format: gerber::CoordinateFormat::default(),
+        apertures,
+        macros: Vec::new(),
+        file_attributes: vec![
+            "TF.GenerationSoftware,nextjlc,nextjlc".to_string(),
+            file_function.to_string(),
+        ],
+        commands,
+        has_end_marker: true,
+    }
+}
@@ -0,0 +1,81 @@
+/* src/cancel.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How many loop iterations (usually lines) pass between cancellation checks.
+pub const CHECK_INTERVAL: usize = 1024;
+
+/// Error returned when an operation was aborted through a cancellation source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Operation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A source that long-running operations poll to find out whether they should stop.
+///
+/// Any `Fn() -> bool` closure is a cancellation source, which lets the WASM layer
+/// poll a JavaScript callback without this crate depending on `js-sys`.
+pub trait Cancellation {
+    fn is_cancelled(&self) -> bool;
+
+    /// Returns `Err(Cancelled)` if cancellation was requested.
+    fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<F: Fn() -> bool> Cancellation for F {
+    fn is_cancelled(&self) -> bool {
+        self()
+    }
+}
+
+/// A cancellation source that never fires, used by the non-cancellable entry points.
+pub struct NeverCancel;
+
+impl Cancellation for NeverCancel {
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// A cloneable, thread-safe cancellation token.
+/// Cloning shares the flag, so any clone can cancel the operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every operation observing this token.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Cancellation for CancelToken {
+    fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,215 @@
+/* src/classify.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::gerber;
+use crate::outline;
+use crate::rename;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// This regex matches the X2 file polarity attribute and captures its value.
+static FILE_POLARITY_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"%TF\.FilePolarity,(Positive|Negative)\*?%").expect("Invalid file polarity regex")
+});
+
+/// The role of a layer read from its X2 attributes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct X2Classification {
+    /// Logical name, e.g. "Gerber_BoardOutlineLayer"
+    pub logical_name: String,
+    /// `%TF.FileFunction` value as written, e.g. "Profile,NP"
+    pub file_function: String,
+    /// `%TF.FilePolarity,Negative`: the image shows where material is removed
+    pub negative: bool,
+}
+
+/// Classifies a Gerber layer by its `%TF.FileFunction` and `%TF.FilePolarity`
+/// attributes, whatever the file is called. Returns `None` without a file
+/// function or for one that has no JLC layer.
+pub(crate) fn classify_x2(content: &str) -> Option<X2Classification> {
+    let fields = gerber::file_function(content)?;
+    let logical_name = rename::logical_name_from_file_function(&fields)?;
+    Some(X2Classification {
+        logical_name,
+        file_function: fields.join(","),
+        negative: FILE_POLARITY_REGEX
+            .captures(content)
+            .is_some_and(|caps| &caps[1] == "Negative"),
+    })
+}
+
+/// Paste or silkscreen, as told apart by [`sniff_paste_or_silk`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteOrSilk {
+    Paste,
+    Silkscreen,
+}
+
+/// Tells a paste layer from a silkscreen by its objects: paste is pads,
+/// flashed or as regions, while silkscreen is mostly stroked outlines and
+/// text. Returns `None` for a layer without objects.
+pub fn sniff_paste_or_silk(content: &str) -> Option<PasteOrSilk> {
+    let layer = gerber::parse_gerber(content);
+    let (mut pads, mut strokes) = (0usize, 0usize);
+    let mut in_region = false;
+    for command in &layer.commands {
+        match command {
+            gerber::Command::RegionStart => {
+                in_region = true;
+                pads += 1;
+            }
+            gerber::Command::RegionEnd => in_region = false,
+            gerber::Command::Flash(_) => pads += 1,
+            gerber::Command::Draw { .. } if !in_region => strokes += 1,
+            _ => {}
+        }
+    }
+    if pads + strokes == 0 {
+        return None;
+    }
+    Some(if strokes > pads {
+        PasteOrSilk::Silkscreen
+    } else {
+        PasteOrSilk::Paste
+    })
+}
+
+/// A layer guessed from the objects of a Gerber file whose name matched no rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentGuess {
+    /// Logical name, e.g. "Gerber_TopSilkscreenLayer"
+    pub logical_name: String,
+    /// How likely the guess is, from 0 to 100
+    pub score: u8,
+    /// What the guess was based on, for the user to check
+    pub evidence: String,
+}
+
+/// Guesses below this score are reported but not applied
+pub const MIN_GUESS_SCORE: u8 = 50;
+
+// Strokes at most this wide are taken for outline or silkscreen lines, in mm
+const THIN_STROKE_MM: f64 = 0.3;
+
+// These regexes match the side named in a file name, e.g. "top", "F_", "bot".
+static TOP_SIDE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(^|[-_. ])(top|front|f[-_.])").expect("Invalid top side regex"));
+static BOTTOM_SIDE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(^|[-_. ])(bot|back|b[-_.])").expect("Invalid bottom side regex")
+});
+
+// This regex matches extensions that say a file is Gerber but not which layer.
+static GENERIC_GERBER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\.(gbr|ger|gbx|pho|art)$").expect("Invalid generic Gerber regex")
+});
+
+/// True for names such as "layer3.gbr" whose extension does not tell the layer.
+pub(crate) fn has_generic_gerber_extension(name: &str) -> bool {
+    GENERIC_GERBER_REGEX.is_match(name)
+}
+
+/// Guesses the layer of a Gerber file from its objects: aperture sizes, the
+/// share of pads and strokes, and whether strokes close into a contour. X2
+/// attributes win when present. The side comes from the file name; without
+/// one the top side is assumed and the score lowered. Returns `None` for
+/// content that is not Gerber or has no objects.
+pub fn guess_layer(name: &str, content: &str) -> Option<ContentGuess> {
+    if let Some(x2) = classify_x2(content) {
+        return Some(ContentGuess {
+            logical_name: x2.logical_name,
+            score: 95,
+            evidence: format!("file function {}", x2.file_function),
+        });
+    }
+    if !content.contains("%FS") {
+        return None;
+    }
+    let layer = gerber::parse_gerber(content);
+    let (mut pads, mut strokes, mut thin_strokes) = (0usize, 0usize, 0usize);
+    let mut aperture: Option<&gerber::Aperture> = None;
+    let mut in_region = false;
+    for command in &layer.commands {
+        match command {
+            gerber::Command::SelectAperture(code) => aperture = layer.apertures.get(code),
+            gerber::Command::RegionStart => {
+                in_region = true;
+                pads += 1;
+            }
+            gerber::Command::RegionEnd => in_region = false,
+            gerber::Command::Flash(_) => pads += 1,
+            gerber::Command::Draw { .. } if !in_region => {
+                strokes += 1;
+                let (half_x, half_y) = aperture.map_or((0.0, 0.0), |a| a.half_extents());
+                if half_x.max(half_y) * 2.0 <= THIN_STROKE_MM {
+                    thin_strokes += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    let total = pads + strokes;
+    if total == 0 {
+        return None;
+    }
+    let stroke_share = strokes as f64 / total as f64;
+    let thin_share = if strokes == 0 {
+        0.0
+    } else {
+        thin_strokes as f64 / strokes as f64
+    };
+
+    let (kind, mut score, evidence) =
+        if pads == 0 && thin_share == 1.0 && !outline::closed_contours(content).is_empty() {
+            (
+                "BoardOutlineLayer",
+                80,
+                "thin strokes closing into a contour, no pads".to_string(),
+            )
+        } else if stroke_share >= 0.7 && thin_share >= 0.8 {
+            (
+                "SilkscreenLayer",
+                60,
+                format!("{} thin strokes and {} pads", strokes, pads),
+            )
+        } else if stroke_share <= 0.1 {
+            // Masks and paste both show pads only; masks also open vias and
+            // through-hole pads, so they are the likelier guess
+            (
+                "SolderMaskLayer",
+                40,
+                format!("{} pads and {} strokes", pads, strokes),
+            )
+        } else {
+            (
+                "Layer",
+                55,
+                format!("{} tracks and {} pads or pours", strokes, pads),
+            )
+        };
+    let logical_name = if kind == "BoardOutlineLayer" {
+        format!("Gerber_{}", kind)
+    } else {
+        let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        let side = match (
+            TOP_SIDE_REGEX.is_match(base),
+            BOTTOM_SIDE_REGEX.is_match(base),
+        ) {
+            (true, false) => "Top",
+            (false, true) => "Bottom",
+            _ => {
+                score -= 20;
+                "Top"
+            }
+        };
+        format!("Gerber_{}{}", side, kind)
+    };
+    Some(ContentGuess {
+        logical_name,
+        score,
+        evidence,
+    })
+}
@@ -0,0 +1,466 @@
+/* src/compare.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::cancel::{CHECK_INTERVAL, Cancellation, Cancelled, NeverCancel};
+use crate::geometry::{BoundingBox, Point, flatten_arc};
+use crate::gerber::{Aperture, Command, GerberLayer, Interpolation, Polarity};
+use std::collections::BTreeMap;
+
+// Upper bound on the raster size, so huge panels cannot exhaust memory.
+const MAX_CELLS_PER_SIDE: usize = 4096;
+
+/// Options for [`layers_with_options`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareOptions {
+    /// Raster cell size in mm; raised automatically for very large layers
+    pub resolution: f64,
+    /// Coordinates closer than this (mm) count as the same primitive position
+    pub tolerance: f64,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            resolution: 0.025,
+            tolerance: 0.001,
+        }
+    }
+}
+
+/// Kind of a graphics primitive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PrimitiveKind {
+    Flash,
+    Line,
+    Arc,
+    Region,
+}
+
+/// A primitive present in one layer but not the other
+#[derive(Debug, Clone, PartialEq)]
+pub struct Primitive {
+    pub kind: PrimitiveKind,
+    /// Flash position, draw end point, or first vertex of a region contour
+    pub position: Point,
+    /// Aperture shape such as "C,0.2"; `None` for regions
+    pub aperture: Option<String>,
+}
+
+/// Geometric difference between two layers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerDiff {
+    /// Dark area of each layer in mm², as rasterized
+    pub area_a: f64,
+    pub area_b: f64,
+    /// Area covered by exactly one of the two layers, in mm²
+    pub xor_area: f64,
+    /// 1.0 for identical images, falling to 0.0 when nothing overlaps
+    pub similarity: f64,
+    /// Cell size actually used for rasterization, in mm
+    pub cell_size: f64,
+    /// Primitives of `a` that have no counterpart in `b`
+    pub missing_in_b: Vec<Primitive>,
+    /// Primitives of `b` that have no counterpart in `a`
+    pub extra_in_b: Vec<Primitive>,
+}
+
+impl LayerDiff {
+    /// True when both the images and the primitive lists match.
+    pub fn is_identical(&self) -> bool {
+        self.xor_area == 0.0 && self.missing_in_b.is_empty() && self.extra_in_b.is_empty()
+    }
+}
+
+/// Compares two layers with the default options.
+pub fn layers(a: &GerberLayer, b: &GerberLayer) -> LayerDiff {
+    layers_with_options(a, b, &CompareOptions::default())
+}
+
+/// Compares two layers by rasterizing both images and matching their primitives.
+///
+/// Macro apertures are not evaluated, so their flashes only take part in the
+/// primitive comparison, not in the area figures.
+pub fn layers_with_options(
+    a: &GerberLayer,
+    b: &GerberLayer,
+    options: &CompareOptions,
+) -> LayerDiff {
+    layers_cancellable(a, b, options, &NeverCancel).expect("NeverCancel never cancels")
+}
+
+/// Same as [`layers_with_options`], but polls `cancel` every [`CHECK_INTERVAL`]
+/// commands while rasterizing and returns `Err(Cancelled)` as soon as
+/// cancellation is requested.
+pub fn layers_cancellable(
+    a: &GerberLayer,
+    b: &GerberLayer,
+    options: &CompareOptions,
+    cancel: &dyn Cancellation,
+) -> Result<LayerDiff, Cancelled> {
+    let bounds = match (a.image_bounds(), b.image_bounds()) {
+        (Some(mut x), Some(y)) => {
+            x.include_box(&y);
+            Some(x)
+        }
+        (x, y) => x.or(y),
+    };
+
+    let (area_a, area_b, xor_area, cell_size) = match bounds {
+        Some(bounds) => {
+            let mut raster_a = Raster::new(bounds, options.resolution);
+            let mut raster_b = Raster::new(bounds, options.resolution);
+            raster_a.paint_layer(a, cancel)?;
+            raster_b.paint_layer(b, cancel)?;
+            let cell_area = raster_a.cell * raster_a.cell;
+            let xor = raster_a
+                .cells
+                .iter()
+                .zip(&raster_b.cells)
+                .filter(|(x, y)| x != y)
+                .count();
+            (
+                raster_a.dark_count() as f64 * cell_area,
+                raster_b.dark_count() as f64 * cell_area,
+                xor as f64 * cell_area,
+                raster_a.cell,
+            )
+        }
+        None => (0.0, 0.0, 0.0, options.resolution),
+    };
+
+    // XOR over union: the union is XOR plus the intersection
+    let union = (area_a + area_b + xor_area) / 2.0;
+    let similarity = if union > 0.0 {
+        1.0 - xor_area / union
+    } else {
+        1.0
+    };
+
+    let primitives_a = collect_primitives(a);
+    let primitives_b = collect_primitives(b);
+    let missing_in_b = unmatched(&primitives_a, &primitives_b, options.tolerance);
+    let extra_in_b = unmatched(&primitives_b, &primitives_a, options.tolerance);
+
+    Ok(LayerDiff {
+        area_a,
+        area_b,
+        xor_area,
+        similarity,
+        cell_size,
+        missing_in_b,
+        extra_in_b,
+    })
+}
+
+/// Describes an aperture shape so equal apertures with different D-codes match.
+fn aperture_shape(aperture: &Aperture) -> String {
+    let parameters: Vec<String> = aperture
+        .parameters
+        .iter()
+        .map(|p| format!("{:.4}", p))
+        .collect();
+    if parameters.is_empty() {
+        aperture.template.clone()
+    } else {
+        format!("{},{}", aperture.template, parameters.join("X"))
+    }
+}
+
+fn collect_primitives(layer: &GerberLayer) -> Vec<Primitive> {
+    let mut primitives = Vec::new();
+    let mut aperture: Option<String> = None;
+    let mut in_region = false;
+    let mut contour_open = false;
+
+    for command in &layer.commands {
+        match command {
+            Command::SelectAperture(code) => {
+                aperture = layer.apertures.get(code).map(aperture_shape);
+            }
+            Command::Flash(p) => primitives.push(Primitive {
+                kind: PrimitiveKind::Flash,
+                position: *p,
+                aperture: aperture.clone(),
+            }),
+            Command::Move(_) if in_region => contour_open = false,
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                ..
+            } => {
+                if in_region {
+                    if !contour_open {
+                        primitives.push(Primitive {
+                            kind: PrimitiveKind::Region,
+                            position: *from,
+                            aperture: None,
+                        });
+                        contour_open = true;
+                    }
+                } else {
+                    let kind = if *interpolation == Interpolation::Linear {
+                        PrimitiveKind::Line
+                    } else {
+                        PrimitiveKind::Arc
+                    };
+                    primitives.push(Primitive {
+                        kind,
+                        position: *to,
+                        aperture: aperture.clone(),
+                    });
+                }
+            }
+            Command::RegionStart => {
+                in_region = true;
+                contour_open = false;
+            }
+            Command::RegionEnd => in_region = false,
+            _ => {}
+        }
+    }
+    primitives
+}
+
+/// Returns the primitives of `from` left over after matching them one-to-one against `against`.
+fn unmatched(from: &[Primitive], against: &[Primitive], tolerance: f64) -> Vec<Primitive> {
+    let quantum = tolerance.max(1e-9);
+    let key = |p: &Primitive| {
+        (
+            p.kind,
+            (p.position.x / quantum).round() as i64,
+            (p.position.y / quantum).round() as i64,
+            p.aperture.clone(),
+        )
+    };
+
+    let mut available: BTreeMap<_, usize> = BTreeMap::new();
+    for p in against {
+        *available.entry(key(p)).or_default() += 1;
+    }
+
+    from.iter()
+        .filter(|p| match available.get_mut(&key(p)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// A boolean bitmap over a fixed area; a cell is dark when its center is covered.
+struct Raster {
+    origin: Point,
+    cell: f64,
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl Raster {
+    fn new(bounds: BoundingBox, resolution: f64) -> Self {
+        let bounds = bounds.expanded(resolution);
+        let longest = bounds.width().max(bounds.height());
+        let cell = resolution.max(longest / MAX_CELLS_PER_SIDE as f64);
+        let width = ((bounds.width() / cell).ceil() as usize).max(1);
+        let height = ((bounds.height() / cell).ceil() as usize).max(1);
+        Raster {
+            origin: Point::new(bounds.min_x, bounds.min_y),
+            cell,
+            width,
+            height,
+            cells: vec![false; width * height],
+        }
+    }
+
+    fn dark_count(&self) -> usize {
+        self.cells.iter().filter(|c| **c).count()
+    }
+
+    /// Sets every cell within `area` whose center satisfies `covered`.
+    fn fill(&mut self, area: BoundingBox, dark: bool, covered: impl Fn(Point) -> bool) {
+        let to_index = |v: f64, origin: f64, limit: usize| {
+            (((v - origin) / self.cell).floor().max(0.0) as usize).min(limit)
+        };
+        let x0 = to_index(area.min_x, self.origin.x, self.width);
+        let x1 = to_index(area.max_x, self.origin.x, self.width - 1);
+        let y0 = to_index(area.min_y, self.origin.y, self.height);
+        let y1 = to_index(area.max_y, self.origin.y, self.height - 1);
+
+        for y in y0..=y1 {
+            let cy = self.origin.y + (y as f64 + 0.5) * self.cell;
+            for x in x0..=x1 {
+                let cx = self.origin.x + (x as f64 + 0.5) * self.cell;
+                if covered(Point::new(cx, cy)) {
+                    self.cells[y * self.width + x] = dark;
+                }
+            }
+        }
+    }
+
+    fn flash(&mut self, aperture: &Aperture, at: Point, dark: bool) {
+        let p = |i: usize| aperture.parameters.get(i).copied().unwrap_or(0.0);
+        let (hx, hy) = aperture.half_extents();
+        let area = BoundingBox {
+            min_x: at.x - hx,
+            min_y: at.y - hy,
+            max_x: at.x + hx,
+            max_y: at.y + hy,
+        };
+        match aperture.template.as_str() {
+            // Polygons are approximated by their circumscribed circle
+            "C" | "P" => self.fill(area, dark, |c| c.distance(&at) <= hx),
+            "R" => self.fill(area, dark, |_| true),
+            "O" => {
+                let radius = hx.min(hy);
+                let (dx, dy) = (hx - radius, hy - radius);
+                let a = Point::new(at.x - dx, at.y - dy);
+                let b = Point::new(at.x + dx, at.y + dy);
+                self.fill(area, dark, |c| segment_distance(c, a, b) <= radius);
+            }
+            _ => {}
+        }
+        // Hole of C/R/O apertures
+        let hole = match aperture.template.as_str() {
+            "C" => p(1),
+            "R" | "O" => p(2),
+            _ => 0.0,
+        };
+        if hole > 0.0 {
+            let r = hole / 2.0;
+            let area = BoundingBox::from_point(at).expanded(r);
+            self.fill(area, !dark, |c| c.distance(&at) <= r);
+        }
+    }
+
+    fn stroke(&mut self, aperture: &Aperture, from: Point, to: Point, dark: bool) {
+        if aperture.template == "C" {
+            let radius = aperture.parameters.first().copied().unwrap_or(0.0) / 2.0;
+            let mut area = BoundingBox::from_point(from);
+            area.include_point(to);
+            self.fill(area.expanded(radius), dark, |c| {
+                segment_distance(c, from, to) <= radius
+            });
+        } else {
+            // Other apertures are stamped along the path
+            let steps = ((from.distance(&to) / (self.cell / 2.0)).ceil() as usize).max(1);
+            for i in 0..=steps {
+                let t = i as f64 / steps as f64;
+                let at = Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t);
+                self.flash(aperture, at, dark);
+            }
+        }
+    }
+
+    fn fill_contour(&mut self, contour: &[Point], dark: bool) {
+        if contour.len() < 3 {
+            return;
+        }
+        let mut area = BoundingBox::from_point(contour[0]);
+        for p in contour {
+            area.include_point(*p);
+        }
+        self.fill(area, dark, |c| point_in_polygon(c, contour));
+    }
+
+    fn paint_layer(
+        &mut self,
+        layer: &GerberLayer,
+        cancel: &dyn Cancellation,
+    ) -> Result<(), Cancelled> {
+        let tolerance = self.cell / 4.0;
+        let mut aperture: Option<&Aperture> = None;
+        let mut dark = true;
+        let mut in_region = false;
+        let mut contour: Vec<Point> = Vec::new();
+
+        for (index, command) in layer.commands.iter().enumerate() {
+            if index % CHECK_INTERVAL == 0 {
+                cancel.check()?;
+            }
+            match command {
+                Command::SelectAperture(code) => aperture = layer.apertures.get(code),
+                Command::SetPolarity(polarity) => dark = *polarity == Polarity::Dark,
+                Command::Flash(p) => {
+                    if let Some(aperture) = aperture {
+                        self.flash(aperture, *p, dark);
+                    }
+                }
+                Command::Move(_) if in_region => {
+                    self.fill_contour(&contour, dark);
+                    contour.clear();
+                }
+                Command::Draw {
+                    from,
+                    to,
+                    interpolation,
+                    center,
+                } => {
+                    let path = match (interpolation, center) {
+                        (Interpolation::Linear, _) | (_, None) => vec![*from, *to],
+                        (kind, Some(c)) => flatten_arc(
+                            *from,
+                            *to,
+                            *c,
+                            *kind == Interpolation::Clockwise,
+                            tolerance,
+                        ),
+                    };
+                    if in_region {
+                        if contour.is_empty() {
+                            contour.push(*from);
+                        }
+                        contour.extend_from_slice(&path[1..]);
+                    } else if let Some(aperture) = aperture {
+                        for pair in path.windows(2) {
+                            self.stroke(aperture, pair[0], pair[1], dark);
+                        }
+                    }
+                }
+                Command::RegionStart => {
+                    in_region = true;
+                    contour.clear();
+                }
+                Command::RegionEnd => {
+                    self.fill_contour(&contour, dark);
+                    contour.clear();
+                    in_region = false;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Distance from `p` to the segment `a`-`b`.
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_sq = dx * dx + dy * dy;
+    if length_sq == 0.0 {
+        return p.distance(&a);
+    }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_sq).clamp(0.0, 1.0);
+    p.distance(&Point::new(a.x + t * dx, a.y + t * dy))
+}
+
+/// Even-odd point-in-polygon test.
+fn point_in_polygon(p: Point, polygon: &[Point]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
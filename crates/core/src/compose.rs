@@ -0,0 +1,252 @@
+/* src/compose.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::Point;
+use crate::gerber::{self, Aperture, Command, GerberLayer, Polarity};
+use crate::writer::{self, WriterOptions};
+use std::collections::BTreeMap;
+
+// D-codes below 10 are reserved
+const FIRST_APERTURE_CODE: u32 = 10;
+
+/// Merges several Gerber layers into one image, later layers drawn on top.
+///
+/// Apertures of later layers are renumbered after those of the first, and
+/// each layer starts in dark polarity as it did on its own. File attributes
+/// come from the first layer. Returns `None` for an empty slice or when a
+/// layer uses aperture macros, whose bodies cannot be rewritten safely.
+pub fn merge_layers(contents: &[&str]) -> Option<String> {
+    let mut layers = contents.iter().map(|c| gerber::parse_gerber(c));
+    let mut merged: GerberLayer = layers.next()?;
+    if !merged.macros.is_empty() {
+        return None;
+    }
+
+    for layer in layers {
+        if !layer.macros.is_empty() {
+            return None;
+        }
+        let first_code = merged
+            .apertures
+            .keys()
+            .max()
+            .map_or(FIRST_APERTURE_CODE, |code| code + 1);
+        let mut codes: BTreeMap<u32, u32> = BTreeMap::new();
+        for ((code, mut aperture), new_code) in layer.apertures.into_iter().zip(first_code..) {
+            aperture.code = new_code;
+            codes.insert(code, new_code);
+            merged.apertures.insert(new_code, aperture);
+        }
+
+        merged.commands.push(Command::SetPolarity(Polarity::Dark));
+        merged
+            .commands
+            .extend(layer.commands.into_iter().map(|command| match command {
+                Command::SelectAperture(code) => {
+                    Command::SelectAperture(codes.get(&code).copied().unwrap_or(code))
+                }
+                other => other,
+            }));
+    }
+    merged.has_end_marker = true;
+    Some(writer::write_gerber(&merged, &WriterOptions::default()))
+}
+
+/// One graphical object of a layer as seen by a [`split_layer`] predicate
+#[derive(Debug, Clone, Copy)]
+pub struct SplitObject<'a> {
+    /// The aperture a flash or draw uses; `None` for regions
+    pub aperture: Option<&'a Aperture>,
+    /// Object attributes (%TO) in effect, as written
+    pub attributes: &'a [String],
+    pub polarity: Polarity,
+}
+
+impl SplitObject<'_> {
+    /// The `.AperFunction` value of the aperture, e.g. "Profile" or "Conductor"
+    pub fn aperture_function(&self) -> Option<&str> {
+        self.aperture?.attributes.iter().find_map(|a| {
+            a.strip_prefix("TA.AperFunction,")
+                .map(|v| v.split(',').next().unwrap_or(v))
+        })
+    }
+}
+
+// The graphics state of one output layer, so that objects moved into it are
+// drawn with the aperture, polarity and attributes they had in the source.
+struct Bucket {
+    commands: Vec<Command>,
+    aperture: Option<u32>,
+    polarity: Polarity,
+    attributes: Vec<String>,
+    point: Option<Point>,
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Bucket {
+            commands: Vec::new(),
+            aperture: None,
+            polarity: Polarity::Dark,
+            attributes: Vec::new(),
+            point: None,
+        }
+    }
+
+    fn prepare(&mut self, aperture: Option<u32>, polarity: Polarity, attributes: &[String]) {
+        if self.polarity != polarity {
+            self.commands.push(Command::SetPolarity(polarity));
+            self.polarity = polarity;
+        }
+        if self.attributes != attributes {
+            if !self.attributes.is_empty() {
+                self.commands.push(Command::Attribute("TD".to_string()));
+            }
+            self.commands
+                .extend(attributes.iter().cloned().map(Command::Attribute));
+            self.attributes = attributes.to_vec();
+        }
+        if let Some(code) = aperture
+            && self.aperture != Some(code)
+        {
+            self.commands.push(Command::SelectAperture(code));
+            self.aperture = Some(code);
+        }
+    }
+
+    // Adds a flash, move or draw, moving to the start of a draw when the
+    // bucket's current point is elsewhere.
+    fn push(&mut self, command: &Command) {
+        match command {
+            Command::Flash(p) | Command::Move(p) => self.point = Some(*p),
+            Command::Draw { from, to, .. } => {
+                if self.point != Some(*from) {
+                    self.commands.push(Command::Move(*from));
+                }
+                self.point = Some(*to);
+            }
+            _ => {}
+        }
+        self.commands.push(command.clone());
+    }
+
+    fn into_layer(self, source: &GerberLayer) -> GerberLayer {
+        let used: Vec<u32> = self
+            .commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::SelectAperture(code) => Some(*code),
+                _ => None,
+            })
+            .collect();
+        GerberLayer {
+            apertures: source
+                .apertures
+                .iter()
+                .filter(|(code, _)| used.contains(code))
+                .map(|(code, a)| (*code, a.clone()))
+                .collect(),
+            commands: self.commands,
+            has_end_marker: true,
+            ..source.clone()
+        }
+    }
+}
+
+/// Splits a layer in two: the objects `predicate` accepts, and the rest.
+///
+/// Flashes and draws are tested one by one, regions as a whole. Each output
+/// keeps the format, file attributes and macros of the source and only the
+/// apertures it uses, e.g. to pull the board profile out of a mechanical
+/// layer that also holds dimensions:
+///
+/// ```ignore
+/// let (outline, annotations) =
+///     compose::split_layer(content, |o| o.aperture_function() == Some("Profile"));
+/// ```
+pub fn split_layer<F>(content: &str, predicate: F) -> (String, String)
+where
+    F: Fn(&SplitObject) -> bool,
+{
+    let layer = gerber::parse_gerber(content);
+    let mut matched = Bucket::new();
+    let mut rest = Bucket::new();
+
+    let mut aperture: Option<u32> = None;
+    let mut polarity = Polarity::Dark;
+    let mut attributes: Vec<String> = Vec::new();
+    let mut region: Option<Vec<Command>> = None;
+
+    for command in &layer.commands {
+        if let Some(contour) = region.as_mut() {
+            match command {
+                Command::RegionEnd => {
+                    let object = SplitObject {
+                        aperture: None,
+                        attributes: &attributes,
+                        polarity,
+                    };
+                    let bucket = if predicate(&object) {
+                        &mut matched
+                    } else {
+                        &mut rest
+                    };
+                    bucket.prepare(None, polarity, &attributes);
+                    bucket.commands.push(Command::RegionStart);
+                    for c in contour.iter() {
+                        bucket.push(c);
+                    }
+                    bucket.commands.push(Command::RegionEnd);
+                    region = None;
+                }
+                Command::Move(_) | Command::Draw { .. } => contour.push(command.clone()),
+                _ => {}
+            }
+            continue;
+        }
+        match command {
+            Command::SelectAperture(code) => aperture = Some(*code),
+            Command::SetPolarity(p) => polarity = *p,
+            Command::Attribute(attribute) => {
+                if let Some(name) = attribute.strip_prefix("TD") {
+                    if name.is_empty() {
+                        attributes.clear();
+                    } else {
+                        attributes.retain(|a| a[2..].split(',').next() != Some(name));
+                    }
+                } else {
+                    let name = attribute[2..].split(',').next();
+                    attributes.retain(|a| a[2..].split(',').next() != name);
+                    attributes.push(attribute.clone());
+                }
+            }
+            Command::RegionStart => region = Some(Vec::new()),
+            // Each output moves to the start of its own draws
+            Command::Flash(_) | Command::Draw { .. } => {
+                let object = SplitObject {
+                    aperture: aperture.and_then(|code| layer.apertures.get(&code)),
+                    attributes: &attributes,
+                    polarity,
+                };
+                let bucket = if predicate(&object) {
+                    &mut matched
+                } else {
+                    &mut rest
+                };
+                bucket.prepare(aperture, polarity, &attributes);
+                bucket.push(command);
+            }
+            _ => {}
+        }
+    }
+
+    let options = WriterOptions::default();
+    (
+        writer::write_gerber(&matched.into_layer(&layer), &options),
+        writer::write_gerber(&rest.into_layer(&layer), &options),
+    )
+}
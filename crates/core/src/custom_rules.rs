@@ -0,0 +1,296 @@
+/* src/custom_rules.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::json::{self, JsonValue};
+use crate::rename::{self, Confidence, EdaType, LogicalLayer, MatchSource, RenameResult};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Logical names besides the layers that rules may map to
+const EXTRA_LOGICAL_NAMES: &[&str] = &[
+    "Drill_Source",
+    "Drill_Report",
+    "Gerber_Layer_Drawing_Parameters",
+    "Gerber_DocumentLayer",
+];
+
+/// Error produced when a rule description is invalid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleError {
+    pub message: String,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+fn error(message: String) -> RuleError {
+    RuleError { message }
+}
+
+/// A rename rule supplied by the user, e.g. for a custom Altium OutputJob naming
+#[derive(Debug, Clone)]
+pub struct CustomRule {
+    /// Logical name, e.g. "Gerber_TopLayer"
+    pub logical_name: String,
+    pub pattern: Regex,
+    /// Rules above 0 are tried before the built-in rules and override them;
+    /// rules at 0 or below only place files the built-in rules left alone.
+    /// Higher priorities are tried first.
+    pub priority: i32,
+    /// The software the rule is for; `None` applies it to all
+    pub eda_type: Option<EdaType>,
+}
+
+impl PartialEq for CustomRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.logical_name == other.logical_name
+            && self.pattern.as_str() == other.pattern.as_str()
+            && self.priority == other.priority
+            && self.eda_type == other.eda_type
+    }
+}
+
+impl CustomRule {
+    /// Checks the logical name and compiles the pattern.
+    pub fn new(
+        logical_name: &str,
+        pattern: &str,
+        priority: i32,
+        eda_type: Option<EdaType>,
+    ) -> Result<CustomRule, RuleError> {
+        if LogicalLayer::from_logical_name(logical_name).is_none()
+            && !EXTRA_LOGICAL_NAMES.contains(&logical_name)
+        {
+            return Err(error(format!("Unknown logical name '{}'", logical_name)));
+        }
+        let pattern = Regex::new(pattern)
+            .map_err(|e| error(format!("Invalid pattern for '{}': {}", logical_name, e)))?;
+        Ok(CustomRule {
+            logical_name: logical_name.to_string(),
+            pattern,
+            priority,
+            eda_type,
+        })
+    }
+}
+
+/// User rules merged into the built-in rename rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CustomRules {
+    /// Sorted by priority, highest first
+    rules: Vec<CustomRule>,
+}
+
+impl CustomRules {
+    pub fn new(rules: Vec<CustomRule>) -> CustomRules {
+        let mut rules = rules;
+        // Stable, so rules of equal priority keep their order
+        rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
+        CustomRules { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn rules(&self) -> &[CustomRule] {
+        &self.rules
+    }
+
+    /// Adds the rules of `other`, keeping the priority order.
+    pub fn extend(&mut self, other: CustomRules) {
+        let mut rules = std::mem::take(&mut self.rules);
+        rules.extend(other.rules);
+        *self = CustomRules::new(rules);
+    }
+
+    /// Parses rules from JSON, either an array of rules or an object with a
+    /// `rules` array. Each rule has `name`, `pattern`, and optionally
+    /// `priority` (default 1) and `eda` (e.g. "ad").
+    pub fn from_json(input: &str) -> Result<CustomRules, RuleError> {
+        let value = json::parse_json(input).map_err(|e| error(e.to_string()))?;
+        let items = value
+            .as_array()
+            .or_else(|| value.get("rules").and_then(JsonValue::as_array))
+            .ok_or_else(|| error("Expected an array of rules".to_string()))?;
+        let mut rules = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let field = |key: &str| item.get(key).and_then(JsonValue::as_str);
+            let fields = RuleFields {
+                name: field("name").or_else(|| field("logical_name")),
+                pattern: field("pattern"),
+                priority: item.get("priority").and_then(JsonValue::as_f64),
+                eda: field("eda"),
+            };
+            rules.push(fields.build(index)?);
+        }
+        Ok(CustomRules::new(rules))
+    }
+
+    /// Parses rules from TOML, as `[[rule]]` tables with the same keys as
+    /// [`CustomRules::from_json`]. Only strings, integers and comments are read:
+    ///
+    /// ```toml
+    /// [[rule]]
+    /// name = "Gerber_TopLayer"
+    /// pattern = '(?i)-top-copper\.gbr$'
+    /// priority = 10
+    /// ```
+    pub fn from_toml(input: &str) -> Result<CustomRules, RuleError> {
+        let mut tables: Vec<BTreeMap<String, TomlValue>> = Vec::new();
+        for (number, line) in input.lines().enumerate() {
+            let line = strip_toml_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "[[rule]]" || line == "[[rules]]" {
+                tables.push(BTreeMap::new());
+                continue;
+            }
+            let line_error = |what: &str| error(format!("{} on line {}", what, number + 1));
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| line_error("Expected key = value"))?;
+            let table = tables
+                .last_mut()
+                .ok_or_else(|| line_error("Key outside a [[rule]] table"))?;
+            let value =
+                parse_toml_value(value.trim()).ok_or_else(|| line_error("Invalid value"))?;
+            table.insert(key.trim().to_string(), value);
+        }
+
+        let mut rules = Vec::new();
+        for (index, table) in tables.iter().enumerate() {
+            let field = |key: &str| match table.get(key) {
+                Some(TomlValue::String(s)) => Some(s.as_str()),
+                _ => None,
+            };
+            let fields = RuleFields {
+                name: field("name").or_else(|| field("logical_name")),
+                pattern: field("pattern"),
+                priority: match table.get("priority") {
+                    Some(TomlValue::Integer(n)) => Some(*n as f64),
+                    _ => None,
+                },
+                eda: field("eda"),
+            };
+            rules.push(fields.build(index)?);
+        }
+        Ok(CustomRules::new(rules))
+    }
+
+    /// Like [`rename::map_filenames_detailed`], with these rules merged into the built-in ones.
+    pub fn map_filenames_detailed(&self, files: &[String], eda_type: EdaType) -> RenameResult {
+        let mut result = rename::map_filenames_detailed(files, eda_type);
+        let applicable: Vec<&CustomRule> = self
+            .rules
+            .iter()
+            .filter(|r| r.eda_type.is_none_or(|eda| eda == eda_type))
+            .collect();
+        for entry in result.entries.values_mut() {
+            let unmatched = entry.source == MatchSource::Unmatched;
+            let rule = applicable
+                .iter()
+                .filter(|r| r.priority > 0 || unmatched)
+                .find(|r| r.pattern.is_match(&entry.original));
+            if let Some(rule) = rule {
+                entry.new_name = rename::get_final_filename(&rule.logical_name);
+                entry.logical_name = Some(rule.logical_name.clone());
+                entry.source = MatchSource::Custom {
+                    pattern: rule.pattern.as_str().to_string(),
+                    priority: rule.priority,
+                };
+                entry.confidence = Confidence::High;
+            }
+        }
+        result
+    }
+}
+
+// Fields of one rule as read from JSON or TOML
+struct RuleFields<'a> {
+    name: Option<&'a str>,
+    pattern: Option<&'a str>,
+    priority: Option<f64>,
+    eda: Option<&'a str>,
+}
+
+impl RuleFields<'_> {
+    fn build(&self, index: usize) -> Result<CustomRule, RuleError> {
+        let missing = |key: &str| error(format!("Rule {} has no {}", index + 1, key));
+        let name = self.name.ok_or_else(|| missing("name"))?;
+        let pattern = self.pattern.ok_or_else(|| missing("pattern"))?;
+        let eda_type =
+            match self.eda {
+                None | Some("" | "all") => None,
+                Some(id) => Some(EdaType::from_id(id).ok_or_else(|| {
+                    error(format!("Rule {} has an unknown eda '{}'", index + 1, id))
+                })?),
+            };
+        CustomRule::new(
+            name,
+            pattern,
+            self.priority.map_or(1, |p| p as i32),
+            eda_type,
+        )
+    }
+}
+
+enum TomlValue {
+    String(String),
+    Integer(i64),
+}
+
+// Removes a # comment that is not inside a string.
+fn strip_toml_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+fn parse_toml_value(text: &str) -> Option<TomlValue> {
+    if let Some(literal) = text.strip_prefix('\'') {
+        return Some(TomlValue::String(literal.strip_suffix('\'')?.to_string()));
+    }
+    if let Some(basic) = text.strip_prefix('"') {
+        let basic = basic.strip_suffix('"')?;
+        let mut out = String::new();
+        let mut chars = basic.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next()? {
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                '"' => out.push('"'),
+                '\\' => out.push('\\'),
+                _ => return None,
+            }
+        }
+        return Some(TomlValue::String(out));
+    }
+    text.replace('_', "").parse().ok().map(TomlValue::Integer)
+}
@@ -0,0 +1,190 @@
+/* src/dcode.rs */
+
+/* SPDX-License-Identifier: Apache-2.0 */
+/*
+ * Author HalfSweet <halfsweet@halfsweet.cn>
+ * Author Canmi <t@canmi.icu>
+ * Author CharlieYu <charlieyu4994@charlie.moe>
+ */
+
+use crate::cancel::{CHECK_INTERVAL, Cancellation, Cancelled, NeverCancel};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// This regex matches any standard D-code (Dxx*).
+static DCODE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(D\d{2,4}\*)").expect("Failed to compile D-Code regex"));
+
+/// Gerber flavor for D-code processing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GerberFlavor {
+    KiCad,
+    Altium,
+}
+
+/// What [`process_d_codes`] does with a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineAction {
+    /// The line is copied unchanged
+    Keep,
+    /// A D-code at the start of the line gets "G54" in front of it
+    Prefix,
+}
+
+/// One row of the D-code decision table. A line gets the action of the
+/// first rule of its flavor that matches it.
+#[derive(Debug, Clone, Copy)]
+pub struct DcodeRule {
+    /// Short machine-readable name, e.g. for tests and traces
+    pub id: &'static str,
+    pub description: &'static str,
+    pub matches: fn(&str) -> bool,
+    pub action: LineAction,
+}
+
+fn is_aperture_definition(line: &str) -> bool {
+    line.contains("%ADD")
+}
+
+fn is_already_prefixed(line: &str) -> bool {
+    line.contains("G54D")
+}
+
+// D01 to D03 are operations, not apertures, so they are never selected
+fn is_operation_code(line: &str) -> bool {
+    matches!(line.trim(), "D01*" | "D02*" | "D03*")
+}
+
+fn is_coordinate_operation(line: &str) -> bool {
+    ["G01", "G02", "G36", "G37"]
+        .iter()
+        .any(|code| line.starts_with(code))
+        && (line.contains('X') || line.contains('Y'))
+}
+
+fn is_aperture_select(line: &str) -> bool {
+    DCODE_REGEX.is_match(line)
+}
+
+fn any_line(_: &str) -> bool {
+    true
+}
+
+const ALREADY_PREFIXED: DcodeRule = DcodeRule {
+    id: "already_prefixed",
+    description: "the line already selects its aperture with G54",
+    matches: is_already_prefixed,
+    action: LineAction::Keep,
+};
+
+const OPERATION_CODE: DcodeRule = DcodeRule {
+    id: "operation_code",
+    description: "a bare D01, D02 or D03 is an operation, not an aperture",
+    matches: is_operation_code,
+    action: LineAction::Keep,
+};
+
+const APERTURE_SELECT: DcodeRule = DcodeRule {
+    id: "aperture_select",
+    description: "a D-code at the start of the line selects an aperture",
+    matches: is_aperture_select,
+    action: LineAction::Prefix,
+};
+
+const OTHER: DcodeRule = DcodeRule {
+    id: "other",
+    description: "no D-code at the start of the line",
+    matches: any_line,
+    action: LineAction::Keep,
+};
+
+/// Decision table for KiCad layers, tried in order
+pub const KICAD_RULES: &[DcodeRule] = &[
+    DcodeRule {
+        id: "aperture_definition",
+        description: "%ADD defines an aperture rather than selecting it",
+        matches: is_aperture_definition,
+        action: LineAction::Keep,
+    },
+    ALREADY_PREFIXED,
+    OPERATION_CODE,
+    APERTURE_SELECT,
+    OTHER,
+];
+
+/// Decision table for Altium layers, tried in order
+pub const ALTIUM_RULES: &[DcodeRule] = &[
+    ALREADY_PREFIXED,
+    DcodeRule {
+        id: "coordinate_operation",
+        description: "a G01/G02/G36/G37 line with coordinates draws with the current aperture",
+        matches: is_coordinate_operation,
+        action: LineAction::Keep,
+    },
+    OPERATION_CODE,
+    APERTURE_SELECT,
+    OTHER,
+];
+
+impl GerberFlavor {
+    /// The decision table [`process_d_codes`] follows for this flavor
+    pub fn rules(&self) -> &'static [DcodeRule] {
+        match self {
+            GerberFlavor::KiCad => KICAD_RULES,
+            GerberFlavor::Altium => ALTIUM_RULES,
+        }
+    }
+}
+
+/// The rule that decides what happens to `line`.
+pub fn decide(line: &str, flavor: GerberFlavor) -> &'static DcodeRule {
+    flavor
+        .rules()
+        .iter()
+        .find(|rule| (rule.matches)(line))
+        .expect("Every table ends with a rule matching any line")
+}
+
+/// Process Gerber data to prepend "G54" to D-codes according to the CAD flavor.
+///
+/// Each line is handled by the first matching rule of the flavor's decision
+/// table, see [`GerberFlavor::rules`]. Only a D-code at the start of a line,
+/// such as "D10*", is prefixed.
+///
+/// # Arguments
+/// * `gerber_data` - Raw Gerber file content
+/// * `flavor` - KiCad or Altium
+///
+/// # Returns
+/// Processed Gerber content with appropriate D-codes prefixed with "G54"
+pub fn process_d_codes(gerber_data: String, flavor: GerberFlavor) -> String {
+    process_d_codes_cancellable(gerber_data, flavor, &NeverCancel)
+        .expect("NeverCancel never cancels")
+}
+
+/// Same as [`process_d_codes`], but polls `cancel` every [`CHECK_INTERVAL`] lines
+/// and returns `Err(Cancelled)` as soon as cancellation is requested.
+pub fn process_d_codes_cancellable(
+    gerber_data: String,
+    flavor: GerberFlavor,
+    cancel: &dyn Cancellation,
+) -> Result<String, Cancelled> {
+    let input_lines: Vec<&str> = gerber_data.split('\n').collect();
+    let mut processed_lines = Vec::with_capacity(input_lines.len());
+
+    for (index, line) in input_lines.into_iter().enumerate() {
+        if index % CHECK_INTERVAL == 0 {
+            cancel.check()?;
+        }
+
+        match decide(line, flavor).action {
+            LineAction::Keep => processed_lines.push(line.to_string()),
+            LineAction::Prefix => {
+                let modified_line = DCODE_REGEX.replace_all(line, "G54$1");
+                processed_lines.push(modified_line.to_string());
+            }
+        }
+    }
+
+    Ok(processed_lines.join("\n"))
+}
@@ -0,0 +1,112 @@
+/* src/diagnostic.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::json::JsonValue;
+use crate::suggest::Suggestion;
+use std::fmt;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message about the conversion, optionally tied to one input file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<String>,
+    pub message: String,
+    /// Stable identifier for diagnostics the frontend handles specially,
+    /// such as [`ALREADY_CONVERTED`]
+    pub code: Option<&'static str>,
+    /// One-click fixes the frontend can offer for this diagnostic
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// Code of the warning that an input which is already a converted JLC
+/// archive was converted again, for the frontend to confirm with the user
+pub const ALREADY_CONVERTED: &str = "already_converted";
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            file: None,
+            message: message.into(),
+            code: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(Severity::Info, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Attaches the file the diagnostic refers to.
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<Suggestion>) -> Self {
+        self.suggestions = suggestions;
+        self
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        JsonValue::Object(vec![
+            ("severity".to_string(), severity.into()),
+            (
+                "file".to_string(),
+                self.file
+                    .as_deref()
+                    .map_or(JsonValue::Null, JsonValue::from),
+            ),
+            ("message".to_string(), self.message.as_str().into()),
+            ("code".to_string(), self.code.into()),
+            (
+                "suggestions".to_string(),
+                JsonValue::Array(self.suggestions.iter().map(Suggestion::to_json).collect()),
+            ),
+        ])
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.file {
+            Some(file) => write!(f, "{}: {}: {}", severity, file, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
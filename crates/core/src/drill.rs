@@ -0,0 +1,2921 @@
+/* src/drill.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::cancel::{Cancellation, Cancelled, NeverCancel};
+use crate::geometry::{BoundingBox, Point, extend_bounds, flatten_arc};
+use crate::header::get_drill_header;
+use crate::json::JsonValue;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+/// Hole plating type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoleType {
+    Plated,    // PTH - Plated Through Hole
+    NonPlated, // NPTH - Non-Plated Through Hole
+}
+
+/// Unit type for drill files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrillUnit {
+    Inch,
+    Metric,
+}
+
+impl DrillUnit {
+    /// Millimeters per unit
+    pub fn to_mm(self) -> f64 {
+        match self {
+            DrillUnit::Inch => INCH_TO_MM,
+            DrillUnit::Metric => 1.0,
+        }
+    }
+}
+
+/// Drill command types - coordinates stored in mm
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrillCommand {
+    /// Simple hole at (x, y) - coordinates in mm
+    Hole { x: f64, y: f64 },
+    /// Routed path from M15 to M16, as the points the tool passes through
+    /// Arcs are split into chords; each segment becomes a G85 slot for JLC
+    /// Coordinates in mm
+    Route { points: Vec<Point> },
+}
+
+/// A tool definition with its associated drill commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillOperation {
+    pub tool: u32,           // Tool number in the source file
+    pub diameter: f64,       // Tool diameter in mm
+    pub hole_type: HoleType, // PTH or NPTH
+    pub commands: Vec<DrillCommand>,
+    /// Copper layers joined, for blind and buried vias; `None` for through holes
+    pub span: Option<LayerSpan>,
+}
+
+/// Parsed drill file representation
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillFile {
+    pub operations: Vec<DrillOperation>,
+    pub warnings: Vec<String>, // Non-fatal issues found while parsing
+}
+
+impl DrillFile {
+    /// Serializes the parsed file: every tool with its holes and routes, in
+    /// mm, and the parse warnings.
+    pub fn to_json(&self) -> JsonValue {
+        let operations = self
+            .operations
+            .iter()
+            .map(|op| {
+                let commands = op
+                    .commands
+                    .iter()
+                    .map(|command| match command {
+                        DrillCommand::Hole { x, y } => Point::new(*x, *y).to_json(),
+                        DrillCommand::Route { points } => JsonValue::Object(vec![(
+                            "route".to_string(),
+                            JsonValue::Array(points.iter().map(Point::to_json).collect()),
+                        )]),
+                    })
+                    .collect();
+                JsonValue::Object(vec![
+                    ("tool".to_string(), op.tool.into()),
+                    ("diameter".to_string(), op.diameter.into()),
+                    (
+                        "plated".to_string(),
+                        (op.hole_type == HoleType::Plated).into(),
+                    ),
+                    ("from_layer".to_string(), op.span.map(|s| s.from).into()),
+                    ("to_layer".to_string(), op.span.map(|s| s.to).into()),
+                    ("commands".to_string(), JsonValue::Array(commands)),
+                ])
+            })
+            .collect();
+        JsonValue::Object(vec![
+            ("operations".to_string(), JsonValue::Array(operations)),
+            (
+                "warnings".to_string(),
+                JsonValue::Array(self.warnings.iter().map(|w| w.as_str().into()).collect()),
+            ),
+        ])
+    }
+
+    /// Restores a file serialized by [`DrillFile::to_json`].
+    /// Returns `None` if a required field is missing or has the wrong type.
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let command = |value: &JsonValue| match value.get("route") {
+            Some(route) => Some(DrillCommand::Route {
+                points: route
+                    .as_array()?
+                    .iter()
+                    .map(Point::from_json)
+                    .collect::<Option<_>>()?,
+            }),
+            None => Point::from_json(value).map(|p| DrillCommand::Hole { x: p.x, y: p.y }),
+        };
+        let operation = |value: &JsonValue| {
+            let layer = |key: &str| value.get(key).and_then(JsonValue::as_f64);
+            Some(DrillOperation {
+                tool: value.get("tool")?.as_f64()? as u32,
+                diameter: value.get("diameter")?.as_f64()?,
+                hole_type: if value.get("plated")?.as_bool()? {
+                    HoleType::Plated
+                } else {
+                    HoleType::NonPlated
+                },
+                commands: value
+                    .get("commands")?
+                    .as_array()?
+                    .iter()
+                    .map(command)
+                    .collect::<Option<_>>()?,
+                span: match (layer("from_layer"), layer("to_layer")) {
+                    (Some(from), Some(to)) => Some(LayerSpan::new(from as u32, to as u32)),
+                    _ => None,
+                },
+            })
+        };
+        Some(DrillFile {
+            operations: value
+                .get("operations")?
+                .as_array()?
+                .iter()
+                .map(operation)
+                .collect::<Option<_>>()?,
+            warnings: value
+                .get("warnings")?
+                .as_array()?
+                .iter()
+                .map(|w| w.as_str().map(str::to_string))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// Result of processing drill files
+#[derive(Debug)]
+pub struct DrillResult {
+    pub pth_content: Option<String>,
+    pub npth_content: Option<String>,
+    /// Blind and buried vias, one file per layer span
+    pub spans: Vec<(LayerSpan, String)>,
+    pub warnings: Vec<String>,
+    /// (filename, description) of every drill file whose unit was corrected
+    pub unit_fixes: Vec<(String, String)>,
+    /// The output tool each input tool went to
+    pub tools: Vec<ToolAssignment>,
+    /// Hole counts of the output files
+    pub report: DrillReport,
+}
+
+/// Holes drilled by one output tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolReport {
+    /// Tool number in the JLC file of its hole type or span
+    pub tool: u32,
+    pub diameter: f64, // Tool diameter in mm
+    pub hole_type: HoleType,
+    /// Copper layers joined, for blind and buried vias; `None` for through holes
+    pub span: Option<LayerSpan>,
+    pub holes: usize,
+    /// Routed slots, one per G85 segment as written
+    pub slots: usize,
+}
+
+/// Holes and slots of one hole type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HoleCounts {
+    pub holes: usize,
+    pub slots: usize,
+}
+
+/// Statistics of the drill output, e.g. for a summary before upload
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrillReport {
+    /// Plated tools first, then non-plated, each with through holes before
+    /// blind and buried vias and by tool number
+    pub tools: Vec<ToolReport>,
+    /// Plated holes, including blind and buried vias
+    pub pth: HoleCounts,
+    pub npth: HoleCounts,
+    /// Smallest and largest tool diameter, in mm
+    pub smallest_drill: Option<f64>,
+    pub largest_drill: Option<f64>,
+    /// Holes removed as duplicates of another, see
+    /// [`DrillProcessOptions::dedupe_epsilon`]
+    pub duplicates_removed: usize,
+}
+
+impl DrillReport {
+    /// Counts the holes of merged drill files, whose tools are numbered as
+    /// in the output.
+    pub fn from_files<'a>(files: impl IntoIterator<Item = &'a DrillFile>) -> Self {
+        let mut report = DrillReport::default();
+        for op in files.into_iter().flat_map(|file| &file.operations) {
+            let mut tool = ToolReport {
+                tool: op.tool,
+                diameter: op.diameter,
+                hole_type: op.hole_type,
+                span: op.span,
+                holes: 0,
+                slots: 0,
+            };
+            for command in &op.commands {
+                match command {
+                    DrillCommand::Hole { .. } => tool.holes += 1,
+                    DrillCommand::Route { points } => tool.slots += points.len().saturating_sub(1),
+                }
+            }
+            let counts = match op.hole_type {
+                HoleType::Plated => &mut report.pth,
+                HoleType::NonPlated => &mut report.npth,
+            };
+            counts.holes += tool.holes;
+            counts.slots += tool.slots;
+            report.smallest_drill = Some(
+                report
+                    .smallest_drill
+                    .map_or(op.diameter, |d| d.min(op.diameter)),
+            );
+            report.largest_drill = Some(
+                report
+                    .largest_drill
+                    .map_or(op.diameter, |d| d.max(op.diameter)),
+            );
+            report.tools.push(tool);
+        }
+        report.tools.sort_by_key(|t| {
+            (
+                t.hole_type == HoleType::NonPlated,
+                t.span.map(|s| (s.from, s.to)),
+                t.tool,
+            )
+        });
+        report
+    }
+
+    /// Drilled holes of every hole type, without slots
+    pub fn total_holes(&self) -> usize {
+        self.pth.holes + self.npth.holes
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.pth.slots + self.npth.slots
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let counts = |counts: &HoleCounts| {
+            JsonValue::Object(vec![
+                ("holes".to_string(), counts.holes.into()),
+                ("slots".to_string(), counts.slots.into()),
+            ])
+        };
+        let tools = self
+            .tools
+            .iter()
+            .map(|t| {
+                JsonValue::Object(vec![
+                    ("tool".to_string(), t.tool.into()),
+                    ("diameter".to_string(), t.diameter.into()),
+                    (
+                        "plated".to_string(),
+                        (t.hole_type == HoleType::Plated).into(),
+                    ),
+                    ("from_layer".to_string(), t.span.map(|s| s.from).into()),
+                    ("to_layer".to_string(), t.span.map(|s| s.to).into()),
+                    ("holes".to_string(), t.holes.into()),
+                    ("slots".to_string(), t.slots.into()),
+                ])
+            })
+            .collect();
+        JsonValue::Object(vec![
+            ("tools".to_string(), JsonValue::Array(tools)),
+            ("total_holes".to_string(), self.total_holes().into()),
+            ("slot_count".to_string(), self.slot_count().into()),
+            ("pth".to_string(), counts(&self.pth)),
+            ("npth".to_string(), counts(&self.npth)),
+            ("smallest_drill".to_string(), self.smallest_drill.into()),
+            ("largest_drill".to_string(), self.largest_drill.into()),
+            (
+                "duplicates_removed".to_string(),
+                self.duplicates_removed.into(),
+            ),
+        ])
+    }
+}
+
+// Regex patterns for parsing
+static AD_TOOL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^T(\d+)F\d+S\d+C([\d.]+)").expect("Invalid AD tool regex"));
+
+static KICAD_TOOL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^T(\d+)C([\d.]+)").expect("Invalid KiCad tool regex"));
+
+static COORD_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:X([\d.-]+))?(?:Y([\d.-]+))?$").expect("Invalid coord regex"));
+
+static TOOL_SELECT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^T(\d+)$").expect("Invalid tool select regex"));
+
+static ROUTE_START_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^G00(?:X([\d.-]+))?(?:Y([\d.-]+))?").expect("Invalid route start regex")
+});
+
+static ROUTE_TO_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^G01(?:X([\d.-]+))?(?:Y([\d.-]+))?").expect("Invalid route to regex")
+});
+
+// This regex matches an arc route (G02 clockwise, G03 counterclockwise) and
+// captures the direction, end point, radius and center offsets.
+static ROUTE_ARC_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^G0([23])(?:X([\d.-]+))?(?:Y([\d.-]+))?(?:A([\d.-]+))?(?:I([\d.-]+))?(?:J([\d.-]+))?",
+    )
+    .expect("Invalid route arc regex")
+});
+
+// This regex matches a repeat code, R<count> with the step between holes,
+// e.g. R5X0.254 for five more holes 0.254 apart along X.
+static REPEAT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^R(\d+)(?:X([\d.+-]+))?(?:Y([\d.+-]+))?$").expect("Invalid repeat regex")
+});
+
+static FILE_FORMAT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)FILE_FORMAT\s*=\s*(\d+)\s*:\s*(\d+)").expect("Invalid file format regex")
+});
+
+// Matches ";TYPE=PLATED" and variants like "; TYPE = NON-PLATED"
+static TYPE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)TYPE\s*=\s*(NON[_\- ]?PLATED|PLATED)").expect("Invalid type regex")
+});
+
+// Matches the plating of a drill X2 attribute, file-wide as in
+// "; #@! TF.FileFunction,NonPlated,1,2,NPTH" or per tool as in
+// "; #@! TA.AperFunction,Plated,PTH,ViaDrill", also in "%TF...*%" form
+static X2_PLATING_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bT[FA]\.(?:FileFunction|AperFunction)\s*,\s*(NonPlated|Plated)\b")
+        .expect("Invalid X2 plating regex")
+});
+
+// Matches OrCAD/Allegro tool comments, e.g.
+// "; Holesize 1. = 35.000000 Tolerance = +0.000000/-0.000000 PLATED MILS Quantity = 12"
+static HOLESIZE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^;\s*Holesize\s+(\d+)\.?\s*=\s*([\d.]+)(.*)$").expect("Invalid holesize regex")
+});
+
+// Matches the hole count at the end of a ";Holesize" comment, "Quantity = 12"
+static QUANTITY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bQuantity\s*=\s*(\d+)").expect("Invalid quantity regex"));
+
+// Matches the digit template of a unit line, e.g. "METRIC,LZ,000.000"
+static UNIT_FORMAT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:METRIC|INCH)\s*,(?:[^,]*,)?\s*(0+)\.(0+)")
+        .expect("Invalid unit format regex")
+});
+
+// Matches integer (decimal-point free) X/Y coordinates in a body line
+static RAW_COORD_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[XY]([+-]?\d+)(?:[^\d.]|$)").expect("Invalid raw coord regex"));
+
+// Matches X/Y coordinates written with a decimal point in a body line
+static DECIMAL_COORD_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[XY]([+-]?\d*\.\d+)").expect("Invalid decimal coord regex"));
+
+const INCH_TO_MM: f64 = 25.4;
+const MIL_TO_MM: f64 = 0.0254;
+
+// Drill extents may exceed the board outline by this much (mm) before they count as a mismatch
+const BOARD_MARGIN_MM: f64 = 1.0;
+// Holes of a real design spread over at least this fraction of the board size
+const MIN_BOARD_SPREAD: f64 = 0.25;
+
+// Board extents (mm) considered plausible when inferring a coordinate format
+const MIN_PLAUSIBLE_EXTENT_MM: f64 = 5.0;
+const MAX_PLAUSIBLE_EXTENT_MM: f64 = 600.0;
+const TYPICAL_EXTENT_MM: f64 = 100.0;
+
+/// Drill file extensions: Excellon (`.drl`, `.xln`, `.exc`), Eagle (`.drd`),
+/// pcb-rnd (`.cnc`), Allegro (`.tap`) and CNC (`.nc`)
+const DRILL_EXTENSIONS: &[&str] = &["drl", "drd", "xln", "exc", "nc", "cnc", "tap"];
+
+/// Altium blind and buried via files, one per drill pair from `.tx1` to `.tx9`.
+/// The rename rules match them by the same pattern.
+pub(crate) const BLIND_BURIED_DRILL_PATTERN: &str = r"(?i)\.tx[1-9]$";
+
+static BLIND_BURIED_DRILL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(BLIND_BURIED_DRILL_PATTERN).expect("Invalid blind and buried drill regex")
+});
+
+/// Detect if a file is a drill file based on filename
+pub fn is_drill_file(filename: &str) -> bool {
+    let lower = filename.to_lowercase();
+    let extension = lower.rsplit_once('.').map_or("", |(_, ext)| ext);
+    DRILL_EXTENSIONS.contains(&extension)
+        || BLIND_BURIED_DRILL_REGEX.is_match(&lower)
+        || extension == "txt" && (lower.contains("hole") || lower.contains("drill"))
+}
+
+/// Detect if content is an Excellon drill program, regardless of filename.
+///
+/// Gerber signatures (%FS, %MO, %ADD, G04 comments) rule a file out first, so a
+/// copper layer saved with a `.TXT` extension is never mistaken for a drill file.
+/// Otherwise an M48 header, an INCH/METRIC unit line, or a `T..C..` tool
+/// definition followed by coordinates identifies Excellon.
+pub fn is_drill_content(content: &str) -> bool {
+    drill_content_signal(content) == Some(true)
+}
+
+// What the content says about being a drill file: `Some(false)` for Gerber,
+// `Some(true)` for Excellon, `None` when it shows neither
+fn drill_content_signal(content: &str) -> Option<bool> {
+    let mut has_tool_definition = false;
+    let mut has_coordinate = false;
+
+    for line in content.lines().take(500) {
+        let line = line.trim();
+        let upper = line.to_uppercase();
+
+        if upper.starts_with("%FS")
+            || upper.starts_with("%MO")
+            || upper.starts_with("%ADD")
+            || upper.starts_with("%TF")
+            || upper.starts_with("G04")
+        {
+            return Some(false);
+        }
+
+        if upper == "M48" || upper.starts_with("METRIC") || upper.starts_with("INCH") {
+            return Some(true);
+        }
+
+        if AD_TOOL_REGEX.is_match(&upper) || KICAD_TOOL_REGEX.is_match(&upper) {
+            has_tool_definition = true;
+        } else if (upper.starts_with('X') || upper.starts_with('Y')) && !upper.ends_with('*') {
+            has_coordinate = true;
+        }
+    }
+
+    (has_tool_definition && has_coordinate).then_some(true)
+}
+
+/// Classify a file as a drill file, using its content as the primary signal and
+/// falling back to the filename when the content is inconclusive: empty, or
+/// neither Gerber nor recognizably Excellon.
+pub fn is_drill_file_with_content(filename: &str, content: &str) -> bool {
+    drill_content_signal(content).unwrap_or_else(|| is_drill_file(filename))
+}
+
+/// Plating implied by the file name, for dialects such as Proteus that write
+/// plated and non-plated holes to separate files without a TYPE marker.
+pub(crate) fn hole_type_from_filename(filename: &str) -> Option<HoleType> {
+    let lower = filename.to_lowercase();
+    if ["npth", "non-plated", "non plated", "nonplated", "unplated"]
+        .iter()
+        .any(|hint| lower.contains(hint))
+    {
+        Some(HoleType::NonPlated)
+    } else {
+        None
+    }
+}
+
+/// Detect if a drill file is a through-hole file (not blind/buried via)
+pub fn is_through_drill(filename: &str) -> bool {
+    // TX1, TX2, etc. are blind/buried vias
+    !BLIND_BURIED_DRILL_REGEX.is_match(filename)
+}
+
+/// Detect EDA type from drill file content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrillEdaType {
+    Altium,
+    KiCad,
+    Eagle,
+    PcbRnd,
+    /// Cadence Allegro and OrCAD, which list tools in ";Holesize" comments
+    Allegro,
+    Unknown,
+}
+
+pub(crate) fn detect_drill_eda(content: &str) -> DrillEdaType {
+    let lower = content.to_lowercase();
+    if lower.contains("kicad") {
+        DrillEdaType::KiCad
+    } else if AD_TOOL_REGEX.is_match(content) {
+        // AD uses T01F00S00C format
+        DrillEdaType::Altium
+    } else if lower.contains("altium") {
+        DrillEdaType::Altium
+    } else if lower.contains("eagle") {
+        DrillEdaType::Eagle
+    } else if lower.contains("pcb-rnd") {
+        DrillEdaType::PcbRnd
+    } else if lower.contains("allegro")
+        || content.lines().any(|l| HOLESIZE_REGEX.is_match(l.trim()))
+    {
+        DrillEdaType::Allegro
+    } else {
+        DrillEdaType::Unknown
+    }
+}
+
+/// Like [`detect_drill_eda`], falling back to the extensions only one
+/// exporter writes (`.drd`, `.cnc`, `.tap`) when the content does not tell.
+pub(crate) fn detect_drill_eda_for(filename: &str, content: &str) -> DrillEdaType {
+    match detect_drill_eda(content) {
+        DrillEdaType::Unknown => {
+            let lower = filename.to_lowercase();
+            match lower.rsplit_once('.').map_or("", |(_, ext)| ext) {
+                "drd" => DrillEdaType::Eagle,
+                "cnc" => DrillEdaType::PcbRnd,
+                "tap" => DrillEdaType::Allegro,
+                _ => DrillEdaType::Unknown,
+            }
+        }
+        eda_type => eda_type,
+    }
+}
+
+/// Whether a header switches to incremental coordinates with `ICI` or
+/// `ICI,ON`. A `G91` in the body does the same from that point on.
+fn header_is_incremental(header: &[&str]) -> bool {
+    header.iter().any(|line| {
+        let upper = line.to_uppercase();
+        upper == "ICI" || upper.replace(' ', "") == "ICI,ON"
+    })
+}
+
+// Most holes a single repeat code may add; more is taken for a corrupt file
+const MAX_REPEAT_HOLES: u32 = 10_000;
+
+/// Adds the holes of a repeat code to `op`: `count` holes, each `step` (mm)
+/// from the one before, starting from the hole at `from`. Returns the last
+/// hole, which later coordinates and repeats continue from.
+fn repeat_holes(
+    op: &mut DrillOperation,
+    from: Point,
+    count: u32,
+    step: (f64, f64),
+    warnings: &mut Vec<String>,
+) -> Point {
+    if count > MAX_REPEAT_HOLES {
+        warnings.push(format!(
+            "Repeat code for {} holes was cut to {}",
+            count, MAX_REPEAT_HOLES
+        ));
+    }
+    let mut at = from;
+    for _ in 0..count.min(MAX_REPEAT_HOLES) {
+        at = Point::new(at.x + step.0, at.y + step.1);
+        op.commands.push(DrillCommand::Hole { x: at.x, y: at.y });
+    }
+    at
+}
+
+// Chord tolerance of routed arcs, in mm
+const ROUTE_ARC_TOLERANCE_MM: f64 = 0.005;
+
+/// Splits a routed arc into the chords JLC's G85 format can express, and
+/// returns the points after `start`. The center is given by the I/J offsets
+/// from `start`, or else by the `radius` (A), which is negative for arcs over
+/// 180°. Without either the route is taken as a straight move.
+fn arc_points(
+    start: Point,
+    end: Point,
+    clockwise: bool,
+    radius: Option<f64>,
+    offset: (Option<f64>, Option<f64>),
+) -> Vec<Point> {
+    let center = match (offset, radius) {
+        ((None, None), Some(radius)) => arc_center(start, end, radius, clockwise),
+        ((None, None), None) => None,
+        ((i, j), _) => Some(Point::new(
+            start.x + i.unwrap_or(0.0),
+            start.y + j.unwrap_or(0.0),
+        )),
+    };
+    match center {
+        Some(center) => flatten_arc(start, end, center, clockwise, ROUTE_ARC_TOLERANCE_MM)
+            .into_iter()
+            .skip(1)
+            .collect(),
+        None => vec![end],
+    }
+}
+
+/// Ends the route in progress, adding it to the tool it was milled with.
+/// A route that never moved is dropped.
+fn end_route(route: Option<(u32, Vec<Point>)>, operations: &mut BTreeMap<u32, DrillOperation>) {
+    if let Some((tool, points)) = route
+        && points.len() >= 2
+        && let Some(op) = operations.get_mut(&tool)
+    {
+        op.commands.push(DrillCommand::Route { points });
+    }
+}
+
+// The center of an arc of `radius` from `start` to `end`, on the side that
+// gives the minor arc for a positive radius.
+fn arc_center(start: Point, end: Point, radius: f64, clockwise: bool) -> Option<Point> {
+    let chord = start.distance(&end);
+    let half = chord / 2.0;
+    if chord == 0.0 || radius.abs() < half {
+        return None;
+    }
+    let height = (radius * radius - half * half).sqrt();
+    // Unit normal to the left of the direction of travel
+    let (nx, ny) = (-(end.y - start.y) / chord, (end.x - start.x) / chord);
+    let side = if clockwise != (radius < 0.0) {
+        -1.0
+    } else {
+        1.0
+    };
+    Some(Point::new(
+        (start.x + end.x) / 2.0 + side * height * nx,
+        (start.y + end.y) / 2.0 + side * height * ny,
+    ))
+}
+
+// The unit of a header unit line, e.g. "INCH,TZ" or "METRIC"
+fn unit_statement(line: &str) -> Option<DrillUnit> {
+    let upper = line.to_uppercase();
+    if upper.starts_with("INCH") {
+        Some(DrillUnit::Inch)
+    } else if upper.starts_with("METRIC") {
+        Some(DrillUnit::Metric)
+    } else {
+        None
+    }
+}
+
+// The unit an M71 (metric) or M72 (inch) command switches to
+fn unit_switch(line: &str) -> Option<DrillUnit> {
+    match line {
+        "M71" => Some(DrillUnit::Metric),
+        "M72" => Some(DrillUnit::Inch),
+        _ => None,
+    }
+}
+
+/// Parse coordinate string according to FILE_FORMAT and convert to mm
+fn parse_ad_coordinate(
+    coord: &str,
+    integer_places: u32,
+    decimal_places: u32,
+    is_lz: bool,
+    unit: DrillUnit,
+) -> f64 {
+    // If already contains decimal point, parse directly
+    if coord.contains('.') {
+        let val: f64 = coord.parse().unwrap_or(0.0);
+        return if unit == DrillUnit::Inch {
+            val * INCH_TO_MM
+        } else {
+            val
+        };
+    }
+
+    let sign = if coord.starts_with('-') { -1.0 } else { 1.0 };
+    let abs_coord = coord.trim_start_matches(['+', '-']);
+
+    let val = if is_lz {
+        // LZ (Leading Zero) mode: Integer part has fixed length
+        // In Excellon, LZ usually means leading zeros are kept, trailing zeros suppressed
+        let split_pos = integer_places as usize;
+        if abs_coord.len() <= split_pos {
+            abs_coord.parse::<f64>().unwrap_or(0.0)
+        } else {
+            let int_part = &abs_coord[0..split_pos];
+            let dec_part = &abs_coord[split_pos..];
+            let int_val: f64 = int_part.parse().unwrap_or(0.0);
+            let dec_val: f64 = if !dec_part.is_empty() {
+                dec_part.parse::<f64>().unwrap_or(0.0) / 10_f64.powi(dec_part.len() as i32)
+            } else {
+                0.0
+            };
+            int_val + dec_val
+        }
+    } else {
+        // TZ (Trailing Zero) mode: Decimal part has fixed length
+        if let Ok(v) = abs_coord.parse::<i64>() {
+            v as f64 / 10_f64.powi(decimal_places as i32)
+        } else {
+            0.0
+        }
+    };
+
+    let result = sign * val;
+    if unit == DrillUnit::Inch {
+        result * INCH_TO_MM
+    } else {
+        result
+    }
+}
+
+/// Parse a plating marker such as "PLATED" or "NON_PLATED"
+fn parse_type_marker(text: &str) -> Option<HoleType> {
+    TYPE_REGEX.captures(text).map(|caps| {
+        if caps[1].to_uppercase().starts_with("NON") {
+            HoleType::NonPlated
+        } else {
+            HoleType::Plated
+        }
+    })
+}
+
+/// Parse the plating of a `TF.FileFunction` or `TA.AperFunction` attribute;
+/// `MixedPlating` files state it per tool instead
+fn parse_x2_plating(text: &str) -> Option<HoleType> {
+    X2_PLATING_REGEX.captures(text).map(|caps| {
+        if caps[1].eq_ignore_ascii_case("NonPlated") {
+            HoleType::NonPlated
+        } else {
+            HoleType::Plated
+        }
+    })
+}
+
+// Whether a drill file states its unit, with a METRIC or INCH line or an
+// M71/M72 switch
+fn declares_unit(content: &str) -> bool {
+    content.lines().any(|line| {
+        let upper = line.trim().to_uppercase();
+        upper.starts_with("METRIC") || upper.starts_with("INCH") || unit_switch(&upper).is_some()
+    })
+}
+
+// Whether a drill file states the plating of its holes itself, with TYPE
+// markers or X2 attributes
+fn declares_plating(content: &str) -> bool {
+    content
+        .lines()
+        .any(|line| parse_type_marker(line).is_some() || parse_x2_plating(line).is_some())
+}
+
+/// Hole counts that ";Holesize" tool comments declare, as (tool, count), e.g.
+/// from "... PLATED MILS Quantity = 12". Tools without a count are left out.
+pub(crate) fn declared_hole_counts(content: &str) -> Vec<(u32, usize)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let caps = HOLESIZE_REGEX.captures(line.trim())?;
+            let count = QUANTITY_REGEX.captures(&caps[3])?[1].parse().ok()?;
+            Some((caps[1].parse().ok()?, count))
+        })
+        .collect()
+}
+
+// Warnings for tools whose holes differ in number from their tool comments
+fn hole_count_warnings(drill: &DrillFile, content: &str, filename: &str) -> Vec<String> {
+    declared_hole_counts(content)
+        .into_iter()
+        .filter_map(|(tool, declared)| {
+            let read: usize = drill
+                .operations
+                .iter()
+                .filter(|op| op.tool == tool)
+                .map(|op| op.commands.len())
+                .sum();
+            (read != declared).then(|| {
+                format!(
+                    "{}: T{} has {} holes, but its tool comment lists {}",
+                    filename, tool, read, declared
+                )
+            })
+        })
+        .collect()
+}
+
+/// Parse a ";Holesize" tool comment into (tool, diameter in mm, plating)
+fn parse_holesize_comment(line: &str, unit: DrillUnit) -> Option<(u32, f64, Option<HoleType>)> {
+    let caps = HOLESIZE_REGEX.captures(line)?;
+    let tool_num: u32 = caps[1].parse().ok()?;
+    let size: f64 = caps[2].parse().ok()?;
+    let rest = caps[3].to_uppercase();
+
+    let diameter = if rest.contains("MILS") {
+        size * MIL_TO_MM
+    } else if rest.contains("MM") || rest.contains("METRIC") {
+        size
+    } else if rest.contains("INCH") || unit == DrillUnit::Inch {
+        size * INCH_TO_MM
+    } else {
+        size
+    };
+
+    let hole_type =
+        if rest.contains("NON_PLATED") || rest.contains("NON-PLATED") || rest.contains("NONPLATED")
+        {
+            Some(HoleType::NonPlated)
+        } else if rest.contains("PLATED") {
+            Some(HoleType::Plated)
+        } else {
+            None
+        };
+
+    Some((tool_num, diameter, hole_type))
+}
+
+/// Infer (integer, decimal) digits for a file in `unit` lacking FILE_FORMAT,
+/// with a score that is lower for more plausible formats.
+///
+/// In LZ mode the integer part always keeps its full width, so the shortest
+/// coordinate bounds the integer digits and the longest gives the total width.
+/// Remaining candidates are scored by how plausible the resulting board extent is.
+fn infer_format(coords: &[&str], is_lz: bool, unit: DrillUnit) -> Option<(f64, (u32, u32))> {
+    let digit_count = |c: &&str| c.trim_start_matches(['+', '-']).len();
+    let max_len = coords.iter().map(digit_count).max()?;
+    let min_len = coords.iter().map(digit_count).min()?;
+    // Inch files carry one more decimal digit than metric ones, e.g. 2:4 and 3:3
+    let decimals = match unit {
+        DrillUnit::Metric => 2..=4,
+        DrillUnit::Inch => 3..=5,
+    };
+
+    let candidates: Vec<(u32, u32)> = if is_lz {
+        (1..=min_len.min(4))
+            .filter(|&i| max_len > i)
+            .map(|i| (i as u32, (max_len - i) as u32))
+            .collect()
+    } else {
+        decimals
+            .map(|d| (max_len.saturating_sub(d).max(1) as u32, d as u32))
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .filter_map(|(integer, decimal)| {
+            let extent = coords
+                .iter()
+                .map(|c| parse_ad_coordinate(c, integer, decimal, is_lz, unit).abs())
+                .fold(0.0_f64, f64::max);
+            extent_score(extent).map(|score| (score, (integer, decimal)))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+// How far a board extent (mm) is from a typical board, or `None` when no
+// board is that size
+fn extent_score(extent: f64) -> Option<f64> {
+    (MIN_PLAUSIBLE_EXTENT_MM..=MAX_PLAUSIBLE_EXTENT_MM)
+        .contains(&extent)
+        .then(|| (extent / TYPICAL_EXTENT_MM).ln().abs())
+}
+
+/// The unit of tool diameters given without a unit statement, when they
+/// settle it: no real hole is under 0.1 mm, and few are over 0.4 inch.
+fn unit_from_diameters(diameters: &[f64]) -> Option<DrillUnit> {
+    let largest = diameters.iter().copied().fold(0.0_f64, f64::max);
+    if largest > 0.4 {
+        Some(DrillUnit::Metric)
+    } else if largest > 0.0 {
+        (largest < 0.1).then_some(DrillUnit::Inch)
+    } else {
+        None
+    }
+}
+
+/// Infer the unit of a file without a unit statement, and its digits when it
+/// has no format statement either, from the tool diameters and coordinates:
+/// integer `coords` by their digit counts, and `decimal_extent`, the largest
+/// coordinate written with a decimal point, by its magnitude. Metric wins
+/// when both units are as plausible.
+fn infer_unit_and_format(
+    diameters: &[f64],
+    coords: &[&str],
+    decimal_extent: Option<f64>,
+    is_lz: bool,
+    format: Option<(u32, u32)>,
+) -> Option<(DrillUnit, (u32, u32))> {
+    let units = match unit_from_diameters(diameters) {
+        Some(unit) => vec![unit],
+        None => vec![DrillUnit::Metric, DrillUnit::Inch],
+    };
+    let mut best: Option<(f64, DrillUnit, (u32, u32))> = None;
+    for unit in units {
+        let scale = if unit == DrillUnit::Inch {
+            INCH_TO_MM
+        } else {
+            1.0
+        };
+        let candidate = match format {
+            Some((integer, decimal)) => {
+                let extent = coords
+                    .iter()
+                    .map(|c| parse_ad_coordinate(c, integer, decimal, is_lz, unit).abs())
+                    .fold(0.0_f64, f64::max);
+                extent_score(extent).map(|score| (score, (integer, decimal)))
+            }
+            None if coords.is_empty() => None,
+            None => infer_format(coords, is_lz, unit),
+        };
+        // Coordinates with a decimal point only depend on the unit
+        let candidate = candidate.or_else(|| {
+            let extent = decimal_extent? * scale;
+            extent_score(extent).map(|score| (score, format.unwrap_or((2, 4))))
+        });
+        if let Some((score, digits)) = candidate
+            && best.is_none_or(|(best_score, _, _)| score < best_score)
+        {
+            best = Some((score, unit, digits));
+        }
+    }
+    best.map(|(_, unit, digits)| (unit, digits))
+}
+
+/// Header terminators: "%" (rewind stop) or "M95" (end of header)
+fn is_header_terminator(line: &str) -> bool {
+    line == "%" || line == "M95"
+}
+
+/// A line that can only appear in the drill body
+fn is_body_command(line: &str) -> bool {
+    TOOL_SELECT_REGEX.is_match(line)
+        || line.starts_with('X')
+        || line.starts_with('Y')
+        || line.starts_with("G00")
+        || line.starts_with("G01")
+        || line.starts_with("G05")
+        || line.starts_with("G85")
+}
+
+/// Locate the first body line of a drill file (lines must be trimmed).
+/// Files that omit the "%" rewind, or the whole M48 header, get their body start
+/// detected at the first tool selection or coordinate, with a warning.
+fn locate_body_start(lines: &[&str]) -> (usize, Vec<String>) {
+    let mut warnings = Vec::new();
+    if !lines.contains(&"M48") {
+        warnings.push("Drill file has no M48 header; header fields may be missing.".to_string());
+    }
+
+    if let Some(index) = lines.iter().position(|l| is_header_terminator(l)) {
+        return (index + 1, warnings);
+    }
+
+    let mut start = lines
+        .iter()
+        .position(|l| is_body_command(l))
+        .unwrap_or(lines.len());
+    // A tool definition right before the first coordinate is also the active tool
+    if start > 0
+        && (AD_TOOL_REGEX.is_match(lines[start - 1]) || KICAD_TOOL_REGEX.is_match(lines[start - 1]))
+    {
+        start -= 1;
+    }
+    warnings.push(format!(
+        "Drill header terminator ('%') not found; body assumed to start at line {}.",
+        start + 1
+    ));
+    (start, warnings)
+}
+
+/// Parse an Altium Designer Excellon drill file
+pub fn parse_ad_excellon(content: &str) -> DrillFile {
+    let mut operations: Vec<DrillOperation> = Vec::new();
+    let mut tool_map: BTreeMap<u32, (f64, HoleType)> = BTreeMap::new();
+    let mut current_hole_type = HoleType::Plated;
+
+    // Default values
+    let mut unit = DrillUnit::Metric;
+    let mut integer_places: u32 = 2;
+    let mut decimal_places: u32 = 4; // Default FILE_FORMAT assumption
+    let mut is_lz = true; // AD default is LZ
+    let mut has_file_format = false;
+    let mut has_unit = false;
+    let mut body_coords: Vec<&str> = Vec::new();
+    let mut decimal_extent: Option<f64> = None;
+    let mut holesizes: Vec<(u32, f64, Option<HoleType>)> = Vec::new();
+
+    let lines: Vec<&str> = content.lines().map(str::trim).collect();
+    let (body_start, mut warnings) = locate_body_start(&lines);
+
+    // First pass: parse header to get unit, format, and tool definitions
+    for (index, &line) in lines.iter().enumerate() {
+        if is_header_terminator(line) {
+            continue;
+        }
+
+        if index >= body_start {
+            // Remember raw coordinates in case the format has to be inferred
+            if !line.starts_with(';') {
+                body_coords.extend(
+                    RAW_COORD_REGEX
+                        .captures_iter(line)
+                        .filter_map(|caps| caps.get(1).map(|m| m.as_str())),
+                );
+                for caps in DECIMAL_COORD_REGEX.captures_iter(line) {
+                    if let Ok(value) = caps[1].parse::<f64>() {
+                        decimal_extent = Some(decimal_extent.unwrap_or(0.0).max(value.abs()));
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Check for unit
+        let upper = line.to_uppercase();
+        if let Some(switched) = unit_switch(&upper) {
+            unit = switched;
+            has_unit = true;
+        } else if upper.starts_with("INCH") {
+            unit = DrillUnit::Inch;
+            has_unit = true;
+            if upper.contains("LZ") {
+                is_lz = true;
+            } else if upper.contains("TZ") {
+                is_lz = false;
+            }
+        } else if upper.starts_with("METRIC") {
+            unit = DrillUnit::Metric;
+            has_unit = true;
+            if upper.contains("LZ") {
+                is_lz = true;
+            } else if upper.contains("TZ") {
+                is_lz = false;
+            }
+        }
+
+        // Check for FILE_FORMAT
+        if let Some(caps) = FILE_FORMAT_REGEX.captures(line) {
+            // FILE_FORMAT=2:5 means 2 integer digits, 5 decimal digits
+            integer_places = caps[1].parse().unwrap_or(2);
+            decimal_places = caps[2].parse().unwrap_or(4);
+            has_file_format = true;
+        } else if !has_file_format && let Some(caps) = UNIT_FORMAT_REGEX.captures(line) {
+            // "METRIC,LZ,000.000" spells out the digit template
+            integer_places = caps[1].len() as u32;
+            decimal_places = caps[2].len() as u32;
+            has_file_format = true;
+        }
+
+        // Check for TYPE markers (";TYPE=PLATED", "; TYPE = NON-PLATED", ...)
+        // and X2 attributes, which apply to the tools defined after them
+        if let Some(hole_type) = parse_type_marker(line).or_else(|| parse_x2_plating(line)) {
+            current_hole_type = hole_type;
+        }
+
+        // OrCAD/Allegro list tool sizes in ";Holesize" comments
+        if let Some(holesize) = parse_holesize_comment(line, unit) {
+            holesizes.push(holesize);
+            continue;
+        }
+
+        // Parse tool definition (AD "T01F00S00C0.8" or generic "T01C0.8")
+        if let Some(caps) = AD_TOOL_REGEX
+            .captures(line)
+            .or_else(|| KICAD_TOOL_REGEX.captures(line))
+        {
+            let tool_num: u32 = caps[1].parse().unwrap_or(0);
+            let diameter_raw: f64 = caps[2].parse().unwrap_or(0.0);
+            // Convert diameter to mm if needed
+            let diameter_mm = if unit == DrillUnit::Inch {
+                diameter_raw * INCH_TO_MM
+            } else {
+                diameter_raw
+            };
+            tool_map.insert(tool_num, (diameter_mm, current_hole_type));
+        }
+    }
+
+    // Header-less files: the unit and digits have to come from the numbers.
+    // Inline tool definitions count too, as those files put them in the body.
+    if !has_unit {
+        let diameters: Vec<f64> = lines
+            .iter()
+            .filter_map(|line| {
+                AD_TOOL_REGEX
+                    .captures(line)
+                    .or_else(|| KICAD_TOOL_REGEX.captures(line))
+            })
+            .filter_map(|caps| caps[2].parse().ok())
+            .collect();
+        let format = has_file_format.then_some((integer_places, decimal_places));
+        if let Some((inferred, (integer, decimal))) =
+            infer_unit_and_format(&diameters, &body_coords, decimal_extent, is_lz, format)
+        {
+            unit = inferred;
+            integer_places = integer;
+            decimal_places = decimal;
+            has_file_format = true;
+            if unit == DrillUnit::Inch {
+                for (diameter, _) in tool_map.values_mut() {
+                    *diameter *= INCH_TO_MM;
+                }
+            }
+            let unit_name = if unit == DrillUnit::Inch {
+                "inch"
+            } else {
+                "mm"
+            };
+            warnings.push(if body_coords.is_empty() {
+                format!(
+                    "Drill file has no unit statement; read as {} from its numbers.",
+                    unit_name
+                )
+            } else {
+                format!(
+                    "Drill file has no unit statement; read as {} with {}:{} digits from its numbers.",
+                    unit_name, integer, decimal
+                )
+            });
+        }
+    }
+
+    // ";Holesize" comments define tools without a C diameter and may carry plating
+    for (tool_num, diameter, hole_type) in holesizes {
+        let entry = tool_map
+            .entry(tool_num)
+            .or_insert((diameter, hole_type.unwrap_or(current_hole_type)));
+        if entry.0 == 0.0 {
+            entry.0 = diameter;
+        }
+        if let Some(hole_type) = hole_type {
+            entry.1 = hole_type;
+        }
+    }
+
+    // Without any format statement, infer the digits from the coordinates
+    if !has_file_format
+        && unit == DrillUnit::Metric
+        && let Some((_, (integer, decimal))) = infer_format(&body_coords, is_lz, DrillUnit::Metric)
+    {
+        integer_places = integer;
+        decimal_places = decimal;
+    }
+
+    // Initialize operations for each tool
+    let mut tool_operations: BTreeMap<u32, DrillOperation> = BTreeMap::new();
+    for (tool_num, (diameter, hole_type)) in &tool_map {
+        tool_operations.insert(
+            *tool_num,
+            DrillOperation {
+                tool: *tool_num,
+                diameter: *diameter,
+                hole_type: *hole_type,
+                commands: Vec::new(),
+                span: None,
+            },
+        );
+    }
+
+    // Second pass: parse drill commands
+    let mut current_tool: Option<u32> = None;
+    // Tool and points of the path being routed, between M15 and M16
+    let mut route: Option<(u32, Vec<Point>)> = None;
+    let mut last_x: f64 = 0.0;
+    let mut last_y: f64 = 0.0;
+    let mut incremental = header_is_incremental(&lines[..body_start]);
+    // Absolute position of a coordinate word; in incremental mode it is an
+    // offset from the last position
+    let position = |raw: &str, last: f64, incremental: bool, unit: DrillUnit| {
+        let value = parse_ad_coordinate(raw, integer_places, decimal_places, is_lz, unit);
+        if incremental { last + value } else { value }
+    };
+
+    for &line in &lines[body_start..] {
+        if is_header_terminator(line) || line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        // M71 / M72 - metric or inch from here on; the digits stay the same
+        if let Some(switched) = unit_switch(line) {
+            unit = switched;
+            continue;
+        }
+
+        // Inline tool definition, as written by header-less files
+        if let Some(caps) = AD_TOOL_REGEX
+            .captures(line)
+            .or_else(|| KICAD_TOOL_REGEX.captures(line))
+        {
+            let tool_num: u32 = caps[1].parse().unwrap_or(0);
+            let diameter_raw: f64 = caps[2].parse().unwrap_or(0.0);
+            let diameter = if unit == DrillUnit::Inch {
+                diameter_raw * INCH_TO_MM
+            } else {
+                diameter_raw
+            };
+            tool_map
+                .entry(tool_num)
+                .or_insert((diameter, current_hole_type));
+            tool_operations
+                .entry(tool_num)
+                .or_insert_with(|| DrillOperation {
+                    tool: tool_num,
+                    diameter,
+                    hole_type: current_hole_type,
+                    commands: Vec::new(),
+                    span: None,
+                });
+            current_tool = Some(tool_num);
+            continue;
+        }
+
+        // Tool selection; a tool with no definition, as in Allegro files
+        // whose sizes are in a separate tool report, is kept without a size
+        if let Some(caps) = TOOL_SELECT_REGEX.captures(line) {
+            let tool_num: u32 = caps[1].parse().unwrap_or(0);
+            if tool_num != 0 && !tool_map.contains_key(&tool_num) {
+                tool_map.insert(tool_num, (0.0, current_hole_type));
+                tool_operations.insert(
+                    tool_num,
+                    DrillOperation {
+                        tool: tool_num,
+                        diameter: 0.0,
+                        hole_type: current_hole_type,
+                        commands: Vec::new(),
+                        span: None,
+                    },
+                );
+            }
+            if tool_num != 0 {
+                current_tool = Some(tool_num);
+            }
+            continue;
+        }
+
+        // Route start (G00)
+        if let Some(caps) = ROUTE_START_REGEX.captures(line) {
+            if let Some(x_match) = caps.get(1) {
+                last_x = position(x_match.as_str(), last_x, incremental, unit);
+            }
+            if let Some(y_match) = caps.get(2) {
+                last_y = position(y_match.as_str(), last_y, incremental, unit);
+            }
+            continue;
+        }
+
+        // M15 - start routing (drill down)
+        if line == "M15" {
+            end_route(route.take(), &mut tool_operations);
+            route = current_tool.map(|tool| (tool, vec![Point::new(last_x, last_y)]));
+            continue;
+        }
+
+        // Route to (G01) - during routing
+        if let Some((_, points)) = &mut route
+            && let Some(caps) = ROUTE_TO_REGEX.captures(line)
+        {
+            if let Some(x_match) = caps.get(1) {
+                last_x = position(x_match.as_str(), last_x, incremental, unit);
+            }
+            if let Some(y_match) = caps.get(2) {
+                last_y = position(y_match.as_str(), last_y, incremental, unit);
+            }
+            points.push(Point::new(last_x, last_y));
+            continue;
+        }
+
+        // Arc route (G02/G03) - during routing
+        if let Some((_, points)) = &mut route
+            && let Some(caps) = ROUTE_ARC_REGEX.captures(line)
+        {
+            let value = |index: usize| {
+                caps.get(index).map(|m| {
+                    parse_ad_coordinate(m.as_str(), integer_places, decimal_places, is_lz, unit)
+                })
+            };
+            let start = Point::new(last_x, last_y);
+            if let Some(x) = caps.get(2) {
+                last_x = position(x.as_str(), last_x, incremental, unit);
+            }
+            if let Some(y) = caps.get(3) {
+                last_y = position(y.as_str(), last_y, incremental, unit);
+            }
+            points.extend(arc_points(
+                start,
+                Point::new(last_x, last_y),
+                &caps[1] == "2",
+                value(4),
+                (value(5), value(6)),
+            ));
+            continue;
+        }
+
+        // M16 - end routing (drill up)
+        if line == "M16" {
+            end_route(route.take(), &mut tool_operations);
+            continue;
+        }
+
+        // G90 / G91 - absolute or incremental coordinates
+        if line == "G90" || line == "G91" {
+            incremental = line == "G91";
+            continue;
+        }
+
+        // Repeat code (R#X#Y#) - more holes stepping from the last one
+        if let Some(caps) = REPEAT_REGEX.captures(line) {
+            let step = |index: usize| {
+                caps.get(index).map_or(0.0, |m| {
+                    parse_ad_coordinate(m.as_str(), integer_places, decimal_places, is_lz, unit)
+                })
+            };
+            if let Some(tool) = current_tool
+                && let Some(op) = tool_operations.get_mut(&tool)
+            {
+                let last = repeat_holes(
+                    op,
+                    Point::new(last_x, last_y),
+                    caps[1].parse().unwrap_or(0),
+                    (step(2), step(3)),
+                    &mut warnings,
+                );
+                (last_x, last_y) = (last.x, last.y);
+            }
+            continue;
+        }
+
+        // Simple hole coordinate (X...Y...)
+        if let Some(caps) = COORD_REGEX.captures(line) {
+            if caps.get(1).is_none() && caps.get(2).is_none() {
+                continue;
+            }
+            if let Some(tool) = current_tool
+                && let Some(op) = tool_operations.get_mut(&tool)
+            {
+                if let Some(x_match) = caps.get(1) {
+                    last_x = position(x_match.as_str(), last_x, incremental, unit);
+                }
+                if let Some(y_match) = caps.get(2) {
+                    last_y = position(y_match.as_str(), last_y, incremental, unit);
+                }
+                op.commands.push(DrillCommand::Hole {
+                    x: last_x,
+                    y: last_y,
+                });
+            }
+        }
+    }
+    end_route(route, &mut tool_operations);
+
+    // Collect non-empty operations
+    for (_, op) in tool_operations {
+        if !op.commands.is_empty() {
+            if op.diameter == 0.0 {
+                warnings.push(format!(
+                    "T{} is used but never defined; its {} holes have no size without a tool report",
+                    op.tool,
+                    op.commands.len()
+                ));
+            }
+            operations.push(op);
+        }
+    }
+
+    DrillFile {
+        operations,
+        warnings,
+    }
+}
+
+/// Parse a KiCad Excellon drill file
+/// KiCad uses METRIC and decimal coordinates by default
+pub fn parse_kicad_excellon(content: &str) -> (DrillFile, HoleType) {
+    let mut operations: Vec<DrillOperation> = Vec::new();
+    let mut tool_map: BTreeMap<u32, (f64, HoleType)> = BTreeMap::new();
+
+    // Determine hole type from the file function attribute; files without
+    // one are told apart by the NPTH in their header comments
+    let file_function = content
+        .lines()
+        .find(|line| line.contains("TF.FileFunction"))
+        .map(parse_x2_plating);
+    let hole_type = match file_function {
+        Some(Some(hole_type)) => hole_type,
+        // MixedPlating: the tools carry their own plating
+        Some(None) => HoleType::Plated,
+        None if content.contains("NonPlated") || content.contains("NPTH") => HoleType::NonPlated,
+        None => HoleType::Plated,
+    };
+
+    let lines: Vec<&str> = content.lines().map(str::trim).collect();
+    let (body_start, mut warnings) = locate_body_start(&lines);
+
+    // First pass: parse header. A "TA.AperFunction" attribute gives the
+    // plating of the tools defined after it
+    let mut current_hole_type = hole_type;
+    let mut marks_tools = false;
+    // Millimeters per file unit; KiCad writes mm unless told to use inch
+    let mut scale = 1.0;
+    for &line in &lines[..body_start] {
+        if let Some(unit) = unit_switch(line).or_else(|| unit_statement(line)) {
+            scale = unit.to_mm();
+        }
+        if line.contains("TA.AperFunction")
+            && let Some(tool_type) = parse_x2_plating(line)
+        {
+            current_hole_type = tool_type;
+            marks_tools = true;
+        }
+        if let Some(caps) = KICAD_TOOL_REGEX.captures(line) {
+            let tool_num: u32 = caps[1].parse().unwrap_or(0);
+            let diameter = caps[2].parse().unwrap_or(0.0) * scale;
+            tool_map.insert(tool_num, (diameter, current_hole_type));
+        }
+    }
+
+    // A merged PTH and NPTH file can only be split when it marks its tools
+    if file_function == Some(None) && !marks_tools {
+        warnings.push(
+            "Plated and non-plated holes are merged in this file without per-tool plating; all are read as plated. Export PTH and NPTH to separate files."
+                .to_string(),
+        );
+    }
+
+    // Initialize operations
+    let mut tool_operations: BTreeMap<u32, DrillOperation> = BTreeMap::new();
+    for (tool_num, (diameter, tool_type)) in &tool_map {
+        tool_operations.insert(
+            *tool_num,
+            DrillOperation {
+                tool: *tool_num,
+                diameter: *diameter,
+                hole_type: *tool_type,
+                commands: Vec::new(),
+                span: None,
+            },
+        );
+    }
+
+    // Second pass: parse commands
+    let mut current_tool: Option<u32> = None;
+    let mut route: Option<(u32, Vec<Point>)> = None;
+    // Current position, which incremental coordinates are relative to
+    let mut at = Point::new(0.0, 0.0);
+    let mut incremental = header_is_incremental(&lines[..body_start]);
+
+    for &line in &lines[body_start..] {
+        if is_header_terminator(line) || line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        // M71 / M72 - metric or inch from here on
+        if let Some(unit) = unit_switch(line) {
+            scale = unit.to_mm();
+            continue;
+        }
+
+        // Inline tool definition, as written by header-less files
+        if let Some(caps) = KICAD_TOOL_REGEX.captures(line) {
+            let tool_num: u32 = caps[1].parse().unwrap_or(0);
+            let diameter = caps[2].parse().unwrap_or(0.0) * scale;
+            tool_map.entry(tool_num).or_insert((diameter, hole_type));
+            tool_operations
+                .entry(tool_num)
+                .or_insert_with(|| DrillOperation {
+                    tool: tool_num,
+                    diameter,
+                    hole_type,
+                    commands: Vec::new(),
+                    span: None,
+                });
+            current_tool = Some(tool_num);
+            continue;
+        }
+
+        // Tool selection
+        if let Some(caps) = TOOL_SELECT_REGEX.captures(line) {
+            let tool_num: u32 = caps[1].parse().unwrap_or(0);
+            if tool_map.contains_key(&tool_num) {
+                current_tool = Some(tool_num);
+            }
+            continue;
+        }
+
+        // G90 / G91 - absolute or incremental coordinates
+        if line == "G90" || line == "G91" {
+            incremental = line == "G91";
+            continue;
+        }
+
+        // KiCad uses decimal coordinates, in mm unless inch is selected
+        let position = |caps: &regex::Captures, x: usize, y: usize| {
+            let value = |index: usize| {
+                caps.get(index)
+                    .and_then(|m| m.as_str().parse::<f64>().ok())
+                    .map(|v| v * scale)
+            };
+            let axis = |index: usize, last: f64| match value(index) {
+                Some(v) if incremental => last + v,
+                Some(v) => v,
+                None => last,
+            };
+            Point::new(axis(x, at.x), axis(y, at.y))
+        };
+
+        // Route start
+        if let Some(caps) = ROUTE_START_REGEX.captures(line) {
+            at = position(&caps, 1, 2);
+            continue;
+        }
+
+        if line == "M15" {
+            end_route(route.take(), &mut tool_operations);
+            route = current_tool.map(|tool| (tool, vec![at]));
+            continue;
+        }
+
+        if let Some((_, points)) = &mut route
+            && let Some(caps) = ROUTE_TO_REGEX.captures(line)
+        {
+            at = position(&caps, 1, 2);
+            points.push(at);
+            continue;
+        }
+
+        if let Some((_, points)) = &mut route
+            && let Some(caps) = ROUTE_ARC_REGEX.captures(line)
+        {
+            let value = |index: usize| {
+                caps.get(index)
+                    .and_then(|m| m.as_str().parse::<f64>().ok())
+                    .map(|v| v * scale)
+            };
+            let start = at;
+            at = position(&caps, 2, 3);
+            points.extend(arc_points(
+                start,
+                at,
+                &caps[1] == "2",
+                value(4),
+                (value(5), value(6)),
+            ));
+            continue;
+        }
+
+        if line == "M16" {
+            end_route(route.take(), &mut tool_operations);
+            continue;
+        }
+
+        // A hole, with an axis left out keeping its last value
+        if let Some(caps) = COORD_REGEX.captures(line)
+            && (caps.get(1).is_some() || caps.get(2).is_some())
+            && let Some(tool) = current_tool
+            && let Some(op) = tool_operations.get_mut(&tool)
+        {
+            at = position(&caps, 1, 2);
+            op.commands.push(DrillCommand::Hole { x: at.x, y: at.y });
+            continue;
+        }
+
+        if let Some(caps) = REPEAT_REGEX.captures(line)
+            && let Some(tool) = current_tool
+            && let Some(op) = tool_operations.get_mut(&tool)
+        {
+            let step = |index: usize| {
+                caps.get(index)
+                    .map_or(0.0, |m| m.as_str().parse().unwrap_or(0.0) * scale)
+            };
+            at = repeat_holes(
+                op,
+                at,
+                caps[1].parse().unwrap_or(0),
+                (step(2), step(3)),
+                &mut warnings,
+            );
+        }
+    }
+    end_route(route, &mut tool_operations);
+
+    for (_, op) in tool_operations {
+        if !op.commands.is_empty() {
+            operations.push(op);
+        }
+    }
+
+    (
+        DrillFile {
+            operations,
+            warnings,
+        },
+        hole_type,
+    )
+}
+
+/// Parses a drill file with the parser for the software that wrote it.
+pub fn parse_excellon(content: &str) -> DrillFile {
+    match detect_drill_eda(content) {
+        DrillEdaType::KiCad => parse_kicad_excellon(content).0,
+        // The other dialects follow the generic Excellon the AD parser reads
+        _ => parse_ad_excellon(content),
+    }
+}
+
+/// Bounding box of all hole centers and route points, in mm.
+pub(crate) fn drill_bounds(drill: &DrillFile) -> Option<BoundingBox> {
+    let mut bounds = None;
+    for cmd in drill.operations.iter().flat_map(|op| op.commands.iter()) {
+        match cmd {
+            DrillCommand::Hole { x, y } => extend_bounds(&mut bounds, Point::new(*x, *y)),
+            DrillCommand::Route { points } => {
+                for point in points {
+                    extend_bounds(&mut bounds, *point);
+                }
+            }
+        }
+    }
+    bounds
+}
+
+/// Multiplies all coordinates and tool diameters by `factor`.
+pub(crate) fn scale_drill_file(drill: &mut DrillFile, factor: f64) {
+    for op in &mut drill.operations {
+        op.diameter *= factor;
+        for cmd in &mut op.commands {
+            match cmd {
+                DrillCommand::Hole { x, y } => {
+                    *x *= factor;
+                    *y *= factor;
+                }
+                DrillCommand::Route { points } => {
+                    for point in points {
+                        point.x *= factor;
+                        point.y *= factor;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of checking a drill file's extents against the board outline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnitAlignment {
+    /// The holes lie on the board as parsed
+    Aligned,
+    /// The holes only lie on the board after scaling by this factor,
+    /// so the file's unit was misdeclared (25.4 = inch data read as mm)
+    Rescaled(f64),
+    /// The holes lie on the board in the unit the file declares, but bunch
+    /// in a corner of it and would spread over it scaled by this factor.
+    /// The file is left as parsed.
+    Suspect(f64),
+    /// The holes do not lie on the board at any unit
+    Mismatch,
+}
+
+/// Checks that the holes of a parsed drill file lie on the board and fixes the unit if not.
+///
+/// Old Eagle jobs often pair metric Gerbers with inch drills whose header
+/// lacks or misstates the unit, which shows up as holes off the board. Since
+/// output drills are always metric, a misread unit is fixed by rescaling the
+/// parsed file, which regenerates every coordinate in mm. Holes that fit the
+/// board as parsed are only rescaled when `unit_declared` is false, i.e. the
+/// unit was inferred or assumed.
+pub fn align_units_to_board(
+    drill: &mut DrillFile,
+    board: &BoundingBox,
+    unit_declared: bool,
+) -> UnitAlignment {
+    let Some(bounds) = drill_bounds(drill) else {
+        return UnitAlignment::Aligned;
+    };
+    let board = board.expanded(BOARD_MARGIN_MM);
+    let fits = |factor: f64| {
+        board.contains(Point::new(bounds.min_x * factor, bounds.min_y * factor))
+            && board.contains(Point::new(bounds.max_x * factor, bounds.max_y * factor))
+    };
+
+    // Inch data read as mm still fits the board, but bunches in a corner.
+    // Holes that also fit when scaled up, and then spread over a good part of
+    // the board, were likely meant as inches.
+    let spread = bounds.width().max(bounds.height()) * INCH_TO_MM;
+    let spreads_as_inch = spread >= board.width().max(board.height()) * MIN_BOARD_SPREAD;
+
+    let factor = if fits(1.0) {
+        if !(spreads_as_inch && fits(INCH_TO_MM)) {
+            return UnitAlignment::Aligned;
+        }
+        if unit_declared {
+            return UnitAlignment::Suspect(INCH_TO_MM);
+        }
+        INCH_TO_MM
+    } else if fits(INCH_TO_MM) {
+        INCH_TO_MM
+    } else if fits(1.0 / INCH_TO_MM) {
+        1.0 / INCH_TO_MM
+    } else {
+        return UnitAlignment::Mismatch;
+    };
+    scale_drill_file(drill, factor);
+    UnitAlignment::Rescaled(factor)
+}
+
+/// The copper layers joined by a blind or buried via, numbered from 1 at the
+/// top; `from` is always the upper one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerSpan {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl LayerSpan {
+    pub fn new(a: u32, b: u32) -> Self {
+        LayerSpan {
+            from: a.min(b),
+            to: a.max(b),
+        }
+    }
+
+    pub fn is_through(&self, copper_layers: u32) -> bool {
+        self.from == 1 && self.to == copper_layers
+    }
+
+    /// Blind vias reach an outer layer, buried vias join inner layers only.
+    pub fn is_blind(&self, copper_layers: u32) -> bool {
+        self.from == 1 || self.to == copper_layers
+    }
+
+    /// JLC file name, e.g. "Drill_PTH_Blind_L1_L2.DRL"
+    pub fn file_name(&self, copper_layers: u32) -> String {
+        let kind = if self.is_blind(copper_layers) {
+            "Blind"
+        } else {
+            "Buried"
+        };
+        format!("Drill_PTH_{}_L{}_L{}.DRL", kind, self.from, self.to)
+    }
+}
+
+// This regex matches an Altium layer name and captures its kind and number.
+static ALTIUM_LAYER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(top|bottom|mid[- ]?layer|internal ?plane|plane|layer|l)\s*(\d*)$")
+        .expect("Invalid Altium layer regex")
+});
+
+// This regex matches a layer pair, as in "Layer Pair : Top Layer to Mid-Layer 1".
+static LAYER_PAIR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^;?\s*Layer[ _]?Pair\s*[:=]\s*(.+?)\s+to\s+(.+?)\s*$")
+        .expect("Invalid layer pair regex")
+});
+
+// This regex matches a file line of an Altium drill report and captures the name.
+static REPORT_FILE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bFile\s*:\s*(\S.*?)\s*$").expect("Invalid report file regex"));
+
+/// The copper layer number of an Altium layer name: "Top Layer" is 1,
+/// "Mid-Layer n" is n + 1, internal planes follow the `mid_layers` mid layers
+/// and "Bottom Layer" is `copper_layers`.
+pub(crate) fn altium_layer_number(name: &str, copper_layers: u32, mid_layers: u32) -> Option<u32> {
+    let name = name.trim();
+    let name = name
+        .strip_suffix(" Layer")
+        .or_else(|| name.strip_suffix(" layer"))
+        .unwrap_or(name);
+    let caps = ALTIUM_LAYER_REGEX.captures(name)?;
+    let number: Option<u32> = caps[2].parse().ok();
+    let kind = caps[1].to_lowercase();
+    match kind.as_str() {
+        "top" => Some(1),
+        "bottom" => Some(copper_layers),
+        "layer" | "l" => number,
+        _ if kind.contains("plane") => number.map(|n| mid_layers + n + 1),
+        _ => number.map(|n| n + 1),
+    }
+}
+
+fn parse_layer_pair(line: &str, copper_layers: u32, mid_layers: u32) -> Option<LayerSpan> {
+    let caps = LAYER_PAIR_REGEX.captures(line.trim())?;
+    Some(LayerSpan::new(
+        altium_layer_number(&caps[1], copper_layers, mid_layers)?,
+        altium_layer_number(&caps[2], copper_layers, mid_layers)?,
+    ))
+}
+
+/// The layer pair of each drill file listed in an Altium drill report
+/// (`.DRR`), as (file name, span). A report lists every file under the
+/// "Layer Pair : Top Layer to Mid-Layer 1" line of its span.
+pub fn parse_drill_report_spans(
+    report: &str,
+    copper_layers: u32,
+    mid_layers: u32,
+) -> Vec<(String, LayerSpan)> {
+    let mut spans = Vec::new();
+    let mut current: Option<LayerSpan> = None;
+    for line in report.lines() {
+        if LAYER_PAIR_REGEX.is_match(line.trim()) {
+            current = parse_layer_pair(line, copper_layers, mid_layers);
+        } else if let Some(span) = current
+            && let Some(caps) = REPORT_FILE_REGEX.captures(line)
+        {
+            spans.push((caps[1].to_string(), span));
+        }
+    }
+    spans
+}
+
+// This regex matches a tool line of an Allegro tool report, as in
+// "T01   12.00 MIL   PLATED   104" or "T2 0.0350", and captures the tool
+// number, its size and the rest of the line.
+static TOOL_REPORT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*T(\d+)\s*[:=]?\s+([\d]*\.?[\d]+)(.*)$").expect("Invalid tool report regex")
+});
+
+// This regex matches a units line of a tool report, as in "Units: MILS".
+static REPORT_UNITS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\bunits?\s*[:=]?\s*(mils?|inch(?:es)?|in|mm|millimet(?:er|re)s?|metric|english)\b",
+    )
+    .expect("Invalid report units regex")
+});
+
+/// A tool listed in a drill tool report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReportedTool {
+    pub tool: u32,
+    /// Diameter in mm
+    pub diameter: f64,
+    pub hole_type: Option<HoleType>,
+}
+
+/// Whether a file is an Allegro tool report, such as `nc_tools_auto.txt`,
+/// `nc_tools_report.txt` or `ncdrill.log`.
+pub(crate) fn is_tool_report(filename: &str) -> bool {
+    let name = base_name(filename).to_lowercase();
+    name.starts_with("nc_tools") || name.starts_with("ncdrill") && name.ends_with(".log")
+}
+
+/// The tools listed in an Allegro tool report (`nc_tools_report`,
+/// `ncdrill.log`). Sizes are read in the unit given on their line or in a
+/// "Units:" line; without either, values above 10 are taken as mils, tool
+/// sets with sizes under 0.1 as inch, and anything else as mm.
+pub fn parse_tool_report(report: &str) -> Vec<ReportedTool> {
+    let report_unit = report.lines().find_map(|line| {
+        let caps = REPORT_UNITS_REGEX.captures(line)?;
+        let unit = caps[1].to_lowercase();
+        Some(if unit.starts_with("mil") {
+            MIL_TO_MM
+        } else if unit.starts_with("in") || unit == "english" {
+            INCH_TO_MM
+        } else {
+            1.0
+        })
+    });
+
+    // (tool, size, scale given on the line, plating)
+    let mut entries: Vec<(u32, f64, Option<f64>, Option<HoleType>)> = Vec::new();
+    for line in report.lines() {
+        let Some(caps) = TOOL_REPORT_REGEX.captures(line) else {
+            continue;
+        };
+        let (Ok(tool), Ok(size)) = (caps[1].parse::<u32>(), caps[2].parse::<f64>()) else {
+            continue;
+        };
+        let rest = caps[3].to_uppercase();
+        let unit = rest
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .find(|w| !w.is_empty());
+        let scale = match unit {
+            Some("MIL" | "MILS") => Some(MIL_TO_MM),
+            Some("IN" | "INCH" | "INCHES") => Some(INCH_TO_MM),
+            Some("MM") => Some(1.0),
+            _ => None,
+        };
+        let hole_type = if rest.contains("NON_PLATED")
+            || rest.contains("NON-PLATED")
+            || rest.contains("NONPLATED")
+            || rest.contains("NPTH")
+        {
+            Some(HoleType::NonPlated)
+        } else if rest.contains("PLATED") || rest.contains("PTH") {
+            Some(HoleType::Plated)
+        } else {
+            None
+        };
+        if tool > 0 && size > 0.0 {
+            entries.push((tool, size, scale, hole_type));
+        }
+    }
+
+    let unitless: Vec<f64> = entries
+        .iter()
+        .filter(|(_, _, scale, _)| scale.is_none())
+        .map(|(_, size, _, _)| *size)
+        .collect();
+    let inferred = if unitless.iter().any(|&size| size > 10.0) {
+        MIL_TO_MM
+    } else if unitless.iter().any(|&size| size < 0.1) {
+        INCH_TO_MM
+    } else {
+        1.0
+    };
+
+    entries
+        .into_iter()
+        .map(|(tool, size, scale, hole_type)| ReportedTool {
+            tool,
+            diameter: size * scale.or(report_unit).unwrap_or(inferred),
+            hole_type,
+        })
+        .collect()
+}
+
+/// Sizes the tools of `drill` that its file never defined, as in Allegro
+/// coordinate-only drill files, from a tool report; plating given in the
+/// report applies to those tools too. Returns how many tools were sized, and
+/// warns about tools the report does not list.
+pub fn apply_tool_report(drill: &mut DrillFile, tools: &[ReportedTool]) -> usize {
+    let mut sized = 0;
+    for op in drill.operations.iter_mut().filter(|op| op.diameter == 0.0) {
+        match tools.iter().find(|t| t.tool == op.tool) {
+            Some(reported) => {
+                op.diameter = reported.diameter;
+                if let Some(hole_type) = reported.hole_type {
+                    op.hole_type = hole_type;
+                }
+                sized += 1;
+            }
+            None => drill.warnings.push(format!(
+                "T{} is not in the tool report; its holes still have no size",
+                op.tool
+            )),
+        }
+    }
+    if sized > 0 {
+        let sizes: Vec<u32> = drill
+            .operations
+            .iter()
+            .filter(|op| op.diameter > 0.0)
+            .map(|op| op.tool)
+            .collect();
+        drill.warnings.retain(|w| {
+            !(w.ends_with("holes have no size without a tool report")
+                && sizes.iter().any(|t| w.starts_with(&format!("T{} ", t))))
+        });
+    }
+    sized
+}
+
+/// Parses an Excellon file whose tool sizes are in a separate Allegro tool
+/// report, see [`parse_tool_report`] and [`apply_tool_report`].
+pub fn parse_excellon_with_report(content: &str, report: &str) -> DrillFile {
+    let mut drill = parse_ad_excellon(content);
+    apply_tool_report(&mut drill, &parse_tool_report(report));
+    drill
+}
+
+/// The layer pair a drill file declares in a header comment such as
+/// ";Layer Pair : Top Layer to Mid-Layer 1".
+pub(crate) fn header_layer_span(
+    content: &str,
+    copper_layers: u32,
+    mid_layers: u32,
+) -> Option<LayerSpan> {
+    content
+        .lines()
+        .take_while(|line| line.trim() != "%")
+        .find_map(|line| parse_layer_pair(line, copper_layers, mid_layers))
+}
+
+// This regex matches a layer pair in a drill file name, as in "Drill-1-2.TX1"
+// or "RoundHoles-L2-L3.txt", and captures both layer numbers.
+static FILENAME_SPAN_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)[-_ ]L?(\d{1,2})[-_]L?(\d{1,2})(?:[-_ .]|$)")
+        .expect("Invalid file name span regex")
+});
+
+/// The layer pair in an Altium drill file name, such as `Drill-1-2.TX1` or
+/// `RoundHoles-L2-L3.txt`. Layers are numbered from 1 at the top.
+pub(crate) fn span_from_filename(filename: &str) -> Option<LayerSpan> {
+    let caps = FILENAME_SPAN_REGEX.captures(base_name(filename))?;
+    let from: u32 = caps[1].parse().ok()?;
+    let to: u32 = caps[2].parse().ok()?;
+    (from != to && from >= 1 && to >= 1).then(|| LayerSpan::new(from, to))
+}
+
+// The base name of a path, for matching against report entries
+fn base_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// An input tool and the output tool it was merged into
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolAssignment {
+    /// Drill file the tool is defined in
+    pub file: String,
+    /// Tool number in that file
+    pub source_tool: u32,
+    /// Tool number in the JLC file of its hole type
+    pub output_tool: u32,
+    pub diameter: f64, // Tool diameter in mm
+    pub hole_type: HoleType,
+}
+
+/// Drill files merged into one file per hole type
+#[derive(Debug, Clone)]
+pub struct MergedDrills {
+    pub pth: Option<DrillFile>,
+    pub npth: Option<DrillFile>,
+    /// Every input tool with holes, by file and tool number
+    pub tools: Vec<ToolAssignment>,
+    pub warnings: Vec<String>,
+}
+
+// An output tool, the (file, tool) pairs merged into it, and the most holes
+// of any one of them
+type ToolGroup = (DrillOperation, Vec<(usize, u32)>, usize);
+
+/// Tools whose diameters differ by no more than this (mm) are merged, so
+/// rounding noise such as 0.99999 and 1.0 mm does not add drill sizes.
+pub const DEFAULT_DIAMETER_TOLERANCE_MM: f64 = 0.005;
+
+/// Merges (filename, drill) pairs and splits them by hole type.
+///
+/// Tools are keyed by file and tool number, since each exporter numbers its
+/// tools from T1: T1 of one file and T1 of another are unrelated. Tools of
+/// the same hole type whose diameters are within
+/// [`DEFAULT_DIAMETER_TOLERANCE_MM`] are merged, and output tools are
+/// numbered from T01 by diameter, as [`generate_jlc_excellon`] writes them.
+/// [`MergedDrills::tools`] records the output tool of every input tool.
+pub fn merge_drill_files(files: Vec<(String, DrillFile)>) -> MergedDrills {
+    merge_drill_files_with_tolerance(files, DEFAULT_DIAMETER_TOLERANCE_MM)
+}
+
+/// Like [`merge_drill_files`], merging tools whose diameters differ by up to
+/// `tolerance` mm. Each merged tool takes the diameter of its member with the
+/// most holes; a tolerance of 0 only merges equal diameters.
+pub fn merge_drill_files_with_tolerance(
+    files: Vec<(String, DrillFile)>,
+    tolerance: f64,
+) -> MergedDrills {
+    // Sizes closer than this are equal whatever the tolerance, in mm
+    const EPSILON_MM: f64 = 1e-6;
+    let tolerance = tolerance.max(EPSILON_MM);
+    let file_names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+    let mut tools: BTreeMap<(usize, u32), DrillOperation> = BTreeMap::new();
+    for (index, (_, file)) in files.into_iter().enumerate() {
+        for op in file.operations {
+            match tools.get_mut(&(index, op.tool)) {
+                Some(existing) => existing.commands.extend(op.commands),
+                None => {
+                    tools.insert((index, op.tool), op);
+                }
+            }
+        }
+    }
+
+    let mut assignments = Vec::new();
+    let mut split = |hole_type: HoleType| -> Option<DrillFile> {
+        // Through holes sort first, then each via span, then by diameter
+        let mut members: Vec<(&(usize, u32), &DrillOperation)> = tools
+            .iter()
+            .filter(|(_, op)| op.hole_type == hole_type)
+            .collect();
+        members.sort_by(|(_, a), (_, b)| {
+            let span = |op: &DrillOperation| op.span.map(|s| (s.from, s.to));
+            span(a)
+                .cmp(&span(b))
+                .then(a.diameter.total_cmp(&b.diameter))
+        });
+
+        // Groups of tools of one span, within `tolerance` of the group's smallest
+        let mut groups: Vec<ToolGroup> = Vec::new();
+        let mut smallest = 0.0;
+        for (&(index, tool), op) in members {
+            let joins = groups.last().is_some_and(|(group, _, _)| {
+                group.span == op.span && op.diameter - smallest <= tolerance
+            });
+            if !joins {
+                smallest = op.diameter;
+                groups.push((
+                    DrillOperation {
+                        commands: Vec::new(),
+                        ..op.clone()
+                    },
+                    Vec::new(),
+                    0,
+                ));
+            }
+            let (group, sources, most_holes) = groups.last_mut().expect("group was pushed");
+            if op.commands.len() > *most_holes {
+                *most_holes = op.commands.len();
+                group.diameter = op.diameter;
+            }
+            group.commands.extend(op.commands.iter().cloned());
+            sources.push((index, tool));
+        }
+        if groups.is_empty() {
+            return None;
+        }
+
+        let mut operations = Vec::new();
+        for (position, (mut op, sources, _)) in groups.into_iter().enumerate() {
+            let output_tool = position as u32 + 1;
+            for (index, tool) in sources {
+                assignments.push(ToolAssignment {
+                    file: file_names[index].clone(),
+                    source_tool: tool,
+                    output_tool,
+                    diameter: op.diameter,
+                    hole_type,
+                });
+            }
+            op.tool = output_tool;
+            operations.push(op);
+        }
+        Some(DrillFile {
+            operations,
+            warnings: Vec::new(),
+        })
+    };
+    let pth = split(HoleType::Plated);
+    let npth = split(HoleType::NonPlated);
+
+    MergedDrills {
+        pth,
+        npth,
+        tools: assignments,
+        warnings: Vec::new(),
+    }
+}
+
+/// Holes closer than this (mm) are the same hole, see [`remove_duplicate_holes`]
+pub const DEFAULT_DUPLICATE_EPSILON_MM: f64 = 0.001;
+
+/// Removes holes that lie within `epsilon` mm of another hole of the file,
+/// as when a via file and a PTH file both list the same hole. The largest
+/// of coincident holes is kept; tools left without holes stay, so output
+/// tool numbers do not change. Returns how many holes were removed.
+pub fn remove_duplicate_holes(drill: &mut DrillFile, epsilon: f64) -> usize {
+    let epsilon = epsilon.max(1e-6);
+    // Holes by grid cell of size `epsilon`: a duplicate is in a neighbouring cell
+    let cell = |p: Point| {
+        (
+            (p.x / epsilon).floor() as i64,
+            (p.y / epsilon).floor() as i64,
+        )
+    };
+    let mut kept: HashMap<(i64, i64), Vec<Point>> = HashMap::new();
+
+    // Largest tools first, so their holes are the ones kept
+    let mut order: Vec<usize> = (0..drill.operations.len()).collect();
+    order.sort_by(|&a, &b| {
+        drill.operations[b]
+            .diameter
+            .total_cmp(&drill.operations[a].diameter)
+    });
+    let mut removed = 0;
+    for index in order {
+        drill.operations[index].commands.retain(|command| {
+            let DrillCommand::Hole { x, y } = *command else {
+                return true;
+            };
+            let hole = Point { x, y };
+            let (cx, cy) = cell(hole);
+            let duplicate = (cx - 1..=cx + 1)
+                .flat_map(|i| (cy - 1..=cy + 1).map(move |j| (i, j)))
+                .filter_map(|key| kept.get(&key))
+                .flatten()
+                .any(|p| (p.x - x).hypot(p.y - y) <= epsilon);
+            if duplicate {
+                removed += 1;
+            } else {
+                kept.entry((cx, cy)).or_default().push(hole);
+            }
+            !duplicate
+        });
+    }
+    removed
+}
+
+impl MergedDrills {
+    /// Runs [`remove_duplicate_holes`] on each hole type and warns about
+    /// the holes removed. Returns how many were removed.
+    pub fn remove_duplicate_holes(&mut self, epsilon: f64) -> usize {
+        let mut total = 0;
+        for (drill, label) in [(&mut self.pth, "plated"), (&mut self.npth, "non-plated")] {
+            let Some(drill) = drill else { continue };
+            let removed = remove_duplicate_holes(drill, epsilon);
+            if removed > 0 {
+                self.warnings.push(format!(
+                    "Removed {} duplicate {} hole(s) within {} mm of another hole",
+                    removed, label, epsilon
+                ));
+            }
+            total += removed;
+        }
+        total
+    }
+}
+
+/// Merge multiple drill files and split by hole type
+pub fn merge_and_split_drills(files: Vec<DrillFile>) -> (Option<DrillFile>, Option<DrillFile>) {
+    let (pth, npth, _) = merge_and_split_drills_with_dedupe(files, None);
+    (pth, npth)
+}
+
+/// Like [`merge_and_split_drills`], also removing holes within `epsilon` mm
+/// of another when it is set. Returns how many duplicates were removed.
+pub fn merge_and_split_drills_with_dedupe(
+    files: Vec<DrillFile>,
+    epsilon: Option<f64>,
+) -> (Option<DrillFile>, Option<DrillFile>, usize) {
+    let mut merged = merge_drill_files(
+        files
+            .into_iter()
+            .enumerate()
+            .map(|(i, file)| (format!("drill file {}", i + 1), file))
+            .collect(),
+    );
+    let removed = epsilon.map_or(0, |epsilon| merged.remove_duplicate_holes(epsilon));
+    (merged.pth, merged.npth, removed)
+}
+
+/// Which zeros a coordinate written without a decimal point keeps, as the
+/// `LZ`/`TZ` of the unit line declares
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZeroFormat {
+    /// Leading zeros are kept and trailing ones dropped
+    #[default]
+    Leading,
+    /// Trailing zeros are kept and leading ones dropped
+    Trailing,
+}
+
+impl ZeroFormat {
+    pub fn id(&self) -> &'static str {
+        match self {
+            ZeroFormat::Leading => "LZ",
+            ZeroFormat::Trailing => "TZ",
+        }
+    }
+}
+
+/// Number format of the drill files written for JLC. The default is the
+/// `METRIC,LZ,0000.00000` format with 5-decimal coordinates; the others match
+/// older or alternate parsers when a review flags the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrillOutputOptions {
+    pub zeros: ZeroFormat,
+    /// Digits before the decimal point, in mm
+    pub integer_digits: usize,
+    /// Digits after the decimal point, which is also the precision of the
+    /// tool diameters
+    pub decimal_digits: usize,
+    /// Writes coordinates with a decimal point, e.g. `X12.70000`, rather than
+    /// as fixed digits with `zeros` suppressed, e.g. `X0012.7` for 4.5 LZ
+    pub decimal_point: bool,
+}
+
+impl Default for DrillOutputOptions {
+    fn default() -> Self {
+        Self {
+            zeros: ZeroFormat::Leading,
+            integer_digits: 4,
+            decimal_digits: 5,
+            decimal_point: true,
+        }
+    }
+}
+
+impl DrillOutputOptions {
+    /// The unit line of the header, e.g. `METRIC,LZ,0000.00000`
+    pub fn unit_line(&self) -> String {
+        format!(
+            "METRIC,{},{}.{}",
+            self.zeros.id(),
+            "0".repeat(self.integer_digits),
+            "0".repeat(self.decimal_digits)
+        )
+    }
+
+    /// Writes a coordinate in mm in this format
+    pub fn coordinate(&self, value: f64) -> String {
+        if self.decimal_point {
+            return format!("{:.*}", self.decimal_digits, value);
+        }
+        let scaled = (value.abs() * 10f64.powi(self.decimal_digits as i32)).round() as u64;
+        let digits = format!(
+            "{:0width$}",
+            scaled,
+            width = self.integer_digits + self.decimal_digits
+        );
+        // Trailing zeros are only dropped from the decimals, so a value keeps
+        // its full integer part and reads back the same with or without them
+        let split = digits.len() - self.decimal_digits;
+        let digits = match self.zeros {
+            ZeroFormat::Leading => format!(
+                "{}{}",
+                &digits[..split],
+                digits[split..].trim_end_matches('0')
+            ),
+            ZeroFormat::Trailing => digits.trim_start_matches('0').to_string(),
+        };
+        match digits.as_str() {
+            "" => "0".to_string(),
+            _ if scaled > 0 && value < 0.0 => format!("-{}", digits),
+            _ => digits,
+        }
+    }
+
+    fn point(&self, x: f64, y: f64) -> String {
+        format!("X{}Y{}", self.coordinate(x), self.coordinate(y))
+    }
+}
+
+/// Generate JLC format Excellon content
+/// All coordinates are already in mm
+pub fn generate_jlc_excellon(drill: &DrillFile, hole_type: HoleType) -> String {
+    generate_jlc_excellon_with(drill, hole_type, &DrillOutputOptions::default())
+}
+
+/// Like [`generate_jlc_excellon`], in the number format of `options`
+pub fn generate_jlc_excellon_with(
+    drill: &DrillFile,
+    hole_type: HoleType,
+    options: &DrillOutputOptions,
+) -> String {
+    let mut output = Vec::new();
+    write_jlc_excellon(&mut output, drill, hole_type, options).expect("Writing to memory");
+    String::from_utf8(output).expect("Excellon output is ASCII")
+}
+
+/// Writes the JLC format Excellon file of `drill` to `out` a line at a time,
+/// so a large file is never held in memory as a whole. `out` is written to
+/// often; wrap files and sockets in a `BufWriter`.
+pub fn write_jlc_excellon<W: Write + ?Sized>(
+    out: &mut W,
+    drill: &DrillFile,
+    hole_type: HoleType,
+    options: &DrillOutputOptions,
+) -> io::Result<()> {
+    match hole_type {
+        HoleType::Plated => write_excellon(out, drill, "PLATED", "PTH_Through", options),
+        HoleType::NonPlated => write_excellon(out, drill, "NON_PLATED", "NPTH_Through", options),
+    }
+}
+
+/// Generate JLC format Excellon content for the plated vias of a blind or
+/// buried layer span
+pub fn generate_jlc_span_excellon(
+    drill: &DrillFile,
+    span: LayerSpan,
+    copper_layers: u32,
+) -> String {
+    generate_jlc_span_excellon_with(drill, span, copper_layers, &DrillOutputOptions::default())
+}
+
+/// Like [`generate_jlc_span_excellon`], in the number format of `options`
+pub fn generate_jlc_span_excellon_with(
+    drill: &DrillFile,
+    span: LayerSpan,
+    copper_layers: u32,
+    options: &DrillOutputOptions,
+) -> String {
+    let mut output = Vec::new();
+    write_jlc_span_excellon(&mut output, drill, span, copper_layers, options)
+        .expect("Writing to memory");
+    String::from_utf8(output).expect("Excellon output is ASCII")
+}
+
+/// Like [`write_jlc_excellon`], for the plated vias of a blind or buried
+/// layer span
+pub fn write_jlc_span_excellon<W: Write + ?Sized>(
+    out: &mut W,
+    drill: &DrillFile,
+    span: LayerSpan,
+    copper_layers: u32,
+    options: &DrillOutputOptions,
+) -> io::Result<()> {
+    let name = span.file_name(copper_layers);
+    let layer_name = name.trim_start_matches("Drill_").trim_end_matches(".DRL");
+    write_excellon(out, drill, "PLATED", layer_name, options)
+}
+
+fn write_excellon<W: Write + ?Sized>(
+    out: &mut W,
+    drill: &DrillFile,
+    type_str: &str,
+    layer_name: &str,
+    options: &DrillOutputOptions,
+) -> io::Result<()> {
+    // Add header
+    out.write_all(get_drill_header(type_str, layer_name).as_bytes())?;
+
+    // File header
+    writeln!(out, "M48")?;
+    writeln!(out, "{}", options.unit_line())?;
+
+    // Tool definitions
+    let precision = options.decimal_digits;
+    for (i, op) in drill.operations.iter().enumerate() {
+        let tool_num = i + 1;
+        writeln!(
+            out,
+            ";Hole size {} = {:.*} METRIC",
+            tool_num, precision, op.diameter
+        )?;
+        writeln!(out, "T{:02}C{:.*}", tool_num, precision, op.diameter)?;
+    }
+
+    writeln!(out, "%")?;
+    writeln!(out, "G05")?;
+    writeln!(out, "G90")?;
+
+    // Drill commands - coordinates are already in mm
+    for (i, op) in drill.operations.iter().enumerate() {
+        let tool_num = i + 1;
+        writeln!(out, "T{:02}", tool_num)?;
+
+        for cmd in &op.commands {
+            match cmd {
+                DrillCommand::Hole { x, y } => {
+                    writeln!(out, "{}", options.point(*x, *y))?;
+                }
+                DrillCommand::Route { points } => {
+                    // One G85 slot per segment, in the order they were routed
+                    for pair in points.windows(2) {
+                        writeln!(
+                            out,
+                            "{}G85{}",
+                            options.point(pair[0].x, pair[0].y),
+                            options.point(pair[1].x, pair[1].y)
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    writeln!(out, "M30")
+}
+
+/// Smallest hole JLC drills mechanically, in mm
+pub const MIN_DRILL_MM: f64 = 0.15;
+/// Smallest hole of the standard services; smaller ones cost extra, in mm
+pub const TYPICAL_MIN_DRILL_MM: f64 = 0.3;
+/// Largest drill bit; larger holes must be routed, in mm
+pub const MAX_DRILL_MM: f64 = 6.3;
+/// Largest board thickness to plated hole diameter ratio JLC plates reliably
+pub const MAX_ASPECT_RATIO: f64 = 10.0;
+/// Board thickness assumed for the aspect ratio, in mm
+pub const STANDARD_BOARD_THICKNESS_MM: f64 = 1.6;
+
+/// Warnings for the tools of a merged `hole_type` file that JLC cannot make
+/// or only makes at extra cost: holes below [`MIN_DRILL_MM`] or
+/// [`TYPICAL_MIN_DRILL_MM`], above [`MAX_DRILL_MM`], and plated through holes
+/// deeper than [`MAX_ASPECT_RATIO`] times their diameter on a
+/// [`STANDARD_BOARD_THICKNESS_MM`] board. `label` names the file in messages.
+pub fn drill_capability_warnings(
+    drill: &DrillFile,
+    hole_type: HoleType,
+    label: &str,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for op in &drill.operations {
+        let count = op.commands.len();
+        if count == 0 {
+            continue;
+        }
+        let tool = format!(
+            "{} T{:02} ({} x {:.3} mm)",
+            label, op.tool, count, op.diameter
+        );
+        if op.diameter < MIN_DRILL_MM {
+            warnings.push(format!(
+                "{}: below JLC's {} mm minimum drill; these holes cannot be made",
+                tool, MIN_DRILL_MM
+            ));
+        } else if op.diameter < TYPICAL_MIN_DRILL_MM {
+            warnings.push(format!(
+                "{}: below the typical {} mm minimum drill; expect extra cost",
+                tool, TYPICAL_MIN_DRILL_MM
+            ));
+        }
+        let drilled = op
+            .commands
+            .iter()
+            .any(|c| matches!(c, DrillCommand::Hole { .. }));
+        if drilled && op.diameter > MAX_DRILL_MM {
+            warnings.push(format!(
+                "{}: above JLC's maximum drill of {} mm; route these holes as cutouts",
+                tool, MAX_DRILL_MM
+            ));
+        }
+        let ratio = STANDARD_BOARD_THICKNESS_MM / op.diameter;
+        if hole_type == HoleType::Plated
+            && op.span.is_none()
+            && op.diameter >= MIN_DRILL_MM
+            && ratio > MAX_ASPECT_RATIO
+        {
+            warnings.push(format!(
+                "{}: aspect ratio {:.1}:1 on a {} mm board exceeds {}:1; plating may fail",
+                tool, ratio, STANDARD_BOARD_THICKNESS_MM, MAX_ASPECT_RATIO
+            ));
+        }
+    }
+    warnings
+}
+
+/// Router bit that cuts oversized holes routed as slots, in mm
+pub const ROUTE_BIT_MM: f64 = 2.0;
+
+/// What becomes of drilled holes larger than [`MAX_DRILL_MM`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizedHoles {
+    /// Left as drill tools, with a warning
+    #[default]
+    Keep,
+    /// Routed as a circle of G85 slots with a [`ROUTE_BIT_MM`] bit
+    Slots,
+    /// Non-plated holes drawn as cutouts on the board outline layer; plated
+    /// ones are routed as slots, since the outline is not plated
+    Outline,
+}
+
+/// Removes the drilled holes larger than `max` mm from `drill` and returns
+/// their centres and diameters. Tools left without holes are dropped.
+pub fn take_oversized_holes(drill: &mut DrillFile, max: f64) -> Vec<(Point, f64)> {
+    let mut taken = Vec::new();
+    for op in drill.operations.iter_mut().filter(|op| op.diameter > max) {
+        op.commands.retain(|command| match *command {
+            DrillCommand::Hole { x, y } => {
+                taken.push((Point { x, y }, op.diameter));
+                false
+            }
+            DrillCommand::Route { .. } => true,
+        });
+    }
+    drill.operations.retain(|op| !op.commands.is_empty());
+    taken
+}
+
+/// Adds `holes`, as (centre, diameter) pairs, to `drill` as circular routes
+/// of a [`ROUTE_BIT_MM`] tool whose outer edge follows each hole.
+pub fn route_holes(drill: &mut DrillFile, holes: &[(Point, f64)], hole_type: HoleType) {
+    let routes = holes.iter().map(|&(center, diameter)| {
+        let radius = (diameter - ROUTE_BIT_MM) / 2.0;
+        let start = Point::new(center.x + radius, center.y);
+        DrillCommand::Route {
+            points: flatten_arc(start, start, center, false, ROUTE_ARC_TOLERANCE_MM),
+        }
+    });
+    match drill
+        .operations
+        .iter_mut()
+        .find(|op| op.span.is_none() && (op.diameter - ROUTE_BIT_MM).abs() < 1e-6)
+    {
+        Some(op) => op.commands.extend(routes),
+        None => drill.operations.push(DrillOperation {
+            tool: 0,
+            diameter: ROUTE_BIT_MM,
+            hole_type,
+            commands: routes.collect(),
+            span: None,
+        }),
+    }
+    // Output tools are numbered by diameter
+    drill
+        .operations
+        .sort_by(|a, b| a.diameter.total_cmp(&b.diameter));
+    for (index, op) in drill.operations.iter_mut().enumerate() {
+        op.tool = index as u32 + 1;
+    }
+}
+
+/// Main entry point: process multiple drill files and return PTH/NPTH content
+pub fn process_drill_files(contents: &[String], filenames: &[String]) -> DrillResult {
+    process_drill_files_with_options(contents, filenames, &DrillProcessOptions::default())
+}
+
+/// What is known about the stackup of a set, to place blind and buried vias,
+/// and the tool sizes its drill files leave to a separate report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanContext {
+    pub copper_layers: u32,
+    /// Altium mid layers, which are numbered before the internal planes
+    pub mid_layers: u32,
+    /// (drill file name, span) pairs, e.g. from [`parse_drill_report_spans`]
+    pub spans: Vec<(String, LayerSpan)>,
+    /// Tools from an Allegro tool report, see [`parse_tool_report`]
+    pub tool_report: Vec<ReportedTool>,
+}
+
+impl SpanContext {
+    // The span of a drill file: from the report, else from its header or name
+    fn span_of(&self, filename: &str, content: &str) -> Option<LayerSpan> {
+        self.spans
+            .iter()
+            .find(|(name, _)| base_name(name).eq_ignore_ascii_case(base_name(filename)))
+            .map(|(_, span)| *span)
+            .or_else(|| header_layer_span(content, self.copper_layers, self.mid_layers))
+            // Names are only trusted when the stackup tells through from blind
+            .or_else(|| {
+                span_from_filename(filename)
+                    .filter(|span| self.copper_layers > 0 && span.to <= self.copper_layers)
+            })
+    }
+}
+
+/// JLC makes blind and buried vias on boards of this many layers and more
+pub const MIN_BLIND_VIA_LAYERS: u32 = 4;
+
+/// Options for [`process_drill_files_with_options`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillProcessOptions {
+    /// Board outline extents (in mm) every file is checked against, see
+    /// [`align_units_to_board`]
+    pub board: Option<BoundingBox>,
+    /// Converts files with a misdeclared unit to mm, instead of only
+    /// reporting them
+    pub fix_units: bool,
+    /// Stackup to place blind and buried via files (Altium `.TX1` to `.TX9`)
+    /// by their layer pair. Each span gets its own output in
+    /// [`DrillResult::spans`]; files whose span is unknown are skipped with a
+    /// warning.
+    pub context: SpanContext,
+    /// Tools whose diameters differ by up to this many mm are merged, see
+    /// [`merge_drill_files_with_tolerance`]
+    pub tolerance: f64,
+    /// Number format of the output files
+    pub format: DrillOutputOptions,
+    /// Removes holes within this many mm of another of the same hole type,
+    /// see [`remove_duplicate_holes`]
+    pub dedupe_epsilon: Option<f64>,
+}
+
+impl Default for DrillProcessOptions {
+    fn default() -> Self {
+        Self {
+            board: None,
+            fix_units: false,
+            context: SpanContext::default(),
+            tolerance: DEFAULT_DIAMETER_TOLERANCE_MM,
+            format: DrillOutputOptions::default(),
+            dedupe_epsilon: None,
+        }
+    }
+}
+
+/// Merges drill files into the JLC PTH and NPTH files, and one file per
+/// blind or buried via span, as `options` say.
+pub fn process_drill_files_with_options(
+    contents: &[String],
+    filenames: &[String],
+    options: &DrillProcessOptions,
+) -> DrillResult {
+    let mut processed = process_drill_files_parsed(contents, filenames, options);
+    let format = &options.format;
+    let copper_layers = options.context.copper_layers;
+    for (drill, hole_type, label) in [
+        (&processed.pth, HoleType::Plated, "PTH"),
+        (&processed.npth, HoleType::NonPlated, "NPTH"),
+    ] {
+        if let Some(drill) = drill {
+            let warnings = drill_capability_warnings(drill, hole_type, label);
+            processed.warnings.extend(warnings);
+        }
+    }
+    for (span, drill) in &processed.spans {
+        let label = span.file_name(copper_layers);
+        let warnings = drill_capability_warnings(drill, HoleType::Plated, &label);
+        processed.warnings.extend(warnings);
+    }
+    DrillResult {
+        pth_content: processed
+            .pth
+            .map(|f| generate_jlc_excellon_with(&f, HoleType::Plated, format)),
+        npth_content: processed
+            .npth
+            .map(|f| generate_jlc_excellon_with(&f, HoleType::NonPlated, format)),
+        spans: processed
+            .spans
+            .into_iter()
+            .map(|(span, f)| {
+                let content = generate_jlc_span_excellon_with(&f, span, copper_layers, format);
+                (span, content)
+            })
+            .collect(),
+        warnings: processed.warnings,
+        unit_fixes: processed.unit_fixes,
+        tools: processed.tools,
+        report: processed.report,
+    }
+}
+
+/// Merged drill files before they are written, see
+/// [`process_drill_files_parsed`]
+#[derive(Debug)]
+pub struct ProcessedDrills {
+    pub pth: Option<DrillFile>,
+    pub npth: Option<DrillFile>,
+    /// Blind and buried vias, one file per layer span
+    pub spans: Vec<(LayerSpan, DrillFile)>,
+    /// Problems found while reading and merging the files. Tools JLC cannot
+    /// make are left to [`drill_capability_warnings`], to be checked once
+    /// the files are final.
+    pub warnings: Vec<String>,
+    /// (filename, description) of every drill file whose unit was corrected
+    pub unit_fixes: Vec<(String, String)>,
+    /// The output tool each input tool went to
+    pub tools: Vec<ToolAssignment>,
+    /// Hole counts of the output files
+    pub report: DrillReport,
+}
+
+/// Like [`process_drill_files_with_options`], returning the merged files
+/// parsed, to be streamed with [`write_jlc_excellon`] and
+/// [`write_jlc_span_excellon`] rather than held as text.
+pub fn process_drill_files_parsed(
+    contents: &[String],
+    filenames: &[String],
+    options: &DrillProcessOptions,
+) -> ProcessedDrills {
+    process_drill_files_cancellable(contents, filenames, options, &NeverCancel)
+        .expect("NeverCancel never cancels")
+}
+
+/// Same as [`process_drill_files_parsed`], but polls `cancel` before each
+/// input file and each merge, returning `Err(Cancelled)` as soon as
+/// cancellation is requested.
+pub fn process_drill_files_cancellable(
+    contents: &[String],
+    filenames: &[String],
+    options: &DrillProcessOptions,
+    cancel: &dyn Cancellation,
+) -> Result<ProcessedDrills, Cancelled> {
+    let board = options.board.as_ref();
+    let fix_units = options.fix_units;
+    let context = &options.context;
+    let tolerance = options.tolerance;
+    let mut all_files: Vec<(String, DrillFile)> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut unit_fixes: Vec<(String, String)> = Vec::new();
+    let mut duplicates_removed = 0;
+
+    for (content, filename) in contents.iter().zip(filenames.iter()) {
+        cancel.check()?;
+        // Blind/buried vias have a span other than top to bottom
+        let span = context
+            .span_of(filename, content)
+            .filter(|span| !span.is_through(context.copper_layers));
+        if span.is_none() && !is_through_drill(filename) {
+            warnings.push(format!(
+                "Skipped blind/buried via file: {}. Its layer pair was not found in the drill report or its header.",
+                filename
+            ));
+            continue;
+        }
+        if span.is_some() && context.copper_layers < MIN_BLIND_VIA_LAYERS {
+            warnings.push(format!(
+                "Skipped blind/buried via file: {}. JLC makes blind and buried vias only on boards with {} or more layers.",
+                filename, MIN_BLIND_VIA_LAYERS
+            ));
+            continue;
+        }
+
+        let eda_type = detect_drill_eda_for(filename, content);
+        let mut check_board = |drill_file: &mut DrillFile| {
+            let Some(board) = board else {
+                return;
+            };
+            let mut checked = drill_file.clone();
+            match align_units_to_board(&mut checked, board, declares_unit(content)) {
+                UnitAlignment::Aligned => {}
+                UnitAlignment::Suspect(factor) => {
+                    let unit = if factor > 1.0 { "inch" } else { "mm" };
+                    warnings.push(format!(
+                        "{}: holes bunch in a corner of the board, but would spread over it when read as {}; check the declared drill unit",
+                        filename, unit
+                    ));
+                }
+                UnitAlignment::Rescaled(factor) => {
+                    let unit = if factor > 1.0 { "inch" } else { "mm" };
+                    if fix_units {
+                        *drill_file = checked;
+                        unit_fixes.push((
+                            filename.clone(),
+                            format!(
+                                "holes match the board outline when read as {}; converted to mm",
+                                unit
+                            ),
+                        ));
+                    } else {
+                        warnings.push(format!(
+                            "{}: holes only match the board outline when read as {}",
+                            filename, unit
+                        ));
+                    }
+                }
+                UnitAlignment::Mismatch => warnings.push(format!(
+                    "{}: holes extend beyond the board outline; check the drill units and origin",
+                    filename
+                )),
+            }
+        };
+
+        let mut drill_file = match eda_type {
+            // KiCad already separates PTH and NPTH
+            DrillEdaType::KiCad => parse_kicad_excellon(content).0,
+            _ => {
+                let mut drill_file = parse_ad_excellon(content);
+                // Tools default to plated; a non-plated file name overrides that
+                // unless the file states its plating with TYPE markers or X2
+                // attributes, or marked some tools itself
+                if hole_type_from_filename(filename) == Some(HoleType::NonPlated)
+                    && !declares_plating(content)
+                    && drill_file
+                        .operations
+                        .iter()
+                        .all(|op| op.hole_type == HoleType::Plated)
+                {
+                    for op in &mut drill_file.operations {
+                        op.hole_type = HoleType::NonPlated;
+                    }
+                }
+                drill_file
+            }
+        };
+        if !context.tool_report.is_empty() {
+            apply_tool_report(&mut drill_file, &context.tool_report);
+        }
+        check_board(&mut drill_file);
+        warnings.extend(hole_count_warnings(&drill_file, content, filename));
+        warnings.extend(
+            drill_file
+                .warnings
+                .iter()
+                .map(|w| format!("{}: {}", filename, w)),
+        );
+        for op in &mut drill_file.operations {
+            op.span = span;
+        }
+        all_files.push((filename.clone(), drill_file));
+    }
+
+    // Blind and buried vias go to one file per span
+    let mut span_files: BTreeMap<(u32, u32), Vec<(String, DrillFile)>> = BTreeMap::new();
+    for (filename, file) in &mut all_files {
+        let (vias, through): (Vec<DrillOperation>, Vec<DrillOperation>) =
+            file.operations.drain(..).partition(|op| op.span.is_some());
+        file.operations = through;
+        for op in vias {
+            let Some(span) = op.span else { continue };
+            let files = span_files.entry((span.from, span.to)).or_default();
+            match files.iter_mut().find(|(name, _)| name == filename) {
+                Some((_, via_file)) => via_file.operations.push(op),
+                None => files.push((
+                    filename.clone(),
+                    DrillFile {
+                        operations: vec![op],
+                        warnings: Vec::new(),
+                    },
+                )),
+            }
+        }
+    }
+
+    // Vias are plated, whatever the file says
+    let mut spans = Vec::new();
+    for ((from, to), mut files) in span_files {
+        cancel.check()?;
+        for (_, file) in &mut files {
+            for op in &mut file.operations {
+                op.hole_type = HoleType::Plated;
+            }
+        }
+        let mut merged = merge_drill_files_with_tolerance(files, tolerance);
+        if let Some(epsilon) = options.dedupe_epsilon {
+            duplicates_removed += merged.remove_duplicate_holes(epsilon);
+        }
+        warnings.extend(merged.warnings);
+        if let Some(file) = merged.pth {
+            spans.push((LayerSpan { from, to }, file));
+        }
+    }
+
+    cancel.check()?;
+    let mut merged = merge_drill_files_with_tolerance(all_files, tolerance);
+    if let Some(epsilon) = options.dedupe_epsilon {
+        duplicates_removed += merged.remove_duplicate_holes(epsilon);
+    }
+    warnings.extend(merged.warnings);
+    let mut report = DrillReport::from_files(
+        merged
+            .pth
+            .iter()
+            .chain(&merged.npth)
+            .chain(spans.iter().map(|(_, file)| file)),
+    );
+    report.duplicates_removed = duplicates_removed;
+    Ok(ProcessedDrills {
+        pth: merged.pth,
+        npth: merged.npth,
+        spans,
+        warnings,
+        unit_fixes,
+        tools: merged.tools,
+        report,
+    })
+}
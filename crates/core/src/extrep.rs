@@ -0,0 +1,129 @@
+/* src/extrep.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::gerber;
+use crate::rename::{self, EdaType};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// This regex matches one row of the report table, e.g. ".GTL    Top Layer".
+static ENTRY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(\.[A-Za-z0-9]+)\s+(\S.*?)\s*$").expect("Invalid entry regex"));
+
+/// One row of an Altium extension report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionEntry {
+    /// Extension including the dot, upper-cased (".GTL")
+    pub extension: String,
+    /// Layer description as written by Altium ("Top Layer")
+    pub description: String,
+}
+
+/// Parsed Altium Designer Gerber extension report (`.EXTREP`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionReport {
+    pub entries: Vec<ExtensionEntry>,
+}
+
+impl ExtensionReport {
+    /// The description Altium gave to the extension of `filename`.
+    pub fn description_for(&self, filename: &str) -> Option<&str> {
+        let extension = filename.rfind('.').map(|i| filename[i..].to_uppercase())?;
+        self.entries
+            .iter()
+            .find(|e| e.extension == extension)
+            .map(|e| e.description.as_str())
+    }
+}
+
+/// Checks if a file is an Altium extension report.
+pub(crate) fn is_extension_report(filename: &str) -> bool {
+    filename.to_lowercase().ends_with(".extrep")
+}
+
+/// Parses an extension report. Separator and title lines are skipped.
+pub fn parse_extension_report(content: &str) -> ExtensionReport {
+    let entries = content
+        .lines()
+        .filter_map(|line| {
+            let caps = ENTRY_REGEX.captures(line)?;
+            Some(ExtensionEntry {
+                extension: caps[1].to_uppercase(),
+                description: caps[2].to_string(),
+            })
+        })
+        .collect();
+    ExtensionReport { entries }
+}
+
+/// Maps an Altium layer description to a logical name.
+/// Planes and mechanical layers return `None` because their role depends on the design.
+pub(crate) fn logical_name_from_description(description: &str) -> Option<String> {
+    let key: String = description
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    let name = match key.as_str() {
+        "toplayer" => "Gerber_TopLayer",
+        "bottomlayer" => "Gerber_BottomLayer",
+        "topoverlay" => "Gerber_TopSilkscreenLayer",
+        "bottomoverlay" => "Gerber_BottomSilkscreenLayer",
+        "topsolder" => "Gerber_TopSolderMaskLayer",
+        "bottomsolder" => "Gerber_BottomSolderMaskLayer",
+        "toppaste" => "Gerber_TopPasteMaskLayer",
+        "bottompaste" => "Gerber_BottomPasteMaskLayer",
+        _ => {
+            let index: u32 = key.strip_prefix("midlayer")?.parse().ok()?;
+            return Some(format!("Gerber_InnerLayer{}", index));
+        }
+    };
+    Some(name.to_string())
+}
+
+/// Cross-checks files against the extension report and returns one warning per conflict.
+///
+/// A file conflicts when the report's role for its extension differs from the
+/// role given by our rename rules, or from the role in its own X2 attributes.
+/// The latter catches files whose extension the user changed after export.
+pub fn verify_mapping(report: &ExtensionReport, files: &[(String, String)]) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for (name, content) in files {
+        let Some(description) = report.description_for(name) else {
+            continue;
+        };
+        let Some(expected) = logical_name_from_description(description) else {
+            continue;
+        };
+
+        if let Some(mapped) = rename::logical_name_for(name, EdaType::Ad)
+            && mapped != expected
+        {
+            warnings.push(format!(
+                "{}: extension report lists it as \"{}\" but it would be renamed as {}",
+                name, description, mapped
+            ));
+        }
+
+        if let Some(fields) = gerber::file_function(content)
+            && let Some(actual) = rename::logical_name_from_file_function(&fields)
+            && actual != expected
+        {
+            warnings.push(format!(
+                "{}: extension report lists it as \"{}\" but its content is {} ({}); was the file renamed after export?",
+                name,
+                description,
+                actual,
+                fields.join(",")
+            ));
+        }
+    }
+
+    warnings
+}
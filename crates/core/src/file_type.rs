@@ -0,0 +1,88 @@
+/* src/file_type.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+/// This function attempts to identify the CAD software that generated a file
+/// based on the presence of specific keywords in its content.
+///
+/// The matching is case-insensitive and checks for keywords in the following order:
+/// 1. "altium" or "protel" (Protel 99 SE, its predecessor)
+/// 2. "kicad"
+/// 3. "easyeda"
+/// 4. "eagle" (also written by Fusion 360)
+/// 5. "pads layout" or "pads professional"
+/// 6. "proteus"
+/// 7. "librepcb"
+/// 8. "horizon eda" or "generationsoftware,horizon"
+/// 9. "diptrace"
+///
+/// It returns the first match it finds.
+///
+/// # Arguments
+///
+/// * `content` - A string slice (`&str`) representing the content of the file.
+///
+/// # Returns
+///
+/// An `Option<&'static str>`:
+/// - `Some("Altium")` if "altium" or "protel" is found.
+/// - `Some("KiCad")` if "kicad" is found.
+/// - `Some("EasyEDA")` if "easyeda" is found.
+/// - `Some("Eagle")` if "eagle" is found.
+/// - `Some("PADS")` if "pads layout" or "pads professional" is found.
+/// - `Some("Proteus")` if "proteus" is found.
+/// - `Some("LibrePCB")` if "librepcb" is found.
+/// - `Some("Horizon")` if "horizon eda" or "generationsoftware,horizon" is found.
+/// - `Some("DipTrace")` if "diptrace" is found.
+/// - `None` if none of the keywords are found.
+pub fn identify_software(content: &str) -> Option<&'static str> {
+    // Convert the entire content to lowercase for case-insensitive matching.
+    let lowercased_content = content.to_lowercase();
+
+    // Check for keywords in a specific order of priority.
+    if lowercased_content.contains("altium") || lowercased_content.contains("protel") {
+        return Some("Altium");
+    }
+
+    if lowercased_content.contains("kicad") {
+        return Some("KiCad");
+    }
+
+    if lowercased_content.contains("easyeda") {
+        return Some("EasyEDA");
+    }
+
+    if lowercased_content.contains("eagle") {
+        return Some("Eagle");
+    }
+
+    if lowercased_content.contains("pads layout")
+        || lowercased_content.contains("pads professional")
+    {
+        return Some("PADS");
+    }
+
+    if lowercased_content.contains("proteus") {
+        return Some("Proteus");
+    }
+
+    if lowercased_content.contains("librepcb") {
+        return Some("LibrePCB");
+    }
+
+    if lowercased_content.contains("horizon eda")
+        || lowercased_content.contains("generationsoftware,horizon")
+    {
+        return Some("Horizon");
+    }
+
+    if lowercased_content.contains("diptrace") {
+        return Some("DipTrace");
+    }
+
+    // If no keywords are matched, return None.
+    None
+}
@@ -5,44 +5,113 @@
  * Author HalfSweet <halfsweet@halfsweet.cn>
  */
 
+use crate::cancel::{CHECK_INTERVAL, Cancellation, Cancelled, NeverCancel};
 use md5::{Digest, Md5};
 use regex::Regex;
 use std::collections::BTreeMap;
 
+/// How the fingerprint aperture is made from the aperture it is based on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FingerprintMode {
+    /// Resize the first modifier of the chosen aperture definition
+    #[default]
+    Standard,
+    /// For very old Altium (AD9) exports, whose apertures may carry
+    /// nonstandard modifiers: unless the chosen definition is a plain
+    /// C/R/O/P aperture, append a circle instead of resizing it
+    Legacy,
+}
+
 /// The main public function for embedding a fingerprint aperture.
 pub fn add_fingerprint(gerber_content: &str, is_foreign_board_file: bool) -> String {
+    add_fingerprint_with_mode(
+        gerber_content,
+        is_foreign_board_file,
+        FingerprintMode::Standard,
+    )
+}
+
+/// Like [`add_fingerprint`], with the aperture made according to `mode`.
+pub fn add_fingerprint_with_mode(
+    gerber_content: &str,
+    is_foreign_board_file: bool,
+    mode: FingerprintMode,
+) -> String {
+    embed_fingerprint(gerber_content, is_foreign_board_file, mode, &NeverCancel)
+        .expect("NeverCancel never cancels")
+}
+
+/// Same as [`add_fingerprint`], but polls `cancel` between stages and inside the
+/// line loops, returning `Err(Cancelled)` as soon as cancellation is requested.
+pub fn add_fingerprint_cancellable(
+    gerber_content: &str,
+    is_foreign_board_file: bool,
+    cancel: &dyn Cancellation,
+) -> Result<String, Cancelled> {
+    embed_fingerprint(
+        gerber_content,
+        is_foreign_board_file,
+        FingerprintMode::Standard,
+        cancel,
+    )
+}
+
+/// Like [`add_fingerprint_cancellable`], with the aperture made according to `mode`.
+pub fn add_fingerprint_with_mode_cancellable(
+    gerber_content: &str,
+    is_foreign_board_file: bool,
+    mode: FingerprintMode,
+    cancel: &dyn Cancellation,
+) -> Result<String, Cancelled> {
+    embed_fingerprint(gerber_content, is_foreign_board_file, mode, cancel)
+}
+
+fn embed_fingerprint(
+    gerber_content: &str,
+    is_foreign_board_file: bool,
+    mode: FingerprintMode,
+    cancel: &dyn Cancellation,
+) -> Result<String, Cancelled> {
     // Normalize line endings and strip BOM to ensure consistent scanning.
     let mut normalized = gerber_content.replace("\r\n", "\n");
     normalized = normalized.trim_start_matches('\u{FEFF}').to_string();
 
     if normalized.len() > 30_000_000 {
-        return normalized;
+        return Ok(normalized);
     }
 
     let (definitions, numbers) = scan_for_aperture_definitions(&normalized);
     if definitions.len() < 5 {
-        return normalized;
+        return Ok(normalized);
     }
 
     let (template, target_number, original_number) =
         select_injection_template(&definitions, &numbers);
 
+    cancel.check()?;
     let content_with_shifted_ids = renumber_apertures(&normalized, original_number);
 
+    cancel.check()?;
     let final_dimension_str =
         generate_hashed_dimension(&content_with_shifted_ids, is_foreign_board_file);
 
-    let final_fingerprint_line = create_fingerprint_aperture_line(
-        &template,
-        target_number,
-        original_number,
-        &final_dimension_str,
-    );
+    let final_fingerprint_line = if mode == FingerprintMode::Legacy && !is_plain_aperture(&template)
+    {
+        format!("%ADD{}C,{}*%", target_number, final_dimension_str)
+    } else {
+        create_fingerprint_aperture_line(
+            &template,
+            target_number,
+            original_number,
+            &final_dimension_str,
+        )
+    };
 
     insert_new_aperture_line(
         &content_with_shifted_ids,
         &final_fingerprint_line,
         target_number,
+        cancel,
     )
 }
 
@@ -53,11 +122,11 @@ fn scan_for_aperture_definitions(content: &str) -> (Vec<String>, Vec<u32>) {
 
     for line in content.lines().take(200) {
         let line_trim = line.trim();
-        if let Some(caps) = re.captures(line_trim) {
-            if let Ok(num) = caps[1].parse::<u32>() {
-                definitions.push(line_trim.to_string());
-                numbers.push(num);
-            }
+        if let Some(caps) = re.captures(line_trim)
+            && let Ok(num) = caps[1].parse::<u32>()
+        {
+            definitions.push(line_trim.to_string());
+            numbers.push(num);
         }
     }
     (definitions, numbers)
@@ -85,6 +154,25 @@ fn select_injection_template(definitions: &[String], numbers: &[u32]) -> (String
     (template, target_number, original_number)
 }
 
+/// Whether a definition is a standard circle, rectangle, obround or polygon
+/// with only as many numeric modifiers as the template takes, so resizing
+/// its first modifier keeps it valid.
+fn is_plain_aperture(definition: &str) -> bool {
+    let re = Regex::new(r"^%ADD\d{2,4}([CROP]),([^*]*)\*%$").unwrap();
+    let Some(caps) = re.captures(definition) else {
+        return false;
+    };
+    let modifiers: Vec<&str> = caps[2].split('X').collect();
+    let count = match &caps[1] {
+        "C" => 1..=3,
+        _ => 2..=4,
+    };
+    count.contains(&modifiers.len())
+        && modifiers
+            .iter()
+            .all(|m| m.parse::<f64>().is_ok_and(|v| v >= 0.0) && !m.starts_with('+'))
+}
+
 fn renumber_apertures(content: &str, original_number: u32) -> String {
     let re = Regex::new(r"(?m)^(%ADD|G54D)(\d{2,4})").unwrap();
     re.replace_all(content, |caps: &regex::Captures| {
@@ -148,12 +236,20 @@ fn create_fingerprint_aperture_line(
         .to_string()
 }
 
-fn insert_new_aperture_line(content: &str, fingerprint_line: &str, target_number: u32) -> String {
+fn insert_new_aperture_line(
+    content: &str,
+    fingerprint_line: &str,
+    target_number: u32,
+    cancel: &dyn Cancellation,
+) -> Result<String, Cancelled> {
     let mut result_lines: Vec<String> = Vec::new();
     let mut inserted = false;
     let insertion_anchor = format!("%ADD{}", target_number - 1);
 
-    for line in content.split('\n') {
+    for (index, line) in content.split('\n').enumerate() {
+        if index % CHECK_INTERVAL == 0 {
+            cancel.check()?;
+        }
         result_lines.push(line.to_string());
         if line.starts_with(&insertion_anchor) {
             result_lines.push(fingerprint_line.to_string());
@@ -162,12 +258,15 @@ fn insert_new_aperture_line(content: &str, fingerprint_line: &str, target_number
     }
 
     if inserted {
-        return result_lines.join("\n");
+        return Ok(result_lines.join("\n"));
     }
 
     let mut final_lines: Vec<String> = Vec::new();
     let mut mo_found = false;
-    for line in content.split('\n') {
+    for (index, line) in content.split('\n').enumerate() {
+        if index % CHECK_INTERVAL == 0 {
+            cancel.check()?;
+        }
         if !mo_found && line.starts_with("%MO") {
             mo_found = true;
         } else if mo_found && !inserted && (line.starts_with("%LP") || line.starts_with('G')) {
@@ -180,5 +279,5 @@ fn insert_new_aperture_line(content: &str, fingerprint_line: &str, target_number
     if !inserted {
         final_lines.push(fingerprint_line.to_string());
     }
-    final_lines.join("\n")
+    Ok(final_lines.join("\n"))
 }
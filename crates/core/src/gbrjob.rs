@@ -0,0 +1,249 @@
+/* src/gbrjob.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::json::{self, JsonValue};
+use crate::outline;
+use crate::rename::{self, Confidence, MatchSource, RenameResult};
+use chrono::Local;
+
+/// Name of the generated job file, matching the JLC name of imported job files.
+pub const GBRJOB_FILENAME: &str = "Gerber_GBR_JOB.GBRJOB";
+
+/// Returns the logical name of a JLC output file ("Gerber_TopLayer.GTL" -> "Gerber_TopLayer").
+fn logical_name(filename: &str) -> &str {
+    filename.split('.').next().unwrap_or(filename)
+}
+
+/// Number of copper layers in a set of JLC-named files.
+fn copper_layer_count(filenames: &[&str]) -> u32 {
+    filenames
+        .iter()
+        .filter(|name| {
+            let logical = logical_name(name);
+            logical == "Gerber_TopLayer"
+                || logical == "Gerber_BottomLayer"
+                || logical.starts_with("Gerber_InnerLayer")
+        })
+        .count() as u32
+}
+
+/// The X2 file function and polarity of a JLC layer.
+/// Bottom copper is numbered after the inner layers, as the job format requires.
+pub(crate) fn file_function_for(
+    logical: &str,
+    copper_layers: u32,
+) -> Option<(String, &'static str)> {
+    let bottom = copper_layers.max(2);
+    let function = match logical {
+        "Gerber_TopLayer" => "Copper,L1,Top".to_string(),
+        "Gerber_BottomLayer" => format!("Copper,L{},Bot", bottom),
+        "Gerber_TopSolderMaskLayer" => return Some(("Soldermask,Top".to_string(), "Negative")),
+        "Gerber_BottomSolderMaskLayer" => return Some(("Soldermask,Bot".to_string(), "Negative")),
+        "Gerber_TopSilkscreenLayer" => "Legend,Top".to_string(),
+        "Gerber_BottomSilkscreenLayer" => "Legend,Bot".to_string(),
+        "Gerber_TopPasteMaskLayer" => "Paste,Top".to_string(),
+        "Gerber_BottomPasteMaskLayer" => "Paste,Bot".to_string(),
+        "Gerber_BoardOutlineLayer" => "Profile,NP".to_string(),
+        // Kept by DrillMapPolicy::Document
+        "Gerber_Drill_Map" => "Drillmap".to_string(),
+        "Gerber_VCutLayer" => "Vcut".to_string(),
+        _ => {
+            let inner: u32 = logical.strip_prefix("Gerber_InnerLayer")?.parse().ok()?;
+            format!("Copper,L{},Inr", inner + 1)
+        }
+    };
+    Some((function, "Positive"))
+}
+
+/// Builds a Gerber job file (`.gbrjob`) describing a converted set.
+///
+/// `files` are (output name, content) pairs. Only Gerber layers with a known
+/// role are listed, so KiCad's Gerber viewer can open the set with the right
+/// layer assignment. Sizes are in mm, as the job format specifies.
+pub fn generate_gbrjob(files: &[(String, String)]) -> String {
+    let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+    let copper_layers = copper_layer_count(&names);
+
+    let board_size = files
+        .iter()
+        .find(|(name, _)| logical_name(name) == "Gerber_BoardOutlineLayer")
+        .and_then(|(_, content)| outline::compute_board_size(content));
+
+    let file_attributes: Vec<JsonValue> = files
+        .iter()
+        .filter_map(|(name, _)| {
+            let (function, polarity) = file_function_for(logical_name(name), copper_layers)?;
+            Some(JsonValue::Object(vec![
+                ("Path".to_string(), name.as_str().into()),
+                ("FileFunction".to_string(), function.into()),
+                ("FilePolarity".to_string(), polarity.into()),
+            ]))
+        })
+        .collect();
+
+    let mut general_specs = vec![(
+        "ProjectId".to_string(),
+        JsonValue::Object(vec![
+            ("Name".to_string(), "nextjlc".into()),
+            (
+                "GUID".to_string(),
+                "00000000-0000-0000-0000-000000000000".into(),
+            ),
+            ("Revision".to_string(), "rev?".into()),
+        ]),
+    )];
+    if let Some(size) = board_size {
+        general_specs.push((
+            "Size".to_string(),
+            JsonValue::Object(vec![
+                ("X".to_string(), round_mm(size.width).into()),
+                ("Y".to_string(), round_mm(size.height).into()),
+            ]),
+        ));
+    }
+    general_specs.push(("LayerNumber".to_string(), copper_layers.into()));
+
+    let job = JsonValue::Object(vec![
+        (
+            "Header".to_string(),
+            JsonValue::Object(vec![
+                (
+                    "GenerationSoftware".to_string(),
+                    JsonValue::Object(vec![
+                        ("Vendor".to_string(), "nextjlc".into()),
+                        ("Application".to_string(), "nextjlc".into()),
+                        ("Version".to_string(), env!("CARGO_PKG_VERSION").into()),
+                    ]),
+                ),
+                (
+                    "CreationDate".to_string(),
+                    Local::now()
+                        .format("%Y-%m-%dT%H:%M:%S%:z")
+                        .to_string()
+                        .into(),
+                ),
+            ]),
+        ),
+        ("GeneralSpecs".to_string(), JsonValue::Object(general_specs)),
+        (
+            "FilesAttributes".to_string(),
+            JsonValue::Array(file_attributes),
+        ),
+    ]);
+
+    job.to_pretty_string()
+}
+
+fn round_mm(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// One entry of the `FilesAttributes` list of a job file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobFile {
+    pub path: String,
+    /// X2 file function as written, e.g. "Copper,L2,Inr"
+    pub file_function: String,
+    pub polarity: Option<String>,
+}
+
+impl JobFile {
+    /// Logical name of the layer the job file assigns, e.g. "Gerber_InnerLayer1"
+    pub fn logical_name(&self) -> Option<String> {
+        let fields: Vec<&str> = self.file_function.split(',').map(str::trim).collect();
+        rename::logical_name_from_file_function(&fields)
+    }
+}
+
+/// The parts of a Gerber job file used for layer assignment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GerberJob {
+    /// `Header.GenerationSoftware.Application`, e.g. "Pcbnew"
+    pub application: Option<String>,
+    /// `GeneralSpecs.LayerNumber`
+    pub layer_count: Option<u32>,
+    pub files: Vec<JobFile>,
+}
+
+/// Parses a `.gbrjob` file. Returns `None` when it is not JSON or lists no files.
+pub fn parse_gbrjob(content: &str) -> Option<GerberJob> {
+    let value = json::parse_json(content).ok()?;
+    let files: Vec<JobFile> = value
+        .get("FilesAttributes")?
+        .as_array()?
+        .iter()
+        .filter_map(|entry| {
+            Some(JobFile {
+                path: entry.get("Path")?.as_str()?.to_string(),
+                file_function: entry.get("FileFunction")?.as_str()?.to_string(),
+                polarity: entry
+                    .get("FilePolarity")
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string),
+            })
+        })
+        .collect();
+    if files.is_empty() {
+        return None;
+    }
+    Some(GerberJob {
+        application: value
+            .get("Header")
+            .and_then(|h| h.get("GenerationSoftware"))
+            .and_then(|g| g.get("Application"))
+            .and_then(JsonValue::as_str)
+            .map(str::to_string),
+        layer_count: value
+            .get("GeneralSpecs")
+            .and_then(|g| g.get("LayerNumber"))
+            .and_then(JsonValue::as_f64)
+            .map(|n| n as u32),
+        files,
+    })
+}
+
+// The file name without directories, as job files list paths relative to themselves.
+fn base_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+impl GerberJob {
+    /// The job entry for `file`, matched by file name, ignoring case.
+    pub fn file(&self, file: &str) -> Option<&JobFile> {
+        let name = base_name(file);
+        self.files
+            .iter()
+            .find(|f| base_name(&f.path) == name)
+            .or_else(|| {
+                self.files
+                    .iter()
+                    .find(|f| base_name(&f.path).eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Renames every file the job lists with a known function by that function,
+    /// overriding the filename rules. Returns the number of files assigned.
+    pub fn apply(&self, result: &mut RenameResult) -> usize {
+        let mut count = 0;
+        for entry in result.entries.values_mut() {
+            let Some(job_file) = self.file(&entry.original) else {
+                continue;
+            };
+            let Some(logical) = job_file.logical_name() else {
+                continue;
+            };
+            entry.new_name = rename::get_final_filename(&logical);
+            entry.logical_name = Some(logical);
+            entry.source = MatchSource::GerberJob {
+                file_function: job_file.file_function.clone(),
+            };
+            entry.confidence = Confidence::High;
+            count += 1;
+        }
+        count
+    }
+}
@@ -0,0 +1,215 @@
+/* src/geometry.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::json::JsonValue;
+
+/// A point in board coordinates, always stored in mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    /// Euclidean distance to another point.
+    pub fn distance(&self, other: &Point) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+
+    /// Serializes the point as an `[x, y]` pair, the compact form for long
+    /// lists of coordinates.
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Array(vec![self.x.into(), self.y.into()])
+    }
+
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        match value.as_array()? {
+            [x, y] => Some(Point::new(x.as_f64()?, y.as_f64()?)),
+            _ => None,
+        }
+    }
+}
+
+/// An axis-aligned bounding box in mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    /// Creates a bounding box containing a single point.
+    pub fn from_point(p: Point) -> Self {
+        BoundingBox {
+            min_x: p.x,
+            min_y: p.y,
+            max_x: p.x,
+            max_y: p.y,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width() * self.height()
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+
+    /// Grows the box so it contains `p`.
+    pub fn include_point(&mut self, p: Point) {
+        self.min_x = self.min_x.min(p.x);
+        self.min_y = self.min_y.min(p.y);
+        self.max_x = self.max_x.max(p.x);
+        self.max_y = self.max_y.max(p.y);
+    }
+
+    /// Grows the box so it contains `other`.
+    pub fn include_box(&mut self, other: &BoundingBox) {
+        self.min_x = self.min_x.min(other.min_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+    }
+
+    /// Returns a copy grown by `margin` on every side.
+    pub fn expanded(&self, margin: f64) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x - margin,
+            min_y: self.min_y - margin,
+            max_x: self.max_x + margin,
+            max_y: self.max_y + margin,
+        }
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        p.x >= self.min_x && p.x <= self.max_x && p.y >= self.min_y && p.y <= self.max_y
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min_x <= other.max_x
+            && self.max_x >= other.min_x
+            && self.min_y <= other.max_y
+            && self.max_y >= other.min_y
+    }
+}
+
+/// Folds an optional bounding box with a new point.
+pub fn extend_bounds(bounds: &mut Option<BoundingBox>, p: Point) {
+    match bounds {
+        Some(b) => b.include_point(p),
+        None => *bounds = Some(BoundingBox::from_point(p)),
+    }
+}
+
+/// Computes the bounding box of a circular arc from `start` to `end` around `center`.
+///
+/// The box includes both endpoints plus every axis crossing (0°, 90°, 180°, 270°)
+/// swept by the arc, so it is exact for circular arcs.
+pub fn arc_bounds(start: Point, end: Point, center: Point, clockwise: bool) -> BoundingBox {
+    let mut bounds = BoundingBox::from_point(start);
+    bounds.include_point(end);
+
+    let radius = center.distance(&start);
+    let start_angle = (start.y - center.y).atan2(start.x - center.x);
+    let end_angle = (end.y - center.y).atan2(end.x - center.x);
+    let sweep = arc_sweep(start_angle, end_angle, clockwise);
+
+    for quadrant in 0..4 {
+        let angle = quadrant as f64 * std::f64::consts::FRAC_PI_2;
+        if angle_in_sweep(start_angle, sweep, angle) {
+            bounds.include_point(Point::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            ));
+        }
+    }
+
+    bounds
+}
+
+/// Returns the signed sweep angle (radians) from `start` to `end`.
+/// A full circle is returned when both angles coincide.
+pub(crate) fn arc_sweep(start_angle: f64, end_angle: f64, clockwise: bool) -> f64 {
+    let tau = std::f64::consts::TAU;
+    let mut sweep = end_angle - start_angle;
+    if clockwise {
+        while sweep >= 0.0 {
+            sweep -= tau;
+        }
+    } else {
+        while sweep <= 0.0 {
+            sweep += tau;
+        }
+    }
+    sweep
+}
+
+fn angle_in_sweep(start_angle: f64, sweep: f64, angle: f64) -> bool {
+    let tau = std::f64::consts::TAU;
+    let mut delta = angle - start_angle;
+    if sweep >= 0.0 {
+        delta = delta.rem_euclid(tau);
+        delta <= sweep
+    } else {
+        delta = (-delta).rem_euclid(tau);
+        delta <= -sweep
+    }
+}
+
+/// Approximates a circular arc by a polyline, including both endpoints.
+///
+/// The number of segments is chosen so that no chord deviates from the arc by
+/// more than `tolerance` mm.
+pub fn flatten_arc(
+    start: Point,
+    end: Point,
+    center: Point,
+    clockwise: bool,
+    tolerance: f64,
+) -> Vec<Point> {
+    let radius = center.distance(&start);
+    let start_angle = (start.y - center.y).atan2(start.x - center.x);
+    let end_angle = (end.y - center.y).atan2(end.x - center.x);
+    let sweep = arc_sweep(start_angle, end_angle, clockwise);
+
+    let step = if radius > tolerance {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        std::f64::consts::FRAC_PI_2
+    };
+    let segments = ((sweep.abs() / step).ceil() as usize).clamp(1, 1024);
+
+    let mut points: Vec<Point> = (0..segments)
+        .map(|i| {
+            let angle = start_angle + sweep * i as f64 / segments as f64;
+            Point::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        })
+        .collect();
+    points.push(end);
+    points
+}
@@ -0,0 +1,874 @@
+/* src/gerber.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::{BoundingBox, Point, arc_bounds};
+use crate::json::JsonValue;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+// This regex matches an aperture definition body (without the surrounding %...*%).
+static APERTURE_DEF_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^ADD(\d+)([A-Za-z_$.][^,]*)(?:,(.*))?$").expect("Invalid aperture regex")
+});
+
+// This regex matches a format specification such as FSLAX46Y46.
+static FORMAT_SPEC_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^FS([LTD]?)([AI]?)X(\d)(\d)Y(\d)(\d)").expect("Invalid format spec regex")
+});
+
+// This regex matches the X2 file function attribute and captures its fields.
+static FILE_FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"%TF\.FileFunction,([^*%]*)\*?%").expect("Invalid file function regex")
+});
+
+const INCH_TO_MM: f64 = 25.4;
+
+/// Unit declared by the %MO command (or legacy G70/G71)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Millimeter,
+    Inch,
+}
+
+/// Which zeros are omitted from coordinate data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroOmission {
+    Leading,
+    Trailing,
+}
+
+/// Coordinate format declared by the %FS command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoordinateFormat {
+    pub zero_omission: ZeroOmission,
+    pub incremental: bool,
+    pub integer_digits: u32,
+    pub decimal_digits: u32,
+}
+
+impl Default for CoordinateFormat {
+    fn default() -> Self {
+        CoordinateFormat {
+            zero_omission: ZeroOmission::Leading,
+            incremental: false,
+            integer_digits: 3,
+            decimal_digits: 6,
+        }
+    }
+}
+
+impl CoordinateFormat {
+    /// The format statement without delimiters, e.g. "FSLAX46Y46".
+    pub fn statement(&self) -> String {
+        format!(
+            "FS{}{}X{}{}Y{}{}",
+            match self.zero_omission {
+                ZeroOmission::Leading => "L",
+                ZeroOmission::Trailing => "T",
+            },
+            if self.incremental { "I" } else { "A" },
+            self.integer_digits,
+            self.decimal_digits,
+            self.integer_digits,
+            self.decimal_digits
+        )
+    }
+}
+
+/// Layer polarity set by %LPD / %LPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    Dark,  // Adds material
+    Clear, // Removes material
+}
+
+/// Interpolation mode for D01 operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Clockwise,
+    CounterClockwise,
+}
+
+/// An aperture definition from %ADD.
+///
+/// Parameters of the standard templates (C, R, O, P) are stored in mm.
+/// Macro parameters are kept as written because their meaning depends on the macro.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aperture {
+    pub code: u32,
+    pub template: String,
+    pub parameters: Vec<f64>,
+    /// Aperture attributes (%TA) in effect when the aperture was defined
+    pub attributes: Vec<String>,
+}
+
+impl Aperture {
+    /// True for the built-in C, R, O and P templates.
+    pub fn is_standard(&self) -> bool {
+        matches!(self.template.as_str(), "C" | "R" | "O" | "P")
+    }
+
+    /// Half extents (x, y) of the aperture in mm, used for image bounds.
+    /// Macro apertures return zero because their shape is not evaluated.
+    pub fn half_extents(&self) -> (f64, f64) {
+        let p = |i: usize| self.parameters.get(i).copied().unwrap_or(0.0);
+        match self.template.as_str() {
+            "C" | "P" => (p(0) / 2.0, p(0) / 2.0),
+            "R" | "O" => (p(0) / 2.0, p(1) / 2.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// The X2 aperture function, e.g. "SMDPad" or "ViaPad".
+    pub fn function(&self) -> Option<&str> {
+        self.attributes.iter().find_map(|a| {
+            a.strip_prefix("TA.AperFunction,")
+                .map(|rest| rest.split(',').next().unwrap_or(rest))
+        })
+    }
+}
+
+/// A single graphics command, with all coordinates converted to absolute mm.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    SelectAperture(u32),
+    Move(Point),
+    Flash(Point),
+    /// D01 draw; `center` is the absolute arc center for circular interpolation
+    Draw {
+        from: Point,
+        to: Point,
+        interpolation: Interpolation,
+        center: Option<Point>,
+    },
+    RegionStart,
+    RegionEnd,
+    SetPolarity(Polarity),
+    /// G04 comment text
+    Comment(String),
+    /// Object attribute (%TO) or attribute deletion (%TD) as written, without % and *
+    Attribute(String),
+}
+
+/// Parsed representation of one RS-274X layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct GerberLayer {
+    pub unit: Unit,
+    pub format: CoordinateFormat,
+    pub apertures: BTreeMap<u32, Aperture>,
+    /// Raw aperture macro blocks (the text between % delimiters)
+    pub macros: Vec<String>,
+    /// File attributes (%TF) as written, without % and *
+    pub file_attributes: Vec<String>,
+    pub commands: Vec<Command>,
+    /// Whether the M02 end-of-file marker was found
+    pub has_end_marker: bool,
+}
+
+/// Summary counters for a parsed layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerStats {
+    pub unit: Unit,
+    pub aperture_count: usize,
+    pub macro_count: usize,
+    pub flash_count: usize,
+    pub draw_count: usize,
+    pub arc_count: usize,
+    pub region_count: usize,
+    pub bounds: Option<BoundingBox>,
+}
+
+impl GerberLayer {
+    /// Returns the fields of a file attribute such as `FileFunction`, if present.
+    pub fn file_attribute(&self, name: &str) -> Option<Vec<&str>> {
+        self.file_attributes.iter().find_map(|attr| {
+            let mut fields = attr.strip_prefix("TF.")?.split(',');
+            if fields.next()? == name {
+                Some(fields.collect())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Dark flashes with their aperture, in file order. Clear flashes are
+    /// left out, as they cut the copper instead of forming pads.
+    pub fn dark_flashes(&self) -> Vec<(Point, &Aperture)> {
+        let mut flashes = Vec::new();
+        let mut current = None;
+        let mut polarity = Polarity::Dark;
+        for command in &self.commands {
+            match command {
+                Command::SelectAperture(code) => current = self.apertures.get(code),
+                Command::SetPolarity(p) => polarity = *p,
+                Command::Flash(at) if polarity == Polarity::Dark => {
+                    if let Some(aperture) = current {
+                        flashes.push((*at, aperture));
+                    }
+                }
+                _ => {}
+            }
+        }
+        flashes
+    }
+
+    /// Bounding box of all plotted coordinates (aperture size is ignored).
+    /// This is the natural size measure for outline layers.
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.compute_bounds(false, None)
+    }
+
+    /// Bounding box of the plotted image, including aperture extents.
+    pub fn image_bounds(&self) -> Option<BoundingBox> {
+        self.compute_bounds(true, None)
+    }
+
+    /// Like [`GerberLayer::image_bounds`], counting only objects of one polarity.
+    pub fn polarity_bounds(&self, polarity: Polarity) -> Option<BoundingBox> {
+        self.compute_bounds(true, Some(polarity))
+    }
+
+    fn compute_bounds(&self, with_apertures: bool, only: Option<Polarity>) -> Option<BoundingBox> {
+        let mut bounds: Option<BoundingBox> = None;
+        let mut half = (0.0, 0.0);
+        let mut in_region = false;
+        let mut polarity = Polarity::Dark;
+
+        let mut include = |b: BoundingBox, half: (f64, f64), polarity: Polarity| {
+            if only.is_some_and(|only| only != polarity) {
+                return;
+            }
+            let grown = BoundingBox {
+                min_x: b.min_x - half.0,
+                min_y: b.min_y - half.1,
+                max_x: b.max_x + half.0,
+                max_y: b.max_y + half.1,
+            };
+            match &mut bounds {
+                Some(existing) => existing.include_box(&grown),
+                None => bounds = Some(grown),
+            }
+        };
+
+        for command in &self.commands {
+            match command {
+                Command::SelectAperture(code) if with_apertures => {
+                    half = self
+                        .apertures
+                        .get(code)
+                        .map(|a| a.half_extents())
+                        .unwrap_or((0.0, 0.0));
+                }
+                Command::SetPolarity(p) => polarity = *p,
+                Command::Flash(p) => include(BoundingBox::from_point(*p), half, polarity),
+                Command::Draw {
+                    from,
+                    to,
+                    interpolation,
+                    center,
+                } => {
+                    let segment = match (interpolation, center) {
+                        (Interpolation::Linear, _) | (_, None) => {
+                            let mut b = BoundingBox::from_point(*from);
+                            b.include_point(*to);
+                            b
+                        }
+                        (kind, Some(c)) => {
+                            arc_bounds(*from, *to, *c, *kind == Interpolation::Clockwise)
+                        }
+                    };
+                    // Region contours are filled, so the aperture does not add extent.
+                    let stroke = if in_region { (0.0, 0.0) } else { half };
+                    include(segment, stroke, polarity);
+                }
+                Command::RegionStart => in_region = true,
+                Command::RegionEnd => in_region = false,
+                _ => {}
+            }
+        }
+
+        bounds
+    }
+
+    /// Counts apertures, flashes, draws, arcs and regions in the layer.
+    pub fn stats(&self) -> LayerStats {
+        let mut stats = LayerStats {
+            unit: self.unit,
+            aperture_count: self.apertures.len(),
+            macro_count: self.macros.len(),
+            flash_count: 0,
+            draw_count: 0,
+            arc_count: 0,
+            region_count: 0,
+            bounds: self.image_bounds(),
+        };
+        for command in &self.commands {
+            match command {
+                Command::Flash(_) => stats.flash_count += 1,
+                Command::Draw { interpolation, .. } => {
+                    if *interpolation == Interpolation::Linear {
+                        stats.draw_count += 1;
+                    } else {
+                        stats.arc_count += 1;
+                    }
+                }
+                Command::RegionStart => stats.region_count += 1,
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+impl GerberLayer {
+    /// Serializes the parsed layer, so it can be cached or read by other
+    /// tools without parsing the Gerber text again. Coordinates are in mm, as
+    /// `[x, y]` pairs; each command is an object keyed by its kind, e.g.
+    /// `{"flash": [1, 2]}`.
+    pub fn to_json(&self) -> JsonValue {
+        let strings =
+            |items: &[String]| JsonValue::Array(items.iter().map(|s| s.as_str().into()).collect());
+        let apertures = self
+            .apertures
+            .values()
+            .map(|a| {
+                JsonValue::Object(vec![
+                    ("code".to_string(), a.code.into()),
+                    ("template".to_string(), a.template.as_str().into()),
+                    (
+                        "parameters".to_string(),
+                        JsonValue::Array(a.parameters.iter().map(|&p| p.into()).collect()),
+                    ),
+                    ("attributes".to_string(), strings(&a.attributes)),
+                ])
+            })
+            .collect();
+        let commands = self
+            .commands
+            .iter()
+            .map(|command| {
+                let (kind, value) = match command {
+                    Command::SelectAperture(code) => ("aperture", (*code).into()),
+                    Command::Move(p) => ("move", p.to_json()),
+                    Command::Flash(p) => ("flash", p.to_json()),
+                    Command::Draw {
+                        from,
+                        to,
+                        interpolation,
+                        center,
+                    } => {
+                        return JsonValue::Object(vec![
+                            ("draw".to_string(), to.to_json()),
+                            ("from".to_string(), from.to_json()),
+                            (
+                                "interpolation".to_string(),
+                                match interpolation {
+                                    Interpolation::Linear => "linear",
+                                    Interpolation::Clockwise => "cw",
+                                    Interpolation::CounterClockwise => "ccw",
+                                }
+                                .into(),
+                            ),
+                            (
+                                "center".to_string(),
+                                center.map_or(JsonValue::Null, |c| c.to_json()),
+                            ),
+                        ]);
+                    }
+                    Command::RegionStart => ("region", "start".into()),
+                    Command::RegionEnd => ("region", "end".into()),
+                    Command::SetPolarity(Polarity::Dark) => ("polarity", "dark".into()),
+                    Command::SetPolarity(Polarity::Clear) => ("polarity", "clear".into()),
+                    Command::Comment(text) => ("comment", text.as_str().into()),
+                    Command::Attribute(text) => ("attribute", text.as_str().into()),
+                };
+                JsonValue::Object(vec![(kind.to_string(), value)])
+            })
+            .collect();
+        JsonValue::Object(vec![
+            (
+                "unit".to_string(),
+                match self.unit {
+                    Unit::Millimeter => "mm",
+                    Unit::Inch => "in",
+                }
+                .into(),
+            ),
+            (
+                "format".to_string(),
+                JsonValue::Object(vec![
+                    (
+                        "zero_omission".to_string(),
+                        match self.format.zero_omission {
+                            ZeroOmission::Leading => "leading",
+                            ZeroOmission::Trailing => "trailing",
+                        }
+                        .into(),
+                    ),
+                    ("incremental".to_string(), self.format.incremental.into()),
+                    (
+                        "integer_digits".to_string(),
+                        self.format.integer_digits.into(),
+                    ),
+                    (
+                        "decimal_digits".to_string(),
+                        self.format.decimal_digits.into(),
+                    ),
+                ]),
+            ),
+            ("apertures".to_string(), JsonValue::Array(apertures)),
+            ("macros".to_string(), strings(&self.macros)),
+            (
+                "file_attributes".to_string(),
+                strings(&self.file_attributes),
+            ),
+            ("commands".to_string(), JsonValue::Array(commands)),
+            ("has_end_marker".to_string(), self.has_end_marker.into()),
+        ])
+    }
+
+    /// Restores a layer serialized by [`GerberLayer::to_json`].
+    /// Returns `None` if a required field is missing or has the wrong type.
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let strings = |value: &JsonValue| -> Option<Vec<String>> {
+            value
+                .as_array()?
+                .iter()
+                .map(|s| s.as_str().map(str::to_string))
+                .collect()
+        };
+        let number = |value: &JsonValue, key: &str| value.get(key)?.as_f64();
+        let aperture = |value: &JsonValue| {
+            Some(Aperture {
+                code: number(value, "code")? as u32,
+                template: value.get("template")?.as_str()?.to_string(),
+                parameters: value
+                    .get("parameters")?
+                    .as_array()?
+                    .iter()
+                    .map(JsonValue::as_f64)
+                    .collect::<Option<_>>()?,
+                attributes: strings(value.get("attributes")?)?,
+            })
+        };
+        let command = |value: &JsonValue| {
+            let (kind, field) = value.as_object()?.first()?;
+            Some(match (kind.as_str(), field) {
+                ("aperture", code) => Command::SelectAperture(code.as_f64()? as u32),
+                ("move", p) => Command::Move(Point::from_json(p)?),
+                ("flash", p) => Command::Flash(Point::from_json(p)?),
+                ("draw", to) => Command::Draw {
+                    from: Point::from_json(value.get("from")?)?,
+                    to: Point::from_json(to)?,
+                    interpolation: match value.get("interpolation")?.as_str()? {
+                        "linear" => Interpolation::Linear,
+                        "cw" => Interpolation::Clockwise,
+                        "ccw" => Interpolation::CounterClockwise,
+                        _ => return None,
+                    },
+                    center: match value.get("center")? {
+                        JsonValue::Null => None,
+                        center => Some(Point::from_json(center)?),
+                    },
+                },
+                ("region", edge) => match edge.as_str()? {
+                    "start" => Command::RegionStart,
+                    "end" => Command::RegionEnd,
+                    _ => return None,
+                },
+                ("polarity", polarity) => Command::SetPolarity(match polarity.as_str()? {
+                    "dark" => Polarity::Dark,
+                    "clear" => Polarity::Clear,
+                    _ => return None,
+                }),
+                ("comment", text) => Command::Comment(text.as_str()?.to_string()),
+                ("attribute", text) => Command::Attribute(text.as_str()?.to_string()),
+                _ => return None,
+            })
+        };
+        let format = value.get("format")?;
+        Some(GerberLayer {
+            unit: match value.get("unit")?.as_str()? {
+                "mm" => Unit::Millimeter,
+                "in" => Unit::Inch,
+                _ => return None,
+            },
+            format: CoordinateFormat {
+                zero_omission: match format.get("zero_omission")?.as_str()? {
+                    "leading" => ZeroOmission::Leading,
+                    "trailing" => ZeroOmission::Trailing,
+                    _ => return None,
+                },
+                incremental: format.get("incremental")?.as_bool()?,
+                integer_digits: number(format, "integer_digits")? as u32,
+                decimal_digits: number(format, "decimal_digits")? as u32,
+            },
+            apertures: value
+                .get("apertures")?
+                .as_array()?
+                .iter()
+                .map(|a| aperture(a).map(|a| (a.code, a)))
+                .collect::<Option<_>>()?,
+            macros: strings(value.get("macros")?)?,
+            file_attributes: strings(value.get("file_attributes")?)?,
+            commands: value
+                .get("commands")?
+                .as_array()?
+                .iter()
+                .map(command)
+                .collect::<Option<_>>()?,
+            has_end_marker: value.get("has_end_marker")?.as_bool()?,
+        })
+    }
+}
+
+/// Splits Gerber content into blocks: extended commands (`%...%`) and plain words (`...*`).
+/// Line breaks carry no meaning in Gerber and are dropped.
+fn tokenize(content: &str) -> Vec<(bool, String)> {
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+    let mut in_extended = false;
+
+    for c in content.chars() {
+        match c {
+            '\r' | '\n' | '\u{FEFF}' => {}
+            '%' => {
+                if in_extended {
+                    if !current.is_empty() {
+                        blocks.push((true, std::mem::take(&mut current)));
+                    }
+                    in_extended = false;
+                } else {
+                    current.clear();
+                    in_extended = true;
+                }
+            }
+            '*' if !in_extended => {
+                let word = std::mem::take(&mut current);
+                if !word.trim().is_empty() {
+                    blocks.push((false, word));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    blocks
+}
+
+/// Converts a raw coordinate string to mm according to the format and unit.
+pub(crate) fn decode_coordinate(raw: &str, format: &CoordinateFormat, unit: Unit) -> f64 {
+    let value = if raw.contains('.') {
+        raw.parse::<f64>().unwrap_or(0.0)
+    } else {
+        let negative = raw.starts_with('-');
+        let digits = raw.trim_start_matches(['+', '-']);
+        let magnitude = match format.zero_omission {
+            ZeroOmission::Leading => {
+                digits.parse::<f64>().unwrap_or(0.0) / 10_f64.powi(format.decimal_digits as i32)
+            }
+            ZeroOmission::Trailing => {
+                let total = (format.integer_digits + format.decimal_digits) as usize;
+                let padded = format!("{:0<width$}", digits, width = total.max(digits.len()));
+                let scale = padded.len() as i32 - format.integer_digits as i32;
+                padded.parse::<f64>().unwrap_or(0.0) / 10_f64.powi(scale)
+            }
+        };
+        if negative { -magnitude } else { magnitude }
+    };
+
+    match unit {
+        Unit::Inch => value * INCH_TO_MM,
+        Unit::Millimeter => value,
+    }
+}
+
+/// Splits a word like `G01X100Y-200D01` into (letter, value) pairs.
+fn split_fields(word: &str) -> Vec<(char, String)> {
+    let mut fields: Vec<(char, String)> = Vec::new();
+    for c in word.chars() {
+        if c.is_ascii_alphabetic() {
+            fields.push((c.to_ascii_uppercase(), String::new()));
+        } else if let Some((_, value)) = fields.last_mut()
+            && (c.is_ascii_digit() || c == '-' || c == '+' || c == '.')
+        {
+            value.push(c);
+        }
+    }
+    fields
+}
+
+/// Parser state carried between blocks
+struct ParserState {
+    layer: GerberLayer,
+    aperture_attributes: Vec<String>,
+    position: Point,
+    interpolation: Interpolation,
+    single_quadrant: bool,
+    last_operation: u32,
+}
+
+impl ParserState {
+    fn handle_extended(&mut self, block: &str) {
+        let first = block.split('*').next().unwrap_or("").trim();
+
+        if let Some(caps) = FORMAT_SPEC_REGEX.captures(first) {
+            self.layer.format = CoordinateFormat {
+                zero_omission: if &caps[1] == "T" {
+                    ZeroOmission::Trailing
+                } else {
+                    ZeroOmission::Leading
+                },
+                incremental: &caps[2] == "I",
+                integer_digits: caps[3].parse().unwrap_or(3),
+                decimal_digits: caps[4].parse().unwrap_or(6),
+            };
+        } else if first.starts_with("MOIN") {
+            self.layer.unit = Unit::Inch;
+        } else if first.starts_with("MOMM") {
+            self.layer.unit = Unit::Millimeter;
+        } else if let Some(caps) = APERTURE_DEF_REGEX.captures(first) {
+            let code: u32 = caps[1].parse().unwrap_or(0);
+            let template = caps[2].to_string();
+            let raw: Vec<f64> = caps
+                .get(3)
+                .map(|m| {
+                    m.as_str()
+                        .split('X')
+                        .map(|v| {
+                            // Decimal commas, as written by some European locales
+                            v.trim().replace(',', ".").parse().unwrap_or(0.0)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let scale = if self.layer.unit == Unit::Inch {
+                INCH_TO_MM
+            } else {
+                1.0
+            };
+            let parameters = raw
+                .iter()
+                .enumerate()
+                .map(|(i, v)| match template.as_str() {
+                    "C" | "R" | "O" => v * scale,
+                    // Polygon: vertex count and rotation are unitless
+                    "P" if i == 0 || i == 3 => v * scale,
+                    _ => *v,
+                })
+                .collect();
+            self.layer.apertures.insert(
+                code,
+                Aperture {
+                    code,
+                    template,
+                    parameters,
+                    attributes: self.aperture_attributes.clone(),
+                },
+            );
+        } else if first.starts_with("AM") {
+            self.layer.macros.push(block.to_string());
+        } else if first == "LPD" {
+            self.layer
+                .commands
+                .push(Command::SetPolarity(Polarity::Dark));
+        } else if first == "LPC" {
+            self.layer
+                .commands
+                .push(Command::SetPolarity(Polarity::Clear));
+        } else if first.starts_with("TF") {
+            self.layer.file_attributes.push(first.to_string());
+        } else if let Some(attribute) = first.strip_prefix("TA") {
+            // A new value replaces an existing attribute of the same name
+            let name = attribute.split(',').next().unwrap_or("");
+            self.aperture_attributes
+                .retain(|a| a[2..].split(',').next() != Some(name));
+            self.aperture_attributes.push(first.to_string());
+        } else if first.starts_with("TO") || first.starts_with("TD") {
+            if let Some(name) = first.strip_prefix("TD") {
+                if name.is_empty() {
+                    self.aperture_attributes.clear();
+                } else {
+                    self.aperture_attributes
+                        .retain(|a| a[2..].split(',').next() != Some(name));
+                }
+            }
+            self.layer
+                .commands
+                .push(Command::Attribute(first.to_string()));
+        }
+    }
+
+    fn handle_word(&mut self, word: &str) {
+        let word = word.trim();
+        if let Some(text) = word.strip_prefix("G04") {
+            self.layer
+                .commands
+                .push(Command::Comment(text.trim().to_string()));
+            return;
+        }
+
+        let mut x: Option<&str> = None;
+        let mut y: Option<&str> = None;
+        let mut i: Option<&str> = None;
+        let mut j: Option<&str> = None;
+        let mut operation: Option<u32> = None;
+
+        let fields = split_fields(word);
+        for (letter, value) in &fields {
+            let number: u32 = value.parse().unwrap_or(u32::MAX);
+            match letter {
+                'G' => match number {
+                    1 => self.interpolation = Interpolation::Linear,
+                    2 => self.interpolation = Interpolation::Clockwise,
+                    3 => self.interpolation = Interpolation::CounterClockwise,
+                    36 => self.layer.commands.push(Command::RegionStart),
+                    37 => self.layer.commands.push(Command::RegionEnd),
+                    70 => self.layer.unit = Unit::Inch,
+                    71 => self.layer.unit = Unit::Millimeter,
+                    74 => self.single_quadrant = true,
+                    75 => self.single_quadrant = false,
+                    _ => {}
+                },
+                'M' if number == 2 || number == 0 => self.layer.has_end_marker = true,
+                'X' => x = Some(value),
+                'Y' => y = Some(value),
+                'I' => i = Some(value),
+                'J' => j = Some(value),
+                'D' => {
+                    if number >= 10 && number != u32::MAX {
+                        self.layer.commands.push(Command::SelectAperture(number));
+                    } else {
+                        operation = Some(number);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let has_coordinates = x.is_some() || y.is_some();
+        if operation.is_none() && !has_coordinates {
+            return;
+        }
+        // Coordinates without an operation code reuse the previous one (deprecated but common).
+        let operation = operation.unwrap_or(self.last_operation);
+        self.last_operation = operation;
+
+        let format = self.layer.format;
+        let unit = self.layer.unit;
+        let decode = |raw: &str| decode_coordinate(raw, &format, unit);
+
+        let from = self.position;
+        let mut to = from;
+        if format.incremental {
+            if let Some(v) = x {
+                to.x += decode(v);
+            }
+            if let Some(v) = y {
+                to.y += decode(v);
+            }
+        } else {
+            if let Some(v) = x {
+                to.x = decode(v);
+            }
+            if let Some(v) = y {
+                to.y = decode(v);
+            }
+        }
+
+        match operation {
+            1 => {
+                let center = if self.interpolation == Interpolation::Linear {
+                    None
+                } else {
+                    let offset = Point::new(i.map_or(0.0, decode), j.map_or(0.0, decode));
+                    Some(self.arc_center(from, to, offset))
+                };
+                self.layer.commands.push(Command::Draw {
+                    from,
+                    to,
+                    interpolation: self.interpolation,
+                    center,
+                });
+            }
+            2 => self.layer.commands.push(Command::Move(to)),
+            3 => self.layer.commands.push(Command::Flash(to)),
+            _ => {}
+        }
+        self.position = to;
+    }
+
+    /// Resolves the absolute arc center. In single-quadrant mode the offset signs
+    /// are not given, so the candidate most equidistant to both endpoints wins.
+    fn arc_center(&self, from: Point, to: Point, offset: Point) -> Point {
+        if !self.single_quadrant {
+            return Point::new(from.x + offset.x, from.y + offset.y);
+        }
+        let mut best = Point::new(from.x + offset.x, from.y + offset.y);
+        let mut best_error = f64::MAX;
+        for (sx, sy) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+            let candidate = Point::new(from.x + sx * offset.x.abs(), from.y + sy * offset.y.abs());
+            let error = (candidate.distance(&from) - candidate.distance(&to)).abs();
+            if error < best_error {
+                best_error = error;
+                best = candidate;
+            }
+        }
+        best
+    }
+}
+
+/// Parses RS-274X Gerber content into a [`GerberLayer`].
+///
+/// The parser is tolerant: unknown or malformed blocks are skipped rather than
+/// rejected, since the goal is to inspect real-world exports, not to validate them.
+pub fn parse_gerber(content: &str) -> GerberLayer {
+    let mut state = ParserState {
+        layer: GerberLayer {
+            unit: Unit::Millimeter,
+            format: CoordinateFormat::default(),
+            apertures: BTreeMap::new(),
+            macros: Vec::new(),
+            file_attributes: Vec::new(),
+            commands: Vec::new(),
+            has_end_marker: false,
+        },
+        aperture_attributes: Vec::new(),
+        position: Point::new(0.0, 0.0),
+        interpolation: Interpolation::Linear,
+        single_quadrant: false,
+        last_operation: 2,
+    };
+
+    for (extended, block) in tokenize(content) {
+        if extended {
+            state.handle_extended(&block);
+        } else {
+            state.handle_word(&block);
+        }
+    }
+
+    state.layer
+}
+
+/// Parses the content and returns its [`LayerStats`].
+/// Returns the fields of the `%TF.FileFunction` attribute without parsing the layer.
+pub fn file_function(content: &str) -> Option<Vec<&str>> {
+    let caps = FILE_FUNCTION_REGEX.captures(content)?;
+    Some(caps.get(1)?.as_str().split(',').collect())
+}
+
+pub fn layer_stats(content: &str) -> LayerStats {
+    parse_gerber(content).stats()
+}
+
+/// Computes the bounding box of all plotted coordinates in a Gerber layer.
+pub fn layer_bounds(content: &str) -> Option<BoundingBox> {
+    parse_gerber(content).bounds()
+}
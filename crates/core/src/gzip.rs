@@ -0,0 +1,490 @@
+/* src/gzip.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+// Deflate and gzip for single layers, so frontends can keep intermediate
+// state small. The compressor uses fixed Huffman codes, which suit Gerber
+// text well; the decompressor reads any deflate stream.
+
+use crate::zip::crc32;
+use std::fmt;
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+// How many earlier positions with the same hash are tried per match
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// Order in which dynamic blocks list the code lengths of the code length alphabet
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const GZIP_METHOD_DEFLATE: u8 = 8;
+const GZIP_FLAG_HCRC: u8 = 1 << 1;
+const GZIP_FLAG_EXTRA: u8 = 1 << 2;
+const GZIP_FLAG_NAME: u8 = 1 << 3;
+const GZIP_FLAG_COMMENT: u8 = 1 << 4;
+// "Unknown" operating system, so output does not depend on the platform
+const GZIP_OS_UNKNOWN: u8 = 255;
+
+/// Error produced when decompressing invalid data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InflateError {
+    pub message: String,
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for InflateError {}
+
+fn error(message: &str) -> InflateError {
+    InflateError {
+        message: message.to_string(),
+    }
+}
+
+fn too_large(limit: usize) -> InflateError {
+    InflateError {
+        message: format!("Decompressed data exceeds {} bytes", limit),
+    }
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are packed starting with their most significant bit
+    fn write_code(&mut self, code: u32, length: u32) {
+        self.write(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+// The fixed literal/length code of a symbol, as (code, length).
+fn fixed_code(symbol: u16) -> (u32, u32) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(out: &mut BitWriter, length: usize, distance: usize) {
+    let index = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .expect("Match length is at least 3");
+    let (code, bits) = fixed_code(257 + index as u16);
+    out.write_code(code, bits);
+    out.write(
+        (length - LENGTH_BASE[index] as usize) as u32,
+        LENGTH_EXTRA[index] as u32,
+    );
+
+    let index = DISTANCE_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .expect("Match distance is at least 1");
+    out.write_code(index as u32, 5);
+    out.write(
+        (distance - DISTANCE_BASE[index] as usize) as u32,
+        DISTANCE_EXTRA[index] as u32,
+    );
+}
+
+fn hash(data: &[u8], at: usize) -> usize {
+    let value = (data[at] as u32) << 16 | (data[at + 1] as u32) << 8 | data[at + 2] as u32;
+    (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+// Records `at` as the latest position with its hash.
+fn insert(data: &[u8], at: usize, head: &mut [usize], previous: &mut [usize]) {
+    if at + MIN_MATCH <= data.len() {
+        let h = hash(data, at);
+        previous[at] = head[h];
+        head[h] = at;
+    }
+}
+
+/// Compresses `data` as a raw deflate stream (RFC 1951).
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter {
+        out: Vec::new(),
+        bits: 0,
+        count: 0,
+    };
+    // One final block with fixed Huffman codes
+    out.write(1, 1);
+    out.write(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; data.len()];
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let limit = (data.len() - i).min(MAX_MATCH);
+            let mut candidate = head[hash(data, i)];
+            let mut steps = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && steps < MAX_CHAIN {
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[i..i + limit])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best.0 {
+                    best = (length, i - candidate);
+                    if length == limit {
+                        break;
+                    }
+                }
+                candidate = previous[candidate];
+                steps += 1;
+            }
+        }
+
+        if best.0 >= MIN_MATCH {
+            write_match(&mut out, best.0, best.1);
+            for at in i..i + best.0 {
+                insert(data, at, &mut head, &mut previous);
+            }
+            i += best.0;
+        } else {
+            let (code, bits) = fixed_code(data[i] as u16);
+            out.write_code(code, bits);
+            insert(data, i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+    let (code, bits) = fixed_code(256);
+    out.write_code(code, bits);
+    out.finish()
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u32) -> Result<u32, InflateError> {
+        while self.count < count {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| error("Unexpected end of deflate data"))?;
+            self.position += 1;
+            self.bits |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.bits & ((1u64 << count) - 1) as u32;
+        self.bits >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical Huffman code, as the number of codes of each length and the
+// symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..16 {
+            offsets[length] = offsets[length - 1] + counts[length - 1];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, input: &mut BitReader) -> Result<u16, InflateError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..16 {
+            code |= input.read(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return self
+                    .symbols
+                    .get((index + code - first) as usize)
+                    .copied()
+                    .ok_or_else(|| error("Invalid Huffman code"));
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(error("Invalid Huffman code"))
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = fixed_code(symbol as u16).1 as u8;
+    }
+    (Huffman::new(&lengths), Huffman::new(&[5u8; 30]))
+}
+
+fn dynamic_tables(input: &mut BitReader) -> Result<(Huffman, Huffman), InflateError> {
+    let literals = input.read(5)? as usize + 257;
+    let distances = input.read(5)? as usize + 1;
+    let code_lengths = input.read(4)? as usize + 4;
+
+    let mut lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_lengths) {
+        lengths[symbol] = input.read(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&lengths);
+
+    let mut lengths: Vec<u8> = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let symbol = code_length_code.decode(input)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let last = *lengths
+                    .last()
+                    .ok_or_else(|| error("Repeat without a previous length"))?;
+                (last, 3 + input.read(2)? as usize)
+            }
+            17 => (0, 3 + input.read(3)? as usize),
+            _ => (0, 11 + input.read(7)? as usize),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    if lengths.len() > literals + distances {
+        return Err(error("Code lengths overrun the alphabet"));
+    }
+    Ok((
+        Huffman::new(&lengths[..literals]),
+        Huffman::new(&lengths[literals..]),
+    ))
+}
+
+/// Decompresses a raw deflate stream (RFC 1951) of at most `limit` bytes.
+/// Returns the data and the number of input bytes read.
+fn inflate_stream(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), InflateError> {
+    let mut input = BitReader {
+        data,
+        position: 0,
+        bits: 0,
+        count: 0,
+    };
+    let mut out: Vec<u8> = Vec::new();
+    loop {
+        let last = input.read(1)? == 1;
+        match input.read(2)? {
+            0 => {
+                input.align();
+                let header = data
+                    .get(input.position..input.position + 4)
+                    .ok_or_else(|| error("Unexpected end of deflate data"))?;
+                let length = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let complement = u16::from_le_bytes([header[2], header[3]]);
+                if complement != !(length as u16) {
+                    return Err(error("Stored block length check failed"));
+                }
+                let start = input.position + 4;
+                let block = data
+                    .get(start..start + length)
+                    .ok_or_else(|| error("Unexpected end of deflate data"))?;
+                if out.len() + block.len() > limit {
+                    return Err(too_large(limit));
+                }
+                out.extend_from_slice(block);
+                input.position = start + length;
+            }
+            kind @ (1 | 2) => {
+                let (literal_code, distance_code) = if kind == 1 {
+                    fixed_tables()
+                } else {
+                    dynamic_tables(&mut input)?
+                };
+                loop {
+                    let symbol = literal_code.decode(&mut input)? as usize;
+                    match symbol {
+                        0..=255 if out.len() >= limit => return Err(too_large(limit)),
+                        0..=255 => out.push(symbol as u8),
+                        256 => break,
+                        _ => {
+                            let index = symbol - 257;
+                            let base = *LENGTH_BASE
+                                .get(index)
+                                .ok_or_else(|| error("Invalid length symbol"))?;
+                            let length =
+                                base as usize + input.read(LENGTH_EXTRA[index] as u32)? as usize;
+                            let index = distance_code.decode(&mut input)? as usize;
+                            let base = *DISTANCE_BASE
+                                .get(index)
+                                .ok_or_else(|| error("Invalid distance symbol"))?;
+                            let distance =
+                                base as usize + input.read(DISTANCE_EXTRA[index] as u32)? as usize;
+                            if distance > out.len() {
+                                return Err(error("Distance reaches before the start"));
+                            }
+                            if out.len() + length > limit {
+                                return Err(too_large(limit));
+                            }
+                            let start = out.len() - distance;
+                            for k in 0..length {
+                                out.push(out[start + k]);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => return Err(error("Invalid deflate block type")),
+        }
+        if last {
+            return Ok((out, input.position));
+        }
+    }
+}
+
+/// Decompresses a raw deflate stream (RFC 1951).
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, InflateError> {
+    inflate_limited(data, usize::MAX)
+}
+
+/// Like [`inflate`], failing once the output would exceed `limit` bytes, so
+/// untrusted data cannot expand without bound.
+pub fn inflate_limited(data: &[u8], limit: usize) -> Result<Vec<u8>, InflateError> {
+    inflate_stream(data, limit).map(|(out, _)| out)
+}
+
+/// Compresses `data` as a gzip member (RFC 1952) with no name or timestamp,
+/// so the same input always gives the same bytes.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&GZIP_MAGIC);
+    out.extend_from_slice(&[GZIP_METHOD_DEFLATE, 0, 0, 0, 0, 0, 0, GZIP_OS_UNKNOWN]);
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Decompresses the first member of gzip data, checking its CRC and size.
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 18 || data[..2] != GZIP_MAGIC {
+        return Err(error("Not gzip data"));
+    }
+    if data[2] != GZIP_METHOD_DEFLATE {
+        return Err(error("Unsupported gzip compression method"));
+    }
+    let flags = data[3];
+    let mut position = 10;
+    let truncated = || error("Truncated gzip header");
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        let length = data.get(position..position + 2).ok_or_else(truncated)?;
+        position += 2 + u16::from_le_bytes([length[0], length[1]]) as usize;
+    }
+    for flag in [GZIP_FLAG_NAME, GZIP_FLAG_COMMENT] {
+        if flags & flag != 0 {
+            let end = data
+                .get(position..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(truncated)?;
+            position += end + 1;
+        }
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        position += 2;
+    }
+    let body = data.get(position..).ok_or_else(truncated)?;
+
+    let (out, used) = inflate_stream(body, usize::MAX)?;
+    let trailer = body
+        .get(used..used + 8)
+        .ok_or_else(|| error("Truncated gzip trailer"))?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc != crc32(&out) {
+        return Err(error("gzip CRC check failed"));
+    }
+    if size != out.len() as u32 {
+        return Err(error("gzip size check failed"));
+    }
+    Ok(out)
+}
+
+/// Compresses a layer's text for storage.
+pub fn compress_layer(content: &str) -> Vec<u8> {
+    gzip(content.as_bytes())
+}
+
+/// Restores a layer compressed by [`compress_layer`].
+pub fn decompress_layer(data: &[u8]) -> Result<String, InflateError> {
+    String::from_utf8(gunzip(data)?).map_err(|_| error("Layer is not valid UTF-8"))
+}
@@ -0,0 +1,135 @@
+/* src/junk.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::json::JsonValue;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Why a file was left out of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JunkKind {
+    /// Altium's `Status Report.txt`, the log of an output job run
+    StatusReport,
+    /// Aperture or wheel report (`.apr`, `.rep`, `.whl`) not needed for RS-274-D
+    ApertureReport,
+    /// Altium layer drawing parameters (`.LDP`)
+    DrawingParameters,
+    /// Altium Gerber extension report (`.EXTREP`)
+    ExtensionReport,
+    /// Metadata added by macOS archivers (`__MACOSX/`, `._*`, `.DS_Store`)
+    MacMetadata,
+    /// Preview images and thumbnail caches (`Thumbs.db`, `.png`, `.jpg`, ...)
+    Thumbnail,
+    /// PDF documents such as assembly drawings or schematics
+    Document,
+    /// Drill drawings, left out by [`crate::rename::DrillMapPolicy::Drop`]
+    DrillMap,
+}
+
+impl JunkKind {
+    /// Short lowercase id, e.g. "status-report"
+    pub fn id(&self) -> &'static str {
+        match self {
+            JunkKind::StatusReport => "status-report",
+            JunkKind::ApertureReport => "aperture-report",
+            JunkKind::DrawingParameters => "drawing-parameters",
+            JunkKind::ExtensionReport => "extension-report",
+            JunkKind::MacMetadata => "mac-metadata",
+            JunkKind::Thumbnail => "thumbnail",
+            JunkKind::Document => "document",
+            JunkKind::DrillMap => "drill-map",
+        }
+    }
+
+    /// Parses an id from [`JunkKind::id`].
+    pub fn from_id(id: &str) -> Option<JunkKind> {
+        [
+            JunkKind::StatusReport,
+            JunkKind::ApertureReport,
+            JunkKind::DrawingParameters,
+            JunkKind::ExtensionReport,
+            JunkKind::MacMetadata,
+            JunkKind::Thumbnail,
+            JunkKind::Document,
+            JunkKind::DrillMap,
+        ]
+        .into_iter()
+        .find(|kind| kind.id() == id)
+    }
+
+    /// Why files of this kind are not sent to the fab
+    pub fn description(&self) -> &'static str {
+        match self {
+            JunkKind::StatusReport => "output job status report",
+            JunkKind::ApertureReport => "aperture report not needed by any layer",
+            JunkKind::DrawingParameters => "layer drawing parameters report",
+            JunkKind::ExtensionReport => "Gerber extension report, used only for checking",
+            JunkKind::MacMetadata => "macOS archive metadata",
+            JunkKind::Thumbnail => "preview image",
+            JunkKind::Document => "PDF document",
+            JunkKind::DrillMap => "drill drawing; holes are read from the drill files",
+        }
+    }
+}
+
+/// A file left out of the output, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredFile {
+    /// The file as uploaded
+    pub file: String,
+    pub kind: JunkKind,
+}
+
+impl IgnoredFile {
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("file".to_string(), self.file.as_str().into()),
+            ("kind".to_string(), self.kind.id().into()),
+            ("reason".to_string(), self.kind.description().into()),
+        ])
+    }
+
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        Some(IgnoredFile {
+            file: value.get("file")?.as_str()?.to_string(),
+            kind: JunkKind::from_id(value.get("kind")?.as_str()?)?,
+        })
+    }
+}
+
+// These regexes match the base names of each kind of junk file.
+static JUNK_PATTERNS: Lazy<Vec<(JunkKind, Regex)>> = Lazy::new(|| {
+    [
+        (JunkKind::StatusReport, r"(?i)^status report\.txt$"),
+        (JunkKind::ApertureReport, r"(?i)\.(apr|rep|whl)$"),
+        (JunkKind::DrawingParameters, r"(?i)\.ldp$"),
+        (JunkKind::ExtensionReport, r"(?i)\.extrep$"),
+        (JunkKind::MacMetadata, r"(?i)^(\._.*|\.ds_store)$"),
+        (
+            JunkKind::Thumbnail,
+            r"(?i)^thumbs\.db$|\.(png|jpe?g|bmp|gif|webp)$",
+        ),
+        (JunkKind::Document, r"(?i)\.pdf$"),
+    ]
+    .into_iter()
+    .map(|(kind, pattern)| (kind, Regex::new(pattern).expect("Invalid junk file regex")))
+    .collect()
+});
+
+/// The kind of junk `name` is, or `None` for files that may be fab data.
+/// `name` may include folders, as found in an archive.
+pub fn classify_junk(name: &str) -> Option<JunkKind> {
+    let normalized = name.replace('\\', "/");
+    if normalized.split('/').any(|part| part == "__MACOSX") {
+        return Some(JunkKind::MacMetadata);
+    }
+    let base = normalized.rsplit('/').next().unwrap_or(&normalized);
+    JUNK_PATTERNS
+        .iter()
+        .find(|(_, pattern)| pattern.is_match(base))
+        .map(|(kind, _)| *kind)
+}
@@ -0,0 +1,109 @@
+/* src/kicad.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::pipeline::ConvertOptions;
+use crate::rename::{EdaType, LogicalLayer};
+
+/// The parts of KiCad's Gerber plot dialog that affect conversion.
+/// Layer names are KiCad's canonical names ("F.Cu", "Edge.Cuts", "In1.Cu").
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlotSettings {
+    pub layers: Vec<String>,
+    /// "Use Protel filename extensions". Names keep the layer suffix ("-F_Cu.gtl")
+    /// either way, so both variants convert with the KiCad rules.
+    pub use_protel_extensions: bool,
+    /// "Use drill/place file origin" in the plot dialog
+    pub use_aux_origin: bool,
+    /// "Drill origin: Auxiliary origin" in the drill dialog
+    pub drill_use_aux_origin: bool,
+    /// "Generate Gerber job file"
+    pub create_job_file: bool,
+}
+
+/// Options and notes derived from [`PlotSettings`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotRecommendation {
+    pub options: ConvertOptions,
+    /// JLC layer each selected KiCad layer becomes
+    pub layer_map: Vec<(String, LogicalLayer)>,
+    /// Problems to fix in the plot settings before plotting
+    pub warnings: Vec<String>,
+}
+
+/// Maps a KiCad layer name to its JLC layer. KiCad 5 names ("F.SilkS") are accepted too.
+pub(crate) fn logical_layer_for(kicad_layer: &str) -> Option<LogicalLayer> {
+    let layer = match kicad_layer {
+        "F.Cu" => LogicalLayer::TopLayer,
+        "B.Cu" => LogicalLayer::BottomLayer,
+        "F.Silkscreen" | "F.SilkS" => LogicalLayer::TopSilkscreen,
+        "B.Silkscreen" | "B.SilkS" => LogicalLayer::BottomSilkscreen,
+        "F.Mask" => LogicalLayer::TopSolderMask,
+        "B.Mask" => LogicalLayer::BottomSolderMask,
+        "F.Paste" => LogicalLayer::TopPasteMask,
+        "B.Paste" => LogicalLayer::BottomPasteMask,
+        "Edge.Cuts" => LogicalLayer::BoardOutline,
+        _ => {
+            let index: u8 = kicad_layer
+                .strip_prefix("In")?
+                .strip_suffix(".Cu")?
+                .parse()
+                .ok()?;
+            LogicalLayer::InnerLayer(index)
+        }
+    };
+    Some(layer)
+}
+
+/// Recommends conversion options for a board plotted with `settings`.
+///
+/// Meant for the KiCad action plugin, which knows the plot settings and can
+/// warn before plotting instead of after the upload.
+pub fn recommend_options(settings: &PlotSettings) -> PlotRecommendation {
+    let mut warnings = Vec::new();
+    let mut layer_map = Vec::new();
+
+    for layer in &settings.layers {
+        match logical_layer_for(layer) {
+            Some(logical) => layer_map.push((layer.clone(), logical)),
+            None => warnings.push(format!(
+                "Layer {} is not used for fabrication and will be passed through unchanged",
+                layer
+            )),
+        }
+    }
+
+    let has = |logical: LogicalLayer| layer_map.iter().any(|(_, l)| *l == logical);
+    for (required, kicad_name) in [
+        (LogicalLayer::TopLayer, "F.Cu"),
+        (LogicalLayer::TopSolderMask, "F.Mask"),
+        (LogicalLayer::BoardOutline, "Edge.Cuts"),
+    ] {
+        if !has(required) {
+            warnings.push(format!("Select the {} layer; JLC requires it", kicad_name));
+        }
+    }
+    if has(LogicalLayer::BottomLayer) && !has(LogicalLayer::BottomSolderMask) {
+        warnings.push("B.Cu is plotted without B.Mask".to_string());
+    }
+
+    if settings.use_aux_origin != settings.drill_use_aux_origin {
+        warnings.push(
+            "Gerbers and drill files use different origins; holes will be offset".to_string(),
+        );
+    }
+
+    PlotRecommendation {
+        options: ConvertOptions {
+            eda_type: Some(EdaType::KiCad),
+            // KiCad's own job file lists the plotted layers; add ours otherwise
+            add_gbrjob: !settings.create_job_file,
+            ..ConvertOptions::default()
+        },
+        layer_map,
+        warnings,
+    }
+}
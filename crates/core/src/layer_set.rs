@@ -0,0 +1,2129 @@
+/* src/layer_set.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::autofix::{self, AppliedFix, FixLevel};
+use crate::cancel::{Cancellation, Cancelled, NeverCancel};
+use crate::classify;
+use crate::compose;
+use crate::custom_rules::CustomRules;
+use crate::dcode::{self, GerberFlavor};
+use crate::drill::{self, OversizedHoles};
+use crate::extrep;
+use crate::file_type::identify_software;
+use crate::fingerprint::{self, FingerprintMode};
+use crate::gbrjob;
+use crate::geometry::{BoundingBox, Point};
+use crate::gerber;
+use crate::header;
+use crate::json::JsonValue;
+use crate::junk::{self, IgnoredFile, JunkKind};
+use crate::locale;
+use crate::mask_defined::{self, MaskDefinedArea};
+use crate::outline::sort_gerber_files;
+use crate::rename::{
+    self, CollisionPolicy, Confidence, DRILL_MAP_DOCUMENT_NAME, DrillMapPolicy, EdaType,
+    LogicalLayer, MatchSource, NamingProfile, OutlineSelection, PasteSilkMapping, RenamePlan,
+    RenameResult, V_CUT_NOTE_NAME, VCutOutput,
+};
+use crate::rs274d;
+use crate::service::BoardFacts;
+use crate::silktext;
+use crate::sink::{OutputSink, SinkError};
+use crate::validation::{self, ValidationReport};
+use crate::via_in_pad::{self, ViaInPad};
+use chrono::Local;
+use std::io::{self, Write};
+
+// Lines within this angle of an axis (as a slope) are taken as score lines
+const V_CUT_SLOPE_TOLERANCE: f64 = 0.001;
+// Score lines closer than this (mm) are the same line
+const V_CUT_MERGE_MM: f64 = 0.01;
+
+// Lists the score lines of a V-cut layer as text, and counts the objects
+// that are not straight horizontal or vertical lines.
+fn v_cut_note(source: &str, content: &str) -> (String, usize) {
+    let layer = gerber::parse_gerber(content);
+    let (mut rows, mut columns) = (Vec::new(), Vec::new());
+    let mut skipped = 0;
+    for command in &layer.commands {
+        match command {
+            gerber::Command::Draw {
+                from,
+                to,
+                interpolation: gerber::Interpolation::Linear,
+                ..
+            } => {
+                let (dx, dy) = ((to.x - from.x).abs(), (to.y - from.y).abs());
+                if dy <= dx * V_CUT_SLOPE_TOLERANCE {
+                    rows.push(from.y);
+                } else if dx <= dy * V_CUT_SLOPE_TOLERANCE {
+                    columns.push(from.x);
+                } else {
+                    skipped += 1;
+                }
+            }
+            gerber::Command::Draw { .. } | gerber::Command::Flash(_) => skipped += 1,
+            _ => {}
+        }
+    }
+    let positions = |mut values: Vec<f64>| {
+        values.sort_by(f64::total_cmp);
+        values.dedup_by(|a, b| (*a - *b).abs() <= V_CUT_MERGE_MM);
+        let listed: Vec<String> = values.iter().map(|v| format!("{:.2}", v)).collect();
+        if listed.is_empty() {
+            "-".to_string()
+        } else {
+            listed.join(", ")
+        }
+    };
+    let note = format!(
+        "V-CUT\nScore lines from {}, in mm\nHorizontal at Y = {}\nVertical at X = {}\n",
+        source,
+        positions(rows),
+        positions(columns)
+    );
+    (note, skipped)
+}
+
+// A coordinate of `layer` in its declared unit and format
+fn gerber_coordinate(value_mm: f64, layer: &gerber::GerberLayer) -> String {
+    let value = match layer.unit {
+        gerber::Unit::Inch => value_mm / 25.4,
+        gerber::Unit::Millimeter => value_mm,
+    };
+    let scaled = (value * 10_f64.powi(layer.format.decimal_digits as i32)).round() as i64;
+    match layer.format.zero_omission {
+        gerber::ZeroOmission::Leading => scaled.to_string(),
+        // Trailing zeros may only be left out after a full set of leading digits
+        gerber::ZeroOmission::Trailing => {
+            let digits = (layer.format.integer_digits + layer.format.decimal_digits) as usize;
+            let sign = if scaled < 0 { "-" } else { "" };
+            format!("{}{:0width$}", sign, scaled.unsigned_abs(), width = digits)
+        }
+    }
+}
+
+// Outline content with each (centre, diameter) hole drawn as a circle in
+// the outline's own aperture, or `None` when the outline has no drawn
+// contour to take the aperture from or uses incremental coordinates.
+fn outline_with_cutouts(content: &str, holes: &[(Point, f64)]) -> Option<String> {
+    let layer = gerber::parse_gerber(content);
+    if layer.format.incremental {
+        return None;
+    }
+    let mut current = None;
+    let aperture = layer.commands.iter().find_map(|command| match command {
+        gerber::Command::SelectAperture(code) => {
+            current = Some(*code);
+            None
+        }
+        gerber::Command::Draw { .. } => current,
+        _ => None,
+    })?;
+
+    let coordinate = |value: f64| gerber_coordinate(value, &layer);
+    let mut cutouts = format!(
+        "G04 Holes above the maximum drill, routed as cutouts*\nD{}*\nG75*\n",
+        aperture
+    );
+    for (center, diameter) in holes {
+        let radius = diameter / 2.0;
+        cutouts.push_str(&format!(
+            "X{}Y{}D02*\nG02X{}Y{}I{}J0D01*\n",
+            coordinate(center.x + radius),
+            coordinate(center.y),
+            coordinate(center.x + radius),
+            coordinate(center.y),
+            coordinate(-radius)
+        ));
+    }
+    cutouts.push_str("G01*\n");
+
+    let mut output = content.to_string();
+    match output.rfind("M02*") {
+        Some(end) => output.insert_str(end, &cutouts),
+        None => {
+            if !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str(&cutouts);
+        }
+    }
+    Some(output)
+}
+
+// Width of the narrowest round aperture drawn outside regions, in mm
+fn min_trace_width(layer: &gerber::GerberLayer) -> Option<f64> {
+    let mut width = None;
+    let mut in_region = false;
+    let mut min: Option<f64> = None;
+    for command in &layer.commands {
+        match command {
+            gerber::Command::SelectAperture(code) => {
+                width = layer
+                    .apertures
+                    .get(code)
+                    .filter(|a| a.template == "C")
+                    .and_then(|a| a.parameters.first().copied());
+            }
+            gerber::Command::RegionStart => in_region = true,
+            gerber::Command::RegionEnd => in_region = false,
+            gerber::Command::Draw { .. } if !in_region => {
+                if let Some(w) = width.filter(|&w| w > 0.0) {
+                    min = Some(min.map_or(w, |m: f64| m.min(w)));
+                }
+            }
+            _ => {}
+        }
+    }
+    min
+}
+
+/// Output zip name used unless another template is given, e.g.
+/// "board-4L-100x80mm-20240131.zip".
+pub const DEFAULT_BUNDLE_TEMPLATE: &str = "{project}-{layers}L-{width}x{height}mm-{date}.zip";
+
+/// Name of the order guide file added to every output set.
+pub const ORDER_GUIDE_FILENAME: &str = "PCB下单必读.txt";
+
+/// A single file of the set, tracked from its original name to its output name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerFile {
+    pub original_name: String,
+    pub name: String,
+    pub content: String,
+    /// CAD software detected from the content ("Altium", "KiCad", "EasyEDA", "Eagle", "PADS", "Proteus",
+    /// "LibrePCB", "Horizon", "DipTrace")
+    pub software: Option<&'static str>,
+}
+
+impl LayerFile {
+    /// Creates a file, normalizing line endings and detecting its software.
+    pub fn new(name: &str, content: &str) -> Self {
+        let content = content.replace("\r\n", "\n");
+        LayerFile {
+            original_name: name.to_string(),
+            name: name.to_string(),
+            software: identify_software(&content),
+            content,
+        }
+    }
+
+    /// The rename rules matching this file's own software.
+    pub fn eda_type(&self) -> Option<EdaType> {
+        self.software.and_then(EdaType::from_software)
+    }
+}
+
+/// Maps a software name back to the static name used by `identify_software`.
+fn known_software(name: &str) -> Option<&'static str> {
+    match name {
+        "Altium" => Some("Altium"),
+        "KiCad" => Some("KiCad"),
+        "EasyEDA" => Some("EasyEDA"),
+        "Eagle" => Some("Eagle"),
+        "PADS" => Some("PADS"),
+        "Proteus" => Some("Proteus"),
+        "LibrePCB" => Some("LibrePCB"),
+        "Horizon" => Some("Horizon"),
+        "DipTrace" => Some("DipTrace"),
+        _ => None,
+    }
+}
+
+impl LayerFile {
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            (
+                "original_name".to_string(),
+                self.original_name.as_str().into(),
+            ),
+            ("name".to_string(), self.name.as_str().into()),
+            ("content".to_string(), self.content.as_str().into()),
+            ("software".to_string(), self.software.into()),
+        ])
+    }
+
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        Some(LayerFile {
+            original_name: value.get("original_name")?.as_str()?.to_string(),
+            name: value.get("name")?.as_str()?.to_string(),
+            content: value.get("content")?.as_str()?.to_string(),
+            software: value
+                .get("software")
+                .and_then(JsonValue::as_str)
+                .and_then(known_software),
+        })
+    }
+}
+
+/// A merged drill file of the output. It is kept parsed and only written as
+/// Excellon when the set is output, so [`LayerSet::write_outputs`] streams it
+/// to the sink instead of holding it as text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedDrill {
+    /// What the file was merged from, e.g. "[merged PTH drills]"
+    pub original_name: String,
+    pub name: String,
+    pub hole_type: drill::HoleType,
+    /// Copper layers joined by blind or buried vias, with the copper layer
+    /// count of the board; `None` for through holes
+    pub span: Option<(drill::LayerSpan, u32)>,
+    pub format: drill::DrillOutputOptions,
+    pub drill: drill::DrillFile,
+}
+
+// Counts the bytes written through it
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl MergedDrill {
+    /// Writes the file as JLC Excellon.
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        match self.span {
+            Some((span, copper_layers)) => {
+                drill::write_jlc_span_excellon(out, &self.drill, span, copper_layers, &self.format)
+            }
+            None => drill::write_jlc_excellon(out, &self.drill, self.hole_type, &self.format),
+        }
+    }
+
+    /// The file as JLC Excellon text.
+    pub fn content(&self) -> String {
+        let mut out = Vec::new();
+        self.write(&mut out).expect("Writing to memory");
+        String::from_utf8(out).expect("Excellon output is ASCII")
+    }
+
+    /// Length in bytes of [`MergedDrill::content`], without keeping it.
+    pub fn size(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        self.write(&mut counter).expect("Counting cannot fail");
+        counter.0
+    }
+
+    /// Serializes the file with its drill data as parsed, so restoring it
+    /// does not read Excellon again.
+    pub fn to_json(&self) -> JsonValue {
+        let format = &self.format;
+        JsonValue::Object(vec![
+            (
+                "original_name".to_string(),
+                self.original_name.as_str().into(),
+            ),
+            ("name".to_string(), self.name.as_str().into()),
+            (
+                "plated".to_string(),
+                (self.hole_type == drill::HoleType::Plated).into(),
+            ),
+            (
+                "from_layer".to_string(),
+                self.span.map(|(span, _)| span.from).into(),
+            ),
+            (
+                "to_layer".to_string(),
+                self.span.map(|(span, _)| span.to).into(),
+            ),
+            (
+                "copper_layers".to_string(),
+                self.span.map(|(_, layers)| layers).into(),
+            ),
+            (
+                "trailing_zeros".to_string(),
+                (format.zeros == drill::ZeroFormat::Trailing).into(),
+            ),
+            ("integer_digits".to_string(), format.integer_digits.into()),
+            ("decimal_digits".to_string(), format.decimal_digits.into()),
+            ("decimal_point".to_string(), format.decimal_point.into()),
+            ("drill".to_string(), self.drill.to_json()),
+        ])
+    }
+
+    /// Restores a file serialized by [`MergedDrill::to_json`].
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let number = |key: &str| value.get(key).and_then(JsonValue::as_f64);
+        let hole_type = if value.get("plated")?.as_bool()? {
+            drill::HoleType::Plated
+        } else {
+            drill::HoleType::NonPlated
+        };
+        let span = match (
+            number("from_layer"),
+            number("to_layer"),
+            number("copper_layers"),
+        ) {
+            (Some(from), Some(to), Some(layers)) => {
+                Some((drill::LayerSpan::new(from as u32, to as u32), layers as u32))
+            }
+            _ => None,
+        };
+        let format = drill::DrillOutputOptions {
+            zeros: if value.get("trailing_zeros")?.as_bool()? {
+                drill::ZeroFormat::Trailing
+            } else {
+                drill::ZeroFormat::Leading
+            },
+            integer_digits: number("integer_digits")? as usize,
+            decimal_digits: number("decimal_digits")? as usize,
+            decimal_point: value.get("decimal_point")?.as_bool()?,
+        };
+        Some(MergedDrill {
+            original_name: value.get("original_name")?.as_str()?.to_string(),
+            name: value.get("name")?.as_str()?.to_string(),
+            hole_type,
+            span,
+            format,
+            drill: drill::DrillFile::from_json(value.get("drill")?)?,
+        })
+    }
+}
+
+/// How a Gerber layer identifies its role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerRepresentation {
+    /// Role given by an X2 `%TF.FileFunction` attribute, usually in a `.gbr` file
+    X2,
+    /// Role given by a legacy Protel extension such as `.GTL`
+    Legacy,
+}
+
+/// Records which file was kept when several files described the same layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateDecision {
+    /// Logical name of the layer, e.g. "Gerber_TopLayer"
+    pub role: String,
+    pub kept: String,
+    pub dropped: Vec<String>,
+}
+
+/// Facts gathered about the set while running the pipeline stages.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerSetMetadata {
+    /// Software of the highest-priority file, which decides the rename rules
+    pub primary_software: Option<&'static str>,
+    /// Copper layer count, known after validation
+    pub layer_count: Option<u32>,
+    pub warnings: Vec<String>,
+    /// Problems found before validation that make the set unorderable
+    pub errors: Vec<String>,
+    /// Signs that the input was already a converted JLC archive, kept when
+    /// the conversion was forced anyway
+    pub reconversion_signs: Vec<String>,
+}
+
+/// The set of files making up one board, with a method for each pipeline stage.
+///
+/// Gerber layers and drill files are kept apart because drills are merged and
+/// regenerated as a whole, while Gerber layers are rewritten one by one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerSet {
+    pub layers: Vec<LayerFile>,
+    /// Drill files as uploaded, until [`LayerSet::process_drills`] merges them
+    pub drills: Vec<LayerFile>,
+    /// The merged drill files that replace `drills`
+    pub merged_drills: Vec<MergedDrill>,
+    pub metadata: LayerSetMetadata,
+}
+
+impl LayerSet {
+    /// Builds a set from (filename, content) pairs, classifying drill files by content.
+    pub fn from_files(files: &[(String, String)]) -> Self {
+        let mut set = LayerSet::default();
+        for (name, content) in files {
+            set.add_file(name, content);
+        }
+        set
+    }
+
+    /// Adds one file to the set.
+    pub fn add_file(&mut self, name: &str, content: &str) {
+        let file = LayerFile::new(name, content);
+        if drill::is_drill_file_with_content(&file.name, &file.content) {
+            self.drills.push(file);
+        } else {
+            self.layers.push(file);
+        }
+        self.metadata.primary_software = self.detect_primary_software();
+    }
+
+    /// All files of the set held as text, Gerber layers first. Merged drill
+    /// files are in [`LayerSet::merged_drills`].
+    pub fn files(&self) -> impl Iterator<Item = &LayerFile> {
+        self.layers.iter().chain(self.drills.iter())
+    }
+
+    /// Current names of all files in the set, merged drill files last.
+    pub fn file_names(&self) -> Vec<String> {
+        self.files()
+            .map(|f| &f.name)
+            .chain(self.merged_drills.iter().map(|d| &d.name))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of files [`LayerSet::write_outputs`] writes.
+    pub fn output_count(&self) -> usize {
+        self.files().count() + self.merged_drills.len()
+    }
+
+    /// The software of the highest-priority file, as chosen by `sort_gerber_files`.
+    fn detect_primary_software(&self) -> Option<&'static str> {
+        let mut names: Vec<String> = self.files().map(|f| f.original_name.clone()).collect();
+        let sorted = sort_gerber_files(&mut names);
+        let primary = sorted.first()?;
+        self.files()
+            .find(|f| &f.original_name == primary)
+            .and_then(|f| f.software)
+    }
+
+    /// The rename rules matching the primary software.
+    /// A set whose files already have JLC names is passed through as EasyEDA,
+    /// whatever made it; other EasyEDA exports follow the Altium naming.
+    pub fn eda_type(&self) -> Option<EdaType> {
+        if self.is_jlc_named() {
+            return Some(EdaType::EasyEda);
+        }
+        if self.is_kicad_protel_hybrid() {
+            return Some(EdaType::Ad);
+        }
+        EdaType::from_software(self.metadata.primary_software?)
+    }
+
+    /// The software of each file, classified from its own content, by current name.
+    pub fn file_eda_types(&self) -> Vec<(String, Option<EdaType>)> {
+        self.files()
+            .map(|f| (f.name.clone(), f.eda_type()))
+            .collect()
+    }
+
+    /// True if files of the set were made by different software, e.g. Altium
+    /// copper layers with a KiCad drill file.
+    pub fn is_mixed(&self) -> bool {
+        let mut types = self.files().filter_map(LayerFile::eda_type);
+        types
+            .next()
+            .is_some_and(|first| types.any(|eda_type| eda_type != first))
+    }
+
+    /// True if the set has JLC top copper and every other file has a JLC-style name
+    /// ("Gerber_..." / "Drill_...") or is a text note such as the order guide.
+    pub fn is_jlc_named(&self) -> bool {
+        let has_top = self
+            .layers
+            .iter()
+            .any(|f| rename::layer_for_final_name(&f.name) == Some(LogicalLayer::TopLayer));
+        has_top
+            && self.files().all(|f| {
+                f.name.starts_with("Gerber_")
+                    || f.name.starts_with("Drill_")
+                    || f.name.to_lowercase().ends_with(".txt")
+            })
+    }
+
+    /// Signs that the set is the output of a previous conversion or of
+    /// EasyEDA: JLC file names, the Gerber headers written by
+    /// [`LayerSet::add_headers`], and the order guide.
+    pub fn processed_archive_signs(&self) -> Vec<&'static str> {
+        let mut signs = Vec::new();
+        if self.is_jlc_named() {
+            signs.push("JLC file names");
+        }
+        if self
+            .layers
+            .iter()
+            .any(|f| f.content.contains("G04 Gerber Generator version 0.3*"))
+        {
+            signs.push("EasyEDA Gerber headers");
+        }
+        if self.layers.iter().any(|f| f.name == ORDER_GUIDE_FILENAME) {
+            signs.push("the JLC order guide");
+        }
+        signs
+    }
+
+    /// Records [`LayerSet::processed_archive_signs`] when `forced` rename
+    /// rules override the EasyEDA pass-through, so the run can ask the user
+    /// whether converting the archive again was intended.
+    pub fn check_forced_reconversion(&mut self, forced: Option<EdaType>) {
+        if forced.is_none_or(|eda_type| eda_type == EdaType::EasyEda) {
+            return;
+        }
+        self.metadata.reconversion_signs = self
+            .processed_archive_signs()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+    }
+
+    /// True for a KiCad plot made with "Use Protel filename extensions" whose
+    /// names carry no KiCad layer name ("project.gtl"), recognized by top copper. These need the Altium
+    /// rename rules, while D-codes are still processed as KiCad from the content.
+    pub fn is_kicad_protel_hybrid(&self) -> bool {
+        let has_top = |eda_type| {
+            self.layers
+                .iter()
+                .any(|f| rename::logical_name_for(&f.name, eda_type) == Some("Gerber_TopLayer"))
+        };
+        self.metadata.primary_software == Some("KiCad")
+            && !has_top(EdaType::KiCad)
+            && has_top(EdaType::Ad)
+    }
+
+    /// The role of a layer and how it was determined.
+    /// Legacy extensions win over X2 attributes when a file has both.
+    fn layer_role(file: &LayerFile) -> Option<(String, LayerRepresentation)> {
+        if let Some(name) = rename::logical_name_for(&file.name, EdaType::Ad)
+            && name.starts_with("Gerber_")
+        {
+            return Some((name.to_string(), LayerRepresentation::Legacy));
+        }
+        let fields = gerber::file_function(&file.content)?;
+        rename::logical_name_from_file_function(&fields).map(|name| (name, LayerRepresentation::X2))
+    }
+
+    /// Removes layers exported twice, once as X2 `.gbr` and once with a legacy extension.
+    ///
+    /// For every role with more than one file, the first file in the preferred
+    /// representation is kept. Each decision is returned and added to the warnings.
+    pub fn remove_duplicate_layers(
+        &mut self,
+        prefer: LayerRepresentation,
+    ) -> Vec<DuplicateDecision> {
+        let roles: Vec<Option<(String, LayerRepresentation)>> =
+            self.layers.iter().map(Self::layer_role).collect();
+
+        let mut decisions: Vec<DuplicateDecision> = Vec::new();
+        let mut drop = vec![false; self.layers.len()];
+        for (i, role) in roles.iter().enumerate() {
+            let Some((role, _)) = role else { continue };
+            if decisions.iter().any(|d| &d.role == role) {
+                continue;
+            }
+            let same: Vec<usize> = (i..roles.len())
+                .filter(|j| matches!(&roles[*j], Some((r, _)) if r == role))
+                .collect();
+            if same.len() < 2 {
+                continue;
+            }
+
+            let kept = same
+                .iter()
+                .copied()
+                .find(|j| matches!(&roles[*j], Some((_, rep)) if *rep == prefer))
+                .unwrap_or(same[0]);
+            let mut dropped = Vec::new();
+            for j in same.into_iter().filter(|j| *j != kept) {
+                drop[j] = true;
+                dropped.push(self.layers[j].name.clone());
+            }
+            decisions.push(DuplicateDecision {
+                role: role.clone(),
+                kept: self.layers[kept].name.clone(),
+                dropped,
+            });
+        }
+
+        let mut index = 0;
+        self.layers.retain(|_| {
+            index += 1;
+            !drop[index - 1]
+        });
+        for decision in &decisions {
+            self.metadata.warnings.push(format!(
+                "Duplicate {} layer: kept {}, ignored {}",
+                decision.role,
+                decision.kept,
+                decision.dropped.join(", ")
+            ));
+        }
+        decisions
+    }
+
+    /// Cross-checks the layers against an Altium extension report (`.EXTREP`) in the set.
+    /// Conflicts are added to the warnings; nothing is renamed.
+    pub fn verify_extension_report(&mut self) {
+        let Some(report) = self
+            .layers
+            .iter()
+            .find(|f| extrep::is_extension_report(&f.name))
+            .map(|f| extrep::parse_extension_report(&f.content))
+        else {
+            return;
+        };
+        let files: Vec<(String, String)> = self
+            .layers
+            .iter()
+            .map(|f| (f.name.clone(), f.content.clone()))
+            .collect();
+        self.metadata
+            .warnings
+            .extend(extrep::verify_mapping(&report, &files));
+    }
+
+    /// Rewrites numbers written with a decimal comma ("0,25") in every file,
+    /// warning for each file changed, see [`locale::fix_decimal_commas`].
+    pub fn fix_decimal_commas(&mut self) {
+        for layer in self.layers.iter_mut().chain(&mut self.drills) {
+            let (content, count) = locale::fix_decimal_commas(&layer.content);
+            if count > 0 {
+                layer.content = content;
+                self.metadata.warnings.push(format!(
+                    "{}: {} number(s) were written with a decimal comma and were read as decimals, e.g. 0,25 as 0.25",
+                    layer.name, count
+                ));
+            }
+        }
+    }
+
+    /// Converts RS-274-D layers to 274-X with the sizes from a wheel file
+    /// (`.rep`, `.whl`, `.apr`) in the set. Used wheel files are removed.
+    pub fn upconvert_rs274d(&mut self) {
+        if !self.layers.iter().any(|f| rs274d::is_rs274d(&f.content)) {
+            return;
+        }
+        let unit = rs274d::UpconvertOptions::default().unit;
+        let wheel_file = self.layers.iter().position(|f| {
+            let lower = f.name.to_lowercase();
+            [".rep", ".whl", ".apr"]
+                .iter()
+                .any(|ext| lower.ends_with(ext))
+                && !rs274d::parse_wheel(&f.content, unit).apertures.is_empty()
+        });
+        let Some(index) = wheel_file else {
+            self.metadata.warnings.push(
+                "RS-274-D layers found without a wheel file; their aperture sizes are unknown"
+                    .to_string(),
+            );
+            return;
+        };
+
+        let wheel_file = self.layers.remove(index);
+        let wheel = rs274d::parse_wheel(&wheel_file.content, unit);
+        self.metadata.warnings.extend(
+            wheel
+                .warnings
+                .iter()
+                .map(|w| format!("{}: {}", wheel_file.name, w)),
+        );
+        let options = rs274d::UpconvertOptions::default();
+        for layer in self
+            .layers
+            .iter_mut()
+            .filter(|f| rs274d::is_rs274d(&f.content))
+        {
+            let (content, warnings) = rs274d::upconvert(&layer.content, &wheel, &options);
+            layer.content = content;
+            self.metadata.warnings.push(format!(
+                "{} was converted from RS-274-D using {}; check the aperture sizes",
+                layer.name, wheel_file.name
+            ));
+            self.metadata
+                .warnings
+                .extend(warnings.iter().map(|w| format!("{}: {}", layer.name, w)));
+        }
+    }
+
+    /// Removes files that are not fab data, such as reports, PDFs and macOS
+    /// archive metadata. Run after the stages that read the reports.
+    pub fn remove_junk(&mut self) -> Vec<IgnoredFile> {
+        let mut ignored = Vec::new();
+        for files in [&mut self.layers, &mut self.drills] {
+            files.retain(|f| match junk::classify_junk(&f.original_name) {
+                Some(kind) => {
+                    ignored.push(IgnoredFile {
+                        file: f.original_name.clone(),
+                        kind,
+                    });
+                    false
+                }
+                None => true,
+            });
+        }
+        self.metadata.primary_software = self.detect_primary_software();
+        ignored
+    }
+
+    /// Applies `policy` to the drill drawings of a renamed set and returns
+    /// the files dropped. Run after renaming, which gives drill maps their
+    /// own name.
+    pub fn apply_drill_map_policy(&mut self, policy: DrillMapPolicy) -> Vec<IgnoredFile> {
+        let map_name = rename::final_name_for(LogicalLayer::DrillMap);
+        let parameters_name = rename::get_final_filename("Gerber_Layer_Drawing_Parameters");
+        let mut ignored = Vec::new();
+        let mut kept = 0;
+        let mut warnings = Vec::new();
+        self.layers.retain_mut(|f| {
+            let kind = if f.name.eq_ignore_ascii_case(map_name) {
+                JunkKind::DrillMap
+            } else if f.name == parameters_name {
+                JunkKind::DrawingParameters
+            } else {
+                return true;
+            };
+            if policy == DrillMapPolicy::Document && kind == JunkKind::DrillMap {
+                kept += 1;
+                f.name = match kept {
+                    1 => DRILL_MAP_DOCUMENT_NAME.to_string(),
+                    n => DRILL_MAP_DOCUMENT_NAME.replace(".GBR", &format!("{}.GBR", n)),
+                };
+                return true;
+            }
+            warnings.push(format!(
+                "{} is a drill drawing and was left out; the holes are read from the drill files",
+                f.original_name
+            ));
+            ignored.push(IgnoredFile {
+                file: f.original_name.clone(),
+                kind,
+            });
+            false
+        });
+        self.metadata.warnings.extend(warnings);
+        ignored
+    }
+
+    /// Replaces the V-cut layer with a text note when `output` asks for one.
+    /// Only straight horizontal and vertical lines can be scored; any other
+    /// geometry is left out of the note with a warning.
+    pub fn apply_v_cut_output(&mut self, output: VCutOutput) {
+        if output != VCutOutput::Note {
+            return;
+        }
+        let Some(file) = self
+            .layers
+            .iter_mut()
+            .find(|f| rename::layer_for_final_name(&f.name) == Some(LogicalLayer::VCut))
+        else {
+            return;
+        };
+        let (content, skipped) = v_cut_note(&file.original_name, &file.content);
+        file.name = V_CUT_NOTE_NAME.to_string();
+        file.content = content;
+        // Plain text, so the Gerber stages leave it alone
+        file.software = None;
+        if skipped > 0 {
+            let warning = format!(
+                "{} of {} is not a horizontal or vertical line and was left out of the V-cut note",
+                if skipped == 1 {
+                    "1 object".to_string()
+                } else {
+                    format!("{} objects", skipped)
+                },
+                file.original_name
+            );
+            self.metadata.warnings.push(warning);
+        }
+    }
+
+    /// Stage 1: rename every Gerber layer to its JLC name.
+    pub fn rename(&mut self, eda_type: EdaType) {
+        self.rename_with_outline(eda_type, &OutlineSelection::Auto);
+    }
+
+    /// Like [`LayerSet::rename`], with `outline` choosing the board outline file.
+    pub fn rename_with_outline(&mut self, eda_type: EdaType, outline: &OutlineSelection) {
+        self.rename_planned(
+            eda_type,
+            outline,
+            CollisionPolicy::default(),
+            &CustomRules::default(),
+        );
+    }
+
+    /// Like [`LayerSet::rename_with_outline`], with `rules` merged into the
+    /// built-in rules and files that map to the same name resolved by `policy`.
+    /// Returns the plan that was applied.
+    pub fn rename_planned(
+        &mut self,
+        eda_type: EdaType,
+        outline: &OutlineSelection,
+        policy: CollisionPolicy,
+        rules: &CustomRules,
+    ) -> RenamePlan {
+        // Drill files take part as context for sibling-dependent rules
+        let files = self.file_names();
+        if *outline != OutlineSelection::Auto && !files.iter().any(|f| outline.matches(f)) {
+            self.metadata.warnings.push(format!(
+                "No file matches the outline selection {:?}",
+                outline
+            ));
+        }
+        let hybrid = eda_type == EdaType::Ad && self.is_kicad_protel_hybrid();
+        let mut result = rules.map_filenames_detailed(&files, eda_type);
+        // In a mixed set, a layer made by other software is renamed by that
+        // software's rules when they place it. D-codes and drills already
+        // follow each file's own content.
+        if eda_type != EdaType::EasyEda && !hybrid {
+            let mut by_eda: Vec<(EdaType, RenameResult)> = Vec::new();
+            for layer in &self.layers {
+                let Some(own) = layer.eda_type().filter(|own| *own != eda_type) else {
+                    continue;
+                };
+                if !by_eda.iter().any(|(e, _)| *e == own) {
+                    by_eda.push((own, rules.map_filenames_detailed(&files, own)));
+                }
+                let (_, own_result) = by_eda
+                    .iter()
+                    .find(|(e, _)| *e == own)
+                    .expect("Result was just added");
+                if let Some(entry) = own_result.get(&layer.name)
+                    && !entry.is_untouched()
+                {
+                    result.insert(entry.clone());
+                }
+            }
+        }
+        // X2 attributes state the layer function, so they beat the filename
+        // rules; user rules and drill files are left alone
+        for layer in &self.layers {
+            let Some(class) = classify::classify_x2(&layer.content) else {
+                continue;
+            };
+            let Some(entry) = result.entries.get_mut(&layer.name) else {
+                continue;
+            };
+            let overridable = matches!(
+                entry.source,
+                MatchSource::BuiltIn { .. } | MatchSource::Unmatched
+            ) && entry.logical_name.as_deref() != Some("Drill_Source");
+            if overridable {
+                entry.new_name = rename::get_final_filename(&class.logical_name);
+                entry.logical_name = Some(class.logical_name);
+                entry.source = MatchSource::FileFunction {
+                    file_function: class.file_function,
+                };
+                entry.confidence = Confidence::High;
+            }
+        }
+        // A job file names the function of every layer, so it beats the filename rules
+        if let Some(job) = self
+            .layers
+            .iter()
+            .find(|f| f.name.to_lowercase().ends_with(".gbrjob"))
+            .and_then(|f| gbrjob::parse_gbrjob(&f.content))
+        {
+            job.apply(&mut result);
+        }
+        rename::select_outline(&mut result, &files, outline);
+        // Layers with a bare .gbr name that no rule placed are guessed from
+        // their content, onto layers no other file holds
+        for layer in &self.layers {
+            let unmatched = classify::has_generic_gerber_extension(&layer.name)
+                && result
+                    .get(&layer.name)
+                    .is_some_and(|e| e.source == MatchSource::Unmatched);
+            if !unmatched {
+                continue;
+            }
+            let Some(guess) = classify::guess_layer(&layer.name, &layer.content) else {
+                continue;
+            };
+            let taken = result
+                .iter()
+                .any(|e| e.logical_name.as_deref() == Some(guess.logical_name.as_str()));
+            if taken {
+                continue;
+            }
+            if guess.score < classify::MIN_GUESS_SCORE {
+                self.metadata.warnings.push(format!(
+                    "{} matched no rule and may be {} ({}% sure: {}); it was not renamed",
+                    layer.name, guess.logical_name, guess.score, guess.evidence
+                ));
+                continue;
+            }
+            let Some(entry) = result.entries.get_mut(&layer.name) else {
+                continue;
+            };
+            entry.new_name = rename::get_final_filename(&guess.logical_name);
+            entry.logical_name = Some(guess.logical_name.clone());
+            entry.confidence = if guess.score >= 70 {
+                Confidence::Medium
+            } else {
+                Confidence::Low
+            };
+            let evidence = guess.evidence.clone();
+            entry.source = MatchSource::Content {
+                evidence: guess.evidence,
+                score: guess.score,
+            };
+            self.metadata.warnings.push(format!(
+                "{} matched no rule and was guessed to be {} ({}% sure: {})",
+                layer.name, guess.logical_name, guess.score, evidence
+            ));
+        }
+        let plan = RenamePlan::resolve(&files, result, policy);
+        for layer in &mut self.layers {
+            if let Some(new_name) = plan.renames.get(&layer.name) {
+                layer.name = new_name.clone();
+            }
+        }
+        if hybrid {
+            self.number_kicad_inner_layers();
+        }
+
+        let messages = plan.collision_messages();
+        match policy {
+            CollisionPolicy::Error => self.metadata.errors.extend(messages),
+            CollisionPolicy::KeepFirst => self.metadata.warnings.extend(
+                messages
+                    .into_iter()
+                    .map(|m| format!("{}; only the first was renamed", m)),
+            ),
+            CollisionPolicy::Suffix => self.metadata.warnings.extend(
+                messages
+                    .into_iter()
+                    .map(|m| format!("{}; the others were numbered", m)),
+            ),
+            CollisionPolicy::Merge => {
+                for collision in &plan.collisions {
+                    self.merge_collision(collision);
+                }
+            }
+        }
+        plan
+    }
+
+    // Merges the layers of a collision into the first one. Layers that cannot
+    // be merged keep their original names instead.
+    fn merge_collision(&mut self, collision: &rename::Collision) {
+        let indices: Vec<usize> = collision
+            .sources
+            .iter()
+            .filter_map(|source| {
+                self.layers
+                    .iter()
+                    .position(|f| &f.original_name == source && f.name == collision.target)
+            })
+            .collect();
+        let Some((&first, rest)) = indices.split_first() else {
+            return;
+        };
+        let contents: Vec<&str> = indices
+            .iter()
+            .map(|&i| self.layers[i].content.as_str())
+            .collect();
+        match compose::merge_layers(&contents) {
+            Some(merged) => {
+                self.layers[first].content = merged;
+                let mut rest = rest.to_vec();
+                rest.sort_unstable_by(|a, b| b.cmp(a));
+                for i in rest {
+                    self.layers.remove(i);
+                }
+                self.metadata.warnings.push(format!(
+                    "{} were merged into {}",
+                    collision.sources.join(", "),
+                    collision.target
+                ));
+            }
+            None => {
+                for &i in rest {
+                    self.layers[i].name = self.layers[i].original_name.clone();
+                }
+                self.metadata.warnings.push(format!(
+                    "{} could not be merged into {} because they use aperture macros; only the first was renamed",
+                    collision.sources.join(", "),
+                    collision.target
+                ));
+            }
+        }
+    }
+
+    // KiCad's Protel extensions start the inner layers at .g2 (In1.Cu is .g2),
+    // so shift them down when the stack has no first inner layer.
+    fn number_kicad_inner_layers(&mut self) {
+        let inner_index = |f: &LayerFile| match rename::layer_for_final_name(&f.name) {
+            Some(LogicalLayer::InnerLayer(n)) => Some(n),
+            _ => None,
+        };
+        if self.layers.iter().filter_map(inner_index).min() != Some(2) {
+            return;
+        }
+        for layer in &mut self.layers {
+            if let Some(n) = inner_index(layer) {
+                layer.name = rename::final_name_for(LogicalLayer::InnerLayer(n - 1)).to_string();
+            }
+        }
+    }
+
+    /// Re-reads the paste and silkscreen layers of an Altium set by `mapping`.
+    /// Files that the rename rules placed otherwise, e.g. by X2 attributes,
+    /// are left alone, as are moves onto a layer another file already holds.
+    pub fn remap_paste_silk(&mut self, mapping: &PasteSilkMapping) {
+        let moves: Vec<(usize, LogicalLayer)> = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, f)| {
+                let standard = PasteSilkMapping::standard_layer(&f.original_name);
+                let untouched = f.name == f.original_name
+                    || standard.is_some_and(|l| f.name == rename::final_name_for(l));
+                let layer = mapping.layer_for(&f.original_name, &f.content)?;
+                (untouched && f.name != rename::final_name_for(layer)).then_some((index, layer))
+            })
+            .collect();
+        for &(index, layer) in &moves {
+            let final_name = rename::final_name_for(layer);
+            let held =
+                self.layers.iter().enumerate().any(|(i, f)| {
+                    f.name == final_name && !moves.iter().any(|(moved, _)| *moved == i)
+                });
+            if held {
+                self.metadata.warnings.push(format!(
+                    "{} was not read as {}, which another file already is",
+                    self.layers[index].original_name,
+                    layer.logical_name()
+                ));
+                continue;
+            }
+            let file = &mut self.layers[index];
+            if *mapping == PasteSilkMapping::Auto {
+                self.metadata.warnings.push(format!(
+                    "{} was read as {} from its content",
+                    file.original_name,
+                    layer.logical_name()
+                ));
+            }
+            file.name = final_name.to_string();
+        }
+    }
+
+    /// Renames files to the layers the user chose for them. A file already
+    /// holding one of those layers goes back to its original name.
+    pub fn apply_layer_overrides(&mut self, overrides: &[(String, LogicalLayer)]) {
+        for (file, layer) in overrides {
+            let final_name = rename::final_name_for(*layer);
+            if !self.layers.iter().any(|f| &f.original_name == file) {
+                continue;
+            }
+            for layer_file in &mut self.layers {
+                if &layer_file.original_name == file {
+                    layer_file.name = final_name.to_string();
+                } else if layer_file.name == final_name {
+                    layer_file.name = layer_file.original_name.clone();
+                }
+            }
+        }
+    }
+
+    /// Stage 2: prepend the shared generator header to recognized layers.
+    pub fn add_headers(&mut self) {
+        let shared_header = header::get_gerber_header();
+        for layer in self.layers.iter_mut().filter(|f| f.software.is_some()) {
+            layer.content = format!("{}{}", shared_header, layer.content);
+        }
+    }
+
+    /// Stage 3: prefix D-codes with G54 using each layer's own flavor.
+    pub fn process_d_codes(&mut self) {
+        self.process_d_codes_cancellable(&NeverCancel)
+            .expect("NeverCancel never cancels");
+    }
+
+    /// Same as [`LayerSet::process_d_codes`], but returns `Err(Cancelled)` as
+    /// soon as `cancel` asks to, leaving the set unchanged.
+    pub fn process_d_codes_cancellable(
+        &mut self,
+        cancel: &dyn Cancellation,
+    ) -> Result<(), Cancelled> {
+        let mut processed = Vec::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let flavor = match layer.software {
+                Some("KiCad") => GerberFlavor::KiCad,
+                Some("Altium") | Some("EasyEDA") => GerberFlavor::Altium,
+                _ => continue,
+            };
+            let content =
+                dcode::process_d_codes_cancellable(layer.content.clone(), flavor, cancel)?;
+            processed.push((index, content));
+        }
+        for (index, content) in processed {
+            self.layers[index].content = content;
+        }
+        Ok(())
+    }
+
+    /// Stage 4: embed the fingerprint aperture in recognized layers.
+    pub fn add_fingerprints(&mut self, is_foreign_board_file: bool) {
+        self.add_fingerprints_with_mode(is_foreign_board_file, FingerprintMode::Standard);
+    }
+
+    /// Like [`LayerSet::add_fingerprints`], with the aperture made according to `mode`.
+    pub fn add_fingerprints_with_mode(
+        &mut self,
+        is_foreign_board_file: bool,
+        mode: FingerprintMode,
+    ) {
+        self.add_fingerprints_cancellable(is_foreign_board_file, mode, &NeverCancel)
+            .expect("NeverCancel never cancels");
+    }
+
+    /// Same as [`LayerSet::add_fingerprints_with_mode`], but returns
+    /// `Err(Cancelled)` as soon as `cancel` asks to, leaving the set unchanged.
+    pub fn add_fingerprints_cancellable(
+        &mut self,
+        is_foreign_board_file: bool,
+        mode: FingerprintMode,
+        cancel: &dyn Cancellation,
+    ) -> Result<(), Cancelled> {
+        let mut processed = Vec::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            if layer.software.is_none() {
+                continue;
+            }
+            let content = fingerprint::add_fingerprint_with_mode_cancellable(
+                &layer.content,
+                is_foreign_board_file,
+                mode,
+                cancel,
+            )?;
+            processed.push((index, content));
+        }
+        for (index, content) in processed {
+            self.layers[index].content = content;
+        }
+        Ok(())
+    }
+
+    /// Stage 5: merge all drill files into JLC PTH/NPTH outputs.
+    /// Drills are checked against the outline, so a drill file with a misdeclared
+    /// unit is converted to mm instead of producing holes off the board.
+    /// The merged tools are then checked, see [`LayerSet::check_drill_capabilities`].
+    pub fn process_drills(&mut self) {
+        self.process_drills_with_options(drill::DrillProcessOptions {
+            fix_units: true,
+            ..drill::DrillProcessOptions::default()
+        });
+        self.check_drill_capabilities();
+    }
+
+    /// Like [`LayerSet::process_drills`], with the tolerance, output format and
+    /// unit fixes of `options`. The board outline and the stackup are taken
+    /// from the set, replacing those of `options`. The merged tools are not
+    /// checked, so that oversized holes can be routed first.
+    pub fn process_drills_with_options(
+        &mut self,
+        options: drill::DrillProcessOptions,
+    ) -> Vec<AppliedFix> {
+        self.process_drills_cancellable(options, &NeverCancel)
+            .expect("NeverCancel never cancels")
+    }
+
+    /// Same as [`LayerSet::process_drills_with_options`], but returns
+    /// `Err(Cancelled)` as soon as `cancel` asks to, leaving the set unchanged.
+    pub fn process_drills_cancellable(
+        &mut self,
+        mut options: drill::DrillProcessOptions,
+        cancel: &dyn Cancellation,
+    ) -> Result<Vec<AppliedFix>, Cancelled> {
+        if self.drills.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let contents: Vec<String> = self.drills.iter().map(|f| f.content.clone()).collect();
+        let names: Vec<String> = self.drills.iter().map(|f| f.name.clone()).collect();
+        let board = self
+            .layers
+            .iter()
+            .find(|f| f.name.starts_with("Gerber_BoardOutlineLayer"))
+            .and_then(|f| gerber::layer_bounds(&f.content));
+        options.board = board;
+        options.context = self.drill_span_context();
+        let result = drill::process_drill_files_cancellable(&contents, &names, &options, cancel)?;
+
+        self.drills.clear();
+        self.merged_drills.clear();
+        let format = options.format;
+        let through = [
+            (result.pth, drill::HoleType::Plated, "PTH"),
+            (result.npth, drill::HoleType::NonPlated, "NPTH"),
+        ];
+        for (file, hole_type, label) in through {
+            if let Some(file) = file {
+                self.merged_drills.push(MergedDrill {
+                    original_name: format!("[merged {} drills]", label),
+                    name: format!("Drill_{}_Through.DRL", label),
+                    hole_type,
+                    span: None,
+                    format,
+                    drill: file,
+                });
+            }
+        }
+        let copper_layers = options.context.copper_layers;
+        for (span, file) in result.spans {
+            self.merged_drills.push(MergedDrill {
+                original_name: format!("[merged L{}-L{} vias]", span.from, span.to),
+                name: span.file_name(copper_layers),
+                hole_type: drill::HoleType::Plated,
+                span: Some((span, copper_layers)),
+                format,
+                drill: file,
+            });
+        }
+        self.metadata.warnings.extend(result.warnings);
+
+        let mut fixes: Vec<AppliedFix> = result
+            .unit_fixes
+            .into_iter()
+            .map(|(file, description)| AppliedFix {
+                pass: "drill-units",
+                file: Some(file),
+                description,
+            })
+            .collect();
+        if result.report.duplicates_removed > 0 {
+            fixes.push(AppliedFix {
+                pass: "drill-dedupe",
+                file: None,
+                description: format!(
+                    "removed {} duplicate hole(s) from the merged drill files",
+                    result.report.duplicates_removed
+                ),
+            });
+        }
+        Ok(fixes)
+    }
+
+    /// Turns the holes of the merged through-hole drill files that are larger
+    /// than [`drill::MAX_DRILL_MM`] into routed cutouts, as `mode` says.
+    /// Returns how many holes were converted.
+    pub fn route_oversized_holes(&mut self, mode: OversizedHoles) -> usize {
+        if mode == OversizedHoles::Keep {
+            return 0;
+        }
+        let outline = self
+            .layers
+            .iter()
+            .position(|f| f.name.starts_with("Gerber_BoardOutlineLayer"));
+        let mut converted = 0;
+        for merged in self.merged_drills.iter_mut().filter(|d| d.span.is_none()) {
+            let hole_type = merged.hole_type;
+            let holes = drill::take_oversized_holes(&mut merged.drill, drill::MAX_DRILL_MM);
+            if holes.is_empty() {
+                continue;
+            }
+
+            let cut = mode == OversizedHoles::Outline && hole_type == drill::HoleType::NonPlated;
+            let cutout = outline.filter(|_| cut).and_then(|index| {
+                outline_with_cutouts(&self.layers[index].content, &holes).map(|c| (index, c))
+            });
+            match cutout {
+                Some((index, content)) => self.layers[index].content = content,
+                None => {
+                    if cut {
+                        self.metadata.warnings.push(format!(
+                            "{} holes above {} mm were routed as slots, as the board outline cannot take them",
+                            holes.len(),
+                            drill::MAX_DRILL_MM
+                        ));
+                    }
+                    drill::route_holes(&mut merged.drill, &holes, hole_type);
+                }
+            }
+            converted += holes.len();
+        }
+        converted
+    }
+
+    /// Warns about the tools of the merged drill files that JLC cannot make
+    /// or only makes at extra cost, see [`drill::drill_capability_warnings`].
+    /// Run after [`LayerSet::route_oversized_holes`], so that holes it routed
+    /// are not reported.
+    pub fn check_drill_capabilities(&mut self) {
+        for merged in &self.merged_drills {
+            let label = match (merged.span, merged.hole_type) {
+                (Some(_), _) => merged.name.as_str(),
+                (None, drill::HoleType::Plated) => "PTH",
+                (None, drill::HoleType::NonPlated) => "NPTH",
+            };
+            self.metadata
+                .warnings
+                .extend(drill::drill_capability_warnings(
+                    &merged.drill,
+                    merged.hole_type,
+                    label,
+                ));
+        }
+    }
+
+    // The stackup of the renamed set and the layer pairs of its Altium drill
+    // report, for blind and buried vias, and the tools of an Allegro tool report
+    fn drill_span_context(&self) -> drill::SpanContext {
+        let mut copper_layers = 0;
+        let mut mid_layers = 0;
+        for file in &self.layers {
+            match rename::layer_for_final_name(&file.name) {
+                Some(LogicalLayer::TopLayer | LogicalLayer::BottomLayer) => copper_layers += 1,
+                Some(LogicalLayer::InnerLayer(_)) => {
+                    copper_layers += 1;
+                    if !rename::is_internal_plane(&file.original_name) {
+                        mid_layers += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let spans = self
+            .layers
+            .iter()
+            .filter(|f| f.original_name.to_lowercase().ends_with(".drr"))
+            .flat_map(|f| drill::parse_drill_report_spans(&f.content, copper_layers, mid_layers))
+            .collect();
+        let tool_report = self
+            .layers
+            .iter()
+            .filter(|f| drill::is_tool_report(&f.original_name))
+            .flat_map(|f| drill::parse_tool_report(&f.content))
+            .collect();
+        drill::SpanContext {
+            copper_layers,
+            mid_layers,
+            spans,
+            tool_report,
+        }
+    }
+
+    // The board contour on the outline layer, chosen around the copper
+    fn board_contour(&self) -> Option<BoundingBox> {
+        let copper = self
+            .layers
+            .iter()
+            .filter(|f| {
+                matches!(
+                    rename::layer_for_final_name(&f.name),
+                    Some(
+                        LogicalLayer::TopLayer
+                            | LogicalLayer::BottomLayer
+                            | LogicalLayer::InnerLayer(_)
+                    )
+                )
+            })
+            .filter_map(|f| gerber::parse_gerber(&f.content).image_bounds())
+            .reduce(|mut a, b| {
+                a.include_box(&b);
+                a
+            });
+        self.layers
+            .iter()
+            .find(|f| rename::layer_for_final_name(&f.name) == Some(LogicalLayer::BoardOutline))
+            .and_then(|f| autofix::board_contour(&f.content, copper))
+    }
+
+    /// Strips geometry of the outline and document layers lying more than
+    /// [`autofix::OFF_BOARD_MARGIN_MM`] outside the board contour, which fabs
+    /// would otherwise count into the board size. Does nothing when no closed
+    /// contour is found on the outline layer.
+    pub fn remove_off_board_geometry(&mut self) -> Vec<AppliedFix> {
+        let mut fixes = Vec::new();
+        let Some(board) = self.board_contour() else {
+            return fixes;
+        };
+
+        for layer in &mut self.layers {
+            let annotated = rename::layer_for_final_name(&layer.name)
+                == Some(LogicalLayer::BoardOutline)
+                || layer.name.starts_with("Gerber_DocumentLayer");
+            if !annotated {
+                continue;
+            }
+            if let Some((content, before, after)) = autofix::strip_off_board(&layer.content, &board)
+            {
+                layer.content = content;
+                fixes.push(AppliedFix {
+                    pass: "off-board-geometry",
+                    file: Some(layer.name.clone()),
+                    description: format!(
+                        "removed geometry more than {} mm outside the {:.2} x {:.2} mm board; the layer shrank from {:.2} x {:.2} mm to {:.2} x {:.2} mm",
+                        autofix::OFF_BOARD_MARGIN_MM,
+                        board.width(),
+                        board.height(),
+                        before.width(),
+                        before.height(),
+                        after.width(),
+                        after.height()
+                    ),
+                });
+            }
+        }
+        fixes
+    }
+
+    // Names of the copper layers holding a negative image: Altium internal
+    // planes, and any copper marked negative
+    fn negative_copper_layers(&self) -> Vec<String> {
+        self.layers
+            .iter()
+            .filter(|f| match rename::layer_for_final_name(&f.name) {
+                Some(LogicalLayer::InnerLayer(_)) => {
+                    rename::is_internal_plane(&f.original_name)
+                        || autofix::is_negative_image(&f.content)
+                }
+                Some(LogicalLayer::TopLayer | LogicalLayer::BottomLayer) => {
+                    autofix::is_negative_image(&f.content)
+                }
+                _ => false,
+            })
+            .map(|f| f.name.clone())
+            .collect()
+    }
+
+    /// Converts negative copper images, such as Altium internal planes, to
+    /// positive layers filled over the board contour, since JLC reads every
+    /// copper layer as positive and would make a negative plane inverted.
+    pub fn invert_negative_planes(&mut self) -> Vec<AppliedFix> {
+        let names = self.negative_copper_layers();
+        if names.is_empty() {
+            return Vec::new();
+        }
+        let board = self.board_contour();
+        let mut fixes = Vec::new();
+        for layer in self.layers.iter_mut().filter(|f| names.contains(&f.name)) {
+            let Some((content, area)) = autofix::invert_negative_plane(&layer.content, board)
+            else {
+                continue;
+            };
+            layer.content = content;
+            fixes.push(AppliedFix {
+                pass: "negative-plane",
+                file: Some(layer.name.clone()),
+                description: format!(
+                    "converted the negative plane {} to a positive layer filled over {:.2} x {:.2} mm{}",
+                    layer.original_name,
+                    area.width(),
+                    area.height(),
+                    if board.is_some() {
+                        ""
+                    } else {
+                        " (its own extents, as no board contour was found)"
+                    }
+                ),
+            });
+        }
+        fixes
+    }
+
+    /// Repairs the renamed Gerber layers as far as `level` allows and returns
+    /// the fixes applied. Runs after renaming, since the passes look for layers
+    /// by their JLC names.
+    pub fn apply_fixes(&mut self, level: FixLevel) -> Vec<AppliedFix> {
+        let mut fixes = Vec::new();
+        if level == FixLevel::None {
+            for name in self.negative_copper_layers() {
+                self.metadata.warnings.push(format!(
+                    "{} is a negative plane; JLC reads copper as positive and would make it inverted",
+                    name
+                ));
+            }
+            return fixes;
+        }
+
+        for layer in &mut self.layers {
+            let (content, count) = autofix::repair_zero_size_apertures(&layer.content);
+            if count > 0 {
+                layer.content = content;
+                fixes.push(AppliedFix {
+                    pass: "zero-size-aperture",
+                    file: Some(layer.name.clone()),
+                    description: format!("gave {} zero-size aperture(s) a size of 1 µm", count),
+                });
+            }
+        }
+
+        fixes.extend(self.remove_off_board_geometry());
+        fixes.extend(self.invert_negative_planes());
+
+        if level < FixLevel::Aggressive {
+            return fixes;
+        }
+
+        let has =
+            |layers: &[LayerFile], prefix: &str| layers.iter().any(|f| f.name.starts_with(prefix));
+        if !has(&self.layers, "Gerber_BoardOutlineLayer") {
+            let copper: Vec<&str> = self
+                .layers
+                .iter()
+                .filter(|f| {
+                    f.name.starts_with("Gerber_TopLayer")
+                        || f.name.starts_with("Gerber_BottomLayer")
+                        || f.name.starts_with("Gerber_InnerLayer")
+                })
+                .map(|f| f.content.as_str())
+                .collect();
+            if let Some((content, bounds)) = autofix::synthesize_outline(&copper) {
+                let name = rename::final_name_for(LogicalLayer::BoardOutline).to_string();
+                fixes.push(AppliedFix {
+                    pass: "outline-synthesis",
+                    file: Some(name.clone()),
+                    description: format!(
+                        "no outline layer; drew a {:.2} x {:.2} mm rectangle around the copper",
+                        bounds.width(),
+                        bounds.height()
+                    ),
+                });
+                self.layers.push(LayerFile {
+                    original_name: "[synthesized outline]".to_string(),
+                    name,
+                    content,
+                    software: None,
+                });
+            }
+        }
+
+        for (copper, mask, top) in [
+            (LogicalLayer::TopLayer, LogicalLayer::TopSolderMask, true),
+            (
+                LogicalLayer::BottomLayer,
+                LogicalLayer::BottomSolderMask,
+                false,
+            ),
+        ] {
+            if has(&self.layers, mask.logical_name()) {
+                continue;
+            }
+            let Some(source) = self
+                .layers
+                .iter()
+                .find(|f| f.name.starts_with(copper.logical_name()))
+            else {
+                continue;
+            };
+            if let Some(content) =
+                autofix::regenerate_mask(&source.content, autofix::MASK_EXPANSION_MM, top)
+            {
+                let name = rename::final_name_for(mask).to_string();
+                fixes.push(AppliedFix {
+                    pass: "mask-regeneration",
+                    file: Some(name.clone()),
+                    description: format!(
+                        "no solder mask; opened every pad of {} with {} mm expansion",
+                        source.name,
+                        autofix::MASK_EXPANSION_MM
+                    ),
+                });
+                self.layers.push(LayerFile {
+                    original_name: "[regenerated solder mask]".to_string(),
+                    name,
+                    content,
+                    software: None,
+                });
+            }
+        }
+
+        fixes
+    }
+
+    /// Makes every Gerber layer end with exactly one `M02*` and every drill
+    /// file with one `M30`, as concatenated or truncated inputs may not.
+    /// With [`FixLevel::None`] the files are only reported.
+    pub fn repair_end_markers(&mut self, level: FixLevel) -> Vec<AppliedFix> {
+        let mut fixes = Vec::new();
+        let files = self
+            .layers
+            .iter_mut()
+            .filter(|f| f.content.contains("%FS") || f.content.contains("%MO"))
+            .map(|f| (f, false))
+            .chain(self.drills.iter_mut().map(|f| (f, true)));
+        for (file, drill) in files {
+            let marker = if drill {
+                autofix::DRILL_END_MARKER
+            } else {
+                autofix::GERBER_END_MARKER
+            };
+            if level == FixLevel::None {
+                if let Some(issue) = autofix::check_end_marker(&file.content, drill) {
+                    self.metadata.warnings.push(format!(
+                        "{} has {}",
+                        file.name,
+                        issue.describe(marker)
+                    ));
+                }
+                continue;
+            }
+            if let Some((content, issue)) = autofix::repair_end_marker(&file.content, drill) {
+                file.content = content;
+                fixes.push(AppliedFix {
+                    pass: "end-marker",
+                    file: Some(file.name.clone()),
+                    description: format!(
+                        "{}; it now ends with one {}",
+                        issue.describe(marker),
+                        marker
+                    ),
+                });
+            }
+        }
+        fixes
+    }
+
+    /// Stage 6: validate the renamed set and record the layer count.
+    pub fn validate(&mut self) -> Result<ValidationReport, Vec<String>> {
+        let mut result = validation::validate_gerber_files(&self.file_names());
+        if !self.metadata.errors.is_empty() {
+            let mut errors = self.metadata.errors.clone();
+            if let Err(validation_errors) = result {
+                errors.extend(validation_errors);
+            }
+            result = Err(errors);
+        }
+        if let Ok(report) = &result {
+            self.metadata.layer_count = Some(report.layer_count);
+            self.metadata
+                .warnings
+                .extend(report.warnings.iter().cloned());
+        }
+        result
+    }
+
+    /// Warns about each marking (e.g. an order-number placeholder) that no
+    /// silkscreen layer shows as stroke text. Returns the missing markings.
+    pub fn check_required_markings(&mut self, markings: &[String]) -> Vec<String> {
+        if markings.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = Vec::new();
+        for layer in &self.layers {
+            let mirrored = match rename::layer_for_final_name(&layer.name) {
+                Some(LogicalLayer::TopSilkscreen) => false,
+                Some(LogicalLayer::BottomSilkscreen) => true,
+                _ => continue,
+            };
+            lines.extend(silktext::extract_text(
+                &gerber::parse_gerber(&layer.content),
+                mirrored,
+            ));
+        }
+        let missing: Vec<String> = markings
+            .iter()
+            .filter(|marking| !silktext::contains_marking(&lines, marking))
+            .cloned()
+            .collect();
+        self.metadata.warnings.extend(missing.iter().map(|marking| {
+            format!(
+                "Required marking '{}' was not found on the silkscreen",
+                marking
+            )
+        }));
+        missing
+    }
+
+    /// Warns about stroke text on the bottom copper and silkscreen that reads
+    /// correctly from the top, which comes out mirrored on the board.
+    /// Returns the (layer, text) pairs found.
+    pub fn check_text_orientation(&mut self) -> Vec<(String, String)> {
+        let mut found = Vec::new();
+        for layer in &self.layers {
+            if !matches!(
+                rename::layer_for_final_name(&layer.name),
+                Some(LogicalLayer::BottomLayer | LogicalLayer::BottomSilkscreen)
+            ) {
+                continue;
+            }
+            let lines = silktext::unmirrored_text(&gerber::parse_gerber(&layer.content));
+            found.extend(
+                lines
+                    .into_iter()
+                    .map(|line| (layer.name.clone(), line.text)),
+            );
+        }
+        self.metadata
+            .warnings
+            .extend(found.iter().map(|(layer, text)| {
+                format!(
+                    "Text '{}' on {} reads correctly from the top, so it will be mirrored on the board; check the export's mirror setting",
+                    text, layer
+                )
+            }));
+        found
+    }
+
+    /// Warns about plated holes inside SMD pads of the outer copper layers,
+    /// which JLC only builds with via filling selected. Returns the holes.
+    pub fn check_vias_in_pads(&mut self) -> Vec<ViaInPad> {
+        let copper = |layer: LogicalLayer| {
+            self.layers
+                .iter()
+                .find(|f| rename::layer_for_final_name(&f.name) == Some(layer))
+                .map(|f| f.content.as_str())
+        };
+        let (top, bottom) = (
+            copper(LogicalLayer::TopLayer),
+            copper(LogicalLayer::BottomLayer),
+        );
+        let uploaded: Vec<drill::DrillFile> = self
+            .drills
+            .iter()
+            .filter(|f| drill::hole_type_from_filename(&f.name) != Some(drill::HoleType::NonPlated))
+            .map(|f| drill::parse_excellon(&f.content))
+            .collect();
+        let merged = self
+            .merged_drills
+            .iter()
+            .filter(|d| d.hole_type == drill::HoleType::Plated)
+            .map(|d| &d.drill);
+        let mut found = Vec::new();
+        for drill_file in uploaded.iter().chain(merged) {
+            for (copper, opposite, side) in [(top, bottom, "top"), (bottom, top, "bottom")] {
+                let Some(copper) = copper else {
+                    continue;
+                };
+                for via in via_in_pad::find_vias_in_pads(copper, opposite, drill_file) {
+                    self.metadata.warnings.push(format!(
+                        "Via of {:.3} mm at ({:.3}, {:.3}) lies in a {} SMD pad (D{}); select via filling when ordering",
+                        via.drill_diameter, via.location.x, via.location.y, side, via.pad_code
+                    ));
+                    found.push(via);
+                }
+            }
+        }
+        found
+    }
+
+    /// Warns about BGA areas of the outer layers whose mask openings are
+    /// smaller than the copper pads. Returns the areas.
+    pub fn check_mask_defined_pads(&mut self) -> Vec<MaskDefinedArea> {
+        let content = |layer: LogicalLayer| {
+            self.layers
+                .iter()
+                .find(|f| rename::layer_for_final_name(&f.name) == Some(layer))
+                .map(|f| f.content.as_str())
+        };
+        let sides = [
+            ("top", LogicalLayer::TopLayer, LogicalLayer::TopSolderMask),
+            (
+                "bottom",
+                LogicalLayer::BottomLayer,
+                LogicalLayer::BottomSolderMask,
+            ),
+        ];
+        let mut found = Vec::new();
+        for (side, copper, mask) in sides {
+            let (Some(copper), Some(mask)) = (content(copper), content(mask)) else {
+                continue;
+            };
+            for area in mask_defined::find_mask_defined_areas(copper, mask) {
+                let center = area.bounds.center();
+                self.metadata.warnings.push(format!(
+                    "{} solder mask defined BGA pads on the {} side around ({:.2}, {:.2}): {:.3} mm openings on {:.3} mm pads; mention them when ordering assembly",
+                    area.pad_count,
+                    side,
+                    center.x,
+                    center.y,
+                    area.opening_diameter,
+                    area.pad_diameter
+                ));
+                found.push(area);
+            }
+        }
+        found
+    }
+
+    /// Layer count, board size, smallest drill and narrowest copper trace of
+    /// the set, for [`crate::service::recommend_service`]. Run after validation,
+    /// which counts the layers.
+    pub fn board_facts(&self) -> BoardFacts {
+        let size = self
+            .layers
+            .iter()
+            .find(|f| rename::layer_for_final_name(&f.name) == Some(LogicalLayer::BoardOutline))
+            .and_then(|f| gerber::layer_bounds(&f.content))
+            .map(|b| (b.width(), b.height()));
+        let uploaded: Vec<f64> = self
+            .drills
+            .iter()
+            .flat_map(|f| drill::parse_excellon(&f.content).operations)
+            .map(|op| op.diameter)
+            .collect();
+        let min_drill = uploaded
+            .into_iter()
+            .chain(
+                self.merged_drills
+                    .iter()
+                    .flat_map(|d| d.drill.operations.iter().map(|op| op.diameter)),
+            )
+            .filter(|&d| d > 0.0)
+            .reduce(f64::min);
+        let min_trace = self
+            .layers
+            .iter()
+            .filter(|f| {
+                matches!(
+                    rename::layer_for_final_name(&f.name),
+                    Some(
+                        LogicalLayer::TopLayer
+                            | LogicalLayer::BottomLayer
+                            | LogicalLayer::InnerLayer(_)
+                    )
+                )
+            })
+            .filter_map(|f| min_trace_width(&gerber::parse_gerber(&f.content)))
+            .reduce(f64::min);
+        BoardFacts {
+            layer_count: self.metadata.layer_count,
+            size,
+            min_drill,
+            min_trace,
+        }
+    }
+
+    /// The stem of the first uploaded file, e.g. "board" for "out/board.GTL";
+    /// empty when every file was generated.
+    pub fn project_name(&self) -> String {
+        self.files()
+            .find(|f| !f.original_name.starts_with('['))
+            .map(|f| {
+                let base = f
+                    .original_name
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&f.original_name);
+                base.rsplit_once('.')
+                    .map_or(base, |(stem, _)| stem)
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Name for the output zip by `template`, see [`DEFAULT_BUNDLE_TEMPLATE`]:
+    /// `{project}`, `{layers}` (copper layer count), `{width}` and `{height}`
+    /// (board size in whole mm) and `{date}` (today, as YYYYMMDD). Unknown
+    /// values are written as 0, and an empty project as "pcb".
+    pub fn bundle_name(&self, template: &str) -> String {
+        let layers = self.metadata.layer_count.unwrap_or_else(|| {
+            self.layers
+                .iter()
+                .filter(|f| {
+                    matches!(
+                        rename::layer_for_final_name(&f.name),
+                        Some(
+                            LogicalLayer::TopLayer
+                                | LogicalLayer::BottomLayer
+                                | LogicalLayer::InnerLayer(_)
+                        )
+                    )
+                })
+                .count() as u32
+        });
+        let (width, height) = self.board_facts().size.unwrap_or((0.0, 0.0));
+        let project = match self.project_name() {
+            name if name.is_empty() => "pcb".to_string(),
+            name => name.replace(['/', '\\', ':'], "_"),
+        };
+        template
+            .replace("{project}", &project)
+            .replace("{layers}", &layers.to_string())
+            .replace("{width}", &format!("{:.0}", width))
+            .replace("{height}", &format!("{:.0}", height))
+            .replace("{date}", &Local::now().format("%Y%m%d").to_string())
+    }
+
+    /// Renames files with JLC names by `profile`, for fabs other than JLC.
+    /// Run last, since the stages and validation expect JLC names; file names
+    /// listed in a job file are updated with it.
+    pub fn apply_naming_profile(&mut self, profile: &NamingProfile) {
+        if *profile == NamingProfile::Jlc {
+            return;
+        }
+        // Merged drills and generated files take the stem of an uploaded layer
+        let generated = |f: &LayerFile| f.original_name.starts_with('[');
+        let project = self.project_name();
+        let mut renames: Vec<(String, String)> = Vec::new();
+        for file in self.layers.iter_mut().chain(self.drills.iter_mut()) {
+            let Some(layer) = rename::layer_for_final_name(&file.name) else {
+                continue;
+            };
+            let original = if generated(file) {
+                &project
+            } else {
+                &file.original_name
+            };
+            let new_name = profile.file_name(layer.logical_name(), original);
+            renames.push((
+                std::mem::replace(&mut file.name, new_name.clone()),
+                new_name,
+            ));
+        }
+        for merged in &mut self.merged_drills {
+            let Some(layer) = rename::layer_for_final_name(&merged.name) else {
+                continue;
+            };
+            let new_name = profile.file_name(layer.logical_name(), &project);
+            renames.push((
+                std::mem::replace(&mut merged.name, new_name.clone()),
+                new_name,
+            ));
+        }
+        for job in self.layers.iter_mut().filter(|f| {
+            rename::layer_for_final_name(&f.name) == Some(LogicalLayer::GbrJob)
+                || f.name.to_lowercase().ends_with(".gbrjob")
+        }) {
+            for (old, new) in &renames {
+                job.content = job
+                    .content
+                    .replace(&format!("\"{}\"", old), &format!("\"{}\"", new));
+            }
+        }
+    }
+
+    /// Adds a Gerber job file describing the output layers, replacing any imported one.
+    pub fn add_gbrjob(&mut self) {
+        self.layers
+            .retain(|f| !f.name.to_lowercase().ends_with(".gbrjob"));
+        let outputs: Vec<(String, String)> = self
+            .layers
+            .iter()
+            .map(|f| (f.name.clone(), f.content.clone()))
+            .collect();
+        self.layers.push(LayerFile {
+            original_name: "[generated job file]".to_string(),
+            name: gbrjob::GBRJOB_FILENAME.to_string(),
+            content: gbrjob::generate_gbrjob(&outputs),
+            software: None,
+        });
+    }
+
+    /// Stage 7: add the order guide text file unless the set already has one.
+    pub fn add_order_guide(&mut self) {
+        if self.layers.iter().any(|f| f.name == ORDER_GUIDE_FILENAME) {
+            return;
+        }
+        self.layers.push(LayerFile {
+            original_name: ORDER_GUIDE_FILENAME.to_string(),
+            name: ORDER_GUIDE_FILENAME.to_string(),
+            content: header::get_order_guide_text().to_string(),
+            software: None,
+        });
+    }
+
+    /// Serializes the whole set, so parsing work can be cached between calls.
+    pub fn to_json(&self) -> JsonValue {
+        let files =
+            |files: &[LayerFile]| JsonValue::Array(files.iter().map(LayerFile::to_json).collect());
+        let strings =
+            |items: &[String]| JsonValue::Array(items.iter().map(|w| w.as_str().into()).collect());
+        let metadata = JsonValue::Object(vec![
+            (
+                "primary_software".to_string(),
+                self.metadata.primary_software.into(),
+            ),
+            ("layer_count".to_string(), self.metadata.layer_count.into()),
+            ("warnings".to_string(), strings(&self.metadata.warnings)),
+            ("errors".to_string(), strings(&self.metadata.errors)),
+            (
+                "reconversion_signs".to_string(),
+                strings(&self.metadata.reconversion_signs),
+            ),
+        ]);
+        JsonValue::Object(vec![
+            ("layers".to_string(), files(&self.layers)),
+            ("drills".to_string(), files(&self.drills)),
+            (
+                "merged_drills".to_string(),
+                JsonValue::Array(
+                    self.merged_drills
+                        .iter()
+                        .map(MergedDrill::to_json)
+                        .collect(),
+                ),
+            ),
+            ("metadata".to_string(), metadata),
+        ])
+    }
+
+    /// Restores a set serialized by [`LayerSet::to_json`].
+    /// Returns `None` if a required field is missing or has the wrong type.
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let files = |key: &str| -> Option<Vec<LayerFile>> {
+            value
+                .get(key)?
+                .as_array()?
+                .iter()
+                .map(LayerFile::from_json)
+                .collect()
+        };
+        let metadata = value.get("metadata")?;
+        let strings = |key: &str| -> Vec<String> {
+            metadata
+                .get(key)
+                .and_then(JsonValue::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|w| w.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Some(LayerSet {
+            layers: files("layers")?,
+            drills: files("drills")?,
+            merged_drills: match value.get("merged_drills") {
+                Some(merged) => merged
+                    .as_array()?
+                    .iter()
+                    .map(MergedDrill::from_json)
+                    .collect::<Option<_>>()?,
+                None => Vec::new(),
+            },
+            metadata: LayerSetMetadata {
+                primary_software: metadata
+                    .get("primary_software")
+                    .and_then(JsonValue::as_str)
+                    .and_then(known_software),
+                layer_count: metadata
+                    .get("layer_count")
+                    .and_then(JsonValue::as_f64)
+                    .map(|n| n as u32),
+                warnings: strings("warnings"),
+                errors: strings("errors"),
+                reconversion_signs: strings("reconversion_signs"),
+            },
+        })
+    }
+
+    /// Hands every output file to `sink`, in the order of [`LayerSet::into_outputs`],
+    /// then finishes it. Merged drill files are written straight to the sink,
+    /// see [`OutputSink::write_file_with`]. Returns the number of files written.
+    pub fn write_outputs(&self, sink: &mut dyn OutputSink) -> Result<usize, SinkError> {
+        let mut count = 0;
+        for file in self.files() {
+            sink.write_file(&file.name, file.content.as_bytes())?;
+            count += 1;
+        }
+        for merged in &self.merged_drills {
+            sink.write_file_with(&merged.name, &mut |out| merged.write(out))?;
+            count += 1;
+        }
+        sink.finish()?;
+        Ok(count)
+    }
+
+    /// Consumes the set and returns (output name, content) pairs.
+    pub fn into_outputs(self) -> Vec<(String, String)> {
+        let merged: Vec<(String, String)> = self
+            .merged_drills
+            .iter()
+            .map(|d| (d.name.clone(), d.content()))
+            .collect();
+        self.layers
+            .into_iter()
+            .chain(self.drills)
+            .map(|f| (f.name, f.content))
+            .chain(merged)
+            .collect()
+    }
+}
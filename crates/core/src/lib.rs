@@ -0,0 +1,77 @@
+/* src/lib.rs */
+
+//! Converts the Gerber and drill exports of common EDA tools into the files
+//! JLC expects.
+//!
+//! # Supported API
+//!
+//! These items follow semver: an incompatible change needs a new minor
+//! version while the crate is below 1.0, and a new major version after.
+//! Items are deprecated for at least one minor release before they go.
+//!
+//! - The conversion pipeline: [`Pipeline`], [`pipeline::Session`] and
+//!   [`pipeline::ConvertResult`]
+//! - Its options: [`ConvertOptions`] and the types of its fields
+//! - Diagnostics: [`Diagnostic`], [`autofix::AppliedFix`] and
+//!   [`validation::ValidationReport`]
+//! - Everything in [`prelude`]
+//! - Module entry points for single steps: [`rename::map_filenames_detailed`],
+//!   [`dcode::process_d_codes`], [`fingerprint::add_fingerprint`],
+//!   [`drill::process_drill_files`], [`drill::process_drill_files_with_options`],
+//!   [`validation::validate_gerber_files`], [`gerber::parse_gerber`],
+//!   [`outline::compute_board_size`], [`lint::lint_gerber`] and
+//!   [`gbrjob::generate_gbrjob`]
+//!
+//! Other public items are building blocks of these. They are public for
+//! tools and experiments, and may change in any release.
+
+pub mod autofix;
+pub mod cancel;
+pub mod classify;
+#[cfg(feature = "render")]
+pub mod compare;
+pub mod compose;
+pub mod custom_rules;
+pub mod dcode;
+pub mod diagnostic;
+pub mod drill;
+pub mod extrep;
+pub mod file_type;
+pub mod fingerprint;
+pub mod gbrjob;
+pub mod geometry;
+pub mod gerber;
+#[cfg(feature = "archive")]
+pub mod gzip;
+pub mod header;
+pub mod json;
+pub mod junk;
+pub mod kicad;
+pub mod layer_set;
+pub mod limits;
+pub mod lint;
+pub mod locale;
+pub mod mask_defined;
+pub mod outline;
+pub mod pipeline;
+pub mod prelude;
+pub mod provider;
+pub mod rename;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod rs274d;
+pub mod service;
+pub mod silktext;
+pub mod sink;
+pub mod suggest;
+#[cfg(all(feature = "render", feature = "archive"))]
+pub mod support;
+pub mod templates;
+pub mod transform;
+pub mod validation;
+pub mod via_in_pad;
+pub mod writer;
+#[cfg(feature = "archive")]
+pub mod zip;
+
+pub use prelude::{ConvertOptions, Diagnostic, EdaType, LayerSet, Pipeline};
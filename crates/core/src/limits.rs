@@ -0,0 +1,115 @@
+/* src/limits.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use std::fmt;
+
+/// Resources a conversion run may use. `None` leaves a resource unlimited.
+///
+/// Limits are checked cooperatively: input sizes before the first stage, and
+/// the time budget after each stage and from inside the long ones (D-codes,
+/// fingerprints, drills), which stop where they are once it runs out.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Size of all uploaded files together, in bytes
+    pub max_total_bytes: Option<usize>,
+    /// Size of any one uploaded file, in bytes
+    pub max_file_bytes: Option<usize>,
+    /// Wall-clock time the stages may take together, in milliseconds
+    pub time_budget_ms: Option<f64>,
+}
+
+impl ResourceLimits {
+    /// Checks the uploaded (filename, content) pairs against the size limits.
+    pub fn check_input(&self, files: &[(String, String)]) -> Result<(), LimitExceeded> {
+        if let Some(limit) = self.max_file_bytes
+            && let Some((file, content)) = files.iter().find(|(_, c)| c.len() > limit)
+        {
+            return Err(LimitExceeded::FileBytes {
+                file: file.clone(),
+                bytes: content.len(),
+                limit,
+            });
+        }
+        let bytes = files.iter().map(|(_, c)| c.len()).sum();
+        match self.max_total_bytes {
+            Some(limit) if bytes > limit => Err(LimitExceeded::TotalBytes { bytes, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the time spent so far, after `stage`, against the budget.
+    pub fn check_time(&self, stage: &'static str, elapsed_ms: f64) -> Result<(), LimitExceeded> {
+        match self.time_budget_ms {
+            Some(limit_ms) if elapsed_ms > limit_ms => Err(LimitExceeded::TimeBudget {
+                stage,
+                elapsed_ms,
+                limit_ms,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The resource limit a run stopped at
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitExceeded {
+    TotalBytes {
+        bytes: usize,
+        limit: usize,
+    },
+    FileBytes {
+        file: String,
+        bytes: usize,
+        limit: usize,
+    },
+    /// The budget ran out during `stage`; later stages did not run, nor did
+    /// the rest of `stage` when it was stopped from inside
+    TimeBudget {
+        stage: &'static str,
+        elapsed_ms: f64,
+        limit_ms: f64,
+    },
+}
+
+impl LimitExceeded {
+    /// Short machine-readable name, e.g. for a frontend to pick a message.
+    pub fn id(&self) -> &'static str {
+        match self {
+            LimitExceeded::TotalBytes { .. } => "total_bytes",
+            LimitExceeded::FileBytes { .. } => "file_bytes",
+            LimitExceeded::TimeBudget { .. } => "time_budget",
+        }
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::TotalBytes { bytes, limit } => write!(
+                f,
+                "Limits exceeded: the upload is {} bytes, more than the limit of {}",
+                bytes, limit
+            ),
+            LimitExceeded::FileBytes { file, bytes, limit } => write!(
+                f,
+                "Limits exceeded: {} is {} bytes, more than the limit of {}",
+                file, bytes, limit
+            ),
+            LimitExceeded::TimeBudget {
+                stage,
+                elapsed_ms,
+                limit_ms,
+            } => write!(
+                f,
+                "Limits exceeded: conversion took {:.0} ms by the {} stage, more than the budget of {:.0} ms",
+                elapsed_ms, stage, limit_ms
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
@@ -0,0 +1,251 @@
+/* src/lint.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::diagnostic::Diagnostic;
+use crate::gerber::{self, Command, GerberLayer};
+use crate::layer_set::LayerSet;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// This regex matches an extended command name right after its opening %.
+static EXTENDED_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"%([A-Z]{2})").expect("Invalid extended command regex"));
+
+// This regex matches arithmetic or variable definitions inside a macro body.
+static MACRO_EXPRESSION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\$\d+\s*=|[-+/x]\s*\$\d+|\$\d+\s*[-+/x]").expect("Invalid macro regex")
+});
+
+// This regex matches a G74 (single-quadrant) code, standalone or in front of a word.
+static SINGLE_QUADRANT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)(?:^|\*)\s*G74").expect("Invalid G74 regex"));
+
+/// A Gerber viewer with known rendering quirks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Viewer {
+    JlcUploader,  // Online preview on the JLC order page
+    JlcAssistant, // 嘉立创下单助手 desktop client
+    Gerbv,        // gerbv / gEDA
+    AltiumCam,    // Altium Designer CAMtastic editor
+}
+
+impl Viewer {
+    pub const ALL: [Viewer; 4] = [
+        Viewer::JlcUploader,
+        Viewer::JlcAssistant,
+        Viewer::Gerbv,
+        Viewer::AltiumCam,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Viewer::JlcUploader => "JLC uploader",
+            Viewer::JlcAssistant => "嘉立创下单助手",
+            Viewer::Gerbv => "gerbv",
+            Viewer::AltiumCam => "AD CAM editor",
+        }
+    }
+
+    /// Parses a profile id as used by the frontends ("jlc", "jlc-assistant", "gerbv", "altium").
+    pub fn from_id(id: &str) -> Option<Viewer> {
+        match id.to_lowercase().as_str() {
+            "jlc" | "jlc-uploader" => Some(Viewer::JlcUploader),
+            "jlc-assistant" | "assistant" => Some(Viewer::JlcAssistant),
+            "gerbv" => Some(Viewer::Gerbv),
+            "altium" | "ad" | "ad-cam" => Some(Viewer::AltiumCam),
+            _ => None,
+        }
+    }
+}
+
+/// A construct that a viewer is known to render differently from the Gerber specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    /// Short stable id of the check, e.g. "clear-polarity"
+    pub code: &'static str,
+    pub viewer: Viewer,
+    pub message: String,
+}
+
+/// One known quirk: the construct, the viewers that misrender it and what users see.
+struct Quirk {
+    code: &'static str,
+    viewers: &'static [Viewer],
+    description: &'static str,
+}
+
+const QUIRKS: &[Quirk] = &[
+    Quirk {
+        code: "clear-polarity",
+        viewers: &[Viewer::JlcUploader],
+        description: "clear polarity (%LPC) objects may be drawn as copper instead of cut out",
+    },
+    Quirk {
+        code: "step-repeat",
+        viewers: &[Viewer::JlcUploader, Viewer::JlcAssistant],
+        description: "step-and-repeat (%SR) blocks may be shown only once",
+    },
+    Quirk {
+        code: "aperture-block",
+        viewers: &[Viewer::Gerbv, Viewer::AltiumCam, Viewer::JlcAssistant],
+        description: "block apertures (%AB) may be missing from the preview",
+    },
+    Quirk {
+        code: "load-transform",
+        viewers: &[Viewer::Gerbv, Viewer::AltiumCam],
+        description: "mirror, rotation or scale (%LM/%LR/%LS) may be ignored",
+    },
+    Quirk {
+        code: "image-parameter",
+        viewers: &[Viewer::JlcUploader, Viewer::JlcAssistant],
+        description: "deprecated image parameters (%IP/%IR/%MI/%OF/%SF) may be ignored",
+    },
+    Quirk {
+        code: "single-quadrant",
+        viewers: &[Viewer::JlcUploader, Viewer::JlcAssistant],
+        description: "single-quadrant arcs (G74) may be drawn the wrong way round",
+    },
+    Quirk {
+        code: "full-circle",
+        viewers: &[Viewer::JlcUploader, Viewer::AltiumCam],
+        description: "arcs that end where they start may be drawn as a dot instead of a full circle",
+    },
+    Quirk {
+        code: "region-arc",
+        viewers: &[Viewer::JlcAssistant],
+        description: "regions with arc segments may be filled as polygons",
+    },
+    Quirk {
+        code: "macro-expression",
+        viewers: &[Viewer::JlcUploader, Viewer::JlcAssistant],
+        description: "aperture macros with variables or arithmetic may render with wrong sizes",
+    },
+    Quirk {
+        code: "incremental",
+        viewers: &[Viewer::JlcUploader, Viewer::JlcAssistant, Viewer::AltiumCam],
+        description: "incremental coordinates may drift or place objects off the board",
+    },
+    Quirk {
+        code: "missing-end",
+        viewers: &[
+            Viewer::JlcUploader,
+            Viewer::JlcAssistant,
+            Viewer::Gerbv,
+            Viewer::AltiumCam,
+        ],
+        description: "the file has no M02 end marker and may be treated as truncated",
+    },
+];
+
+/// Returns the ids of the quirky constructs used by a Gerber layer.
+fn constructs_used(content: &str, layer: &GerberLayer) -> Vec<&'static str> {
+    let mut used = Vec::new();
+    let extended: Vec<&str> = EXTENDED_REGEX
+        .captures_iter(content)
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str()))
+        .collect();
+    let has_extended = |names: &[&str]| extended.iter().any(|e| names.contains(e));
+
+    if layer
+        .commands
+        .iter()
+        .any(|c| matches!(c, Command::SetPolarity(gerber::Polarity::Clear)))
+    {
+        used.push("clear-polarity");
+    }
+    if has_extended(&["SR"]) {
+        used.push("step-repeat");
+    }
+    if has_extended(&["AB"]) {
+        used.push("aperture-block");
+    }
+    if has_extended(&["LM", "LR", "LS"]) {
+        used.push("load-transform");
+    }
+    if has_extended(&["IP", "IR", "MI", "OF", "SF"]) {
+        used.push("image-parameter");
+    }
+    if SINGLE_QUADRANT_REGEX.is_match(content) {
+        used.push("single-quadrant");
+    }
+
+    let mut in_region = false;
+    let mut full_circle = false;
+    let mut region_arc = false;
+    for command in &layer.commands {
+        match command {
+            Command::RegionStart => in_region = true,
+            Command::RegionEnd => in_region = false,
+            Command::Draw {
+                from, to, center, ..
+            } if center.is_some() => {
+                if from.distance(to) < 1e-9 {
+                    full_circle = true;
+                }
+                if in_region {
+                    region_arc = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    if full_circle {
+        used.push("full-circle");
+    }
+    if region_arc {
+        used.push("region-arc");
+    }
+
+    if layer
+        .macros
+        .iter()
+        .any(|body| MACRO_EXPRESSION_REGEX.is_match(body))
+    {
+        used.push("macro-expression");
+    }
+    if layer.format.incremental {
+        used.push("incremental");
+    }
+    if !layer.has_end_marker {
+        used.push("missing-end");
+    }
+    used
+}
+
+/// Checks one Gerber layer against the given viewer profiles.
+///
+/// A finding only means the viewer is known to misrender the construct; the
+/// file itself may be perfectly valid. Users can use this to tell whether a
+/// difference between two previews is real.
+pub fn lint_gerber(content: &str, viewers: &[Viewer]) -> Vec<LintFinding> {
+    let layer = gerber::parse_gerber(content);
+    let used = constructs_used(content, &layer);
+
+    let mut findings = Vec::new();
+    for quirk in QUIRKS.iter().filter(|q| used.contains(&q.code)) {
+        for viewer in viewers.iter().filter(|v| quirk.viewers.contains(v)) {
+            findings.push(LintFinding {
+                code: quirk.code,
+                viewer: *viewer,
+                message: format!("{}: {}", viewer.name(), quirk.description),
+            });
+        }
+    }
+    findings
+}
+
+/// Lints every Gerber layer of a set and returns one info diagnostic per finding.
+pub fn lint_layer_set(set: &LayerSet, viewers: &[Viewer]) -> Vec<Diagnostic> {
+    set.layers
+        .iter()
+        .flat_map(|file| {
+            lint_gerber(&file.content, viewers)
+                .into_iter()
+                .map(|finding| Diagnostic::info(finding.message).with_file(file.name.as_str()))
+        })
+        .collect()
+}
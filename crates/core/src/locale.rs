@@ -0,0 +1,79 @@
+/* src/locale.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+// This regex matches a Gerber aperture definition and captures its parameters.
+static APERTURE_PARAMETERS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(%ADD\d+[A-Za-z_$.][^,*%]*,)([^*%]*)").expect("Invalid aperture parameters regex")
+});
+
+// This regex matches the diameter of an Excellon tool definition such as "T1F00S00C0,25".
+static TOOL_DIAMETER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^(T\d+(?:[A-BD-Z][\d.]*)*C)(\d*),(\d+)").expect("Invalid tool diameter regex")
+});
+
+// This regex matches an Excellon coordinate line and captures it whole.
+static COORDINATE_LINE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^(?:G0[0-3])?[XY][+\-\d,.XYIJ]*\r?$").expect("Invalid coordinate line regex")
+});
+
+// This regex matches the Altium ";Holesize" tool comment line.
+static HOLESIZE_LINE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?mi)^;\s*Holesize.*$").expect("Invalid holesize line regex"));
+
+// This regex matches a number with a decimal comma, e.g. "0,25".
+static DECIMAL_COMMA_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\d),(\d)").expect("Invalid decimal comma regex"));
+
+// Replaces the decimal commas of `text`, counting them into `count`.
+fn replace_commas(text: &str, count: &mut usize) -> String {
+    DECIMAL_COMMA_REGEX
+        .replace_all(text, |caps: &Captures| {
+            *count += 1;
+            format!("{}.{}", &caps[1], &caps[2])
+        })
+        .into_owned()
+}
+
+/// Rewrites numbers written with the decimal comma of a European locale, as
+/// some Altium installs do (`%ADD10C,0,25*%`, `T1C0,25`), with decimal points.
+/// Parsers read such numbers as 0. Gerber aperture parameters and Excellon
+/// tool sizes, coordinates and `;Holesize` comments are fixed; other commas,
+/// such as the separators of aperture macros, are left alone.
+///
+/// Returns the new content and the number of numbers changed.
+pub fn fix_decimal_commas(content: &str) -> (String, usize) {
+    if !DECIMAL_COMMA_REGEX.is_match(content) {
+        return (content.to_string(), 0);
+    }
+    let mut count = 0;
+
+    if content.contains("%FS") {
+        // Gerber: after the first comma, aperture parameters are separated by X
+        let fixed = APERTURE_PARAMETERS_REGEX.replace_all(content, |caps: &Captures| {
+            format!("{}{}", &caps[1], replace_commas(&caps[2], &mut count))
+        });
+        return (fixed.into_owned(), count);
+    }
+    if !content.contains("M48") {
+        return (content.to_string(), 0);
+    }
+
+    let fixed = TOOL_DIAMETER_REGEX.replace_all(content, |caps: &Captures| {
+        count += 1;
+        format!("{}{}.{}", &caps[1], &caps[2], &caps[3])
+    });
+    let fixed = COORDINATE_LINE_REGEX.replace_all(&fixed, |caps: &Captures| {
+        replace_commas(&caps[0], &mut count)
+    });
+    let fixed = HOLESIZE_LINE_REGEX.replace_all(&fixed, |caps: &Captures| {
+        replace_commas(&caps[0], &mut count)
+    });
+    (fixed.into_owned(), count)
+}
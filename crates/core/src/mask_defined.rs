@@ -0,0 +1,94 @@
+/* src/mask_defined.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::BoundingBox;
+use crate::gerber::{self, Aperture};
+
+// A mask opening this far from a pad center belongs to another pad, in mm
+const CENTER_TOLERANCE_MM: f64 = 0.01;
+// Fewer round pads than this are not taken for a BGA without an X2 function
+const MIN_BGA_PADS: usize = 4;
+// Openings must be smaller than the pad by more than this, in mm
+const SIZE_TOLERANCE_MM: f64 = 0.001;
+
+/// Round pads of one size whose mask openings are smaller than the copper,
+/// so the mask rather than the copper defines the solderable area. Such BGA
+/// footprints need the matching option when ordering assembly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskDefinedArea {
+    pub pad_count: usize,
+    /// Extents of the pad centers, in mm
+    pub bounds: BoundingBox,
+    /// Copper pad diameter, in mm
+    pub pad_diameter: f64,
+    /// Mask opening diameter, or its smaller side when not round, in mm
+    pub opening_diameter: f64,
+}
+
+// Diameter of a round pad, or the smaller side of another shape
+fn size(aperture: &Aperture) -> f64 {
+    let (hx, hy) = aperture.half_extents();
+    2.0 * hx.min(hy)
+}
+
+/// Finds the BGA areas of `copper`, an outer copper layer, whose pads are
+/// solder mask defined by `mask`, the solder mask of the same side.
+///
+/// Pads with the X2 `BGAPad` function always count. Without a function, a
+/// group of at least four round pads of one size is taken for a BGA;
+/// pads marked for any other function are left out.
+pub fn find_mask_defined_areas(copper: &str, mask: &str) -> Vec<MaskDefinedArea> {
+    let copper = gerber::parse_gerber(copper);
+    let mask = gerber::parse_gerber(mask);
+    let openings = mask.dark_flashes();
+
+    // Pads grouped by (pad aperture, opening aperture), with the BGA flag
+    let mut groups: Vec<(u32, u32, bool, MaskDefinedArea)> = Vec::new();
+    for (at, pad) in copper.dark_flashes() {
+        let bga = match pad.function() {
+            Some(function) => function == "BGAPad",
+            None if pad.template == "C" => false,
+            None => continue,
+        };
+        let Some((_, opening)) = openings
+            .iter()
+            .find(|(center, _)| center.distance(&at) <= CENTER_TOLERANCE_MM)
+        else {
+            continue;
+        };
+        let (pad_diameter, opening_diameter) = (size(pad), size(opening));
+        if opening_diameter >= pad_diameter - SIZE_TOLERANCE_MM {
+            continue;
+        }
+        match groups
+            .iter_mut()
+            .find(|(p, o, _, _)| *p == pad.code && *o == opening.code)
+        {
+            Some((_, _, _, area)) => {
+                area.pad_count += 1;
+                area.bounds.include_point(at);
+            }
+            None => groups.push((
+                pad.code,
+                opening.code,
+                bga,
+                MaskDefinedArea {
+                    pad_count: 1,
+                    bounds: BoundingBox::from_point(at),
+                    pad_diameter,
+                    opening_diameter,
+                },
+            )),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, _, bga, area)| *bga || area.pad_count >= MIN_BGA_PADS)
+        .map(|(_, _, _, area)| area)
+        .collect()
+}
@@ -0,0 +1,266 @@
+/* src/outline.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::{BoundingBox, arc_bounds};
+use crate::gerber::{self, Command, Interpolation};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+// Endpoints closer than this are joined when tracing contours, in mm
+const CONTOUR_JOIN_TOLERANCE_MM: f64 = 0.001;
+
+// This regex captures the inner-layer number for KiCad, Altium and renamed files.
+static INNER_LAYER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)In(\d+)[._]Cu|\.G(\d+)$|Gerber_InnerLayer(\d+)")
+        .expect("Failed to compile inner layer regex")
+});
+
+/// Define the priority order for KiCad names.
+const KICAD_NAMES: &[&str] = &["Edge_Cuts", "F_Cu", "F_Mask"];
+
+/// Define the priority order for Gerber extensions.
+const GERBER_EXTENSIONS: &[&str] = &["gto", "gtl", "gbl", "gm13", "gm1", "gko"];
+
+/// This function sorts a list of file paths based on predefined Gerber file patterns.
+/// It prioritizes files with KiCad specific names, then standard Gerber extensions.
+///
+/// Files with equal priority (e.g. inner layers, which match no pattern) are ordered
+/// by their numeric inner-layer index and finally by filename, so the output is
+/// deterministic regardless of the order the platform listed the files in.
+///
+/// # Arguments
+///
+/// * `files` - A mutable slice of `String`s, where each string is a file path.
+///
+/// # Returns
+///
+/// A new `Vec<String>` containing the sorted file paths.
+pub fn sort_gerber_files(files: &mut [String]) -> Vec<String> {
+    files.sort_by(|a, b| {
+        // Compare by priority first (lower numbers have higher priority),
+        // then by inner-layer index, then by filename as the final tie-breaker.
+        get_file_priority(a)
+            .cmp(&get_file_priority(b))
+            .then_with(|| get_inner_layer_index(a).cmp(&get_inner_layer_index(b)))
+            .then_with(|| a.cmp(b))
+    });
+    // Return a new Vec containing the sorted file paths.
+    files.to_vec()
+}
+
+// This helper function determines the priority of a file based on its name.
+//
+// # Arguments
+//
+// * `file_path` - A reference to a string slice (`&str`) representing the file path.
+//
+// # Returns
+//
+// An `isize` value representing the priority. Lower values indicate higher priority.
+fn get_file_priority(file_path: &str) -> isize {
+    // First, check for KiCad specific names in the file path.
+    // KiCad 5 writes them with a dot ("Edge.Cuts").
+    let kicad_path = file_path.replace('.', "_");
+    for (index, name) in KICAD_NAMES.iter().enumerate() {
+        if kicad_path.contains(name) {
+            // Return a high priority (low number) if a KiCad name is found.
+            return index as isize;
+        }
+    }
+
+    // If no KiCad name is found, check for standard Gerber file extensions.
+    for (index, ext) in GERBER_EXTENSIONS.iter().enumerate() {
+        if file_path.to_lowercase().ends_with(&format!(".{}", ext)) {
+            // Return a medium priority if a Gerber extension is found.
+            // The offset by KICAD_NAMES.len() ensures these are lower priority than KiCad files.
+            return (index + KICAD_NAMES.len()) as isize;
+        }
+    }
+
+    // If no specific patterns are matched, assign the lowest priority.
+    isize::MAX
+}
+
+// This helper function extracts the numeric inner-layer index from a file name.
+// It understands KiCad (`In2_Cu`), Altium (`.G2`) and already-renamed
+// (`Gerber_InnerLayer2`) naming.
+//
+// # Returns
+//
+// The inner-layer number, or `u32::MAX` for files that are not inner layers so
+// they sort after all inner layers with the same priority.
+fn get_inner_layer_index(file_path: &str) -> u32 {
+    INNER_LAYER_REGEX
+        .captures(file_path)
+        .and_then(|caps| {
+            caps.iter()
+                .skip(1)
+                .flatten()
+                .next()
+                .and_then(|m| m.as_str().parse().ok())
+        })
+        .unwrap_or(u32::MAX)
+}
+
+/// Patterns that identify a board outline file, in order of preference.
+/// Renamed JLC names come first, then KiCad, then Altium keep-out/mechanical layers.
+const OUTLINE_PATTERNS: &[&str] = &[
+    "gerber_boardoutlinelayer",
+    "edge_cuts",
+    "edge.cuts",
+    ".gko",
+    ".gm1",
+    ".gm13",
+];
+
+/// Physical board dimensions in mm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// This function picks the file that most likely holds the board outline.
+///
+/// # Arguments
+///
+/// * `files` - A slice of file names.
+///
+/// # Returns
+///
+/// The name of the best outline candidate, or `None` if no file looks like an outline.
+pub fn find_outline_file(files: &[String]) -> Option<String> {
+    OUTLINE_PATTERNS.iter().find_map(|pattern| {
+        files
+            .iter()
+            .filter(|f| {
+                let lower = f.to_lowercase();
+                if pattern.starts_with('.') {
+                    lower.ends_with(pattern)
+                } else {
+                    lower.contains(pattern)
+                }
+            })
+            .min()
+            .cloned()
+    })
+}
+
+/// This function computes the board size from the content of an outline layer.
+///
+/// The size is the extent of the outline's center line, ignoring the aperture
+/// width, which matches how fabs quote board dimensions.
+///
+/// # Returns
+///
+/// `Some(BoardSize)` with width and height in mm, or `None` if the layer has no geometry.
+pub fn compute_board_size(outline_content: &str) -> Option<BoardSize> {
+    crate::gerber::layer_bounds(outline_content).map(|b| BoardSize {
+        width: b.width(),
+        height: b.height(),
+    })
+}
+
+/// Extents of every closed contour drawn on a layer: chains of draws whose ends
+/// meet, and filled regions. Open chains, such as dimension lines, are skipped.
+pub(crate) fn closed_contours(content: &str) -> Vec<BoundingBox> {
+    let layer = gerber::parse_gerber(content);
+    let key = |x: f64, y: f64| {
+        (
+            (x / CONTOUR_JOIN_TOLERANCE_MM).round() as i64,
+            (y / CONTOUR_JOIN_TOLERANCE_MM).round() as i64,
+        )
+    };
+
+    let mut contours = Vec::new();
+    let mut nodes: BTreeMap<(i64, i64), usize> = BTreeMap::new();
+    // Union-find parent of each node, and the degree of each node
+    let mut parent: Vec<usize> = Vec::new();
+    let mut degree: Vec<usize> = Vec::new();
+    let mut edges: Vec<(usize, BoundingBox)> = Vec::new();
+    let mut in_region = false;
+    let mut region: Option<BoundingBox> = None;
+
+    fn find(parent: &mut [usize], mut node: usize) -> usize {
+        while parent[node] != node {
+            parent[node] = parent[parent[node]];
+            node = parent[node];
+        }
+        node
+    }
+
+    for command in &layer.commands {
+        match command {
+            Command::RegionStart => in_region = true,
+            Command::RegionEnd => {
+                contours.extend(region.take());
+                in_region = false;
+            }
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } => {
+                let bounds = match (interpolation, center) {
+                    (Interpolation::Linear, _) | (_, None) => {
+                        let mut b = BoundingBox::from_point(*from);
+                        b.include_point(*to);
+                        b
+                    }
+                    (kind, Some(c)) => {
+                        arc_bounds(*from, *to, *c, *kind == Interpolation::Clockwise)
+                    }
+                };
+                if in_region {
+                    match &mut region {
+                        Some(existing) => existing.include_box(&bounds),
+                        None => region = Some(bounds),
+                    }
+                    continue;
+                }
+                let mut node = |x: f64, y: f64| {
+                    *nodes.entry(key(x, y)).or_insert_with(|| {
+                        parent.push(parent.len());
+                        degree.push(0);
+                        parent.len() - 1
+                    })
+                };
+                let (a, b) = (node(from.x, from.y), node(to.x, to.y));
+                degree[a] += 1;
+                degree[b] += 1;
+                let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+                parent[root_a] = root_b;
+                edges.push((a, bounds));
+            }
+            _ => {}
+        }
+    }
+
+    // A chain is closed when every node in it joins an even number of draws
+    let mut closed: BTreeMap<usize, bool> = BTreeMap::new();
+    for (node, degree) in degree.iter().enumerate() {
+        let root = find(&mut parent, node);
+        *closed.entry(root).or_insert(true) &= degree.is_multiple_of(2);
+    }
+    let mut extents: BTreeMap<usize, BoundingBox> = BTreeMap::new();
+    for (node, bounds) in edges {
+        let root = find(&mut parent, node);
+        extents
+            .entry(root)
+            .and_modify(|existing| existing.include_box(&bounds))
+            .or_insert(bounds);
+    }
+    contours.extend(
+        extents
+            .into_iter()
+            .filter(|(root, _)| closed.get(root).copied().unwrap_or(false))
+            .map(|(_, bounds)| bounds),
+    );
+    contours
+}
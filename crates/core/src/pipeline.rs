@@ -0,0 +1,986 @@
+/* src/pipeline.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::autofix::{AppliedFix, FixLevel};
+use crate::cancel::{Cancellation, Cancelled, NeverCancel};
+use crate::custom_rules::CustomRules;
+use crate::diagnostic::{self, Diagnostic};
+use crate::drill::{
+    DEFAULT_DIAMETER_TOLERANCE_MM, DrillOutputOptions, DrillProcessOptions, OversizedHoles,
+};
+use crate::fingerprint::FingerprintMode;
+use crate::geometry::BoundingBox;
+use crate::gerber::{self, Polarity, Unit};
+use crate::json::JsonValue;
+use crate::junk::IgnoredFile;
+use crate::layer_set::{DEFAULT_BUNDLE_TEMPLATE, LayerRepresentation, LayerSet};
+use crate::limits::{LimitExceeded, ResourceLimits};
+use crate::provider::{AsyncFileProvider, FileProvider, ProviderError};
+use crate::rename::{
+    CollisionPolicy, DrillMapPolicy, EdaType, LogicalLayer, NamingProfile, OutlineSelection,
+    PasteSilkMapping, VCutOutput,
+};
+use crate::service::{self, ServiceRecommendation};
+use crate::suggest;
+use crate::validation::ValidationReport;
+use std::sync::OnceLock;
+
+/// Options controlling a full conversion run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertOptions {
+    /// Rename rules to apply; `None` picks them from the detected software
+    pub eda_type: Option<EdaType>,
+    pub is_foreign_board_file: bool,
+    /// How the fingerprint aperture is made; `Legacy` suits very old Altium exports
+    pub fingerprint_mode: FingerprintMode,
+    pub add_order_guide: bool,
+    /// Add a `.gbrjob` file, so the output can be checked in KiCad's Gerber viewer
+    pub add_gbrjob: bool,
+    /// Representation kept when a layer is exported both as X2 and with a legacy extension
+    pub prefer_representation: LayerRepresentation,
+    /// Which repairs run without asking; every applied fix is listed in the result
+    pub auto_fix: FixLevel,
+    /// File that becomes the board outline
+    pub outline: OutlineSelection,
+    /// Extra rename rules, tried with the built-in ones
+    pub custom_rules: CustomRules,
+    /// How files that map to the same output name are resolved
+    pub collision_policy: CollisionPolicy,
+    /// Text the silkscreen must show, such as "JLCJLCJLCJLC" for the order number
+    pub required_markings: Vec<String>,
+    /// Files, by uploaded name, to use as a given layer whatever the rename rules say
+    pub layer_overrides: Vec<(String, LogicalLayer)>,
+    /// Leave reports, PDFs, images and macOS metadata out of the output
+    pub ignore_junk: bool,
+    /// How Altium paste and silkscreen extensions are read
+    pub paste_silk: PasteSilkMapping,
+    /// Output file names; anything but JLC names is for other fabs
+    pub naming_profile: NamingProfile,
+    /// Whether drill drawings are dropped or kept as documentation
+    pub drill_map: DrillMapPolicy,
+    /// Whether a V-cut layer, picked by a custom rule or override, is kept as
+    /// a layer or written as a note
+    pub v_cut: VCutOutput,
+    /// Name of the output zip, see [`LayerSet::bundle_name`]
+    pub bundle_template: String,
+    /// Drill tools whose diameters differ by up to this many mm are merged
+    pub drill_tolerance: f64,
+    /// Merged drill holes within this many mm of another are removed
+    pub drill_dedupe_epsilon: Option<f64>,
+    /// Whether holes too large to drill are kept or routed as cutouts
+    pub oversized_holes: OversizedHoles,
+    /// Number format of the merged drill files
+    pub drill_output: DrillOutputOptions,
+    /// Input sizes and time the run may use; unlimited by default
+    pub limits: ResourceLimits,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            eda_type: None,
+            is_foreign_board_file: false,
+            fingerprint_mode: FingerprintMode::Standard,
+            add_order_guide: true,
+            add_gbrjob: false,
+            prefer_representation: LayerRepresentation::Legacy,
+            auto_fix: FixLevel::Safe,
+            outline: OutlineSelection::Auto,
+            custom_rules: CustomRules::default(),
+            collision_policy: CollisionPolicy::KeepFirst,
+            required_markings: Vec::new(),
+            layer_overrides: Vec::new(),
+            ignore_junk: true,
+            paste_silk: PasteSilkMapping::Standard,
+            naming_profile: NamingProfile::Jlc,
+            drill_map: DrillMapPolicy::Drop,
+            v_cut: VCutOutput::Note,
+            bundle_template: DEFAULT_BUNDLE_TEMPLATE.to_string(),
+            drill_tolerance: DEFAULT_DIAMETER_TOLERANCE_MM,
+            drill_dedupe_epsilon: None,
+            oversized_holes: OversizedHoles::Keep,
+            drill_output: DrillOutputOptions::default(),
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+/// Size of one file before and after a stage, in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetric {
+    /// The file as uploaded, so it can be followed across renames
+    pub file: String,
+    /// 0 for files the stage created
+    pub input_bytes: usize,
+    /// 0 for files the stage removed
+    pub output_bytes: usize,
+}
+
+/// Wall-clock time spent in one pipeline stage, and how it changed the set
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    /// Time for the whole set; stages work on all files at once
+    pub milliseconds: f64,
+    pub input_bytes: usize,
+    pub output_bytes: usize,
+    /// Files whose size the stage changed, in set order
+    pub files: Vec<FileMetric>,
+}
+
+impl StageTiming {
+    pub fn to_json(&self) -> JsonValue {
+        let files = self
+            .files
+            .iter()
+            .map(|f| {
+                JsonValue::Object(vec![
+                    ("file".to_string(), f.file.as_str().into()),
+                    ("input_bytes".to_string(), f.input_bytes.into()),
+                    ("output_bytes".to_string(), f.output_bytes.into()),
+                ])
+            })
+            .collect();
+        JsonValue::Object(vec![
+            ("stage".to_string(), self.stage.into()),
+            ("milliseconds".to_string(), self.milliseconds.into()),
+            ("input_bytes".to_string(), self.input_bytes.into()),
+            ("output_bytes".to_string(), self.output_bytes.into()),
+            ("files".to_string(), JsonValue::Array(files)),
+        ])
+    }
+
+    /// Restores a timing serialized by [`StageTiming::to_json`].
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let stage = value.get("stage")?.as_str()?;
+        let bytes = |value: &JsonValue, key: &str| -> Option<usize> {
+            Some(value.get(key)?.as_f64()? as usize)
+        };
+        let files = value
+            .get("files")?
+            .as_array()?
+            .iter()
+            .map(|f| {
+                Some(FileMetric {
+                    file: f.get("file")?.as_str()?.to_string(),
+                    input_bytes: bytes(f, "input_bytes")?,
+                    output_bytes: bytes(f, "output_bytes")?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(StageTiming {
+            stage: STAGES.iter().find(|s| **s == stage)?,
+            milliseconds: value.get("milliseconds")?.as_f64()?,
+            input_bytes: bytes(value, "input_bytes")?,
+            output_bytes: bytes(value, "output_bytes")?,
+            files,
+        })
+    }
+}
+
+// Size of every file in a set, by uploaded name
+fn file_sizes(set: &LayerSet) -> Vec<(String, usize)> {
+    set.layers
+        .iter()
+        .map(|f| (f.original_name.clone(), f.content.len()))
+        .chain(
+            set.drills
+                .iter()
+                .map(|f| (f.original_name.clone(), f.content.len())),
+        )
+        .chain(
+            set.merged_drills
+                .iter()
+                .map(|d| (d.original_name.clone(), d.size())),
+        )
+        .collect()
+}
+
+// The files whose size differs between two snapshots
+fn changed_files(before: &[(String, usize)], after: &[(String, usize)]) -> Vec<FileMetric> {
+    let size = |files: &[(String, usize)], name: &str| {
+        files
+            .iter()
+            .find(|(n, _)| n == name)
+            .map_or(0, |(_, size)| *size)
+    };
+    let mut names: Vec<&str> = after.iter().map(|(n, _)| n.as_str()).collect();
+    names.extend(
+        before
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .filter(|n| !after.iter().any(|(a, _)| a == n)),
+    );
+    names
+        .into_iter()
+        .map(|name| FileMetric {
+            file: name.to_string(),
+            input_bytes: size(before, name),
+            output_bytes: size(after, name),
+        })
+        .filter(|m| m.input_bytes != m.output_bytes)
+        .collect()
+}
+
+/// Aggregate facts about one conversion run.
+///
+/// The crate never sends these anywhere; frontends that choose to collect
+/// anonymous usage metrics can read them from here.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionStats {
+    /// Software detected from the primary file ("Altium", "KiCad", "EasyEDA", "Eagle", "PADS", "Proteus",
+    /// "LibrePCB", "Horizon", "DipTrace")
+    pub eda_detected: Option<&'static str>,
+    /// Rename rules that were applied
+    pub eda_type: Option<EdaType>,
+    pub input_file_count: usize,
+    pub output_file_count: usize,
+    pub layer_count: Option<u32>,
+    pub stage_timings: Vec<StageTiming>,
+    pub warning_count: usize,
+    pub error_count: usize,
+    pub fix_count: usize,
+}
+
+impl ConversionStats {
+    /// Total time across all stages, in milliseconds.
+    pub fn total_milliseconds(&self) -> f64 {
+        self.stage_timings.iter().map(|t| t.milliseconds).sum()
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let eda_type = self.eda_type.as_ref().map(EdaType::id);
+        let timings = self
+            .stage_timings
+            .iter()
+            .map(StageTiming::to_json)
+            .collect();
+        JsonValue::Object(vec![
+            ("eda_detected".to_string(), self.eda_detected.into()),
+            ("eda_type".to_string(), eda_type.into()),
+            ("input_file_count".to_string(), self.input_file_count.into()),
+            (
+                "output_file_count".to_string(),
+                self.output_file_count.into(),
+            ),
+            ("layer_count".to_string(), self.layer_count.into()),
+            ("stage_timings".to_string(), JsonValue::Array(timings)),
+            ("warning_count".to_string(), self.warning_count.into()),
+            ("error_count".to_string(), self.error_count.into()),
+            ("fix_count".to_string(), self.fix_count.into()),
+        ])
+    }
+}
+
+/// Polarity, units, format and extents of one output Gerber layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSummary {
+    pub file: String,
+    /// Negative image, by `%TF.FilePolarity,Negative` or `%IPNEG`
+    pub negative: bool,
+    pub unit: Unit,
+    /// Format statement, e.g. "FSLAX46Y46"
+    pub format: String,
+    /// Extents of the dark and of the clear objects, in mm
+    pub dark_extents: Option<BoundingBox>,
+    pub clear_extents: Option<BoundingBox>,
+}
+
+impl LayerSummary {
+    pub fn from_layer(file: &str, content: &str) -> Self {
+        let layer = gerber::parse_gerber(content);
+        let negative = content.contains("%IPNEG")
+            || layer
+                .file_attribute("FilePolarity")
+                .is_some_and(|fields| fields.first() == Some(&"Negative"));
+        LayerSummary {
+            file: file.to_string(),
+            negative,
+            unit: layer.unit,
+            format: layer.format.statement(),
+            dark_extents: layer.polarity_bounds(Polarity::Dark),
+            clear_extents: layer.polarity_bounds(Polarity::Clear),
+        }
+    }
+}
+
+fn format_extents(bounds: &Option<BoundingBox>) -> String {
+    match bounds {
+        Some(b) => format!(
+            "{:.2},{:.2} .. {:.2},{:.2}",
+            b.min_x, b.min_y, b.max_x, b.max_y
+        ),
+        None => "-".to_string(),
+    }
+}
+
+/// Everything produced by [`Pipeline::run`]
+#[derive(Debug, Clone)]
+pub struct ConvertResult {
+    pub set: LayerSet,
+    pub report: Result<ValidationReport, Vec<String>>,
+    pub stats: ConversionStats,
+    /// Automatic fixes applied during the run, for the user to review
+    pub fixes: Vec<AppliedFix>,
+    /// Files left out of the output as not being fab data
+    pub ignored: Vec<IgnoredFile>,
+    /// Options of the run; apply a suggestion to a copy and run again to fix an error
+    pub options: ConvertOptions,
+    /// The limit the run stopped at, if any; its message is also in `report`
+    pub limit_exceeded: Option<LimitExceeded>,
+}
+
+impl ConvertResult {
+    /// A summary of every output Gerber layer, in output order.
+    pub fn layer_summaries(&self) -> Vec<LayerSummary> {
+        self.set
+            .layers
+            .iter()
+            .filter(|f| f.content.contains("%FS"))
+            .map(|f| LayerSummary::from_layer(&f.name, &f.content))
+            .collect()
+    }
+
+    /// [`ConvertResult::layer_summaries`] as a plain-text table, one row per layer.
+    pub fn summary_table(&self) -> String {
+        let rows: Vec<[String; 6]> = self
+            .layer_summaries()
+            .iter()
+            .map(|s| {
+                [
+                    s.file.clone(),
+                    if s.negative { "negative" } else { "positive" }.to_string(),
+                    match s.unit {
+                        Unit::Millimeter => "mm",
+                        Unit::Inch => "inch",
+                    }
+                    .to_string(),
+                    s.format.clone(),
+                    format_extents(&s.dark_extents),
+                    format_extents(&s.clear_extents),
+                ]
+            })
+            .collect();
+        let header = [
+            "File",
+            "Polarity",
+            "Unit",
+            "Format",
+            "Dark extents (mm)",
+            "Clear extents (mm)",
+        ]
+        .map(String::from);
+
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let line = |row: &[String; 6]| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            cells.join(" | ").trim_end().to_string()
+        };
+        let mut table = vec![line(&header)];
+        table.push(
+            widths
+                .iter()
+                .map(|w| "-".repeat(*w))
+                .collect::<Vec<_>>()
+                .join("-+-"),
+        );
+        table.extend(rows.iter().map(line));
+        table.join("\n")
+    }
+
+    /// Name for the output zip, by the run's bundle template.
+    pub fn bundle_name(&self) -> String {
+        self.set.bundle_name(&self.options.bundle_template)
+    }
+
+    /// The cheapest JLC service the converted board fits.
+    pub fn service_recommendation(&self) -> ServiceRecommendation {
+        service::recommend_service(&self.set.board_facts())
+    }
+
+    /// Applied fixes, warnings and validation errors of the run as diagnostics.
+    /// A forced conversion of an already converted archive comes first, with
+    /// the [`diagnostic::ALREADY_CONVERTED`] code.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let signs = &self.set.metadata.reconversion_signs;
+        if !signs.is_empty() {
+            diagnostics.push(
+                Diagnostic::warning(format!(
+                    "The upload looks like an already converted JLC archive ({}); converting it again can corrupt it",
+                    signs.join(", ")
+                ))
+                .with_code(diagnostic::ALREADY_CONVERTED),
+            );
+        }
+        diagnostics.extend(self.fixes.iter().map(|fix| {
+            let diagnostic = Diagnostic::info(format!("fixed: {}", fix.description));
+            match &fix.file {
+                Some(file) => diagnostic.with_file(file.as_str()),
+                None => diagnostic,
+            }
+        }));
+        diagnostics.extend(self.ignored.iter().map(|ignored| {
+            Diagnostic::info(format!("ignored: {}", ignored.kind.description()))
+                .with_file(ignored.file.as_str())
+        }));
+        diagnostics.extend(self.set.metadata.warnings.iter().map(Diagnostic::warning));
+        if self.report.is_ok() {
+            let recommendation = self.service_recommendation();
+            let mut message = format!(
+                "recommended service: {}",
+                recommendation.service.description()
+            );
+            if !recommendation.reasons.is_empty() {
+                message.push_str(&format!(" ({})", recommendation.reasons.join("; ")));
+            }
+            diagnostics.push(Diagnostic::info(message));
+        }
+        if let Err(errors) = &self.report {
+            diagnostics.extend(errors.iter().map(|error| {
+                Diagnostic::error(error.as_str()).with_suggestions(suggest::suggest_fixes(
+                    &self.set,
+                    error,
+                    self.options.auto_fix,
+                ))
+            }));
+        }
+        diagnostics
+    }
+}
+
+// Clock of the embedder in milliseconds, see [`set_clock`]
+static CLOCK: OnceLock<fn() -> f64> = OnceLock::new();
+
+/// Installs the clock, in milliseconds, that times stages on wasm32, where
+/// `std::time::Instant` panics; the bindings install `Date.now`. Without one,
+/// stages there take 0 ms and the time budget never runs out. Only the first
+/// call has an effect.
+pub fn set_clock(clock: fn() -> f64) {
+    let _ = CLOCK.set(clock);
+}
+
+/// Monotonic clock; `std::time::Instant` panics on wasm32-unknown-unknown.
+struct Timer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+    #[cfg(target_arch = "wasm32")]
+    start: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    CLOCK.get().map_or(0.0, |clock| clock())
+}
+
+impl Timer {
+    fn start() -> Self {
+        Timer {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+            #[cfg(target_arch = "wasm32")]
+            start: now_ms(),
+        }
+    }
+
+    fn elapsed_ms(&self) -> f64 {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.start.elapsed().as_secs_f64() * 1000.0
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            now_ms() - self.start
+        }
+    }
+}
+
+// Cancels a stage once the time budget runs out, or when `cancel` asks to
+struct Deadline<'a> {
+    timer: Timer,
+    /// Time the earlier stages took
+    spent_ms: f64,
+    limit_ms: Option<f64>,
+    cancel: &'a dyn Cancellation,
+}
+
+impl Deadline<'_> {
+    fn elapsed_ms(&self) -> f64 {
+        self.spent_ms + self.timer.elapsed_ms()
+    }
+
+    fn exceeded(&self, stage: &'static str) -> Option<LimitExceeded> {
+        let limit_ms = self.limit_ms?;
+        let elapsed_ms = self.elapsed_ms();
+        (elapsed_ms >= limit_ms).then_some(LimitExceeded::TimeBudget {
+            stage,
+            elapsed_ms,
+            limit_ms,
+        })
+    }
+}
+
+impl Cancellation for Deadline<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.limit_ms
+            .is_some_and(|limit_ms| self.elapsed_ms() >= limit_ms)
+            || self.cancel.is_cancelled()
+    }
+}
+
+/// Runs every conversion stage over a set of files.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    pub options: ConvertOptions,
+}
+
+impl Pipeline {
+    pub fn new(options: ConvertOptions) -> Self {
+        Pipeline { options }
+    }
+
+    /// Converts (filename, content) pairs into a JLC-ready set.
+    pub fn run(&self, files: &[(String, String)]) -> ConvertResult {
+        Session::new(self.options.clone(), files).finish()
+    }
+
+    /// Same as [`Pipeline::run`], but returns `Err(Cancelled)` as soon as
+    /// `cancel` asks to, also from inside the longer stages.
+    pub fn run_cancellable(
+        &self,
+        files: &[(String, String)],
+        cancel: &dyn Cancellation,
+    ) -> Result<ConvertResult, Cancelled> {
+        Session::new(self.options.clone(), files).finish_cancellable(cancel)
+    }
+
+    /// Converts the files of `provider`.
+    pub fn run_provider(
+        &self,
+        provider: &dyn FileProvider,
+    ) -> Result<ConvertResult, ProviderError> {
+        Ok(self.run(&provider.load()?))
+    }
+
+    /// Converts the files of `provider` once they are fetched.
+    pub async fn run_async(
+        &self,
+        provider: &impl AsyncFileProvider,
+    ) -> Result<ConvertResult, ProviderError> {
+        Ok(self.run(&provider.load().await?))
+    }
+}
+
+/// Pipeline stages in the order they run; stages that do not apply are skipped.
+pub const STAGES: &[&str] = &[
+    "load",
+    "decimals",
+    "verify",
+    "upconvert",
+    "ignore",
+    "dedupe",
+    "rename",
+    "overrides",
+    "v_cut",
+    "fix",
+    "headers",
+    "dcodes",
+    "fingerprint",
+    "drills",
+    "oversized",
+    "drill_checks",
+    "end_markers",
+    "validate",
+    "markings",
+    "orientation",
+    "vias",
+    "mask_pads",
+    "gbrjob",
+    "order_guide",
+    "naming",
+];
+
+/// A conversion run that can be stepped one stage at a time and serialized
+/// between stages, so a frontend can resume it after a page reload.
+#[derive(Debug, Clone)]
+pub struct Session {
+    options: ConvertOptions,
+    set: LayerSet,
+    /// Index into [`STAGES`] of the stage to run next
+    next_stage: usize,
+    /// Rename rules, known once the set is deduplicated
+    eda_type: Option<EdaType>,
+    input_file_count: usize,
+    timings: Vec<StageTiming>,
+    fixes: Vec<AppliedFix>,
+    ignored: Vec<IgnoredFile>,
+    report: Option<Result<ValidationReport, Vec<String>>>,
+    /// Not serialized; a resumed run only keeps its message in `report`
+    limit_exceeded: Option<LimitExceeded>,
+}
+
+impl Session {
+    /// Starts a run by loading (filename, content) pairs. Input over the size
+    /// limits of the options is not loaded, and the run is finished at once.
+    pub fn new(options: ConvertOptions, files: &[(String, String)]) -> Self {
+        let checked = options.limits.check_input(files);
+        let mut session = Session {
+            options,
+            set: LayerSet::default(),
+            next_stage: 1,
+            eda_type: None,
+            input_file_count: files.len(),
+            timings: Vec::new(),
+            fixes: Vec::new(),
+            ignored: Vec::new(),
+            report: None,
+            limit_exceeded: None,
+        };
+        match checked {
+            Ok(()) => session
+                .timed("load", |s| {
+                    s.set = LayerSet::from_files(files);
+                    Ok(())
+                })
+                .expect("loading is not cancellable"),
+            Err(exceeded) => session.stop(exceeded),
+        }
+        session
+    }
+
+    /// The limit the run stopped at, if any.
+    pub fn limit_exceeded(&self) -> Option<&LimitExceeded> {
+        self.limit_exceeded.as_ref()
+    }
+
+    // Skips the remaining stages and fails the run with `exceeded`
+    fn stop(&mut self, exceeded: LimitExceeded) {
+        self.next_stage = STAGES.len();
+        self.report = Some(Err(vec![exceeded.to_string()]));
+        self.limit_exceeded = Some(exceeded);
+    }
+
+    /// The set as it stands after the stages run so far.
+    pub fn set(&self) -> &LayerSet {
+        &self.set
+    }
+
+    /// The stage [`Session::step`] runs next, `None` once all have run.
+    pub fn next_stage(&self) -> Option<&'static str> {
+        STAGES.get(self.next_stage).copied()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_stage().is_none()
+    }
+
+    /// Runs the next stage, or skips it when it does not apply, and returns
+    /// its name. Returns `None` once all stages have run.
+    pub fn step(&mut self) -> Option<&'static str> {
+        self.step_cancellable(&NeverCancel)
+            .expect("NeverCancel never cancels")
+    }
+
+    /// Same as [`Session::step`], but returns `Err(Cancelled)` as soon as
+    /// `cancel` asks to. A cancelled stage leaves the session as it was, so
+    /// it runs again on the next step.
+    ///
+    /// The time budget is polled the same way, so a long stage stops once it
+    /// runs out and the run fails with [`LimitExceeded::TimeBudget`].
+    pub fn step_cancellable(
+        &mut self,
+        cancel: &dyn Cancellation,
+    ) -> Result<Option<&'static str>, Cancelled> {
+        let Some(stage) = self.next_stage() else {
+            return Ok(None);
+        };
+        cancel.check()?;
+        if self.applies(stage) {
+            let deadline = Deadline {
+                timer: Timer::start(),
+                spent_ms: self.timings.iter().map(|t| t.milliseconds).sum(),
+                limit_ms: self.options.limits.time_budget_ms,
+                cancel,
+            };
+            if let Err(cancelled) = self.timed(stage, |s| s.execute(stage, &deadline)) {
+                let exceeded = deadline.exceeded(stage).ok_or(cancelled)?;
+                self.stop(exceeded);
+                return Ok(Some(stage));
+            }
+            self.next_stage += 1;
+            let elapsed_ms = self.timings.iter().map(|t| t.milliseconds).sum();
+            if let Err(exceeded) = self.options.limits.check_time(stage, elapsed_ms) {
+                self.stop(exceeded);
+            }
+        } else {
+            self.next_stage += 1;
+        }
+        Ok(Some(stage))
+    }
+
+    /// Runs the remaining stages.
+    pub fn finish(self) -> ConvertResult {
+        self.finish_cancellable(&NeverCancel)
+            .expect("NeverCancel never cancels")
+    }
+
+    /// Same as [`Session::finish`], but returns `Err(Cancelled)` as soon as
+    /// `cancel` asks to.
+    pub fn finish_cancellable(
+        mut self,
+        cancel: &dyn Cancellation,
+    ) -> Result<ConvertResult, Cancelled> {
+        while self.step_cancellable(cancel)?.is_some() {}
+        let report = self.report.unwrap_or_else(|| Err(Vec::new()));
+        let stats = ConversionStats {
+            eda_detected: self.set.metadata.primary_software,
+            eda_type: self.eda_type,
+            input_file_count: self.input_file_count,
+            output_file_count: self.set.output_count(),
+            layer_count: self.set.metadata.layer_count,
+            stage_timings: self.timings,
+            warning_count: self.set.metadata.warnings.len(),
+            error_count: report.as_ref().map_or_else(Vec::len, |_| 0),
+            fix_count: self.fixes.len(),
+        };
+        Ok(ConvertResult {
+            set: self.set,
+            report,
+            stats,
+            fixes: self.fixes,
+            ignored: self.ignored,
+            options: self.options,
+            limit_exceeded: self.limit_exceeded,
+        })
+    }
+
+    // Runs `f` and records its timing, unless it was cancelled
+    fn timed(
+        &mut self,
+        stage: &'static str,
+        f: impl FnOnce(&mut Self) -> Result<(), Cancelled>,
+    ) -> Result<(), Cancelled> {
+        let before = file_sizes(&self.set);
+        let timer = Timer::start();
+        f(self)?;
+        let milliseconds = timer.elapsed_ms();
+        let after = file_sizes(&self.set);
+        self.timings.push(StageTiming {
+            stage,
+            milliseconds,
+            input_bytes: before.iter().map(|(_, size)| size).sum(),
+            output_bytes: after.iter().map(|(_, size)| size).sum(),
+            files: changed_files(&before, &after),
+        });
+        Ok(())
+    }
+
+    fn applies(&self, stage: &str) -> bool {
+        // Sets already in JLC form are only validated and fingerprinted, since
+        // rewriting them again can corrupt them
+        let jlc_named = self.eda_type == Some(EdaType::EasyEda);
+        match stage {
+            "ignore" => self.options.ignore_junk,
+            "rename" => !jlc_named && self.eda_type.is_some(),
+            "overrides" => !jlc_named && !self.options.layer_overrides.is_empty(),
+            "v_cut" => !jlc_named && self.options.v_cut != VCutOutput::Layer,
+            "fix" | "headers" | "dcodes" | "drills" | "drill_checks" => !jlc_named,
+            "oversized" => !jlc_named && self.options.oversized_holes != OversizedHoles::Keep,
+            "end_markers" => !jlc_named,
+            "markings" => !self.options.required_markings.is_empty(),
+            "gbrjob" => self.options.add_gbrjob,
+            "order_guide" => self.options.add_order_guide,
+            "naming" => self.options.naming_profile != NamingProfile::Jlc,
+            _ => true,
+        }
+    }
+
+    // The long stages poll `cancel`, and leave the set as it was when cancelled
+    fn execute(&mut self, stage: &str, cancel: &dyn Cancellation) -> Result<(), Cancelled> {
+        let options = &self.options;
+        let set = &mut self.set;
+        match stage {
+            "decimals" => set.fix_decimal_commas(),
+            "verify" => set.verify_extension_report(),
+            "upconvert" => set.upconvert_rs274d(),
+            // After the stages that read reports, and before copies such as
+            // macOS "._" files can be taken for duplicate layers
+            "ignore" => self.ignored = set.remove_junk(),
+            "dedupe" => {
+                set.remove_duplicate_layers(options.prefer_representation);
+                set.check_forced_reconversion(options.eda_type);
+                self.eda_type = options.eda_type.or_else(|| set.eda_type());
+            }
+            "rename" => {
+                if let Some(eda_type) = self.eda_type {
+                    set.rename_planned(
+                        eda_type,
+                        &options.outline,
+                        options.collision_policy,
+                        &options.custom_rules,
+                    );
+                    if eda_type == EdaType::Ad && options.paste_silk != PasteSilkMapping::Standard {
+                        set.remap_paste_silk(&options.paste_silk);
+                    }
+                    self.ignored
+                        .extend(set.apply_drill_map_policy(options.drill_map));
+                }
+            }
+            "overrides" => set.apply_layer_overrides(&options.layer_overrides),
+            "v_cut" => set.apply_v_cut_output(options.v_cut),
+            "fix" => self.fixes.extend(set.apply_fixes(options.auto_fix)),
+            "headers" => set.add_headers(),
+            "dcodes" => set.process_d_codes_cancellable(cancel)?,
+            "fingerprint" => set.add_fingerprints_cancellable(
+                options.is_foreign_board_file,
+                options.fingerprint_mode,
+                cancel,
+            )?,
+            "drills" => {
+                let options = DrillProcessOptions {
+                    fix_units: options.auto_fix >= FixLevel::Safe,
+                    tolerance: options.drill_tolerance,
+                    format: options.drill_output,
+                    dedupe_epsilon: options.drill_dedupe_epsilon,
+                    ..DrillProcessOptions::default()
+                };
+                self.fixes
+                    .extend(set.process_drills_cancellable(options, cancel)?);
+            }
+            "oversized" => {
+                set.route_oversized_holes(options.oversized_holes);
+            }
+            // After routing, which removes the holes too large to drill
+            "drill_checks" => set.check_drill_capabilities(),
+            // After every stage that edits layer content
+            "end_markers" => self.fixes.extend(set.repair_end_markers(options.auto_fix)),
+            "validate" => self.report = Some(set.validate()),
+            "markings" => {
+                set.check_required_markings(&options.required_markings);
+            }
+            "orientation" => {
+                set.check_text_orientation();
+            }
+            "vias" => {
+                set.check_vias_in_pads();
+            }
+            "mask_pads" => {
+                set.check_mask_defined_pads();
+            }
+            "gbrjob" => set.add_gbrjob(),
+            "order_guide" => set.add_order_guide(),
+            "naming" => set.apply_naming_profile(&options.naming_profile),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Serializes the run between stages. The options are not included;
+    /// pass the same ones to [`Session::resume`].
+    pub fn to_json(&self) -> JsonValue {
+        let report = match &self.report {
+            None => JsonValue::Null,
+            Some(Ok(report)) => JsonValue::Object(vec![
+                ("layer_count".to_string(), report.layer_count.into()),
+                (
+                    "warnings".to_string(),
+                    JsonValue::Array(report.warnings.iter().map(|w| w.as_str().into()).collect()),
+                ),
+            ]),
+            Some(Err(errors)) => JsonValue::Object(vec![(
+                "errors".to_string(),
+                JsonValue::Array(errors.iter().map(|e| e.as_str().into()).collect()),
+            )]),
+        };
+        JsonValue::Object(vec![
+            ("next_stage".to_string(), self.next_stage().into()),
+            (
+                "eda_type".to_string(),
+                self.eda_type.as_ref().map(EdaType::id).into(),
+            ),
+            ("input_file_count".to_string(), self.input_file_count.into()),
+            ("set".to_string(), self.set.to_json()),
+            (
+                "timings".to_string(),
+                JsonValue::Array(self.timings.iter().map(StageTiming::to_json).collect()),
+            ),
+            (
+                "fixes".to_string(),
+                JsonValue::Array(self.fixes.iter().map(AppliedFix::to_json).collect()),
+            ),
+            (
+                "ignored".to_string(),
+                JsonValue::Array(self.ignored.iter().map(IgnoredFile::to_json).collect()),
+            ),
+            ("report".to_string(), report),
+        ])
+    }
+
+    /// Restores a run serialized by [`Session::to_json`] with its options.
+    /// Returns `None` if a required field is missing or has the wrong type.
+    pub fn resume(value: &JsonValue, options: ConvertOptions) -> Option<Self> {
+        let next_stage = match value.get("next_stage")? {
+            JsonValue::Null => STAGES.len(),
+            stage => STAGES.iter().position(|s| Some(*s) == stage.as_str())?,
+        };
+        let items = |key: &str| value.get(key).and_then(JsonValue::as_array).unwrap_or(&[]);
+        let strings = |items: &[JsonValue]| -> Vec<String> {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        };
+        let report = match value.get("report") {
+            None | Some(JsonValue::Null) => None,
+            Some(report) => Some(match report.get("errors").and_then(JsonValue::as_array) {
+                Some(errors) => Err(strings(errors)),
+                None => Ok(ValidationReport {
+                    layer_count: report.get("layer_count")?.as_f64()? as u32,
+                    warnings: strings(
+                        report
+                            .get("warnings")
+                            .and_then(JsonValue::as_array)
+                            .unwrap_or(&[]),
+                    ),
+                }),
+            }),
+        };
+        Some(Session {
+            options,
+            set: LayerSet::from_json(value.get("set")?)?,
+            next_stage,
+            eda_type: value
+                .get("eda_type")
+                .and_then(JsonValue::as_str)
+                .and_then(EdaType::from_id),
+            input_file_count: value.get("input_file_count")?.as_f64()? as usize,
+            timings: items("timings")
+                .iter()
+                .filter_map(StageTiming::from_json)
+                .collect(),
+            fixes: items("fixes")
+                .iter()
+                .filter_map(AppliedFix::from_json)
+                .collect(),
+            ignored: items("ignored")
+                .iter()
+                .filter_map(IgnoredFile::from_json)
+                .collect(),
+            report,
+            limit_exceeded: None,
+        })
+    }
+}
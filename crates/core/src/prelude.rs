@@ -0,0 +1,34 @@
+/* src/prelude.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+//! The main types of the crate, for `use nextjlc::prelude::*;`.
+//!
+//! Items stay exported from here across internal refactors, so depending on the
+//! prelude is the stable way to use the library; see the crate documentation
+//! for the rest of the supported API.
+
+pub use crate::autofix::{AppliedFix, FixLevel};
+pub use crate::cancel::{CancelToken, Cancellation, Cancelled, NeverCancel};
+pub use crate::custom_rules::{CustomRule, CustomRules, RuleError};
+pub use crate::diagnostic::{Diagnostic, Severity};
+pub use crate::drill::{DrillOutputOptions, DrillProcessOptions, OversizedHoles, ZeroFormat};
+pub use crate::fingerprint::FingerprintMode;
+pub use crate::junk::{IgnoredFile, JunkKind};
+pub use crate::layer_set::{LayerFile, LayerSet, MergedDrill};
+pub use crate::limits::{LimitExceeded, ResourceLimits};
+pub use crate::pipeline::{
+    ConversionStats, ConvertOptions, ConvertResult, LayerSummary, Pipeline, Session,
+};
+pub use crate::provider::{AsyncFileProvider, FileProvider, MemoryProvider, ProviderError};
+pub use crate::rename::{
+    CollisionPolicy, DrillMapPolicy, EdaType, LogicalLayer, NamingProfile, OutlineSelection,
+    PasteSilkMapping, RenamePlan, VCutOutput,
+};
+pub use crate::service::{JlcService, ServiceRecommendation};
+pub use crate::sink::{MemorySink, OutputSink, SinkError};
+pub use crate::suggest::{FixAction, Suggestion};
+pub use crate::validation::ValidationReport;
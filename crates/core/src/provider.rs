@@ -0,0 +1,213 @@
+/* src/provider.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use std::fmt;
+use std::future::Future;
+
+/// Error produced when a provider cannot list or read its files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderError {
+    pub message: String,
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+impl ProviderError {
+    pub fn new(message: impl Into<String>) -> Self {
+        ProviderError {
+            message: message.into(),
+        }
+    }
+}
+
+/// A source of input files for the pipeline, such as memory, a zip archive
+/// or a directory.
+pub trait FileProvider {
+    /// Names of the files, as paths with forward slashes
+    fn list(&self) -> Result<Vec<String>, ProviderError>;
+
+    /// The bytes of one file named by [`FileProvider::list`].
+    fn read(&self, name: &str) -> Result<Vec<u8>, ProviderError>;
+
+    /// Every file as (name, content) pairs, the input of
+    /// [`crate::pipeline::Pipeline::run`]. Content that is not UTF-8 is
+    /// converted lossily; fab files are ASCII.
+    fn load(&self) -> Result<Vec<(String, String)>, ProviderError> {
+        self.list()?
+            .into_iter()
+            .map(|name| {
+                let data = self.read(&name)?;
+                Ok((name, String::from_utf8_lossy(&data).into_owned()))
+            })
+            .collect()
+    }
+}
+
+/// A [`FileProvider`] whose files must be fetched, e.g. over HTTP from the
+/// browser. The crate brings no executor; the caller awaits the future.
+pub trait AsyncFileProvider {
+    fn load(&self) -> impl Future<Output = Result<Vec<(String, String)>, ProviderError>>;
+}
+
+/// Files held in memory, in the order they were added
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryProvider {
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl MemoryProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing one of the same name.
+    pub fn add(&mut self, name: &str, data: impl Into<Vec<u8>>) {
+        let data = data.into();
+        match self.files.iter_mut().find(|(n, _)| n == name) {
+            Some(file) => file.1 = data,
+            None => self.files.push((name.to_string(), data)),
+        }
+    }
+}
+
+impl From<Vec<(String, String)>> for MemoryProvider {
+    fn from(files: Vec<(String, String)>) -> Self {
+        let mut provider = MemoryProvider::new();
+        for (name, content) in files {
+            provider.add(&name, content);
+        }
+        provider
+    }
+}
+
+impl FileProvider for MemoryProvider {
+    fn list(&self) -> Result<Vec<String>, ProviderError> {
+        Ok(self.files.iter().map(|(name, _)| name.clone()).collect())
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, ProviderError> {
+        self.files
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, data)| data.clone())
+            .ok_or_else(|| ProviderError::new(format!("No file named {}", name)))
+    }
+}
+
+/// The files of a zip archive held in memory, read once when created
+#[cfg(feature = "archive")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipProvider {
+    entries: Vec<crate::zip::ZipEntry>,
+}
+
+#[cfg(feature = "archive")]
+impl ZipProvider {
+    pub fn new(data: &[u8]) -> Result<Self, ProviderError> {
+        let entries = crate::zip::read_zip(data).map_err(|e| ProviderError::new(e.message))?;
+        Ok(ZipProvider { entries })
+    }
+}
+
+#[cfg(feature = "archive")]
+impl FileProvider for ZipProvider {
+    fn list(&self) -> Result<Vec<String>, ProviderError> {
+        Ok(self.entries.iter().map(|e| e.name.clone()).collect())
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, ProviderError> {
+        self.entries
+            .iter()
+            .find(|e| e.name == name)
+            .map(|e| e.data.clone())
+            .ok_or_else(|| ProviderError::new(format!("No file named {} in the archive", name)))
+    }
+}
+
+/// The files below a directory, including subdirectories; names are
+/// relative to it. Not available on wasm32, which has no file system.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryProvider {
+    root: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DirectoryProvider {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        DirectoryProvider { root: root.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileProvider for DirectoryProvider {
+    fn list(&self) -> Result<Vec<String>, ProviderError> {
+        let io_error = |e: std::io::Error| ProviderError::new(e.to_string());
+        let mut names = Vec::new();
+        let mut pending = vec![self.root.clone()];
+        while let Some(directory) = pending.pop() {
+            for entry in std::fs::read_dir(&directory).map_err(io_error)? {
+                let path = entry.map_err(io_error)?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&self.root) {
+                    let parts: Vec<String> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect();
+                    names.push(parts.join("/"));
+                }
+            }
+        }
+        // read_dir order depends on the file system
+        names.sort();
+        Ok(names)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, ProviderError> {
+        std::fs::read(self.root.join(name))
+            .map_err(|e| ProviderError::new(format!("{}: {}", name, e)))
+    }
+}
+
+/// Files fetched one by one with a caller-supplied function, e.g. a wrapper
+/// around the browser's `fetch`
+pub struct FetchProvider<F> {
+    names: Vec<String>,
+    fetch: F,
+}
+
+impl<F, Fut> FetchProvider<F>
+where
+    F: Fn(&str) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>, ProviderError>>,
+{
+    pub fn new(names: Vec<String>, fetch: F) -> Self {
+        FetchProvider { names, fetch }
+    }
+}
+
+impl<F, Fut> AsyncFileProvider for FetchProvider<F>
+where
+    F: Fn(&str) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>, ProviderError>>,
+{
+    async fn load(&self) -> Result<Vec<(String, String)>, ProviderError> {
+        let mut files = Vec::with_capacity(self.names.len());
+        for name in &self.names {
+            let data = (self.fetch)(name).await?;
+            files.push((name.clone(), String::from_utf8_lossy(&data).into_owned()));
+        }
+        Ok(files)
+    }
+}
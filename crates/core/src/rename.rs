@@ -0,0 +1,1374 @@
+/* src/rename.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::classify::{self, PasteOrSilk};
+use crate::drill;
+use crate::json::JsonValue;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Defines the supported EDA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdaType {
+    Ad,       // Represents Altium Designer
+    KiCad,    // Represents KiCad
+    Eagle,    // Represents Autodesk EAGLE and Fusion 360 Electronics
+    Pads,     // Represents Siemens (Mentor) PADS Layout
+    Proteus,  // Represents Labcenter Proteus ARES / PCB Design
+    LibrePcb, // Represents LibrePCB
+    Horizon,  // Represents Horizon EDA
+    DipTrace, // Represents Novarm DipTrace
+    EasyEda,  // Files already named for JLC (EasyEDA, or a previous conversion); kept as they are
+}
+
+impl EdaType {
+    /// Short lowercase id, e.g. "ad" or "kicad"
+    pub fn id(&self) -> &'static str {
+        match self {
+            EdaType::Ad => "ad",
+            EdaType::KiCad => "kicad",
+            EdaType::Eagle => "eagle",
+            EdaType::Pads => "pads",
+            EdaType::Proteus => "proteus",
+            EdaType::LibrePcb => "librepcb",
+            EdaType::Horizon => "horizon",
+            EdaType::DipTrace => "diptrace",
+            EdaType::EasyEda => "easyeda",
+        }
+    }
+
+    /// Parses an id from [`EdaType::id`], ignoring case.
+    pub fn from_id(id: &str) -> Option<EdaType> {
+        [
+            EdaType::Ad,
+            EdaType::KiCad,
+            EdaType::Eagle,
+            EdaType::Pads,
+            EdaType::Proteus,
+            EdaType::LibrePcb,
+            EdaType::Horizon,
+            EdaType::DipTrace,
+            EdaType::EasyEda,
+        ]
+        .into_iter()
+        .find(|eda| eda.id().eq_ignore_ascii_case(id))
+    }
+
+    /// The rename rules for a software name from `identify_software`.
+    /// EasyEDA exports follow the Altium naming.
+    pub fn from_software(software: &str) -> Option<EdaType> {
+        match software {
+            "Altium" | "EasyEDA" => Some(EdaType::Ad),
+            "KiCad" => Some(EdaType::KiCad),
+            "Eagle" => Some(EdaType::Eagle),
+            "PADS" => Some(EdaType::Pads),
+            "Proteus" => Some(EdaType::Proteus),
+            "LibrePCB" => Some(EdaType::LibrePcb),
+            "Horizon" => Some(EdaType::Horizon),
+            "DipTrace" => Some(EdaType::DipTrace),
+            _ => None,
+        }
+    }
+}
+
+/// A struct to hold a single renaming rule.
+/// It pairs a logical name (e.g., "Gerber_TopLayer") with a compiled Regex pattern.
+/// A rule with `unless_sibling` only applies when no other file in the set matches it.
+struct Rule {
+    logical_name: &'static str,
+    pattern: Regex,
+    unless_sibling: Option<Regex>,
+}
+
+impl Rule {
+    fn matches(&self, file: &str, files: &[String]) -> bool {
+        self.pattern.is_match(file)
+            && self.unless_sibling.as_ref().is_none_or(|sibling| {
+                !files
+                    .iter()
+                    .any(|other| other != file && sibling.is_match(other))
+            })
+    }
+}
+
+impl Rule {
+    // Conditional rules are guesses that depend on which other files were exported
+    fn confidence(&self) -> Confidence {
+        if self.unless_sibling.is_none() {
+            Confidence::High
+        } else {
+            Confidence::Medium
+        }
+    }
+
+    fn source(&self) -> MatchSource {
+        MatchSource::BuiltIn {
+            pattern: self.pattern.as_str().to_string(),
+            unless_sibling: self.unless_sibling.as_ref().map(|r| r.as_str().to_string()),
+        }
+    }
+}
+
+/// A helper function to create a Rule, panicking if the regex is invalid.
+/// This ensures that all regex patterns are validated at compile time.
+fn rule(logical_name: &'static str, pattern_str: &'static str) -> Rule {
+    Rule {
+        logical_name,
+        pattern: Regex::new(pattern_str).expect("Invalid regex pattern"),
+        unless_sibling: None,
+    }
+}
+
+/// Highest inner layer number the rules recognize, enough for a 32-layer board.
+pub const MAX_INNER_LAYERS: u8 = 30;
+
+/// Creates one rule per inner layer, where `pattern(n)` matches inner layer n.
+fn inner_rules(pattern: impl Fn(u8) -> String) -> Vec<Rule> {
+    (1..=MAX_INNER_LAYERS)
+        .map(|n| Rule {
+            logical_name: LogicalLayer::InnerLayer(n).logical_name(),
+            pattern: Regex::new(&pattern(n)).expect("Invalid regex pattern"),
+            unless_sibling: None,
+        })
+        .collect()
+}
+
+/// Creates a Rule that is skipped when a sibling file matches `sibling_str`.
+fn rule_unless(
+    logical_name: &'static str,
+    pattern_str: &'static str,
+    sibling_str: &'static str,
+) -> Rule {
+    Rule {
+        unless_sibling: Some(Regex::new(sibling_str).expect("Invalid regex pattern")),
+        ..rule(logical_name, pattern_str)
+    }
+}
+
+/// Static list of rules for Altium Designer, initialized lazily and only once.
+/// Note: Drill files (.TXT, .TX1, etc.) are now handled by drill.rs module,
+/// so they are marked as "Drill_Source" for identification only.
+/// A mechanical layer is only the outline when there is no keep-out layer,
+/// and a holes .TXT is only a drill when there is no .DRL file.
+/// Internal planes (.GP1, .GP2, ... from Protel 99 SE and old Altium) are
+/// numbered after the mid layers, see [`number_plane_layers`].
+static AD_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule("Gerber_BoardOutlineLayer", "(?i)\\.GKO$"),
+        rule_unless(
+            "Gerber_BoardOutlineLayer",
+            "(?i)\\.GM(1|13)$",
+            "(?i)\\.GKO$",
+        ),
+        rule("Gerber_DocumentLayer", "(?i)\\.GM$"),
+        rule("Gerber_TopLayer", "(?i)\\.GTL$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)\\.GTO$"),
+        rule("Gerber_TopSolderMaskLayer", "(?i)\\.GTS$"),
+        rule("Gerber_TopPasteMaskLayer", "(?i)\\.GTP$"),
+        rule("Gerber_BottomLayer", "(?i)\\.GBL$"),
+        rule("Gerber_BottomSilkscreenLayer", "(?i)\\.GBO$"),
+        rule("Gerber_BottomSolderMaskLayer", "(?i)\\.GBS$"),
+        rule("Gerber_BottomPasteMaskLayer", "(?i)\\.GBP$"),
+        // Drill files - marked for identification, processed by drill.rs
+        rule_unless("Drill_Source", "(?i).*holes?.*\\.txt$", "(?i)\\.DRL$"),
+        rule("Drill_Source", drill::BLIND_BURIED_DRILL_PATTERN),
+        rule("Drill_PTH_Through_GBR", "(?i)\\.GD1$"),
+        rule("Drill_PTH_Through_Via_GBR", "(?i)\\.GG1$"),
+        rule("Drill_Report", "(?i)\\.DRR$"),
+        rule("Gerber_Layer_Drawing_Parameters", "(?i)\\.LDP$"),
+    ];
+    rules.extend(inner_rules(|n| format!("(?i)\\.G{}$", n)));
+    rules.extend((1..=MAX_PLANE_LAYERS).map(|n| Rule {
+        logical_name: &PLANE_LAYER_NAMES[n as usize - 1],
+        pattern: Regex::new(&format!("(?i)\\.GP{}$", n)).expect("Invalid regex pattern"),
+        unless_sibling: None,
+    }));
+    rules
+});
+
+/// Highest internal plane number Altium exports (.GP1 to .GP16).
+pub const MAX_PLANE_LAYERS: u8 = 16;
+
+// Logical names of the internal planes, renumbered by number_plane_layers.
+static PLANE_LAYER_NAMES: Lazy<Vec<String>> = Lazy::new(|| {
+    (1..=MAX_PLANE_LAYERS)
+        .map(|n| format!("Gerber_PlaneLayer{}", n))
+        .collect()
+});
+
+// This regex matches the extension of an Altium internal plane, .GP1 to .GP16.
+static PLANE_FILE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\.GP([1-9]|1[0-6])$").expect("Invalid plane file regex"));
+
+/// Whether `file` is an Altium internal plane. Planes are exported as
+/// negative images: the drawn objects are where copper is removed.
+pub(crate) fn is_internal_plane(file: &str) -> bool {
+    PLANE_FILE_REGEX.is_match(file)
+}
+
+/// Static list of rules for KiCad, initialized lazily and only once.
+/// Note: Drill files (.DRL) are now handled by drill.rs module,
+/// so they are marked as "Drill_Source" for identification only.
+static KICAD_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    // KiCad 5 separates the layer name with a dot ("-F.Cu.gbr") and calls the
+    // silkscreen "SilkS"; KiCad 6 and later use "-F_Cu.gbr" and "Silkscreen"
+    let mut rules = vec![
+        rule("Gerber_BoardOutlineLayer", "(?i).*Edge[._]Cuts.*"),
+        rule("Gerber_DocumentLayer", "(?i).*GM.*"),
+        rule("Gerber_TopLayer", "(?i).*F[._]Cu.*"),
+        rule("Gerber_TopSilkscreenLayer", "(?i).*F[._]Silk(screen|S).*"),
+        rule("Gerber_TopSolderMaskLayer", "(?i).*F[._]Mask.*"),
+        rule("Gerber_TopPasteMaskLayer", "(?i).*F[._]Paste.*"),
+        rule("Gerber_BottomLayer", "(?i).*B[._]Cu.*"),
+        rule(
+            "Gerber_BottomSilkscreenLayer",
+            "(?i).*B[._]Silk(screen|S).*",
+        ),
+        rule("Gerber_BottomSolderMaskLayer", "(?i).*B[._]Mask.*"),
+        rule("Gerber_BottomPasteMaskLayer", "(?i).*B[._]Paste.*"),
+        // Drill files - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)\\.DRL$"),
+        // Drill map GBR files (not processed, just identified)
+        rule("Drill_MAP_GBR", r"(?i).*[-_]drl_map(?:\.GBR)?$"),
+        rule("Gerber_GBR_JOB", "(?i)\\.gbrjob$"),
+    ];
+    rules.extend(inner_rules(|n| format!("(?i).*In{}[._]Cu.*", n)));
+    rules
+});
+
+/// Static list of rules for Autodesk EAGLE and Fusion 360, initialized lazily and only once.
+/// Covers the CAM processor names (copper_top.gbr, profile.gbr, ...) and the
+/// legacy extensions of the old eagle.cam job (.cmp, .sol, .plc, ...).
+static EAGLE_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule("Gerber_BoardOutlineLayer", "(?i)(^|[-_.])profile\\.gbr$"),
+        rule("Gerber_BoardOutlineLayer", "(?i)\\.(dim|gml)$"),
+        rule("Gerber_TopLayer", "(?i)copper_top\\.gbr$"),
+        rule("Gerber_TopLayer", "(?i)\\.cmp$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)silkscreen_top\\.gbr$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)\\.plc$"),
+        rule("Gerber_TopSolderMaskLayer", "(?i)soldermask_top\\.gbr$"),
+        rule("Gerber_TopSolderMaskLayer", "(?i)\\.stc$"),
+        rule("Gerber_TopPasteMaskLayer", "(?i)solderpaste_top\\.gbr$"),
+        rule("Gerber_TopPasteMaskLayer", "(?i)\\.crc$"),
+        rule("Gerber_BottomLayer", "(?i)copper_bottom\\.gbr$"),
+        rule("Gerber_BottomLayer", "(?i)\\.sol$"),
+        rule(
+            "Gerber_BottomSilkscreenLayer",
+            "(?i)silkscreen_bottom\\.gbr$",
+        ),
+        rule("Gerber_BottomSilkscreenLayer", "(?i)\\.pls$"),
+        rule(
+            "Gerber_BottomSolderMaskLayer",
+            "(?i)soldermask_bottom\\.gbr$",
+        ),
+        rule("Gerber_BottomSolderMaskLayer", "(?i)\\.sts$"),
+        rule(
+            "Gerber_BottomPasteMaskLayer",
+            "(?i)solderpaste_bottom\\.gbr$",
+        ),
+        rule("Gerber_BottomPasteMaskLayer", "(?i)\\.crs$"),
+        // Drill files - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)\\.(drd|xln)$"),
+        rule("Gerber_GBR_JOB", "(?i)\\.gbrjob$"),
+    ];
+    // Inner copper: EAGLE layer 2 is the first inner layer
+    rules.extend(inner_rules(|n| {
+        format!("(?i)copper_l{0}\\.gbr$|\\.ly{0}$", n + 1)
+    }));
+    rules
+});
+
+/// Static list of rules for PADS Layout photoplot (.pho) and NC drill outputs,
+/// initialized lazily and only once.
+static PADS_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule(
+            "Gerber_BoardOutlineLayer",
+            "(?i)(^|[-_. ])(brd|board|outline)\\.pho$",
+        ),
+        rule("Gerber_TopLayer", "(?i)(^|[-_. ])top\\.pho$"),
+        rule("Gerber_BottomLayer", "(?i)(^|[-_. ])bot(tom)?\\.pho$"),
+        rule("Gerber_TopSolderMaskLayer", "(?i)(^|[-_. ])smt\\.pho$"),
+        rule("Gerber_BottomSolderMaskLayer", "(?i)(^|[-_. ])smb\\.pho$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)(^|[-_. ])sst\\.pho$"),
+        rule("Gerber_BottomSilkscreenLayer", "(?i)(^|[-_. ])ssb\\.pho$"),
+        rule("Gerber_TopPasteMaskLayer", "(?i)(^|[-_. ])spt\\.pho$"),
+        rule("Gerber_BottomPasteMaskLayer", "(?i)(^|[-_. ])spb\\.pho$"),
+        // Drill drawing is documentation, like KiCad's drill map
+        rule("Drill_MAP_GBR", "(?i)drill_?drawing\\.pho$"),
+        // NC drill files (drl001.drl) - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)\\.drl$"),
+        rule("Drill_Report", "(?i)\\.lst$"),
+    ];
+    rules.extend(inner_rules(|n| {
+        format!("(?i)(^|[-_. ])in(ner)?{}\\.pho$", n)
+    }));
+    rules
+});
+
+/// Static list of rules for Proteus CADCAM output, initialized lazily and only once.
+/// Proteus names every file "<project> - CADCAM <layer>.TXT" (or .GBR in newer
+/// versions), so the layer is taken from the words after "CADCAM".
+static PROTEUS_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule(
+            "Gerber_BoardOutlineLayer",
+            "(?i)CADCAM Board (Edge|Outline)\\.(txt|gbr)$",
+        ),
+        rule_unless(
+            "Gerber_BoardOutlineLayer",
+            "(?i)CADCAM Mechanical 1\\.(txt|gbr)$",
+            "(?i)CADCAM Board (Edge|Outline)\\.(txt|gbr)$",
+        ),
+        rule("Gerber_TopLayer", "(?i)CADCAM Top Copper\\.(txt|gbr)$"),
+        rule(
+            "Gerber_BottomLayer",
+            "(?i)CADCAM Bottom Copper\\.(txt|gbr)$",
+        ),
+        rule(
+            "Gerber_TopSilkscreenLayer",
+            "(?i)CADCAM Top Silk ?Screen\\.(txt|gbr)$",
+        ),
+        rule(
+            "Gerber_BottomSilkscreenLayer",
+            "(?i)CADCAM Bottom Silk ?Screen\\.(txt|gbr)$",
+        ),
+        rule(
+            "Gerber_TopSolderMaskLayer",
+            "(?i)CADCAM Top Solder Resist\\.(txt|gbr)$",
+        ),
+        rule(
+            "Gerber_BottomSolderMaskLayer",
+            "(?i)CADCAM Bottom Solder Resist\\.(txt|gbr)$",
+        ),
+        rule(
+            "Gerber_TopPasteMaskLayer",
+            "(?i)CADCAM Top (SMT )?Paste( Mask)?\\.(txt|gbr)$",
+        ),
+        rule(
+            "Gerber_BottomPasteMaskLayer",
+            "(?i)CADCAM Bottom (SMT )?Paste( Mask)?\\.(txt|gbr)$",
+        ),
+        // Drill files - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)CADCAM Drill.*\\.(txt|drl)$"),
+    ];
+    rules.extend(inner_rules(|n| {
+        format!("(?i)CADCAM Inner {}\\.(txt|gbr)$", n)
+    }));
+    rules
+});
+
+/// Static list of rules for LibrePCB, initialized lazily and only once.
+/// LibrePCB appends an upper-case layer suffix to the project name
+/// ("board_v1_COPPER-TOP.gbr", "board_v1_DRILLS-NPTH.drl").
+static LIBREPCB_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule("Gerber_BoardOutlineLayer", "(?i)_OUTLINES\\.gbr$"),
+        rule("Gerber_TopLayer", "(?i)_COPPER-TOP\\.gbr$"),
+        rule("Gerber_BottomLayer", "(?i)_COPPER-BOTTOM\\.gbr$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)_SILKSCREEN-TOP\\.gbr$"),
+        rule(
+            "Gerber_BottomSilkscreenLayer",
+            "(?i)_SILKSCREEN-BOTTOM\\.gbr$",
+        ),
+        rule("Gerber_TopSolderMaskLayer", "(?i)_SOLDERMASK-TOP\\.gbr$"),
+        rule(
+            "Gerber_BottomSolderMaskLayer",
+            "(?i)_SOLDERMASK-BOTTOM\\.gbr$",
+        ),
+        rule("Gerber_TopPasteMaskLayer", "(?i)_SOLDERPASTE-TOP\\.gbr$"),
+        rule(
+            "Gerber_BottomPasteMaskLayer",
+            "(?i)_SOLDERPASTE-BOTTOM\\.gbr$",
+        ),
+        // Drill files - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)_DRILLS(-N?PTH)?\\.drl$"),
+    ];
+    rules.extend(inner_rules(|n| format!("(?i)_COPPER-IN{}\\.gbr$", n)));
+    rules
+});
+
+/// Static list of rules for Horizon EDA, initialized lazily and only once.
+/// Horizon uses Protel extensions after the output prefix, numbers inner
+/// layers as .g1, .g2, ... and writes drills as "_pth.drl" / "_npth.drl".
+static HORIZON_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule("Gerber_BoardOutlineLayer", "(?i)\\.gko$"),
+        rule_unless("Gerber_BoardOutlineLayer", "(?i)\\.gm1$", "(?i)\\.gko$"),
+        rule("Gerber_TopLayer", "(?i)\\.gtl$"),
+        rule("Gerber_BottomLayer", "(?i)\\.gbl$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)\\.gto$"),
+        rule("Gerber_BottomSilkscreenLayer", "(?i)\\.gbo$"),
+        rule("Gerber_TopSolderMaskLayer", "(?i)\\.gts$"),
+        rule("Gerber_BottomSolderMaskLayer", "(?i)\\.gbs$"),
+        rule("Gerber_TopPasteMaskLayer", "(?i)\\.gtp$"),
+        rule("Gerber_BottomPasteMaskLayer", "(?i)\\.gbp$"),
+        // Drill files - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)_n?pth\\.drl$"),
+    ];
+    rules.extend(inner_rules(|n| format!("(?i)\\.g{}$", n)));
+    rules
+});
+
+/// Static list of rules for DipTrace, initialized lazily and only once.
+/// DipTrace names each file after its layer ("Top.gbr", "TopSilk.gbr"), with an
+/// optional project prefix. Assembly drawings are kept, but are not fabricated.
+static DIPTRACE_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    let mut rules = vec![
+        rule(
+            "Gerber_BoardOutlineLayer",
+            "(?i)(^|[-_ ])BoardOutline\\.gbr$",
+        ),
+        rule("Gerber_TopLayer", "(?i)(^|[-_ ])Top\\.gbr$"),
+        rule("Gerber_BottomLayer", "(?i)(^|[-_ ])Bottom\\.gbr$"),
+        rule("Gerber_TopSilkscreenLayer", "(?i)(^|[-_ ])TopSilk\\.gbr$"),
+        rule(
+            "Gerber_BottomSilkscreenLayer",
+            "(?i)(^|[-_ ])BottomSilk\\.gbr$",
+        ),
+        rule("Gerber_TopSolderMaskLayer", "(?i)(^|[-_ ])TopMask\\.gbr$"),
+        rule(
+            "Gerber_BottomSolderMaskLayer",
+            "(?i)(^|[-_ ])BottomMask\\.gbr$",
+        ),
+        rule("Gerber_TopPasteMaskLayer", "(?i)(^|[-_ ])TopPaste\\.gbr$"),
+        rule(
+            "Gerber_BottomPasteMaskLayer",
+            "(?i)(^|[-_ ])BottomPaste\\.gbr$",
+        ),
+        rule("Gerber_TopAssemblyLayer", "(?i)(^|[-_ ])TopAssy\\.gbr$"),
+        rule(
+            "Gerber_BottomAssemblyLayer",
+            "(?i)(^|[-_ ])BottomAssy\\.gbr$",
+        ),
+        // Drill files - marked for identification, processed by drill.rs
+        rule("Drill_Source", "(?i)(^|[-_ ])(Through|NonPlated)\\.drl$"),
+    ];
+    rules.extend(inner_rules(|n| format!("(?i)(^|[-_ ])Inner ?{}\\.gbr$", n)));
+    rules
+});
+
+/// A layer of a JLC-ready set, independent of any EDA naming scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogicalLayer {
+    TopLayer,
+    BottomLayer,
+    /// Inner copper layer, numbered from 1 below the top layer
+    InnerLayer(u8),
+    TopSilkscreen,
+    BottomSilkscreen,
+    TopSolderMask,
+    BottomSolderMask,
+    TopPasteMask,
+    BottomPasteMask,
+    BoardOutline,
+    DrillPth,
+    DrillPthVia,
+    DrillNpth,
+    DrillMap,
+    GbrJob,
+    /// Score lines for V-cut panels, usually drawn on an Altium mechanical
+    /// layer chosen by a custom rule, e.g. `(?i)\.GM15$`
+    VCut,
+}
+
+// Names of the fixed layers, as (layer, logical name, final filename).
+const LAYER_NAMES: &[(LogicalLayer, &str, &str)] = &[
+    (
+        LogicalLayer::TopLayer,
+        "Gerber_TopLayer",
+        "Gerber_TopLayer.GTL",
+    ),
+    (
+        LogicalLayer::BottomLayer,
+        "Gerber_BottomLayer",
+        "Gerber_BottomLayer.GBL",
+    ),
+    (
+        LogicalLayer::TopSilkscreen,
+        "Gerber_TopSilkscreenLayer",
+        "Gerber_TopSilkscreenLayer.GTO",
+    ),
+    (
+        LogicalLayer::BottomSilkscreen,
+        "Gerber_BottomSilkscreenLayer",
+        "Gerber_BottomSilkscreenLayer.GBO",
+    ),
+    (
+        LogicalLayer::TopSolderMask,
+        "Gerber_TopSolderMaskLayer",
+        "Gerber_TopSolderMaskLayer.GTS",
+    ),
+    (
+        LogicalLayer::BottomSolderMask,
+        "Gerber_BottomSolderMaskLayer",
+        "Gerber_BottomSolderMaskLayer.GBS",
+    ),
+    (
+        LogicalLayer::TopPasteMask,
+        "Gerber_TopPasteMaskLayer",
+        "Gerber_TopPasteMaskLayer.GTP",
+    ),
+    (
+        LogicalLayer::BottomPasteMask,
+        "Gerber_BottomPasteMaskLayer",
+        "Gerber_BottomPasteMaskLayer.GBP",
+    ),
+    (
+        LogicalLayer::BoardOutline,
+        "Gerber_BoardOutlineLayer",
+        "Gerber_BoardOutlineLayer.GKO",
+    ),
+    (
+        LogicalLayer::DrillPth,
+        "Drill_PTH_Through",
+        "Drill_PTH_Through.DRL",
+    ),
+    (
+        LogicalLayer::DrillPthVia,
+        "Drill_PTH_Through_Via",
+        "Drill_PTH_Through_Via.DRL",
+    ),
+    (
+        LogicalLayer::DrillNpth,
+        "Drill_NPTH_Through",
+        "Drill_NPTH_Through.DRL",
+    ),
+    (LogicalLayer::DrillMap, "Drill_MAP_GBR", "Drill_MAP_GBR.GBR"),
+    (
+        LogicalLayer::GbrJob,
+        "Gerber_GBR_JOB",
+        "Gerber_GBR_JOB.GBRJOB",
+    ),
+    (
+        LogicalLayer::VCut,
+        "Gerber_VCutLayer",
+        "Gerber_VCutLayer.GML",
+    ),
+];
+
+// Inner layer names are formulaic, so they are generated once for every index.
+static INNER_LAYER_NAMES: Lazy<Vec<(String, String)>> = Lazy::new(|| {
+    (0..=u8::MAX)
+        .map(|n| {
+            (
+                format!("Gerber_InnerLayer{}", n),
+                format!("Gerber_InnerLayer{}.G{}", n, n),
+            )
+        })
+        .collect()
+});
+
+impl LogicalLayer {
+    /// The logical name used by the rename rules, e.g. "Gerber_TopLayer".
+    pub fn logical_name(&self) -> &'static str {
+        match self {
+            LogicalLayer::InnerLayer(n) => &INNER_LAYER_NAMES[*n as usize].0,
+            layer => {
+                LAYER_NAMES
+                    .iter()
+                    .find(|(l, _, _)| l == layer)
+                    .expect("Every fixed layer has a name")
+                    .1
+            }
+        }
+    }
+
+    /// Parses a logical name such as "Gerber_InnerLayer2".
+    pub fn from_logical_name(name: &str) -> Option<LogicalLayer> {
+        if let Some(index) = name.strip_prefix("Gerber_InnerLayer") {
+            return index.parse().ok().map(LogicalLayer::InnerLayer);
+        }
+        LAYER_NAMES
+            .iter()
+            .find(|(_, logical, _)| *logical == name)
+            .map(|(layer, _, _)| *layer)
+    }
+}
+
+/// The JLC filename of a layer, e.g. "Gerber_TopLayer.GTL".
+///
+/// Tools that already know each layer's role, such as an EDA plugin, can use
+/// this to write JLC-ready names without going through the rename rules.
+pub fn final_name_for(layer: LogicalLayer) -> &'static str {
+    match layer {
+        LogicalLayer::InnerLayer(n) => &INNER_LAYER_NAMES[n as usize].1,
+        layer => {
+            LAYER_NAMES
+                .iter()
+                .find(|(l, _, _)| *l == layer)
+                .expect("Every fixed layer has a name")
+                .2
+        }
+    }
+}
+
+/// The layer a JLC filename stands for; the reverse of [`final_name_for`].
+/// The extension is matched case-insensitively.
+pub fn layer_for_final_name(filename: &str) -> Option<LogicalLayer> {
+    let (stem, extension) = filename.rsplit_once('.')?;
+    let layer = LogicalLayer::from_logical_name(stem)?;
+    let (expected_stem, expected_extension) = final_name_for(layer).rsplit_once('.')?;
+    (expected_stem == stem && expected_extension.eq_ignore_ascii_case(extension)).then_some(layer)
+}
+
+/// Maps a logical file type name to its final, standardized filename.
+pub(crate) fn get_final_filename(logical_name: &str) -> String {
+    if let Some(layer) = LogicalLayer::from_logical_name(logical_name) {
+        return final_name_for(layer).to_string();
+    }
+    match logical_name {
+        // Drill_Source files are processed by drill.rs, this is a fallback
+        "Drill_Source" => "Drill_Source.DRL".to_string(),
+        "Drill_Report" => "Drill_MAP_GBR.DRR".to_string(),
+        "Gerber_Layer_Drawing_Parameters" => "Gerber_GBR_JOB.LDP".to_string(),
+
+        // A fallback for any other matched logical names not in the primary list.
+        _ => format!("{}.GBR", logical_name),
+    }
+}
+
+/// How Altium's paste and silkscreen extensions are read. Altium versions and
+/// OutputJob settings disagree on them, and a paste layer taken for a
+/// silkscreen silently breaks an SMT order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PasteSilkMapping {
+    /// .GTO/.GBO are silkscreen and .GTP/.GBP paste
+    #[default]
+    Standard,
+    /// Paste or silkscreen is read from the layer content; the side still
+    /// comes from the extension
+    Auto,
+    /// Extensions, without the dot and in any case, and the layer each one
+    /// stands for, e.g. ("GPB", BottomPasteMask); others are read as Standard
+    Table(Vec<(String, LogicalLayer)>),
+}
+
+// The Standard paste and silkscreen extensions
+const STANDARD_PASTE_SILK: &[(&str, LogicalLayer)] = &[
+    ("GTO", LogicalLayer::TopSilkscreen),
+    ("GBO", LogicalLayer::BottomSilkscreen),
+    ("GTP", LogicalLayer::TopPasteMask),
+    ("GBP", LogicalLayer::BottomPasteMask),
+];
+
+impl PasteSilkMapping {
+    /// The layer an Altium file stands for by its extension, as Standard
+    /// reads it; `None` for extensions other than paste and silkscreen.
+    pub fn standard_layer(filename: &str) -> Option<LogicalLayer> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        STANDARD_PASTE_SILK
+            .iter()
+            .find(|(e, _)| e.eq_ignore_ascii_case(extension))
+            .map(|(_, layer)| *layer)
+    }
+
+    /// The layer of an Altium file by this mapping, or `None` when the
+    /// mapping says nothing about its extension.
+    pub fn layer_for(&self, filename: &str, content: &str) -> Option<LogicalLayer> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        match self {
+            PasteSilkMapping::Standard => Self::standard_layer(filename),
+            PasteSilkMapping::Auto => {
+                let standard = Self::standard_layer(filename)?;
+                let top = matches!(
+                    standard,
+                    LogicalLayer::TopSilkscreen | LogicalLayer::TopPasteMask
+                );
+                Some(match (classify::sniff_paste_or_silk(content), top) {
+                    (None, _) => standard,
+                    (Some(PasteOrSilk::Paste), true) => LogicalLayer::TopPasteMask,
+                    (Some(PasteOrSilk::Paste), false) => LogicalLayer::BottomPasteMask,
+                    (Some(PasteOrSilk::Silkscreen), true) => LogicalLayer::TopSilkscreen,
+                    (Some(PasteOrSilk::Silkscreen), false) => LogicalLayer::BottomSilkscreen,
+                })
+            }
+            PasteSilkMapping::Table(table) => table
+                .iter()
+                .find(|(e, _)| e.trim_start_matches('.').eq_ignore_ascii_case(extension))
+                .map(|(_, layer)| *layer)
+                .or_else(|| Self::standard_layer(filename)),
+        }
+    }
+}
+
+/// What happens to drill drawings such as KiCad's `-drl_map.gbr` and
+/// Altium's `.LDP`. They only document the holes, and the JLC uploader may
+/// take them for drill data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrillMapPolicy {
+    /// Leave them out of the output with a warning
+    #[default]
+    Drop,
+    /// Keep Gerber drill maps as [`DRILL_MAP_DOCUMENT_NAME`], a documentation
+    /// layer the uploader ignores. `.LDP` reports are not drawings and are
+    /// still dropped.
+    Document,
+}
+
+/// How a V-cut layer reaches JLC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VCutOutput {
+    /// Keep the score lines as their own Gerber layer, `Gerber_VCutLayer.GML`.
+    /// JLC has no V-cut layer name of its own, so this one has to be pointed
+    /// out in the order remarks; and as EAGLE writes its outline as `.GML`,
+    /// other tools may take the layer for the board outline.
+    Layer,
+    /// Replace the layer with [`V_CUT_NOTE_NAME`], listing the score lines as
+    /// text for the order remarks
+    #[default]
+    Note,
+}
+
+/// Output name of the note written by [`VCutOutput::Note`]
+pub const V_CUT_NOTE_NAME: &str = "V-CUT.txt";
+
+/// Output name of a drill map kept by [`DrillMapPolicy::Document`]
+pub const DRILL_MAP_DOCUMENT_NAME: &str = "Gerber_Drill_Map.GBR";
+
+/// How output files are named once their layer is known
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NamingProfile {
+    /// JLC/EasyEDA names, e.g. "Gerber_TopLayer.GTL"
+    #[default]
+    Jlc,
+    /// Protel extensions on a shared stem, e.g. "board.GTL" and "board-PTH.DRL"
+    Protel { stem: String },
+    /// A template with `{logical}` ("Gerber_TopLayer"), `{layer}` ("TopLayer"),
+    /// `{ext}` ("GTL"), `{stem}` and `{original}` (the input name with and
+    /// without extension), e.g. "{stem}_{layer}.{ext}"
+    Template(String),
+}
+
+impl NamingProfile {
+    /// The output name of a file of `logical_name` that was named `original`.
+    /// Names without a Protel equivalent keep their JLC name.
+    pub fn file_name(&self, logical_name: &str, original: &str) -> String {
+        let jlc = get_final_filename(logical_name);
+        match self {
+            NamingProfile::Jlc => jlc,
+            NamingProfile::Protel { stem } => {
+                let layer = LogicalLayer::from_logical_name(logical_name);
+                let suffix = match layer {
+                    None => return jlc,
+                    Some(LogicalLayer::DrillPth) => "-PTH.DRL",
+                    Some(LogicalLayer::DrillPthVia) => "-PTH-Via.DRL",
+                    Some(LogicalLayer::DrillNpth) => "-NPTH.DRL",
+                    Some(LogicalLayer::DrillMap) => ".GD1",
+                    Some(LogicalLayer::GbrJob) => ".gbrjob",
+                    Some(_) => &jlc[jlc.rfind('.').unwrap_or(jlc.len())..],
+                };
+                format!("{}{}", stem, suffix)
+            }
+            NamingProfile::Template(template) => {
+                let extension = jlc.rsplit_once('.').map_or("", |(_, e)| e);
+                let layer = logical_name
+                    .strip_prefix("Gerber_")
+                    .or_else(|| logical_name.strip_prefix("Drill_"))
+                    .unwrap_or(logical_name);
+                let base = original.rsplit('/').next().unwrap_or(original);
+                let stem = base.rsplit_once('.').map_or(base, |(s, _)| s);
+                template
+                    .replace("{logical}", logical_name)
+                    .replace("{layer}", layer)
+                    .replace("{ext}", extension)
+                    .replace("{stem}", stem)
+                    .replace("{original}", base)
+            }
+        }
+    }
+}
+
+fn rules_for(eda_type: EdaType) -> &'static [Rule] {
+    match eda_type {
+        EdaType::Ad => &AD_RULES,
+        EdaType::KiCad => &KICAD_RULES,
+        EdaType::Eagle => &EAGLE_RULES,
+        EdaType::Pads => &PADS_RULES,
+        EdaType::Proteus => &PROTEUS_RULES,
+        EdaType::LibrePcb => &LIBREPCB_RULES,
+        EdaType::Horizon => &HORIZON_RULES,
+        EdaType::DipTrace => &DIPTRACE_RULES,
+        // Names are already final, so every file keeps its name
+        EdaType::EasyEda => &[],
+    }
+}
+
+/// How sure a rename is of the layer it picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// No rule matched
+    Low,
+    /// A rule matched that depends on sibling files, or a plane was renumbered
+    Medium,
+    /// A rule matched unconditionally, or the user chose the layer
+    High,
+}
+
+impl Confidence {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+}
+
+/// Why a file got its new name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchSource {
+    /// No rule matched; the file keeps its name
+    Unmatched,
+    /// A built-in rule of the EDA type
+    BuiltIn {
+        pattern: String,
+        /// The rule only applies when no other file matches this
+        unless_sibling: Option<String>,
+    },
+    /// A user rule, see [`crate::custom_rules`]
+    Custom { pattern: String, priority: i32 },
+    /// The `%TF.FileFunction` attribute in the file
+    FileFunction { file_function: String },
+    /// The layer function listed for the file in the set's `.gbrjob`
+    GerberJob { file_function: String },
+    /// No rule matched; the layer was guessed from the file's objects, see
+    /// [`crate::classify::guess_layer`]
+    Content { evidence: String, score: u8 },
+    /// The file chosen by an [`OutlineSelection`]
+    OutlineSelection,
+    /// The rules chose this file as the outline, but the selection chose another
+    OutlineReplaced,
+}
+
+/// The rename of one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameEntry {
+    pub original: String,
+    pub new_name: String,
+    /// Logical name of the layer, e.g. "Gerber_TopLayer"; `None` when unmatched
+    pub logical_name: Option<String>,
+    pub source: MatchSource,
+    pub confidence: Confidence,
+}
+
+impl RenameEntry {
+    fn unmatched(original: &str) -> RenameEntry {
+        RenameEntry {
+            original: original.to_string(),
+            new_name: original.to_string(),
+            logical_name: None,
+            source: MatchSource::Unmatched,
+            confidence: Confidence::Low,
+        }
+    }
+
+    /// Whether the file keeps its name
+    pub fn is_untouched(&self) -> bool {
+        self.new_name == self.original
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let (source, pattern): (&str, Option<&str>) = match &self.source {
+            MatchSource::Unmatched => ("unmatched", None),
+            MatchSource::BuiltIn { pattern, .. } => ("built-in", Some(pattern)),
+            MatchSource::Custom { pattern, .. } => ("custom", Some(pattern)),
+            MatchSource::FileFunction { file_function } => ("file-function", Some(file_function)),
+            MatchSource::GerberJob { file_function } => ("gbrjob", Some(file_function)),
+            MatchSource::Content { evidence, .. } => ("content", Some(evidence)),
+            MatchSource::OutlineSelection => ("outline-selection", None),
+            MatchSource::OutlineReplaced => ("outline-replaced", None),
+        };
+        JsonValue::Object(vec![
+            ("original".to_string(), self.original.as_str().into()),
+            ("new_name".to_string(), self.new_name.as_str().into()),
+            (
+                "logical_name".to_string(),
+                self.logical_name.as_deref().into(),
+            ),
+            ("source".to_string(), source.into()),
+            ("pattern".to_string(), pattern.into()),
+            ("confidence".to_string(), self.confidence.id().into()),
+            ("untouched".to_string(), self.is_untouched().into()),
+        ])
+    }
+}
+
+/// The renames of a set of files, keyed by original name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenameResult {
+    pub entries: BTreeMap<String, RenameEntry>,
+}
+
+impl RenameResult {
+    pub fn get(&self, file: &str) -> Option<&RenameEntry> {
+        self.entries.get(file)
+    }
+
+    /// The new name of `file`
+    pub fn new_name(&self, file: &str) -> Option<&str> {
+        self.entries.get(file).map(|e| e.new_name.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RenameEntry> {
+        self.entries.values()
+    }
+
+    /// Files that keep their names
+    pub fn untouched(&self) -> impl Iterator<Item = &RenameEntry> {
+        self.iter().filter(|e| e.is_untouched())
+    }
+
+    /// Original names mapped to new names
+    pub fn to_map(&self) -> BTreeMap<String, String> {
+        self.entries
+            .iter()
+            .map(|(original, e)| (original.clone(), e.new_name.clone()))
+            .collect()
+    }
+
+    pub(crate) fn insert(&mut self, entry: RenameEntry) {
+        self.entries.insert(entry.original.clone(), entry);
+    }
+
+    /// Renames every matched file by `profile` instead of its JLC name.
+    pub fn apply_profile(&mut self, profile: &NamingProfile) {
+        for entry in self.entries.values_mut() {
+            if let Some(logical_name) = &entry.logical_name {
+                entry.new_name = profile.file_name(logical_name, &entry.original);
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(RenameEntry::to_json).collect())
+    }
+}
+
+/// The main function of this module. It takes a list of filenames and an EDA type,
+/// and returns the proposed new, standardized name of every file.
+/// The whole list is the context for rules that depend on sibling files.
+#[deprecated(
+    since = "0.3.0",
+    note = "use `map_filenames_detailed`, which also reports the rule behind each name"
+)]
+pub fn map_filenames(files: &[String], eda_type: EdaType) -> BTreeMap<String, String> {
+    map_filenames_detailed(files, eda_type).to_map()
+}
+
+/// Like [`map_filenames`], returning the rule that picked each name and how
+/// sure it is.
+pub fn map_filenames_detailed(files: &[String], eda_type: EdaType) -> RenameResult {
+    let rules = rules_for(eda_type);
+
+    let mut result = RenameResult::default();
+
+    for file in files {
+        // Stop after the first successful match; unmatched files keep their names
+        let entry = match rules.iter().find(|rule| rule.matches(file, files)) {
+            Some(rule) => RenameEntry {
+                original: file.clone(),
+                new_name: get_final_filename(rule.logical_name),
+                logical_name: Some(rule.logical_name.to_string()),
+                source: rule.source(),
+                confidence: rule.confidence(),
+            },
+            None => RenameEntry::unmatched(file),
+        };
+        result.insert(entry);
+    }
+
+    number_plane_layers(&mut result);
+    result
+}
+
+/// Which file becomes `Gerber_BoardOutlineLayer`, for projects that keep the
+/// outline somewhere the rename rules don't look
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OutlineSelection {
+    /// Use the rename rules
+    #[default]
+    Auto,
+    /// Altium mechanical layer n, exported as `.GMn` (1 to 32)
+    Mechanical(u8),
+    /// Altium keep-out layer (`.GKO`)
+    KeepOut,
+    /// A board profile export, named `*profile*` or `*.gpf`
+    BoardProfile,
+    /// A file by its exact name
+    File(String),
+}
+
+impl OutlineSelection {
+    /// Parses a selection id: "auto", "gm1" to "gm32", "gko", "keepout", "profile",
+    /// or any other text as a file name.
+    pub fn from_id(id: &str) -> OutlineSelection {
+        let lower = id.to_lowercase();
+        match lower.as_str() {
+            "" | "auto" => OutlineSelection::Auto,
+            "gko" | "keepout" | "keep-out" => OutlineSelection::KeepOut,
+            "profile" | "board-profile" => OutlineSelection::BoardProfile,
+            _ => match lower.strip_prefix("gm").and_then(|n| n.parse().ok()) {
+                Some(n @ 1..=32) => OutlineSelection::Mechanical(n),
+                _ => OutlineSelection::File(id.to_string()),
+            },
+        }
+    }
+
+    /// Whether `file` is the selected outline. Always false for `Auto`.
+    pub fn matches(&self, file: &str) -> bool {
+        let lower = file.to_lowercase();
+        match self {
+            OutlineSelection::Auto => false,
+            OutlineSelection::Mechanical(n) => lower.ends_with(&format!(".gm{}", n)),
+            OutlineSelection::KeepOut => lower.ends_with(".gko"),
+            OutlineSelection::BoardProfile => lower.ends_with(".gpf") || lower.contains("profile"),
+            OutlineSelection::File(name) => file == name,
+        }
+    }
+}
+
+/// Like [`map_filenames_detailed`], with `outline` choosing the board outline file.
+///
+/// The files the rules mapped to the outline keep their names instead. When
+/// no file matches the selection, the result is the same as from [`map_filenames_detailed`].
+pub fn map_filenames_with_outline(
+    files: &[String],
+    eda_type: EdaType,
+    outline: &OutlineSelection,
+) -> RenameResult {
+    let mut result = map_filenames_detailed(files, eda_type);
+    select_outline(&mut result, files, outline);
+    result
+}
+
+// Points the outline name at the file `outline` selects, if any.
+pub(crate) fn select_outline(
+    result: &mut RenameResult,
+    files: &[String],
+    outline: &OutlineSelection,
+) {
+    let Some(selected) = files.iter().filter(|f| outline.matches(f)).min() else {
+        return;
+    };
+
+    let outline_name = final_name_for(LogicalLayer::BoardOutline);
+    for entry in result.entries.values_mut() {
+        if entry.original == *selected {
+            entry.new_name = outline_name.to_string();
+            entry.logical_name = Some(LogicalLayer::BoardOutline.logical_name().to_string());
+            entry.source = MatchSource::OutlineSelection;
+            entry.confidence = Confidence::High;
+        } else if entry.new_name == outline_name {
+            *entry = RenameEntry {
+                source: MatchSource::OutlineReplaced,
+                ..RenameEntry::unmatched(&entry.original)
+            };
+        }
+    }
+}
+
+/// What to do when several files map to the same output name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Report the collision as an error; the colliding files keep their names
+    Error,
+    /// The first file in input order gets the name, the others keep theirs
+    #[default]
+    KeepFirst,
+    /// All files get the name and are merged into one layer afterwards
+    Merge,
+    /// The first file gets the name, the others get "_2", "_3", ... before the extension
+    Suffix,
+}
+
+/// Several input files mapped to one output name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub target: String,
+    /// Colliding files in input order
+    pub sources: Vec<String>,
+}
+
+/// A rename map with its collisions resolved by a [`CollisionPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePlan {
+    pub renames: BTreeMap<String, String>,
+    /// The renames before collisions were resolved, with the rules that picked them
+    pub result: RenameResult,
+    pub policy: CollisionPolicy,
+    /// Every collision found, whatever the policy
+    pub collisions: Vec<Collision>,
+}
+
+impl RenamePlan {
+    /// Resolves the collisions of `result`, whose keys are `files`.
+    ///
+    /// Drill files all map to `Drill_Source.DRL` on purpose, since they are
+    /// merged by the drill stage, so that name never collides.
+    pub fn resolve(files: &[String], result: RenameResult, policy: CollisionPolicy) -> RenamePlan {
+        let mut rename_map = result.to_map();
+        let drill_source = get_final_filename("Drill_Source");
+        let mut collisions: Vec<Collision> = Vec::new();
+        for file in files {
+            let Some(target) = rename_map.get(file) else {
+                continue;
+            };
+            if *target == drill_source || target == file {
+                continue;
+            }
+            match collisions.iter_mut().find(|c| &c.target == target) {
+                Some(collision) => collision.sources.push(file.clone()),
+                None => collisions.push(Collision {
+                    target: target.clone(),
+                    sources: vec![file.clone()],
+                }),
+            }
+        }
+        collisions.retain(|c| c.sources.len() > 1);
+
+        for collision in &collisions {
+            let skip = match policy {
+                CollisionPolicy::Error => 0,
+                CollisionPolicy::Merge => continue,
+                CollisionPolicy::KeepFirst | CollisionPolicy::Suffix => 1,
+            };
+            for (i, source) in collision.sources.iter().enumerate().skip(skip) {
+                let new_name = match policy {
+                    CollisionPolicy::Suffix => match collision.target.rsplit_once('.') {
+                        Some((stem, extension)) => format!("{}_{}.{}", stem, i + 1, extension),
+                        None => format!("{}_{}", collision.target, i + 1),
+                    },
+                    _ => source.clone(),
+                };
+                rename_map.insert(source.clone(), new_name);
+            }
+        }
+
+        RenamePlan {
+            renames: rename_map,
+            result,
+            policy,
+            collisions,
+        }
+    }
+
+    /// Collisions as messages, e.g. for a validation report.
+    pub fn collision_messages(&self) -> Vec<String> {
+        self.collisions
+            .iter()
+            .map(|c| format!("{} would be written by {}", c.target, c.sources.join(", ")))
+            .collect()
+    }
+}
+
+/// Like [`map_filenames_detailed`], with collisions resolved by `policy`.
+pub fn plan_filenames(files: &[String], eda_type: EdaType, policy: CollisionPolicy) -> RenamePlan {
+    RenamePlan::resolve(files, map_filenames_detailed(files, eda_type), policy)
+}
+
+/// Renames plane layers ("Gerber_PlaneLayer1.GBR") to the inner layers after
+/// the highest mid layer, keeping the plane order. JLC has no separate plane
+/// layer type, and Protel numbers planes independently of the mid layers.
+fn number_plane_layers(result: &mut RenameResult) {
+    let mut planes: Vec<(u32, String)> = result
+        .iter()
+        .filter_map(|entry| {
+            let index = entry
+                .new_name
+                .strip_prefix("Gerber_PlaneLayer")?
+                .split('.')
+                .next()?
+                .parse()
+                .ok()?;
+            Some((index, entry.original.clone()))
+        })
+        .collect();
+    if planes.is_empty() {
+        return;
+    }
+    planes.sort();
+
+    let mid_layers = result
+        .iter()
+        .filter_map(|entry| match layer_for_final_name(&entry.new_name) {
+            Some(LogicalLayer::InnerLayer(n)) => Some(n),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    for (offset, (_, original)) in planes.into_iter().enumerate() {
+        let layer = LogicalLayer::InnerLayer(mid_layers.saturating_add(offset as u8 + 1));
+        if let Some(entry) = result.entries.get_mut(&original) {
+            entry.new_name = final_name_for(layer).to_string();
+            entry.logical_name = Some(layer.logical_name().to_string());
+            entry.confidence = Confidence::Medium;
+        }
+    }
+}
+
+/// Returns the logical name (e.g. "Gerber_TopLayer") of the first rule matching `file`,
+/// judged without sibling files.
+pub fn logical_name_for(file: &str, eda_type: EdaType) -> Option<&'static str> {
+    logical_name_in_set(file, &[], eda_type)
+}
+
+/// Like [`logical_name_for`], with `files` as the sibling context.
+pub fn logical_name_in_set(
+    file: &str,
+    files: &[String],
+    eda_type: EdaType,
+) -> Option<&'static str> {
+    if eda_type == EdaType::EasyEda {
+        return layer_for_final_name(file).map(|layer| layer.logical_name());
+    }
+    rules_for(eda_type)
+        .iter()
+        .find(|rule| rule.matches(file, files))
+        .map(|rule| rule.logical_name)
+}
+
+/// How one rename rule judged a file, see [`explain`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleTrace {
+    /// Logical name the rule gives, e.g. "Gerber_BoardOutlineLayer"
+    pub logical_name: &'static str,
+    pub pattern: String,
+    /// The rule only applies when no other file matches this
+    pub unless_sibling: Option<String>,
+    pub outcome: RuleOutcome,
+}
+
+/// Why a rule did or did not rename a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleOutcome {
+    /// The rule named the file
+    Matched,
+    /// The pattern does not match the file name
+    NoMatch,
+    /// The pattern matches, but `sibling` in the set matches the rule's
+    /// sibling pattern, so the rule stands aside
+    SiblingPresent { sibling: String },
+    /// The pattern matches, but an earlier rule already named the file
+    Shadowed,
+}
+
+impl RuleOutcome {
+    pub fn id(&self) -> &'static str {
+        match self {
+            RuleOutcome::Matched => "matched",
+            RuleOutcome::NoMatch => "no-match",
+            RuleOutcome::SiblingPresent { .. } => "sibling-present",
+            RuleOutcome::Shadowed => "shadowed",
+        }
+    }
+}
+
+impl RuleTrace {
+    pub fn to_json(&self) -> JsonValue {
+        let sibling = match &self.outcome {
+            RuleOutcome::SiblingPresent { sibling } => Some(sibling.as_str()),
+            _ => None,
+        };
+        JsonValue::Object(vec![
+            ("logical_name".to_string(), self.logical_name.into()),
+            ("pattern".to_string(), self.pattern.as_str().into()),
+            (
+                "unless_sibling".to_string(),
+                self.unless_sibling.as_deref().into(),
+            ),
+            ("outcome".to_string(), self.outcome.id().into()),
+            ("sibling".to_string(), sibling.into()),
+        ])
+    }
+}
+
+impl fmt::Display for RuleTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<15} {:<32} {}",
+            self.outcome.id(),
+            self.logical_name,
+            self.pattern
+        )?;
+        match &self.outcome {
+            RuleOutcome::SiblingPresent { sibling } => write!(f, " (ruled out by {})", sibling),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Every built-in rule of `eda_type`, in the order they are tried, with how
+/// each judged `file` on its own. For "why wasn't my GKO detected" reports.
+pub fn explain(file: &str, eda_type: EdaType) -> Vec<RuleTrace> {
+    explain_in_set(file, &[], eda_type)
+}
+
+/// Like [`explain`], with `files` as the sibling context.
+pub fn explain_in_set(file: &str, files: &[String], eda_type: EdaType) -> Vec<RuleTrace> {
+    let mut matched = false;
+    rules_for(eda_type)
+        .iter()
+        .map(|rule| {
+            let sibling = rule.unless_sibling.as_ref().and_then(|sibling| {
+                files
+                    .iter()
+                    .find(|other| *other != file && sibling.is_match(other))
+            });
+            let outcome = if !rule.pattern.is_match(file) {
+                RuleOutcome::NoMatch
+            } else if let Some(sibling) = sibling {
+                RuleOutcome::SiblingPresent {
+                    sibling: sibling.clone(),
+                }
+            } else if matched {
+                RuleOutcome::Shadowed
+            } else {
+                matched = true;
+                RuleOutcome::Matched
+            };
+            RuleTrace {
+                logical_name: rule.logical_name,
+                pattern: rule.pattern.as_str().to_string(),
+                unless_sibling: rule.unless_sibling.as_ref().map(|r| r.as_str().to_string()),
+                outcome,
+            }
+        })
+        .collect()
+}
+
+/// Maps the fields of an X2 `%TF.FileFunction` attribute, such as
+/// `["Copper", "L2", "Inr"]`, to a logical name.
+/// Copper layer L2 is the first inner layer.
+pub(crate) fn logical_name_from_file_function(fields: &[&str]) -> Option<String> {
+    let side = |top: &str, bottom: &str| match fields.get(1).copied() {
+        Some("Top") => Some(top.to_string()),
+        Some("Bot") => Some(bottom.to_string()),
+        _ => None,
+    };
+    match *fields.first()? {
+        "Copper" => match fields.get(2).copied() {
+            Some("Top") => Some("Gerber_TopLayer".to_string()),
+            Some("Bot") => Some("Gerber_BottomLayer".to_string()),
+            Some("Inr") => {
+                let index: u32 = fields.get(1)?.strip_prefix('L')?.parse().ok()?;
+                Some(format!("Gerber_InnerLayer{}", index.checked_sub(1)?))
+            }
+            _ => None,
+        },
+        "Soldermask" => side("Gerber_TopSolderMaskLayer", "Gerber_BottomSolderMaskLayer"),
+        "Legend" => side("Gerber_TopSilkscreenLayer", "Gerber_BottomSilkscreenLayer"),
+        "Paste" => side("Gerber_TopPasteMaskLayer", "Gerber_BottomPasteMaskLayer"),
+        "Profile" => Some("Gerber_BoardOutlineLayer".to_string()),
+        "Drillmap" => Some("Drill_MAP_GBR".to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,293 @@
+/* src/render.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::cancel::{CHECK_INTERVAL, Cancellation, Cancelled, NeverCancel};
+use crate::geometry::{BoundingBox, Point, flatten_arc};
+use crate::gerber::{Aperture, Command, GerberLayer, Interpolation, Polarity};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Chord tolerance for drawn arcs, in mm
+const ARC_TOLERANCE_MM: f64 = 0.01;
+// Margin around the image, in mm
+const MARGIN_MM: f64 = 0.5;
+
+const DARK_COLOR: &str = "#000000";
+const BACKGROUND_COLOR: &str = "#ffffff";
+// Regions have no aperture, so the heat map draws them in one neutral color
+const REGION_COLOR: &str = "#9e9e9e";
+
+/// How geometry is colored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Dark geometry in black, clear geometry in the background color
+    #[default]
+    Polarity,
+    /// Every aperture in its own color, so geometry that moved to another
+    /// aperture (e.g. after fingerprint renumbering) stands out between two renders
+    ApertureHeatMap,
+}
+
+/// How often an aperture is used in a layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApertureUsage {
+    pub code: u32,
+    pub flashes: usize,
+    pub draws: usize,
+}
+
+/// Counts the flashes and draws of every defined aperture, in D-code order.
+pub fn aperture_usage(layer: &GerberLayer) -> Vec<ApertureUsage> {
+    let mut usage: BTreeMap<u32, ApertureUsage> = layer
+        .apertures
+        .keys()
+        .map(|&code| {
+            (
+                code,
+                ApertureUsage {
+                    code,
+                    flashes: 0,
+                    draws: 0,
+                },
+            )
+        })
+        .collect();
+    let mut current = None;
+    let mut in_region = false;
+    for command in &layer.commands {
+        match command {
+            Command::SelectAperture(code) => current = Some(*code),
+            Command::RegionStart => in_region = true,
+            Command::RegionEnd => in_region = false,
+            Command::Flash(_) => {
+                if let Some(entry) = current.and_then(|c| usage.get_mut(&c)) {
+                    entry.flashes += 1;
+                }
+            }
+            Command::Draw { .. } if !in_region => {
+                if let Some(entry) = current.and_then(|c| usage.get_mut(&c)) {
+                    entry.draws += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    usage.into_values().collect()
+}
+
+/// A distinct color for an aperture. Hues are spread by the golden angle, so
+/// neighbouring D-codes get clearly different colors.
+pub(crate) fn aperture_color(code: u32) -> String {
+    let hue = (code as f64 * 137.508) % 360.0;
+    format!("hsl({:.0},75%,45%)", hue)
+}
+
+/// Renders a layer as SVG in mm, Y pointing up as in the Gerber image.
+///
+/// In heat map mode each aperture is a `<g>` element with a `data-aperture`
+/// attribute and a `<title>`, so viewers show the D-code on hover. Macro
+/// apertures are drawn as their bounding box.
+pub fn render_svg(layer: &GerberLayer, mode: RenderMode) -> String {
+    render_svg_cancellable(layer, mode, &NeverCancel).expect("NeverCancel never cancels")
+}
+
+/// Same as [`render_svg`], but polls `cancel` every [`CHECK_INTERVAL`] commands
+/// and returns `Err(Cancelled)` as soon as cancellation is requested.
+pub fn render_svg_cancellable(
+    layer: &GerberLayer,
+    mode: RenderMode,
+    cancel: &dyn Cancellation,
+) -> Result<String, Cancelled> {
+    let bounds = layer
+        .image_bounds()
+        .map(|b| b.expanded(MARGIN_MM))
+        .unwrap_or_else(|| BoundingBox::from_point(Point::new(0.0, 0.0)).expanded(MARGIN_MM));
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.4} {:.4} {:.4} {:.4}" width="{:.4}mm" height="{:.4}mm">"#,
+        bounds.min_x,
+        -bounds.max_y,
+        bounds.width(),
+        bounds.height(),
+        bounds.width(),
+        bounds.height()
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect x="{:.4}" y="{:.4}" width="{:.4}" height="{:.4}" fill="{}"/>"#,
+        bounds.min_x,
+        -bounds.max_y,
+        bounds.width(),
+        bounds.height(),
+        BACKGROUND_COLOR
+    );
+    out.push_str("<g transform=\"scale(1,-1)\">\n");
+
+    let mut group: Option<String> = None;
+    let mut aperture: Option<(u32, &Aperture)> = None;
+    let mut dark = true;
+    let mut in_region = false;
+    let mut contour: Vec<Point> = Vec::new();
+
+    let color = |code: Option<u32>, dark: bool| match (mode, dark) {
+        (_, false) => BACKGROUND_COLOR.to_string(),
+        (RenderMode::Polarity, true) => DARK_COLOR.to_string(),
+        (RenderMode::ApertureHeatMap, true) => {
+            code.map_or(REGION_COLOR.to_string(), aperture_color)
+        }
+    };
+    // Opens a group for the current color, closing the previous one when it differs
+    let mut open_group = |out: &mut String, code: Option<u32>, dark: bool| {
+        let fill = color(code, dark);
+        let key = format!("{}{:?}", fill, code);
+        if group.as_deref() == Some(key.as_str()) {
+            return;
+        }
+        if group.is_some() {
+            out.push_str("</g>\n");
+        }
+        match (mode, code) {
+            (RenderMode::ApertureHeatMap, Some(code)) => {
+                let _ = writeln!(
+                    out,
+                    r#"<g fill="{0}" stroke="{0}" data-aperture="{1}"><title>D{1}</title>"#,
+                    fill, code
+                );
+            }
+            _ => {
+                let _ = writeln!(out, r#"<g fill="{0}" stroke="{0}">"#, fill);
+            }
+        }
+        group = Some(key);
+    };
+
+    for (index, command) in layer.commands.iter().enumerate() {
+        if index % CHECK_INTERVAL == 0 {
+            cancel.check()?;
+        }
+        match command {
+            Command::SelectAperture(code) => {
+                aperture = layer.apertures.get(code).map(|a| (*code, a));
+            }
+            Command::SetPolarity(polarity) => dark = *polarity == Polarity::Dark,
+            Command::RegionStart => {
+                in_region = true;
+                contour.clear();
+            }
+            Command::RegionEnd => {
+                open_group(&mut out, None, dark);
+                write_contour(&mut out, &contour);
+                contour.clear();
+                in_region = false;
+            }
+            Command::Move(_) if in_region => {
+                open_group(&mut out, None, dark);
+                write_contour(&mut out, &contour);
+                contour.clear();
+            }
+            Command::Flash(p) => {
+                if let Some((code, a)) = aperture {
+                    open_group(&mut out, Some(code), dark);
+                    write_flash(&mut out, a, *p);
+                }
+            }
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } => {
+                let path = match (interpolation, center) {
+                    (Interpolation::Linear, _) | (_, None) => vec![*from, *to],
+                    (kind, Some(c)) => flatten_arc(
+                        *from,
+                        *to,
+                        *c,
+                        *kind == Interpolation::Clockwise,
+                        ARC_TOLERANCE_MM,
+                    ),
+                };
+                if in_region {
+                    if contour.is_empty() {
+                        contour.push(*from);
+                    }
+                    contour.extend_from_slice(&path[1..]);
+                } else if let Some((code, a)) = aperture {
+                    open_group(&mut out, Some(code), dark);
+                    write_stroke(&mut out, a, &path);
+                }
+            }
+            _ => {}
+        }
+    }
+    if group.is_some() {
+        out.push_str("</g>\n");
+    }
+    out.push_str("</g>\n</svg>\n");
+    Ok(out)
+}
+
+fn write_flash(out: &mut String, aperture: &Aperture, at: Point) {
+    let (hx, hy) = aperture.half_extents();
+    match aperture.template.as_str() {
+        // Polygons are drawn as their circumscribed circle
+        "C" | "P" => {
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.4}" cy="{:.4}" r="{:.4}" stroke="none"/>"#,
+                at.x, at.y, hx
+            );
+        }
+        template => {
+            let radius = if template == "O" { hx.min(hy) } else { 0.0 };
+            let _ = writeln!(
+                out,
+                r#"<rect x="{:.4}" y="{:.4}" width="{:.4}" height="{:.4}" rx="{:.4}" stroke="none"/>"#,
+                at.x - hx,
+                at.y - hy,
+                2.0 * hx,
+                2.0 * hy,
+                radius
+            );
+        }
+    }
+}
+
+fn write_stroke(out: &mut String, aperture: &Aperture, path: &[Point]) {
+    let (hx, hy) = aperture.half_extents();
+    let cap = if aperture.template == "C" {
+        "round"
+    } else {
+        "square"
+    };
+    let _ = writeln!(
+        out,
+        r#"<polyline points="{}" fill="none" stroke-width="{:.4}" stroke-linecap="{}" stroke-linejoin="round"/>"#,
+        points(path),
+        2.0 * hx.max(hy),
+        cap
+    );
+}
+
+fn write_contour(out: &mut String, contour: &[Point]) {
+    if contour.len() < 3 {
+        return;
+    }
+    let _ = writeln!(
+        out,
+        r#"<polygon points="{}" stroke="none"/>"#,
+        points(contour)
+    );
+}
+
+fn points(path: &[Point]) -> String {
+    path.iter()
+        .map(|p| format!("{:.4},{:.4}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
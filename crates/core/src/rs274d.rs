@@ -0,0 +1,242 @@
+/* src/rs274d.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::gerber::{Aperture, CoordinateFormat, Unit, ZeroOmission};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// This regex matches one wheel entry, e.g. "D10  ROUND  0.0100" or "D11 RECT 60 x 40 mil".
+static WHEEL_ENTRY_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)^\s*D(\d+)\b[\s,:=]*([A-Za-z]+)?[\s,:=]*(\d*\.?\d+)\s*(mils?|mm|inch|in|")?(?:\s*[xX,]?\s*(\d*\.?\d+))?"#,
+    )
+    .expect("Invalid wheel entry regex")
+});
+
+// This regex matches a unit declaration anywhere in a wheel file.
+static WHEEL_UNIT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(mm|millimet\w*|mils?|inch(?:es)?)\b").expect("Invalid wheel unit regex")
+});
+
+const MIL_PER_INCH: f64 = 1000.0;
+const MM_PER_INCH: f64 = 25.4;
+
+/// How the coordinates of a 274-D file are written. 274-D files carry no
+/// format statement, so this comes from the plot report or the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpconvertOptions {
+    pub unit: Unit,
+    pub format: CoordinateFormat,
+}
+
+impl Default for UpconvertOptions {
+    /// Inch 2.4 with leading zeros omitted, the most common photoplotter setup.
+    fn default() -> Self {
+        UpconvertOptions {
+            unit: Unit::Inch,
+            format: CoordinateFormat {
+                zero_omission: ZeroOmission::Leading,
+                incremental: false,
+                integer_digits: 2,
+                decimal_digits: 4,
+            },
+        }
+    }
+}
+
+/// Apertures read from a wheel file, with sizes in the unit they were converted to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Wheel {
+    pub apertures: Vec<Aperture>,
+    /// Entries that were approximated, e.g. thermals drawn as circles
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum WheelUnit {
+    Mil,
+    Millimeter,
+    Inch,
+}
+
+impl WheelUnit {
+    fn parse(text: &str) -> Option<WheelUnit> {
+        let lower = text.to_lowercase();
+        if lower.starts_with("mil") && !lower.starts_with("millim") {
+            Some(WheelUnit::Mil)
+        } else if lower.starts_with("mm") || lower.starts_with("millim") {
+            Some(WheelUnit::Millimeter)
+        } else if lower.starts_with("in") || lower == "\"" {
+            Some(WheelUnit::Inch)
+        } else {
+            None
+        }
+    }
+
+    fn convert(self, value: f64, to: Unit) -> f64 {
+        let inches = match self {
+            WheelUnit::Mil => value / MIL_PER_INCH,
+            WheelUnit::Millimeter => value / MM_PER_INCH,
+            WheelUnit::Inch => value,
+        };
+        match to {
+            Unit::Inch => inches,
+            Unit::Millimeter => inches * MM_PER_INCH,
+        }
+    }
+}
+
+/// Whether `content` looks like RS-274-D: D-code data without any format
+/// statement or aperture definitions.
+pub(crate) fn is_rs274d(content: &str) -> bool {
+    !content.contains("%FS")
+        && !content.contains("%AD")
+        && content.contains('*')
+        && content.contains('X')
+        && (content.contains("D01") || content.contains("D02") || content.contains("D03"))
+}
+
+/// Parses a wheel (aperture list) from a `.rep`, `.whl` or `.apr` file.
+///
+/// Each line starting with a D-code and a size is an entry; the shape word
+/// (round, square, rect, oval) may be missing, in which case the aperture is round.
+/// Sizes without a unit are taken as mils when any is 1 or more, as inches otherwise.
+pub(crate) fn parse_wheel(content: &str, unit: Unit) -> Wheel {
+    let declared_unit = WHEEL_UNIT_REGEX
+        .captures(content)
+        .and_then(|caps| WheelUnit::parse(&caps[1]));
+
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let Some(caps) = WHEEL_ENTRY_REGEX.captures(line) else {
+            continue;
+        };
+        let Ok(code) = caps[1].parse::<u32>() else {
+            continue;
+        };
+        let shape = caps.get(2).map(|m| m.as_str().to_lowercase());
+        let Ok(width) = caps[3].parse::<f64>() else {
+            continue;
+        };
+        let height = caps.get(5).and_then(|m| m.as_str().parse::<f64>().ok());
+        let line_unit = caps.get(4).and_then(|m| WheelUnit::parse(m.as_str()));
+        entries.push((code, shape, width, height, line_unit));
+    }
+
+    let guessed_unit = if entries.iter().any(|(_, _, w, _, _)| *w >= 1.0) {
+        WheelUnit::Mil
+    } else {
+        WheelUnit::Inch
+    };
+
+    let mut wheel = Wheel::default();
+    for (code, shape, width, height, line_unit) in entries {
+        let source_unit = line_unit.or(declared_unit).unwrap_or(guessed_unit);
+        let width = source_unit.convert(width, unit);
+        let height = height
+            .map(|h| source_unit.convert(h, unit))
+            .unwrap_or(width);
+        let (template, parameters) = match shape.as_deref() {
+            None | Some("c" | "cir" | "circ" | "circle" | "rnd" | "round") => ("C", vec![width]),
+            Some("s" | "sq" | "square") => ("R", vec![width, width]),
+            Some("r" | "rec" | "rect" | "rectangle" | "rectangular") => ("R", vec![width, height]),
+            Some("o" | "ob" | "ovl" | "oval" | "obround") => ("O", vec![width, height]),
+            Some(other) => {
+                wheel.warnings.push(format!(
+                    "D{} ({}) is approximated as a round aperture",
+                    code, other
+                ));
+                ("C", vec![width])
+            }
+        };
+        if wheel.apertures.iter().any(|a| a.code == code) {
+            wheel.warnings.push(format!(
+                "D{} is listed twice in the wheel; the first entry is used",
+                code
+            ));
+            continue;
+        }
+        wheel.apertures.push(Aperture {
+            code,
+            template: template.to_string(),
+            parameters,
+            attributes: Vec::new(),
+        });
+    }
+    wheel
+}
+
+fn format_number(value: f64) -> String {
+    let text = format!("{:.6}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Converts a 274-D file to 274-X by adding the format, unit and the aperture
+/// definitions from `wheel`. The data blocks are kept as they are.
+///
+/// Returns the new content and warnings for D-codes used but missing from the wheel.
+pub fn upconvert(
+    content: &str,
+    wheel: &Wheel,
+    options: &UpconvertOptions,
+) -> (String, Vec<String>) {
+    let mut out = String::new();
+    out.push_str("G04 Converted from RS-274-D by nextjlc*\n");
+    out.push_str(&format!("%{}*%\n", options.format.statement()));
+    out.push_str(match options.unit {
+        Unit::Inch => "%MOIN*%\n",
+        Unit::Millimeter => "%MOMM*%\n",
+    });
+    for aperture in &wheel.apertures {
+        let parameters: Vec<String> = aperture
+            .parameters
+            .iter()
+            .map(|v| format_number(*v))
+            .collect();
+        out.push_str(&format!(
+            "%ADD{}{},{}*%\n",
+            aperture.code,
+            aperture.template,
+            parameters.join("X")
+        ));
+    }
+
+    // M00 (program stop) ended many 274-D files; 274-X requires M02
+    let body = content.trim_end();
+    let body = body
+        .strip_suffix("M00*")
+        .or_else(|| body.strip_suffix("M02*"))
+        .unwrap_or(body);
+    out.push_str(body.trim_end());
+    out.push_str("\nM02*\n");
+
+    let mut warnings = Vec::new();
+    let mut missing: Vec<u32> = used_dcodes(body)
+        .into_iter()
+        .filter(|code| !wheel.apertures.iter().any(|a| a.code == *code))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    if !missing.is_empty() {
+        let codes: Vec<String> = missing.iter().map(|c| format!("D{}", c)).collect();
+        warnings.push(format!("The wheel has no size for {}", codes.join(", ")));
+    }
+    (out, warnings)
+}
+
+// D-codes 10 and up selected anywhere in the data.
+fn used_dcodes(content: &str) -> Vec<u32> {
+    content
+        .split('D')
+        .skip(1)
+        .filter_map(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .filter(|code| *code >= 10)
+        .collect()
+}
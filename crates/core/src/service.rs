@@ -0,0 +1,139 @@
+/* src/service.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::json::JsonValue;
+
+/// JLC PCB service tiers, from cheapest to most capable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JlcService {
+    /// Promotional 1-2 layer boards up to 100 x 100 mm
+    Economy,
+    /// Up to 4 layers with the standard drill and trace limits
+    Standard,
+    /// More layers, or drills and traces below the standard limits
+    Advanced,
+}
+
+impl JlcService {
+    pub fn id(&self) -> &'static str {
+        match self {
+            JlcService::Economy => "economy",
+            JlcService::Standard => "standard",
+            JlcService::Advanced => "advanced",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            JlcService::Economy => "economy 2-layer",
+            JlcService::Standard => "standard 4-layer",
+            JlcService::Advanced => "advanced",
+        }
+    }
+}
+
+// Limits of a tier: (service, max layers, max board side in mm, min drill
+// in mm, min trace in mm). A board goes to the first tier it fits.
+const TIERS: &[(JlcService, u32, f64, f64, f64)] = &[
+    (JlcService::Economy, 2, 100.0, 0.3, 0.127),
+    (JlcService::Standard, 4, 500.0, 0.2, 0.09),
+];
+
+/// Facts about a converted board that decide its service tier. Unknown
+/// facts do not limit the tier.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BoardFacts {
+    pub layer_count: Option<u32>,
+    /// Board width and height, in mm
+    pub size: Option<(f64, f64)>,
+    /// Smallest drill diameter, in mm
+    pub min_drill: Option<f64>,
+    /// Narrowest copper trace, in mm
+    pub min_trace: Option<f64>,
+}
+
+/// The cheapest service a board fits, and why a cheaper one does not
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceRecommendation {
+    pub service: JlcService,
+    pub facts: BoardFacts,
+    /// One entry per fact that rules out a cheaper tier
+    pub reasons: Vec<String>,
+}
+
+impl ServiceRecommendation {
+    pub fn to_json(&self) -> JsonValue {
+        let facts = &self.facts;
+        JsonValue::Object(vec![
+            ("service".to_string(), self.service.id().into()),
+            ("layer_count".to_string(), facts.layer_count.into()),
+            ("width".to_string(), facts.size.map(|(w, _)| w).into()),
+            ("height".to_string(), facts.size.map(|(_, h)| h).into()),
+            ("min_drill".to_string(), facts.min_drill.into()),
+            ("min_trace".to_string(), facts.min_trace.into()),
+            (
+                "reasons".to_string(),
+                JsonValue::Array(self.reasons.iter().map(|r| r.as_str().into()).collect()),
+            ),
+        ])
+    }
+}
+
+// Why `facts` do not fit a tier with these limits; empty when they do
+fn exceeded(
+    facts: &BoardFacts,
+    (service, layers, side, drill, trace): (JlcService, u32, f64, f64, f64),
+) -> Vec<String> {
+    let tier = service.description();
+    let mut reasons = Vec::new();
+    if let Some(count) = facts.layer_count.filter(|&n| n > layers) {
+        reasons.push(format!(
+            "{} copper layers exceed the {} limit of {}",
+            count, tier, layers
+        ));
+    }
+    if let Some((width, height)) = facts.size.filter(|(w, h)| w.max(*h) > side) {
+        reasons.push(format!(
+            "the {:.1} x {:.1} mm board exceeds the {} limit of {} x {} mm",
+            width, height, tier, side, side
+        ));
+    }
+    if let Some(min) = facts.min_drill.filter(|&d| d < drill) {
+        reasons.push(format!(
+            "the {:.3} mm drill is below the {} minimum of {} mm",
+            min, tier, drill
+        ));
+    }
+    if let Some(min) = facts.min_trace.filter(|&t| t < trace) {
+        reasons.push(format!(
+            "the {:.3} mm trace is below the {} minimum of {} mm",
+            min, tier, trace
+        ));
+    }
+    reasons
+}
+
+/// Recommends the cheapest JLC service whose limits `facts` fit.
+pub fn recommend_service(facts: &BoardFacts) -> ServiceRecommendation {
+    let mut reasons = Vec::new();
+    for &tier in TIERS {
+        let exceeded = exceeded(facts, tier);
+        if exceeded.is_empty() {
+            return ServiceRecommendation {
+                service: tier.0,
+                facts: facts.clone(),
+                reasons,
+            };
+        }
+        reasons = exceeded;
+    }
+    ServiceRecommendation {
+        service: JlcService::Advanced,
+        facts: facts.clone(),
+        reasons,
+    }
+}
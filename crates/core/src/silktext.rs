@@ -0,0 +1,438 @@
+/* src/silktext.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::{BoundingBox, Point, flatten_arc};
+use crate::gerber::{Command, GerberLayer, Interpolation};
+use once_cell::sync::Lazy;
+
+// Glyphs taller or wider than this (mm) are board graphics, not text
+const MAX_GLYPH_SIZE_MM: f64 = 10.0;
+// Text lines lower than this (mm) are too small to read reliably
+const MIN_TEXT_HEIGHT_MM: f64 = 0.4;
+// Glyph templates are drawn on a grid 6 units high
+const GLYPH_UNITS: f64 = 6.0;
+// Spacing of the points sampled along strokes, in glyph units
+const SAMPLE_STEP: f64 = 0.25;
+// Largest mean distance (glyph units) for a glyph to count as recognized
+const MATCH_THRESHOLD: f64 = 0.6;
+// Chord tolerance for arcs in stroke text, in mm
+const ARC_TOLERANCE_MM: f64 = 0.01;
+// Fewest glyphs matching better as drawn than mirrored for a line to count
+// as unmirrored text
+const MIN_ORIENTED_GLYPHS: usize = 2;
+// How much closer (glyph units) a glyph must match one way than the other
+const MIRROR_MARGIN: f64 = 0.05;
+
+// Strokes of the recognized characters, modelled on the sans-serif stroke
+// font Altium uses by default. Polylines are separated by ';', points by ' '.
+const GLYPHS: &[(char, &str)] = &[
+    ('0', "0.5,1 0.5,5 1.5,6 2.5,6 3.5,5 3.5,1 2.5,0 1.5,0 0.5,1"),
+    ('1', "1,5 2,6 2,0"),
+    ('2', "0,5 1,6 3,6 4,5 4,4 0,0 4,0"),
+    ('3', "0,5 1,6 3,6 4,5 4,4 3,3 1,3; 3,3 4,2 4,1 3,0 1,0 0,1"),
+    ('4', "3,0 3,6 0,2 4,2"),
+    ('5', "4,6 0,6 0,3 3,3 4,2 4,1 3,0 0,0"),
+    ('6', "4,5 3,6 1,6 0,5 0,1 1,0 3,0 4,1 4,2 3,3 0,3"),
+    ('7', "0,6 4,6 1,0"),
+    (
+        '8',
+        "1,3 0,4 0,5 1,6 3,6 4,5 4,4 3,3 1,3 0,2 0,1 1,0 3,0 4,1 4,2 3,3",
+    ),
+    ('9', "0,1 1,0 3,0 4,1 4,5 3,6 1,6 0,5 0,4 1,3 4,3"),
+    ('A', "0,0 2,6 4,0; 0.7,2 3.3,2"),
+    ('B', "0,0 0,6 3,6 4,5 4,4 3,3 0,3; 3,3 4,2 4,1 3,0 0,0"),
+    ('C', "4,5 3,6 1,6 0,5 0,1 1,0 3,0 4,1"),
+    ('D', "0,0 0,6 3,6 4,5 4,1 3,0 0,0"),
+    ('E', "4,6 0,6 0,0 4,0; 0,3 3,3"),
+    ('F', "4,6 0,6 0,0; 0,3 3,3"),
+    ('G', "4,5 3,6 1,6 0,5 0,1 1,0 3,0 4,1 4,3 2,3"),
+    ('H', "0,0 0,6; 4,0 4,6; 0,3 4,3"),
+    ('I', "1,6 3,6; 2,6 2,0; 1,0 3,0"),
+    ('J', "4,6 4,1 3,0 1,0 0,1"),
+    ('K', "0,0 0,6; 4,6 0,2; 1,3 4,0"),
+    ('L', "0,6 0,0 4,0"),
+    ('M', "0,0 0,6 2,3 4,6 4,0"),
+    ('N', "0,0 0,6 4,0 4,6"),
+    ('O', "0,1 0,5 1,6 4,6 5,5 5,1 4,0 1,0 0,1"),
+    ('P', "0,0 0,6 3,6 4,5 4,4 3,3 0,3"),
+    ('Q', "0,1 0,5 1,6 4,6 5,5 5,1 4,0 1,0 0,1; 3,2 5,0"),
+    ('R', "0,0 0,6 3,6 4,5 4,4 3,3 0,3; 2,3 4,0"),
+    ('S', "4,5 3,6 1,6 0,5 0,4 1,3 3,3 4,2 4,1 3,0 1,0 0,1"),
+    ('T', "0,6 4,6; 2,6 2,0"),
+    ('U', "0,6 0,1 1,0 3,0 4,1 4,6"),
+    ('V', "0,6 2,0 4,6"),
+    ('W', "0,6 1,0 2,4 3,0 4,6"),
+    ('X', "0,0 4,6; 0,6 4,0"),
+    ('Y', "0,6 2,3 4,6; 2,3 2,0"),
+    ('Z', "0,6 4,6 0,0 4,0"),
+    ('-', "0,3 3,3"),
+    ('.', "0,0 0.3,0"),
+    ('/', "0,0 3,6"),
+];
+
+// Templates as sampled points, parsed once.
+static TEMPLATES: Lazy<Vec<(char, Vec<Point>)>> = Lazy::new(|| {
+    GLYPHS
+        .iter()
+        .map(|(c, strokes)| {
+            let polylines: Vec<Vec<Point>> = strokes
+                .split(';')
+                .map(|stroke| {
+                    stroke
+                        .split_whitespace()
+                        .filter_map(|p| {
+                            let (x, y) = p.split_once(',')?;
+                            Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+                        })
+                        .collect()
+                })
+                .collect();
+            // Glyphs are matched from their left edge
+            let left = polylines
+                .iter()
+                .flatten()
+                .map(|p| p.x)
+                .fold(f64::INFINITY, f64::min);
+            let polylines: Vec<Vec<Point>> = polylines
+                .iter()
+                .map(|polyline| {
+                    polyline
+                        .iter()
+                        .map(|p| Point::new(p.x - left, p.y))
+                        .collect()
+                })
+                .collect();
+            (*c, sample(&polylines))
+        })
+        .collect()
+});
+
+/// A line of text read from stroke geometry
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    /// Recognized characters; '?' marks a glyph that matched no template
+    pub text: String,
+    pub bounds: BoundingBox,
+    /// Height of the tallest glyph, in mm
+    pub height: f64,
+}
+
+struct Glyph {
+    bounds: BoundingBox,
+    polylines: Vec<Vec<Point>>,
+}
+
+/// Reads horizontal stroke text from a silkscreen layer, best effort.
+///
+/// Only text drawn with strokes in the Altium default font style is read;
+/// TrueType text (filled regions), rotated text and logos are not. Bottom
+/// layers are viewed from the top, so pass `mirrored` to read them.
+pub fn extract_text(layer: &GerberLayer, mirrored: bool) -> Vec<TextLine> {
+    read_lines(layer, mirrored)
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect()
+}
+
+// The text lines of a layer, each with the distance of every glyph, left to
+// right, to its closest template
+fn read_lines(layer: &GerberLayer, mirrored: bool) -> Vec<(TextLine, Vec<f64>)> {
+    let mut glyphs = collect_glyphs(layer, mirrored);
+    glyphs.sort_by(|a, b| a.bounds.min_x.total_cmp(&b.bounds.min_x));
+
+    // Assign glyphs to lines by their vertical position, left to right
+    let mut lines: Vec<Vec<Glyph>> = Vec::new();
+    for glyph in glyphs {
+        let center = glyph.bounds.center().y;
+        let line = lines.iter_mut().find(|line| {
+            let bounds = line_bounds(line);
+            let height = bounds.height().max(MIN_TEXT_HEIGHT_MM);
+            (center - bounds.center().y).abs() < height * 0.6
+                && glyph.bounds.min_x - bounds.max_x < height * 1.5
+        });
+        match line {
+            Some(line) => line.push(glyph),
+            None => lines.push(vec![glyph]),
+        }
+    }
+
+    lines
+        .iter()
+        .filter_map(|line| {
+            let bounds = line_bounds(line);
+            let height = bounds.height();
+            if height < MIN_TEXT_HEIGHT_MM {
+                return None;
+            }
+            // A space is a step between glyph centers well above the usual advance
+            let centers: Vec<f64> = line.iter().map(|g| g.bounds.center().x).collect();
+            let mut advances: Vec<f64> = centers.windows(2).map(|w| w[1] - w[0]).collect();
+            advances.sort_by(f64::total_cmp);
+            let advance = advances.get(advances.len() / 2).copied().unwrap_or(height);
+            let mut text = String::new();
+            let mut distances = Vec::new();
+            for (i, glyph) in line.iter().enumerate() {
+                if i > 0 && centers[i] - centers[i - 1] > advance * 1.5 {
+                    text.push(' ');
+                }
+                let (c, d) = recognize(glyph, bounds.min_y, height);
+                text.push(c);
+                distances.push(d);
+            }
+            Some((
+                TextLine {
+                    text,
+                    bounds,
+                    height,
+                },
+                distances,
+            ))
+        })
+        .collect()
+}
+
+/// Whether `marking` appears in any of the lines, ignoring case and spaces.
+pub(crate) fn contains_marking(lines: &[TextLine], marking: &str) -> bool {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_uppercase)
+            .collect::<String>()
+    };
+    let marking = normalize(marking);
+    !marking.is_empty()
+        && lines
+            .iter()
+            .any(|line| normalize(&line.text).contains(&marking))
+}
+
+/// Stroke text on a bottom layer that reads correctly as drawn, without
+/// mirroring. Bottom layers are drawn as seen from the top, so such text
+/// comes out mirrored on the board, which usually means the layer was
+/// exported with the wrong mirror setting.
+///
+/// A line is reported when its recognized glyphs mostly match the font
+/// clearly better as drawn than mirrored. Symmetric characters, as in "HOT",
+/// match equally both ways and do not count.
+pub(crate) fn unmirrored_text(layer: &GerberLayer) -> Vec<TextLine> {
+    // Mirrored lines, with their bounds back in layer coordinates and their
+    // glyphs in layer order
+    let mirrored: Vec<(BoundingBox, Vec<f64>)> = read_lines(layer, true)
+        .into_iter()
+        .map(|(line, mut distances)| {
+            let b = line.bounds;
+            let bounds = BoundingBox {
+                min_x: -b.max_x,
+                min_y: b.min_y,
+                max_x: -b.min_x,
+                max_y: b.max_y,
+            };
+            distances.reverse();
+            (bounds, distances)
+        })
+        .collect();
+
+    read_lines(layer, false)
+        .into_iter()
+        .filter(|(line, distances)| {
+            // The same glyphs read mirrored; lines grouped differently are left alone
+            let Some((_, flipped)) = mirrored.iter().find(|(bounds, flipped)| {
+                bounds.intersects(&line.bounds) && flipped.len() == distances.len()
+            }) else {
+                return false;
+            };
+            let glyphs = line.text.chars().filter(|c| !c.is_whitespace());
+            let (mut as_drawn, mut as_mirrored) = (0, 0);
+            for ((c, drawn), flipped) in glyphs.zip(distances).zip(flipped) {
+                if c != '?' && drawn + MIRROR_MARGIN < *flipped {
+                    as_drawn += 1;
+                } else if flipped + MIRROR_MARGIN < *drawn {
+                    as_mirrored += 1;
+                }
+            }
+            as_drawn >= MIN_ORIENTED_GLYPHS && as_drawn > 2 * as_mirrored
+        })
+        .map(|(line, _)| line)
+        .collect()
+}
+
+fn line_bounds(line: &[Glyph]) -> BoundingBox {
+    let mut bounds = line[0].bounds;
+    for glyph in &line[1..] {
+        bounds.include_box(&glyph.bounds);
+    }
+    bounds
+}
+
+// Groups the drawn strokes into glyphs: strokes whose boxes touch, within
+// the stroke width, belong to the same glyph.
+fn collect_glyphs(layer: &GerberLayer, mirrored: bool) -> Vec<Glyph> {
+    let flip = |p: Point| if mirrored { Point::new(-p.x, p.y) } else { p };
+    let mut strokes: Vec<(Vec<Point>, f64)> = Vec::new();
+    let mut width = 0.0;
+    let mut in_region = false;
+    for command in &layer.commands {
+        match command {
+            Command::SelectAperture(code) => {
+                width = layer
+                    .apertures
+                    .get(code)
+                    .filter(|a| a.template == "C")
+                    .map_or(0.0, |a| a.half_extents().0 * 2.0);
+            }
+            Command::RegionStart => in_region = true,
+            Command::RegionEnd => in_region = false,
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } if !in_region && width > 0.0 => {
+                let path = match (interpolation, center) {
+                    (Interpolation::Linear, _) | (_, None) => vec![*from, *to],
+                    (kind, Some(c)) => flatten_arc(
+                        *from,
+                        *to,
+                        *c,
+                        *kind == Interpolation::Clockwise,
+                        ARC_TOLERANCE_MM,
+                    ),
+                };
+                strokes.push((path.into_iter().map(flip).collect(), width));
+            }
+            _ => {}
+        }
+    }
+
+    let bounds: Vec<BoundingBox> = strokes
+        .iter()
+        .map(|(path, width)| {
+            let mut b = BoundingBox::from_point(path[0]);
+            for p in path {
+                b.include_point(*p);
+            }
+            b.expanded(width / 2.0)
+        })
+        .collect();
+
+    // Union-find over touching strokes
+    let mut parent: Vec<usize> = (0..strokes.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut order: Vec<usize> = (0..strokes.len()).collect();
+    order.sort_by(|a, b| bounds[*a].min_x.total_cmp(&bounds[*b].min_x));
+    for (k, &i) in order.iter().enumerate() {
+        for &j in &order[k + 1..] {
+            if bounds[j].min_x > bounds[i].max_x {
+                break;
+            }
+            if bounds[i].intersects(&bounds[j]) {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[ri] = rj;
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Glyph)> = Vec::new();
+    for (i, (path, _)) in strokes.iter().enumerate() {
+        let r = root(&mut parent, i);
+        let mut stroke_bounds = BoundingBox::from_point(path[0]);
+        for p in path {
+            stroke_bounds.include_point(*p);
+        }
+        match groups.iter_mut().find(|(g, _)| *g == r) {
+            Some((_, glyph)) => {
+                glyph.bounds.include_box(&stroke_bounds);
+                glyph.polylines.push(path.clone());
+            }
+            None => groups.push((
+                r,
+                Glyph {
+                    bounds: stroke_bounds,
+                    polylines: vec![path.clone()],
+                },
+            )),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, glyph)| glyph)
+        .filter(|g| g.bounds.width() <= MAX_GLYPH_SIZE_MM && g.bounds.height() <= MAX_GLYPH_SIZE_MM)
+        .collect()
+}
+
+// Points every SAMPLE_STEP along the polylines, including their vertices.
+fn sample(polylines: &[Vec<Point>]) -> Vec<Point> {
+    let mut points = Vec::new();
+    for polyline in polylines {
+        points.extend(polyline.first().copied());
+        for pair in polyline.windows(2) {
+            let steps = (pair[0].distance(&pair[1]) / SAMPLE_STEP).ceil().max(1.0) as usize;
+            for i in 1..=steps {
+                let t = i as f64 / steps as f64;
+                points.push(Point::new(
+                    pair[0].x + (pair[1].x - pair[0].x) * t,
+                    pair[0].y + (pair[1].y - pair[0].y) * t,
+                ));
+            }
+        }
+    }
+    points
+}
+
+// Mean distance from each point of `a` to the nearest point of `b`.
+fn mean_distance(a: &[Point], b: &[Point]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return f64::INFINITY;
+    }
+    let total: f64 = a
+        .iter()
+        .map(|p| {
+            b.iter()
+                .map(|q| p.distance(q))
+                .fold(f64::INFINITY, f64::min)
+        })
+        .sum();
+    total / a.len() as f64
+}
+
+// Matches a glyph against the templates, after scaling the line height to
+// GLYPH_UNITS and moving the glyph to the left edge and the line baseline.
+// Returns the character, '?' when none matches, and the closest distance.
+fn recognize(glyph: &Glyph, baseline: f64, line_height: f64) -> (char, f64) {
+    let scale = GLYPH_UNITS / line_height;
+    let normalized: Vec<Vec<Point>> = glyph
+        .polylines
+        .iter()
+        .map(|polyline| {
+            polyline
+                .iter()
+                .map(|p| Point::new((p.x - glyph.bounds.min_x) * scale, (p.y - baseline) * scale))
+                .collect()
+        })
+        .collect();
+    let points = sample(&normalized);
+
+    let (c, score) = TEMPLATES
+        .iter()
+        .map(|(c, template)| {
+            let score = (mean_distance(&points, template) + mean_distance(template, &points)) / 2.0;
+            (*c, score)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or(('?', f64::INFINITY));
+    if score <= MATCH_THRESHOLD {
+        (c, score)
+    } else {
+        ('?', score)
+    }
+}
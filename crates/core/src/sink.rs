@@ -0,0 +1,175 @@
+/* src/sink.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use std::fmt;
+use std::io::{self, Write};
+
+/// Error produced when a sink cannot take a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkError {
+    pub message: String,
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SinkError {}
+
+impl SinkError {
+    pub fn new(message: impl Into<String>) -> Self {
+        SinkError {
+            message: message.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for SinkError {
+    fn from(error: std::io::Error) -> Self {
+        SinkError::new(error.to_string())
+    }
+}
+
+/// A destination for converted files, such as memory, a zip archive or a
+/// directory. Files are handed over one at a time.
+pub trait OutputSink {
+    fn write_file(&mut self, name: &str, content: &[u8]) -> Result<(), SinkError>;
+
+    /// Writes a file whose content `write` produces, e.g. with
+    /// [`crate::drill::write_jlc_excellon`]. Sinks that can pass it on as it
+    /// is written, such as [`DirectorySink`], do; others collect it first.
+    /// [`ZipSink`] collects each file, so its local header carries the size
+    /// and checksum that streaming unzippers need.
+    fn write_file_with(
+        &mut self,
+        name: &str,
+        write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<(), SinkError> {
+        let mut content = Vec::new();
+        write(&mut content)?;
+        self.write_file(name, &content)
+    }
+
+    /// Called once after the last file, e.g. to write a zip directory.
+    fn finish(&mut self) -> Result<(), SinkError> {
+        Ok(())
+    }
+}
+
+/// Files kept in memory, in the order they were written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemorySink {
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn write_file(&mut self, name: &str, content: &[u8]) -> Result<(), SinkError> {
+        self.files.push((name.to_string(), content.to_vec()));
+        Ok(())
+    }
+}
+
+/// A zip archive streamed to any writer, e.g. an HTTP response body; only
+/// its directory is held in memory. Use a `Vec<u8>` to build it in memory.
+#[cfg(feature = "archive")]
+pub struct ZipSink<W: std::io::Write> {
+    writer: Option<crate::zip::ZipStreamWriter<W>>,
+    out: Option<W>,
+}
+
+#[cfg(feature = "archive")]
+impl<W: std::io::Write> ZipSink<W> {
+    pub fn new(out: W) -> Self {
+        ZipSink {
+            writer: Some(crate::zip::ZipStreamWriter::new(out)),
+            out: None,
+        }
+    }
+
+    /// The writer, once [`OutputSink::finish`] has written the archive.
+    pub fn into_inner(self) -> Option<W> {
+        self.out
+    }
+}
+
+#[cfg(feature = "archive")]
+impl<W: std::io::Write> OutputSink for ZipSink<W> {
+    fn write_file(&mut self, name: &str, content: &[u8]) -> Result<(), SinkError> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| SinkError::new("The zip archive is already finished"))?;
+        Ok(writer.add_file(name, content)?)
+    }
+
+    fn finish(&mut self) -> Result<(), SinkError> {
+        if let Some(writer) = self.writer.take() {
+            self.out = Some(writer.finish()?);
+        }
+        Ok(())
+    }
+}
+
+/// Writes files into a directory, creating it and any subdirectories.
+/// Not available on wasm32, which has no file system.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectorySink {
+    root: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DirectorySink {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        DirectorySink { root: root.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DirectorySink {
+    // Path of output `name`, with its directories created
+    fn create_path(&self, name: &str) -> Result<std::path::PathBuf, SinkError> {
+        // Output names come from the rename rules, but never leave the root
+        if name
+            .split(['/', '\\'])
+            .any(|part| part == ".." || part.is_empty())
+        {
+            return Err(SinkError::new(format!("Invalid output name {}", name)));
+        }
+        let path = self.root.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl OutputSink for DirectorySink {
+    fn write_file(&mut self, name: &str, content: &[u8]) -> Result<(), SinkError> {
+        Ok(std::fs::write(self.create_path(name)?, content)?)
+    }
+
+    fn write_file_with(
+        &mut self,
+        name: &str,
+        write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<(), SinkError> {
+        let file = std::fs::File::create(self.create_path(name)?)?;
+        let mut out = io::BufWriter::new(file);
+        write(&mut out)?;
+        Ok(out.flush()?)
+    }
+}
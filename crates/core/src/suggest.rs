@@ -0,0 +1,152 @@
+/* src/suggest.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::autofix::FixLevel;
+use crate::gerber;
+use crate::json::JsonValue;
+use crate::layer_set::{LayerFile, LayerSet};
+use crate::pipeline::ConvertOptions;
+use crate::rename::{self, LogicalLayer};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// This regex matches names of files that often hold the board outline under
+// another layer: mechanical layers, keep-out layers and board profiles.
+static OUTLINE_CANDIDATE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\.(gm\d+|gko|gkl|gpt|gpb)$|mech|keep.?out|edge|outline|profile")
+        .expect("Invalid outline candidate regex")
+});
+
+// Missing layers the suggestions know how to fill, with the name shown to users.
+const FIXABLE_LAYERS: &[(LogicalLayer, &str)] = &[
+    (LogicalLayer::BoardOutline, "the board outline"),
+    (LogicalLayer::TopLayer, "the top copper"),
+    (LogicalLayer::TopSolderMask, "the top solder mask"),
+    (LogicalLayer::BottomSolderMask, "the bottom solder mask"),
+];
+
+/// A change to the conversion options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixAction {
+    /// Use an input file, named as uploaded, as the given layer
+    AssignLayer { file: String, layer: LogicalLayer },
+    /// Allow the pipeline to repair more on its own
+    SetAutoFix(FixLevel),
+}
+
+/// A one-click fix for a diagnostic. Applying it to the options of a run and
+/// running the pipeline again resolves the diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// Button text, e.g. "Treat board.GM13 as the board outline"
+    pub label: String,
+    pub action: FixAction,
+}
+
+impl Suggestion {
+    /// Adds the override to `options`, replacing any earlier one for the same file or layer.
+    pub fn apply(&self, options: &mut ConvertOptions) {
+        match &self.action {
+            FixAction::AssignLayer { file, layer } => {
+                options
+                    .layer_overrides
+                    .retain(|(f, l)| f != file && l != layer);
+                options.layer_overrides.push((file.clone(), *layer));
+            }
+            FixAction::SetAutoFix(level) => options.auto_fix = options.auto_fix.max(*level),
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut entries = vec![("label".to_string(), self.label.as_str().into())];
+        match &self.action {
+            FixAction::AssignLayer { file, layer } => {
+                entries.push(("action".to_string(), "assign-layer".into()));
+                entries.push(("file".to_string(), file.as_str().into()));
+                entries.push(("layer".to_string(), layer.logical_name().into()));
+            }
+            FixAction::SetAutoFix(level) => {
+                let level = match level {
+                    FixLevel::None => "none",
+                    FixLevel::Safe => "safe",
+                    FixLevel::Aggressive => "aggressive",
+                };
+                entries.push(("action".to_string(), "set-auto-fix".into()));
+                entries.push(("level".to_string(), level.into()));
+            }
+        }
+        JsonValue::Object(entries)
+    }
+}
+
+// Layers that kept a name no JLC layer uses.
+fn unplaced_layers(set: &LayerSet) -> impl Iterator<Item = &LayerFile> {
+    set.layers
+        .iter()
+        .filter(|f| rename::layer_for_final_name(&f.name).is_none())
+}
+
+// Whether an unplaced file could hold `layer`, by X2 file function or by name.
+fn is_candidate(file: &LayerFile, layer: LogicalLayer) -> bool {
+    let by_function = gerber::file_function(&file.content)
+        .and_then(|fields| rename::logical_name_from_file_function(&fields))
+        .is_some_and(|name| name == layer.logical_name());
+    by_function
+        || (layer == LogicalLayer::BoardOutline
+            && OUTLINE_CANDIDATE_REGEX.is_match(&file.original_name))
+}
+
+/// Suggests fixes for one validation error of a converted set, run with `auto_fix`.
+/// Returns nothing when the error can only be fixed in the design.
+pub fn suggest_fixes(set: &LayerSet, error: &str, auto_fix: FixLevel) -> Vec<Suggestion> {
+    let Some(&(layer, layer_label)) = FIXABLE_LAYERS
+        .iter()
+        .find(|(l, _)| error.contains(&format!("'{}'", l.logical_name())))
+    else {
+        return Vec::new();
+    };
+
+    let mut suggestions: Vec<Suggestion> = unplaced_layers(set)
+        .filter(|f| is_candidate(f, layer))
+        .map(|f| Suggestion {
+            label: format!("Treat {} as {}", f.original_name, layer_label),
+            action: FixAction::AssignLayer {
+                file: f.original_name.clone(),
+                layer,
+            },
+        })
+        .collect();
+
+    if auto_fix < FixLevel::Aggressive {
+        let has = |copper: LogicalLayer| {
+            set.layers
+                .iter()
+                .any(|f| rename::layer_for_final_name(&f.name) == Some(copper))
+        };
+        let label = match layer {
+            LogicalLayer::BoardOutline
+                if has(LogicalLayer::TopLayer) || has(LogicalLayer::BottomLayer) =>
+            {
+                Some("Draw a rectangular outline around the copper")
+            }
+            LogicalLayer::TopSolderMask if has(LogicalLayer::TopLayer) => {
+                Some("Generate the top solder mask from the copper pads")
+            }
+            LogicalLayer::BottomSolderMask if has(LogicalLayer::BottomLayer) => {
+                Some("Generate the bottom solder mask from the copper pads")
+            }
+            _ => None,
+        };
+        if let Some(label) = label {
+            suggestions.push(Suggestion {
+                label: label.to_string(),
+                action: FixAction::SetAutoFix(FixLevel::Aggressive),
+            });
+        }
+    }
+    suggestions
+}
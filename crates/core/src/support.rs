@@ -0,0 +1,316 @@
+/* src/support.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::compare;
+use crate::drill::{self, DrillCommand};
+use crate::gerber::{self, Unit};
+use crate::json::JsonValue;
+use crate::layer_set::LayerSet;
+use crate::validation::ValidationReport;
+use crate::zip::ZipWriter;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+
+// This regex matches a Gerber body line: an aperture selection or a coordinate word.
+static GERBER_BODY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:G54)?D\d+\*|^[XYIJ][+-]?\d").expect("Invalid body regex"));
+
+// This regex matches the value of a project id attribute (X2 and KiCad drill comments).
+static PROJECT_ID_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(TF\.ProjectId,)[^*]*").expect("Invalid project id regex"));
+
+// This regex matches Windows and Unix style absolute paths inside comments.
+static PATH_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[A-Za-z]:\\[^\s*]*|/(?:[^\s/*]+/)+[^\s*]*").expect("Invalid path regex")
+});
+
+// Headers longer than this are truncated; real headers are far shorter.
+const MAX_HEADER_LINES: usize = 200;
+
+// Only the first few unmatched primitives of each layer are listed in the diff.
+const MAX_LISTED_PRIMITIVES: usize = 20;
+
+/// Options for [`make_report_bundle_with_options`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundleOptions {
+    /// Also include the complete input and output files. Off by default because
+    /// the geometry is the user's design.
+    pub include_geometry: bool,
+}
+
+/// Builds a redacted bug-report zip with the default options.
+pub fn make_report_bundle(
+    inputs: &[(String, String)],
+    outputs: &LayerSet,
+    report: &Result<ValidationReport, Vec<String>>,
+) -> Vec<u8> {
+    make_report_bundle_with_options(inputs, outputs, report, &BundleOptions::default())
+}
+
+/// Builds a zip that users can attach to an issue.
+///
+/// The bundle holds `manifest.json`, `report.json`, `diffs.json`, and a redacted
+/// header plus statistics for every input and output file. Project ids and file
+/// system paths are removed from the headers.
+pub fn make_report_bundle_with_options(
+    inputs: &[(String, String)],
+    outputs: &LayerSet,
+    report: &Result<ValidationReport, Vec<String>>,
+    options: &BundleOptions,
+) -> Vec<u8> {
+    let mut zip = ZipWriter::new();
+    let mut manifest_inputs = Vec::new();
+    let mut manifest_outputs = Vec::new();
+
+    for (index, (name, content)) in inputs.iter().enumerate() {
+        let content = content.replace("\r\n", "\n");
+        let entry = format!("inputs/{:02}", index);
+        manifest_inputs.push(describe_file(&mut zip, &entry, name, &content, options));
+    }
+    let files = outputs
+        .files()
+        .map(|f| (&f.name, &f.original_name, Cow::Borrowed(f.content.as_str())))
+        .chain(
+            outputs
+                .merged_drills
+                .iter()
+                .map(|d| (&d.name, &d.original_name, Cow::Owned(d.content()))),
+        );
+    for (index, (name, original_name, content)) in files.enumerate() {
+        let entry = format!("outputs/{:02}", index);
+        let mut description = describe_file(&mut zip, &entry, name, &content, options);
+        if let JsonValue::Object(fields) = &mut description {
+            fields.push(("original_name".to_string(), original_name.as_str().into()));
+        }
+        manifest_outputs.push(description);
+    }
+
+    let manifest = JsonValue::Object(vec![
+        ("generator".to_string(), "nextjlc".into()),
+        ("version".to_string(), env!("CARGO_PKG_VERSION").into()),
+        (
+            "primary_software".to_string(),
+            outputs.metadata.primary_software.into(),
+        ),
+        (
+            "include_geometry".to_string(),
+            options.include_geometry.into(),
+        ),
+        ("inputs".to_string(), JsonValue::Array(manifest_inputs)),
+        ("outputs".to_string(), JsonValue::Array(manifest_outputs)),
+    ]);
+    zip.add_file("manifest.json", manifest.to_pretty_string().as_bytes());
+
+    let strings =
+        |items: &[String]| JsonValue::Array(items.iter().map(|s| s.as_str().into()).collect());
+    let report = match report {
+        Ok(report) => JsonValue::Object(vec![
+            ("valid".to_string(), true.into()),
+            ("layer_count".to_string(), report.layer_count.into()),
+            ("warnings".to_string(), strings(&report.warnings)),
+            ("errors".to_string(), JsonValue::Array(Vec::new())),
+        ]),
+        Err(errors) => JsonValue::Object(vec![
+            ("valid".to_string(), false.into()),
+            ("layer_count".to_string(), JsonValue::Null),
+            ("warnings".to_string(), strings(&outputs.metadata.warnings)),
+            ("errors".to_string(), strings(errors)),
+        ]),
+    };
+    zip.add_file("report.json", report.to_pretty_string().as_bytes());
+
+    let diffs = layer_diffs(inputs, outputs);
+    zip.add_file("diffs.json", diffs.to_pretty_string().as_bytes());
+
+    zip.finish()
+}
+
+/// Adds the header and stats of one file to the bundle and returns its manifest entry.
+fn describe_file(
+    zip: &mut ZipWriter,
+    entry: &str,
+    name: &str,
+    content: &str,
+    options: &BundleOptions,
+) -> JsonValue {
+    let is_drill = drill::is_drill_file_with_content(name, content);
+    let stats = if is_drill {
+        drill_stats(content)
+    } else {
+        gerber_stats(content)
+    };
+
+    zip.add_file(
+        &format!("{}.header.txt", entry),
+        redacted_header(content, is_drill).as_bytes(),
+    );
+    zip.add_file(
+        &format!("{}.stats.json", entry),
+        stats.to_pretty_string().as_bytes(),
+    );
+    if options.include_geometry {
+        zip.add_file(&format!("{}.data", entry), content.as_bytes());
+    }
+
+    JsonValue::Object(vec![
+        ("entry".to_string(), entry.into()),
+        ("name".to_string(), name.into()),
+        (
+            "kind".to_string(),
+            if is_drill { "drill" } else { "gerber" }.into(),
+        ),
+        ("size".to_string(), content.len().into()),
+        ("lines".to_string(), content.lines().count().into()),
+        (
+            "software".to_string(),
+            crate::file_type::identify_software(content).into(),
+        ),
+    ])
+}
+
+/// Returns the header of a Gerber or drill file with identifying values removed.
+fn redacted_header(content: &str, is_drill: bool) -> String {
+    let mut header = String::new();
+    let mut in_drill_header = false;
+    for line in content.lines().take(MAX_HEADER_LINES) {
+        let trimmed = line.trim();
+        if is_drill {
+            // Everything up to the end of the M48 header, or up to the first coordinate
+            if trimmed == "M48" {
+                in_drill_header = true;
+            } else if trimmed.starts_with('X') || trimmed.starts_with('Y') {
+                break;
+            }
+        } else if GERBER_BODY_REGEX.is_match(trimmed) {
+            break;
+        }
+
+        let line = PROJECT_ID_REGEX.replace_all(line, "${1}[redacted]");
+        let line = PATH_REGEX.replace_all(&line, "[path]");
+        header.push_str(&line);
+        header.push('\n');
+
+        if in_drill_header && (trimmed == "%" || trimmed == "M95") {
+            break;
+        }
+    }
+    header
+}
+
+fn gerber_stats(content: &str) -> JsonValue {
+    let stats = gerber::layer_stats(content);
+    let bounds = stats.bounds.map(|b| {
+        JsonValue::Object(vec![
+            ("min_x".to_string(), b.min_x.into()),
+            ("min_y".to_string(), b.min_y.into()),
+            ("max_x".to_string(), b.max_x.into()),
+            ("max_y".to_string(), b.max_y.into()),
+        ])
+    });
+    JsonValue::Object(vec![
+        (
+            "unit".to_string(),
+            match stats.unit {
+                Unit::Millimeter => "mm",
+                Unit::Inch => "inch",
+            }
+            .into(),
+        ),
+        ("aperture_count".to_string(), stats.aperture_count.into()),
+        ("macro_count".to_string(), stats.macro_count.into()),
+        ("flash_count".to_string(), stats.flash_count.into()),
+        ("draw_count".to_string(), stats.draw_count.into()),
+        ("arc_count".to_string(), stats.arc_count.into()),
+        ("region_count".to_string(), stats.region_count.into()),
+        ("bounds".to_string(), bounds.unwrap_or(JsonValue::Null)),
+    ])
+}
+
+fn drill_stats(content: &str) -> JsonValue {
+    let drill_file = drill::parse_excellon(content);
+    let tools: Vec<JsonValue> = drill_file
+        .operations
+        .iter()
+        .map(|op| {
+            let slots = op
+                .commands
+                .iter()
+                .filter(|c| matches!(c, DrillCommand::Route { .. }))
+                .count();
+            JsonValue::Object(vec![
+                ("diameter".to_string(), op.diameter.into()),
+                (
+                    "plated".to_string(),
+                    (op.hole_type == drill::HoleType::Plated).into(),
+                ),
+                ("holes".to_string(), (op.commands.len() - slots).into()),
+                ("slots".to_string(), slots.into()),
+            ])
+        })
+        .collect();
+    JsonValue::Object(vec![
+        ("tools".to_string(), JsonValue::Array(tools)),
+        (
+            "warnings".to_string(),
+            JsonValue::Array(
+                drill_file
+                    .warnings
+                    .iter()
+                    .map(|w| w.as_str().into())
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+/// Compares every output Gerber layer with the input it came from.
+fn layer_diffs(inputs: &[(String, String)], outputs: &LayerSet) -> JsonValue {
+    let primitive_list = |primitives: &[compare::Primitive]| {
+        JsonValue::Array(
+            primitives
+                .iter()
+                .take(MAX_LISTED_PRIMITIVES)
+                .map(|p| {
+                    JsonValue::Object(vec![
+                        ("kind".to_string(), format!("{:?}", p.kind).into()),
+                        ("x".to_string(), p.position.x.into()),
+                        ("y".to_string(), p.position.y.into()),
+                        ("aperture".to_string(), p.aperture.clone().into()),
+                    ])
+                })
+                .collect(),
+        )
+    };
+
+    let diffs = outputs
+        .layers
+        .iter()
+        .filter_map(|output| {
+            let (_, input) = inputs
+                .iter()
+                .find(|(name, _)| *name == output.original_name)?;
+            let diff = compare::layers(
+                &gerber::parse_gerber(input),
+                &gerber::parse_gerber(&output.content),
+            );
+            Some(JsonValue::Object(vec![
+                ("input".to_string(), output.original_name.as_str().into()),
+                ("output".to_string(), output.name.as_str().into()),
+                ("area_input".to_string(), diff.area_a.into()),
+                ("area_output".to_string(), diff.area_b.into()),
+                ("xor_area".to_string(), diff.xor_area.into()),
+                ("similarity".to_string(), diff.similarity.into()),
+                ("missing_count".to_string(), diff.missing_in_b.len().into()),
+                ("extra_count".to_string(), diff.extra_in_b.len().into()),
+                ("missing".to_string(), primitive_list(&diff.missing_in_b)),
+                ("extra".to_string(), primitive_list(&diff.extra_in_b)),
+            ]))
+        })
+        .collect();
+    JsonValue::Array(diffs)
+}
@@ -0,0 +1,111 @@
+/* src/templates.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+// Layers plotted by the Gerber output; mechanical 1 carries the board outline.
+const GERBER_LAYERS: &[&str] = &[
+    "TopLayer",
+    "MidLayer1",
+    "MidLayer2",
+    "MidLayer3",
+    "MidLayer4",
+    "MidLayer5",
+    "MidLayer6",
+    "BottomLayer",
+    "TopOverlay",
+    "BottomOverlay",
+    "TopPaste",
+    "BottomPaste",
+    "TopSolder",
+    "BottomSolder",
+    "KeepOutLayer",
+    "Mechanical1",
+];
+
+// Gerber output options: metric 4:6 with all zeros kept. The drill options match.
+const GERBER_OPTIONS: &[(&str, &str)] = &[
+    ("Record", "GerberView"),
+    ("GerberUnit", "Metric"),
+    ("NumberOfDecimals", "6"),
+    (
+        "LeadingAndTrailingZeroesMode",
+        "KeepLeadingAndTrailingZeroes",
+    ),
+    ("OriginPosition", "Relative"),
+    ("EmbeddedApertures", "True"),
+    ("FlashPadShapes", "True"),
+    ("G54OnApertureChange", "False"),
+    ("SoftwareArcs", "False"),
+    ("Panelize", "False"),
+    ("Mirror", "False"),
+    ("GenerateReliefShapes", "True"),
+    ("PlotPositivePlaneLayers", "False"),
+];
+
+const DRILL_OPTIONS: &[(&str, &str)] = &[
+    ("Record", "DrillView"),
+    ("Units", "Metric"),
+    ("NumberOfUnits", "4"),
+    ("NumberOfDecimals", "6"),
+    ("ZeroesMode", "KeepLeadingAndTrailingZeroes"),
+    ("OriginPosition", "Relative"),
+    ("GenerateSeparatePlatedNonPlatedFiles", "True"),
+    ("GenerateDrilledSlotsG85", "True"),
+    ("GenerateEIADrillFile", "False"),
+    ("GenerateBoardEdgeRout", "False"),
+];
+
+fn option_string(options: &[(&str, &str)]) -> String {
+    options
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Returns a recommended Altium Designer output job (`.OutJob`) as text.
+///
+/// The job plots Gerbers and NC drills in metric 4:6 with no zero suppression,
+/// relative origin and separate plated/non-plated drill files, which is what the
+/// converter handles best. Users save it with the `.OutJob` extension and add it
+/// to their project. Lines end with CRLF, as Altium writes them.
+pub fn altium_outjob() -> String {
+    let plot_layers = GERBER_LAYERS
+        .iter()
+        .map(|layer| format!("{}~True", layer))
+        .collect::<Vec<_>>()
+        .join(",");
+    let gerber = format!("{}|Plot.Set={}", option_string(GERBER_OPTIONS), plot_layers);
+
+    let lines = [
+        "[OutputJobFile]".to_string(),
+        "Version=1.0".to_string(),
+        String::new(),
+        "[OutputGroup1]".to_string(),
+        "Name=JLC Fabrication Outputs".to_string(),
+        "Description=Generated by nextjlc".to_string(),
+        "TargetOutputMedium=Folder Structure".to_string(),
+        "VariantName=[No Variations]".to_string(),
+        "OutputMedium1=Folder Structure".to_string(),
+        "OutputMedium1_Type=GeneratedFiles".to_string(),
+        "OutputType1=Gerber".to_string(),
+        "OutputName1=Gerber Files".to_string(),
+        "OutputCategory1=Fabrication".to_string(),
+        "OutputEnabled1=1".to_string(),
+        "OutputEnabled1_OutputMedium1=1".to_string(),
+        "Configuration1_Name1=OutputConfigurationParameter1".to_string(),
+        format!("Configuration1_Item1={}", gerber),
+        "OutputType2=NC Drill".to_string(),
+        "OutputName2=NC Drill Files".to_string(),
+        "OutputCategory2=Fabrication".to_string(),
+        "OutputEnabled2=1".to_string(),
+        "OutputEnabled2_OutputMedium1=1".to_string(),
+        "Configuration2_Name1=OutputConfigurationParameter1".to_string(),
+        format!("Configuration2_Item1={}", option_string(DRILL_OPTIONS)),
+        String::new(),
+    ];
+    lines.join("\r\n")
+}
@@ -0,0 +1,386 @@
+/* src/transform.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::geometry::{BoundingBox, Point, arc_bounds, flatten_arc};
+use crate::gerber::{Command, GerberLayer, Interpolation};
+
+// Chord tolerance for arcs that cross the crop edge, in mm
+const ARC_TOLERANCE_MM: f64 = 0.001;
+
+// Commands of the cropped layer, with the current point tracked so that draws
+// whose predecessor was removed start with a move.
+struct Output {
+    commands: Vec<Command>,
+    point: Option<Point>,
+}
+
+impl Output {
+    fn draw(&mut self, command: Command) {
+        if let Command::Draw { from, to, .. } = &command {
+            if self.point != Some(*from) {
+                self.commands.push(Command::Move(*from));
+            }
+            self.point = Some(*to);
+        }
+        self.commands.push(command);
+    }
+
+    fn line(&mut self, from: Point, to: Point) {
+        self.draw(Command::Draw {
+            from,
+            to,
+            interpolation: Interpolation::Linear,
+            center: None,
+        });
+    }
+}
+
+/// Clips the geometry of a layer to `rect`.
+///
+/// Flashes are kept when their center lies inside. Draws are cut at the edge
+/// along their center line, so a stroke ends half its width past `rect`; arcs
+/// crossing the edge become line segments. Regions are clipped as polygons.
+pub fn crop(layer: &GerberLayer, rect: &BoundingBox) -> GerberLayer {
+    let mut out = Output {
+        commands: Vec::new(),
+        point: None,
+    };
+    let mut region: Option<Vec<Command>> = None;
+
+    for command in &layer.commands {
+        if let Some(contour) = region.as_mut() {
+            match command {
+                Command::RegionEnd => {
+                    crop_region(&mut out, contour, rect);
+                    region = None;
+                }
+                Command::Move(_) | Command::Draw { .. } => contour.push(command.clone()),
+                _ => {}
+            }
+            continue;
+        }
+        match command {
+            Command::RegionStart => region = Some(Vec::new()),
+            Command::Move(_) => {}
+            Command::Flash(p) => {
+                if rect.contains(*p) {
+                    out.commands.push(command.clone());
+                    out.point = Some(*p);
+                }
+            }
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } => match (interpolation, center) {
+                (Interpolation::Linear, _) | (_, None) => {
+                    if let Some((a, b)) = clip_segment(*from, *to, rect) {
+                        out.line(a, b);
+                    }
+                }
+                (kind, Some(c)) => {
+                    let clockwise = *kind == Interpolation::Clockwise;
+                    let bounds = arc_bounds(*from, *to, *c, clockwise);
+                    if inside(&bounds, rect) {
+                        out.draw(command.clone());
+                    } else if bounds.intersects(rect) {
+                        let path = flatten_arc(*from, *to, *c, clockwise, ARC_TOLERANCE_MM);
+                        for pair in path.windows(2) {
+                            if let Some((a, b)) = clip_segment(pair[0], pair[1], rect) {
+                                out.line(a, b);
+                            }
+                        }
+                    }
+                }
+            },
+            other => out.commands.push(other.clone()),
+        }
+    }
+
+    GerberLayer {
+        commands: out.commands,
+        ..layer.clone()
+    }
+}
+
+fn inside(bounds: &BoundingBox, rect: &BoundingBox) -> bool {
+    bounds.min_x >= rect.min_x
+        && bounds.max_x <= rect.max_x
+        && bounds.min_y >= rect.min_y
+        && bounds.max_y <= rect.max_y
+}
+
+// Writes a region, unchanged when it lies inside `rect` and clipped otherwise.
+fn crop_region(out: &mut Output, commands: &[Command], rect: &BoundingBox) {
+    let mut bounds: Option<BoundingBox> = None;
+    let mut include = |b: BoundingBox| match &mut bounds {
+        Some(existing) => existing.include_box(&b),
+        None => bounds = Some(b),
+    };
+    for command in commands {
+        match command {
+            Command::Move(p) => include(BoundingBox::from_point(*p)),
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } => match (interpolation, center) {
+                (Interpolation::Linear, _) | (_, None) => {
+                    include(BoundingBox::from_point(*from));
+                    include(BoundingBox::from_point(*to));
+                }
+                (kind, Some(c)) => include(arc_bounds(
+                    *from,
+                    *to,
+                    *c,
+                    *kind == Interpolation::Clockwise,
+                )),
+            },
+            _ => {}
+        }
+    }
+    let Some(bounds) = bounds else {
+        return;
+    };
+    if !bounds.intersects(rect) {
+        return;
+    }
+
+    out.commands.push(Command::RegionStart);
+    if inside(&bounds, rect) {
+        for command in commands {
+            match command {
+                Command::Move(p) => {
+                    out.commands.push(command.clone());
+                    out.point = Some(*p);
+                }
+                _ => out.draw(command.clone()),
+            }
+        }
+    } else {
+        for contour in contours(commands) {
+            let clipped = clip_polygon(&contour, rect);
+            if clipped.len() < 3 {
+                continue;
+            }
+            out.commands.push(Command::Move(clipped[0]));
+            out.point = Some(clipped[0]);
+            for pair in clipped.windows(2) {
+                out.line(pair[0], pair[1]);
+            }
+            out.line(clipped[clipped.len() - 1], clipped[0]);
+        }
+    }
+    out.commands.push(Command::RegionEnd);
+}
+
+// Splits region commands into closed polylines, arcs flattened.
+fn contours(commands: &[Command]) -> Vec<Vec<Point>> {
+    let mut contours = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    for command in commands {
+        match command {
+            Command::Move(p) => {
+                if current.len() > 1 {
+                    contours.push(std::mem::take(&mut current));
+                }
+                current = vec![*p];
+            }
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } => {
+                if current.is_empty() {
+                    current.push(*from);
+                }
+                match (interpolation, center) {
+                    (Interpolation::Linear, _) | (_, None) => current.push(*to),
+                    (kind, Some(c)) => {
+                        let path = flatten_arc(
+                            *from,
+                            *to,
+                            *c,
+                            *kind == Interpolation::Clockwise,
+                            ARC_TOLERANCE_MM,
+                        );
+                        current.extend_from_slice(&path[1..]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if current.len() > 1 {
+        contours.push(current);
+    }
+    contours
+}
+
+// Liang-Barsky clipping of a segment to a rectangle.
+fn clip_segment(a: Point, b: Point, rect: &BoundingBox) -> Option<(Point, Point)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-dx, a.x - rect.min_x),
+        (dx, rect.max_x - a.x),
+        (-dy, a.y - rect.min_y),
+        (dy, rect.max_y - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| Point::new(a.x + t * dx, a.y + t * dy);
+    Some((at(t0), at(t1)))
+}
+
+// Sutherland-Hodgman clipping of a closed polygon to a rectangle.
+fn clip_polygon(polygon: &[Point], rect: &BoundingBox) -> Vec<Point> {
+    let mut points: Vec<Point> = polygon.to_vec();
+    // A closed contour repeats its first point
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    // Each edge as (vertical, coordinate, inside is above it)
+    for (vertical, edge, above) in [
+        (true, rect.min_x, true),
+        (true, rect.max_x, false),
+        (false, rect.min_y, true),
+        (false, rect.max_y, false),
+    ] {
+        if points.is_empty() {
+            break;
+        }
+        let is_inside = |p: Point| {
+            let value = if vertical { p.x } else { p.y };
+            if above { value >= edge } else { value <= edge }
+        };
+        let intersect = |a: Point, b: Point| {
+            if vertical {
+                let t = (edge - a.x) / (b.x - a.x);
+                Point::new(edge, a.y + t * (b.y - a.y))
+            } else {
+                let t = (edge - a.y) / (b.y - a.y);
+                Point::new(a.x + t * (b.x - a.x), edge)
+            }
+        };
+        let input = std::mem::take(&mut points);
+        let mut previous = input[input.len() - 1];
+        for &current in &input {
+            match (is_inside(current), is_inside(previous)) {
+                (true, true) => points.push(current),
+                (true, false) => {
+                    points.push(intersect(previous, current));
+                    points.push(current);
+                }
+                (false, true) => points.push(intersect(previous, current)),
+                (false, false) => {}
+            }
+            previous = current;
+        }
+    }
+    points
+}
+
+/// Simplifies a layer for on-screen preview, where size matters more than
+/// fidelity. The result is not fit for fabrication.
+///
+/// Points are snapped to a grid of `grid_mm`; attributes and comments are
+/// dropped, as are line draws that become zero-length, moves to the current
+/// point and aperture selections that change nothing.
+pub fn simplify_for_preview(layer: &GerberLayer, grid_mm: f64) -> GerberLayer {
+    let snap = |p: &Point| {
+        Point::new(
+            (p.x / grid_mm).round() * grid_mm,
+            (p.y / grid_mm).round() * grid_mm,
+        )
+    };
+    let mut commands: Vec<Command> = Vec::with_capacity(layer.commands.len());
+    let mut point: Option<Point> = None;
+    let mut aperture: Option<u32> = None;
+
+    for command in &layer.commands {
+        match command {
+            Command::Comment(_) | Command::Attribute(_) => {}
+            Command::SelectAperture(code) => {
+                if aperture != Some(*code) {
+                    aperture = Some(*code);
+                    commands.push(command.clone());
+                }
+            }
+            Command::Move(p) => {
+                let p = snap(p);
+                if point == Some(p) {
+                    continue;
+                }
+                // A move followed by another move does nothing
+                if let Some(Command::Move(last)) = commands.last_mut() {
+                    *last = p;
+                } else {
+                    commands.push(Command::Move(p));
+                }
+                point = Some(p);
+            }
+            Command::Flash(p) => {
+                let p = snap(p);
+                commands.push(Command::Flash(p));
+                point = Some(p);
+            }
+            Command::Draw {
+                from,
+                to,
+                interpolation,
+                center,
+            } => {
+                let (from, to) = (snap(from), snap(to));
+                // Arcs with equal ends are full circles and are kept
+                if from == to && (*interpolation == Interpolation::Linear || center.is_none()) {
+                    continue;
+                }
+                if point != Some(from) {
+                    commands.push(Command::Move(from));
+                }
+                commands.push(Command::Draw {
+                    from,
+                    to,
+                    interpolation: *interpolation,
+                    center: center.as_ref().map(snap),
+                });
+                point = Some(to);
+            }
+            other => commands.push(other.clone()),
+        }
+    }
+
+    let mut apertures = layer.apertures.clone();
+    for aperture in apertures.values_mut() {
+        aperture.attributes.clear();
+    }
+    GerberLayer {
+        apertures,
+        file_attributes: Vec::new(),
+        commands,
+        ..layer.clone()
+    }
+}
@@ -7,7 +7,7 @@
 
 /// A struct to hold the successful result of a validation check.
 /// It contains the calculated number of copper layers and a list of non-critical warnings.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ValidationReport {
     pub layer_count: u32,
     pub warnings: Vec<String>,
@@ -20,6 +20,9 @@ const REQUIRED_PREFIXES: &[&str] = &[
     "Gerber_TopSolderMaskLayer",
 ];
 
+/// Highest copper layer count JLC manufactures
+const MAX_COPPER_LAYERS: u32 = 32;
+
 /// Validates a list of standardized Gerber filenames against a set of manufacturing rules.
 /// This function is pure: it takes a list of names and returns a result without any I/O.
 ///
@@ -123,10 +126,58 @@ pub fn validate_gerber_files(files: &[String]) -> Result<ValidationReport, Vec<S
         errors.push("Invalid layer stackup: A board with top and inner copper layers must also have a bottom copper layer.".to_string());
     }
 
+    // Inner layers must form one sequence, or the stackup order is ambiguous.
+    let mut inner_indices: Vec<u32> = files
+        .iter()
+        .filter_map(|f| {
+            f.strip_prefix("Gerber_InnerLayer")?
+                .split('.')
+                .next()?
+                .parse()
+                .ok()
+        })
+        .collect();
+    inner_indices.sort_unstable();
+    inner_indices.dedup();
+    if inner_indices
+        .iter()
+        .enumerate()
+        .any(|(i, index)| *index != i as u32 + 1)
+    {
+        warnings.push(format!(
+            "Warning: inner layers are not numbered consecutively ({}); check the stackup order.",
+            inner_indices
+                .iter()
+                .map(|i| format!("G{}", i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    // Drill drawings under a drill-like name confuse the JLC uploader
+    if let Some(map) = files.iter().find(|f| f.starts_with("Drill_MAP_GBR")) {
+        warnings.push(format!(
+            "Warning: '{}' is a drill drawing; the JLC uploader may take it for drill data.",
+            map
+        ));
+    }
+
     // --- 4. Calculate final layer count ---
     let total_layer_count =
         (has_top_copper as u32) + (has_bottom_copper as u32) + inner_layer_count;
 
+    if total_layer_count > MAX_COPPER_LAYERS {
+        errors.push(format!(
+            "The board has {} copper layers; JLC manufactures at most {}.",
+            total_layer_count, MAX_COPPER_LAYERS
+        ));
+    } else if total_layer_count > 2 && !total_layer_count.is_multiple_of(2) {
+        warnings.push(format!(
+            "Warning: {} copper layers; multilayer boards are built with an even layer count.",
+            total_layer_count
+        ));
+    }
+
     // --- 5. Return the final result ---
     if errors.is_empty() {
         Ok(ValidationReport {
@@ -0,0 +1,106 @@
+/* src/via_in_pad.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::drill::{DrillCommand, DrillFile, HoleType};
+use crate::geometry::Point;
+use crate::gerber::{self, Aperture};
+
+// A hole this far from the pad center is off-center, in mm
+const CENTER_TOLERANCE_MM: f64 = 0.05;
+
+/// A plated hole inside an SMD pad. JLC only builds these with the via
+/// filling option, and asks about them by email otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViaInPad {
+    /// Hole center, in mm
+    pub location: Point,
+    pub drill_diameter: f64,
+    /// D-code of the pad flash
+    pub pad_code: u32,
+}
+
+// A flashed pad of an outer copper layer
+struct Pad<'a> {
+    at: Point,
+    aperture: &'a Aperture,
+}
+
+impl Pad<'_> {
+    fn contains(&self, p: Point) -> bool {
+        let (dx, dy) = ((p.x - self.at.x).abs(), (p.y - self.at.y).abs());
+        let (hx, hy) = self.aperture.half_extents();
+        match self.aperture.template.as_str() {
+            "C" | "P" => dx.hypot(dy) <= hx,
+            "O" => {
+                // A stadium: a rectangle with round ends along its long side
+                let r = hx.min(hy);
+                let (ex, ey) = ((dx - (hx - r)).max(0.0), (dy - (hy - r)).max(0.0));
+                ex.hypot(ey) <= r
+            }
+            _ => dx <= hx && dy <= hy,
+        }
+    }
+}
+
+fn flashed_pads(layer: &gerber::GerberLayer) -> Vec<Pad<'_>> {
+    layer
+        .dark_flashes()
+        .into_iter()
+        .map(|(at, aperture)| Pad { at, aperture })
+        .collect()
+}
+
+/// Finds the holes of `drill` that lie inside SMD pads of `copper`, an outer
+/// copper layer; `opposite` is the copper of the other side. NPTH holes are
+/// skipped, since they are never filled.
+///
+/// Pads marked with an X2 aperture function are taken at their word. Without
+/// one, a pad is an SMD pad when the hole is off its center, or when nothing
+/// is flashed over the hole on the opposite side; a through-hole pad is
+/// centered on its hole and has a pad on both sides.
+pub fn find_vias_in_pads(copper: &str, opposite: Option<&str>, drill: &DrillFile) -> Vec<ViaInPad> {
+    let layer = gerber::parse_gerber(copper);
+    let opposite_layer = opposite.map(gerber::parse_gerber);
+    let pads = flashed_pads(&layer);
+    let opposite_pads = opposite_layer
+        .as_ref()
+        .map(flashed_pads)
+        .unwrap_or_default();
+
+    let mut found = Vec::new();
+    for op in drill
+        .operations
+        .iter()
+        .filter(|op| op.hole_type == HoleType::Plated)
+    {
+        for command in &op.commands {
+            let DrillCommand::Hole { x, y } = command else {
+                continue;
+            };
+            let hole = Point::new(*x, *y);
+            let Some(pad) = pads.iter().find(|pad| pad.contains(hole)) else {
+                continue;
+            };
+            let smd = match pad.aperture.function() {
+                Some(function) => function == "SMDPad" || function == "BGAPad",
+                None => {
+                    pad.at.distance(&hole) > CENTER_TOLERANCE_MM
+                        || (opposite_layer.is_some()
+                            && !opposite_pads.iter().any(|other| other.contains(hole)))
+                }
+            };
+            if smd {
+                found.push(ViaInPad {
+                    location: hole,
+                    drill_diameter: op.diameter,
+                    pad_code: pad.aperture.code,
+                });
+            }
+        }
+    }
+    found
+}
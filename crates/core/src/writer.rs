@@ -0,0 +1,302 @@
+/* src/writer.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::gerber::{Aperture, Command, GerberLayer, Interpolation, Polarity, Unit};
+
+const INCH_TO_MM: f64 = 25.4;
+
+/// Formatting options for [`write_gerber`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriterOptions {
+    /// Output unit. Ignored when the layer has aperture macros, because macro
+    /// bodies are emitted verbatim and are only valid in the source unit.
+    pub unit: Unit,
+    pub integer_digits: u32,
+    pub decimal_digits: u32,
+    /// Pack several words per line up to this many characters; `None` writes one word per line
+    pub max_line_length: Option<usize>,
+    /// Emit aperture definitions sorted by D-code instead of in parse order
+    pub sort_apertures: bool,
+    pub include_comments: bool,
+    pub include_attributes: bool,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            unit: Unit::Millimeter,
+            integer_digits: 4,
+            decimal_digits: 6,
+            max_line_length: None,
+            sort_apertures: true,
+            include_comments: true,
+            include_attributes: true,
+        }
+    }
+}
+
+impl WriterOptions {
+    /// Compact options for preview payloads: 1 µm resolution, no comments or
+    /// attributes, and several words per line.
+    pub fn preview() -> Self {
+        WriterOptions {
+            decimal_digits: PREVIEW_DECIMAL_DIGITS,
+            max_line_length: Some(255),
+            include_comments: false,
+            include_attributes: false,
+            ..Self::default()
+        }
+    }
+}
+
+// Decimal digits of preview output, in mm
+const PREVIEW_DECIMAL_DIGITS: u32 = 3;
+
+/// Collects output words and packs them into lines.
+struct LineBuffer {
+    output: String,
+    current_len: usize,
+    max_line_length: Option<usize>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, word: &str) {
+        match self.max_line_length {
+            Some(max) if self.current_len > 0 && self.current_len + word.len() <= max => {}
+            _ => {
+                if self.current_len > 0 {
+                    self.output.push('\n');
+                }
+                self.current_len = 0;
+            }
+        }
+        self.output.push_str(word);
+        self.current_len += word.len();
+    }
+
+    /// Extended commands always start on their own line for readability.
+    fn push_line(&mut self, line: &str) {
+        if self.current_len > 0 {
+            self.output.push('\n');
+        }
+        self.output.push_str(line);
+        self.current_len = 0;
+        self.output.push('\n');
+    }
+
+    fn finish(mut self) -> String {
+        if self.current_len > 0 {
+            self.output.push('\n');
+        }
+        self.output
+    }
+}
+
+/// Encodes a mm value as a leading-zero-omitted fixed-point coordinate.
+fn encode(value_mm: f64, unit: Unit, decimal_digits: u32) -> String {
+    let value = match unit {
+        Unit::Inch => value_mm / INCH_TO_MM,
+        Unit::Millimeter => value_mm,
+    };
+    let scaled = (value * 10_f64.powi(decimal_digits as i32)).round() as i64;
+    scaled.to_string()
+}
+
+/// Formats an aperture definition in the output unit.
+fn format_aperture(aperture: &Aperture, unit: Unit) -> String {
+    let scale = if unit == Unit::Inch {
+        1.0 / INCH_TO_MM
+    } else {
+        1.0
+    };
+    let parameters: Vec<String> = aperture
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let value = match aperture.template.as_str() {
+                "C" | "R" | "O" => v * scale,
+                "P" if i == 0 || i == 3 => v * scale,
+                _ => *v,
+            };
+            let text = format!("{:.6}", value);
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        })
+        .collect();
+
+    if parameters.is_empty() {
+        format!("%ADD{}{}*%", aperture.code, aperture.template)
+    } else {
+        format!(
+            "%ADD{}{},{}*%",
+            aperture.code,
+            aperture.template,
+            parameters.join("X")
+        )
+    }
+}
+
+/// Re-emits a parsed layer as RS-274X with controlled formatting.
+///
+/// The output is deterministic for a given layer and options: coordinates are
+/// rounded to `decimal_digits`, modal G-codes are written only when they change,
+/// and the file always ends with exactly one `M02*`.
+pub fn write_gerber(layer: &GerberLayer, options: &WriterOptions) -> String {
+    let unit = if layer.macros.is_empty() {
+        options.unit
+    } else {
+        layer.unit
+    };
+    let digits = options.decimal_digits;
+    let mut out = LineBuffer {
+        output: String::new(),
+        current_len: 0,
+        max_line_length: options.max_line_length,
+    };
+
+    // Header: format, unit, file attributes
+    out.push_line(&format!(
+        "%FSLAX{i}{d}Y{i}{d}*%",
+        i = options.integer_digits,
+        d = digits
+    ));
+    out.push_line(match unit {
+        Unit::Millimeter => "%MOMM*%",
+        Unit::Inch => "%MOIN*%",
+    });
+    if options.include_attributes {
+        for attribute in &layer.file_attributes {
+            out.push_line(&format!("%{}*%", attribute));
+        }
+    }
+
+    // Aperture macros and definitions
+    for macro_block in &layer.macros {
+        // One macro primitive per line, as CAD tools write them
+        let body = macro_block.trim_end_matches('*').replace('*', "*\n");
+        out.push_line(&format!("%{}*%", body));
+    }
+    let mut apertures: Vec<&Aperture> = layer.apertures.values().collect();
+    if !options.sort_apertures {
+        // Parse order is the order of first selection in the command stream
+        let mut order: Vec<u32> = Vec::new();
+        for command in &layer.commands {
+            if let Command::SelectAperture(code) = command
+                && !order.contains(code)
+            {
+                order.push(*code);
+            }
+        }
+        apertures.sort_by_key(|a| {
+            order
+                .iter()
+                .position(|c| *c == a.code)
+                .unwrap_or(usize::MAX)
+        });
+    }
+    for aperture in apertures {
+        if options.include_attributes && !aperture.attributes.is_empty() {
+            for attribute in &aperture.attributes {
+                out.push_line(&format!("%{}*%", attribute));
+            }
+            out.push_line(&format_aperture(aperture, unit));
+            out.push_line("%TD*%");
+        } else {
+            out.push_line(&format_aperture(aperture, unit));
+        }
+    }
+
+    // Graphics stream
+    let xy = |p: &crate::geometry::Point| {
+        format!(
+            "X{}Y{}",
+            encode(p.x, unit, digits),
+            encode(p.y, unit, digits)
+        )
+    };
+    let mut interpolation: Option<Interpolation> = None;
+    let mut multi_quadrant = false;
+
+    for command in &layer.commands {
+        match command {
+            Command::SelectAperture(code) => out.push(&format!("D{}*", code)),
+            Command::Move(p) => out.push(&format!("{}D02*", xy(p))),
+            Command::Flash(p) => out.push(&format!("{}D03*", xy(p))),
+            Command::Draw {
+                from,
+                to,
+                interpolation: kind,
+                center,
+            } => {
+                let mode = match kind {
+                    Interpolation::Linear => "G01",
+                    Interpolation::Clockwise => "G02",
+                    Interpolation::CounterClockwise => "G03",
+                };
+                let prefix = if interpolation == Some(*kind) {
+                    ""
+                } else {
+                    interpolation = Some(*kind);
+                    mode
+                };
+                match (kind, center) {
+                    (Interpolation::Linear, _) | (_, None) => {
+                        out.push(&format!("{}{}D01*", prefix, xy(to)));
+                    }
+                    (_, Some(c)) => {
+                        if !multi_quadrant {
+                            out.push("G75*");
+                            multi_quadrant = true;
+                        }
+                        out.push(&format!(
+                            "{}{}I{}J{}D01*",
+                            prefix,
+                            xy(to),
+                            encode(c.x - from.x, unit, digits),
+                            encode(c.y - from.y, unit, digits)
+                        ));
+                    }
+                }
+            }
+            Command::RegionStart => out.push("G36*"),
+            Command::RegionEnd => out.push("G37*"),
+            Command::SetPolarity(Polarity::Dark) => out.push_line("%LPD*%"),
+            Command::SetPolarity(Polarity::Clear) => out.push_line("%LPC*%"),
+            Command::Comment(text) => {
+                if options.include_comments {
+                    out.push_line(&format!("G04 {}*", text));
+                }
+            }
+            Command::Attribute(attribute) => {
+                if options.include_attributes {
+                    out.push_line(&format!("%{}*%", attribute));
+                }
+            }
+        }
+    }
+
+    out.push("M02*");
+    out.finish()
+}
+
+/// Shrinks a Gerber layer for the renderer or preview path, typically to a
+/// fraction of its size. Coordinates are quantized to 1 µm and attributes and
+/// comments are dropped, so the result must never be sent to the fab; order
+/// output is written from the original content.
+pub fn minify_for_preview(content: &str) -> String {
+    let layer = crate::gerber::parse_gerber(content);
+    let mut options = WriterOptions::preview();
+    // Macro layers stay in their source unit, where 1 µm needs one more digit
+    if !layer.macros.is_empty() && layer.unit == Unit::Inch {
+        options.decimal_digits += 2;
+    }
+    let grid_mm = 10_f64.powi(-(PREVIEW_DECIMAL_DIGITS as i32));
+    write_gerber(
+        &crate::transform::simplify_for_preview(&layer, grid_mm),
+        &options,
+    )
+}
@@ -0,0 +1,370 @@
+/* src/zip.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use crate::gzip;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::io::{self, Write};
+
+// Entries are timestamped 1980-01-01 00:00 (the DOS epoch) so archives are reproducible.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+// General purpose flag bit 11: file names are UTF-8 (needed for "PCB下单必读.txt").
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORED: u16 = 0;
+
+static CRC32_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+        *entry = crc;
+    }
+    table
+});
+
+/// Computes the CRC-32 (IEEE) checksum used by zip and gzip.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a zip archive in memory. Entries are stored without compression.
+#[derive(Default)]
+pub struct ZipWriter {
+    stream: ZipStreamWriter<Vec<u8>>,
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a file. Names use forward slashes for directories, e.g. "inputs/top.gtl".
+    pub fn add_file(&mut self, name: &str, content: &[u8]) {
+        self.stream
+            .add_file(name, content)
+            .expect("Writing to a Vec cannot fail");
+    }
+
+    /// Number of files added so far.
+    pub fn len(&self) -> usize {
+        self.stream.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stream.entries.is_empty()
+    }
+
+    /// Writes the central directory and returns the archive bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.stream.finish().expect("Writing to a Vec cannot fail")
+    }
+}
+
+/// Writes a zip archive to `out` as files are added, so that only the
+/// central directory is held in memory, e.g. to stream a download.
+#[derive(Default)]
+pub struct ZipStreamWriter<W: Write> {
+    out: W,
+    written: u32,
+    entries: Vec<CentralEntry>,
+}
+
+impl<W: Write> ZipStreamWriter<W> {
+    pub fn new(out: W) -> Self {
+        ZipStreamWriter {
+            out,
+            written: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(data)?;
+        self.written += data.len() as u32;
+        Ok(())
+    }
+
+    /// Appends a file, see [`ZipWriter::add_file`].
+    pub fn add_file(&mut self, name: &str, content: &[u8]) -> io::Result<()> {
+        let crc = crc32(content);
+        let size = content.len() as u32;
+        let offset = self.written;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        let out = &mut header;
+        push_u32(out, 0x0403_4B50); // Local file header signature
+        push_u16(out, 20); // Version needed to extract
+        push_u16(out, FLAG_UTF8);
+        push_u16(out, METHOD_STORED);
+        push_u16(out, DOS_TIME);
+        push_u16(out, DOS_DATE);
+        push_u32(out, crc);
+        push_u32(out, size); // Compressed size
+        push_u32(out, size); // Uncompressed size
+        push_u16(out, name.len() as u16);
+        push_u16(out, 0); // Extra field length
+        out.extend_from_slice(name.as_bytes());
+        self.write(&header)?;
+        self.write(content)?;
+
+        self.entries.push(CentralEntry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory_offset = self.written;
+        let mut directory = Vec::new();
+        let out = &mut directory;
+        for entry in &self.entries {
+            push_u32(out, 0x0201_4B50); // Central directory header signature
+            push_u16(out, 20); // Version made by
+            push_u16(out, 20); // Version needed to extract
+            push_u16(out, FLAG_UTF8);
+            push_u16(out, METHOD_STORED);
+            push_u16(out, DOS_TIME);
+            push_u16(out, DOS_DATE);
+            push_u32(out, entry.crc);
+            push_u32(out, entry.size);
+            push_u32(out, entry.size);
+            push_u16(out, entry.name.len() as u16);
+            push_u16(out, 0); // Extra field length
+            push_u16(out, 0); // Comment length
+            push_u16(out, 0); // Disk number
+            push_u16(out, 0); // Internal attributes
+            push_u32(out, 0); // External attributes
+            push_u32(out, entry.offset);
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = out.len() as u32;
+
+        push_u32(out, 0x0605_4B50); // End of central directory signature
+        push_u16(out, 0); // Disk number
+        push_u16(out, 0); // Disk with the central directory
+        push_u16(out, self.entries.len() as u16);
+        push_u16(out, self.entries.len() as u16);
+        push_u32(out, directory_size);
+        push_u32(out, directory_offset);
+        push_u16(out, 0); // Comment length
+        self.write(&directory)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+const METHOD_DEFLATED: u16 = 8;
+
+/// Error produced when reading an invalid or unsupported zip archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipError {
+    pub message: String,
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ZipError {}
+
+fn error(message: impl Into<String>) -> ZipError {
+    ZipError {
+        message: message.into(),
+    }
+}
+
+/// One file read from a zip archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    /// Path inside the archive, with forward slashes
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Bounds on what [`read_zip_with_limits`] will extract, so an untrusted
+/// upload cannot exhaust memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZipLimits {
+    pub max_entries: usize,
+    /// Total size of all extracted files, in bytes
+    pub max_total_size: usize,
+    /// Largest extracted to compressed size ratio of an entry; entries of up
+    /// to [`RATIO_EXEMPT_SIZE`] bytes are not held to it
+    pub max_ratio: usize,
+}
+
+/// Entries that extract to this many bytes or fewer may have any ratio
+pub const RATIO_EXEMPT_SIZE: usize = 1 << 20;
+
+impl Default for ZipLimits {
+    fn default() -> Self {
+        ZipLimits {
+            max_entries: 10_000,
+            max_total_size: 512 << 20,
+            max_ratio: 200,
+        }
+    }
+}
+
+/// A safe relative path for an entry name: backslashes become slashes, and
+/// empty, ".", ".." and drive ("C:") components are dropped, so "../x" or
+/// "/etc/x" cannot reach outside an extraction directory. `None` when
+/// nothing is left.
+pub(crate) fn sanitize_entry_path(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name
+        .split(['/', '\\'])
+        .filter(|part| !matches!(*part, "" | "." | ".."))
+        .filter(|part| !(part.len() == 2 && part.ends_with(':')))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+// Offsets come from the archive, so sums of them are checked: on wasm32 a
+// u32 size added to an offset can overflow usize
+fn slice(data: &[u8], at: usize, len: usize) -> Option<&[u8]> {
+    data.get(at..at.checked_add(len)?)
+}
+
+fn read_u16(data: &[u8], at: usize) -> Result<u16, ZipError> {
+    slice(data, at, 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| error("Truncated zip archive"))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Result<u32, ZipError> {
+    slice(data, at, 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| error("Truncated zip archive"))
+}
+
+/// Reads the files of a zip archive through its central directory, within
+/// the default [`ZipLimits`]. Entries may be stored or deflated; directories
+/// are skipped and names are made relative paths that stay in the archive.
+pub fn read_zip(data: &[u8]) -> Result<Vec<ZipEntry>, ZipError> {
+    read_zip_with_limits(data, &ZipLimits::default())
+}
+
+/// Like [`read_zip`], failing as soon as the archive exceeds `limits`.
+pub fn read_zip_with_limits(data: &[u8], limits: &ZipLimits) -> Result<Vec<ZipEntry>, ZipError> {
+    // The end record is 22 bytes plus a comment of up to 64 KiB
+    let search_start = data.len().saturating_sub(22 + 0xFFFF);
+    let end = (search_start..data.len().saturating_sub(21))
+        .rev()
+        .find(|&at| read_u32(data, at) == Ok(0x0605_4B50))
+        .ok_or_else(|| error("Not a zip archive"))?;
+    let count = read_u16(data, end + 10)? as usize;
+    let mut at = read_u32(data, end + 16)? as usize;
+    if count == 0xFFFF || at == 0xFFFF_FFFF_usize {
+        return Err(error("Zip64 archives are not supported"));
+    }
+    if count > limits.max_entries {
+        return Err(error(format!(
+            "Zip archive has {} entries, more than the limit of {}",
+            count, limits.max_entries
+        )));
+    }
+    let mut remaining = limits.max_total_size;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if read_u32(data, at)? != 0x0201_4B50 {
+            return Err(error("Invalid zip central directory"));
+        }
+        let method = read_u16(data, at + 10)?;
+        let crc = read_u32(data, at + 16)?;
+        let compressed_size = read_u32(data, at + 20)? as usize;
+        let name_length = read_u16(data, at + 28)? as usize;
+        let extra_length = read_u16(data, at + 30)? as usize;
+        let comment_length = read_u16(data, at + 32)? as usize;
+        let offset = read_u32(data, at + 42)? as usize;
+        let name =
+            slice(data, at + 46, name_length).ok_or_else(|| error("Truncated zip archive"))?;
+        let name = String::from_utf8_lossy(name).replace('\\', "/");
+        at += 46 + name_length + extra_length + comment_length;
+        if name.ends_with('/') {
+            continue;
+        }
+        let Some(name) = sanitize_entry_path(&name) else {
+            continue;
+        };
+
+        if read_u32(data, offset)? != 0x0403_4B50 {
+            return Err(error(format!("Invalid local header for {}", name)));
+        }
+        let start = offset
+            + 30
+            + read_u16(data, offset + 26)? as usize
+            + read_u16(data, offset + 28)? as usize;
+        let raw = slice(data, start, compressed_size)
+            .ok_or_else(|| error(format!("Truncated data for {}", name)))?;
+        let limit = remaining.min(
+            compressed_size
+                .saturating_mul(limits.max_ratio)
+                .max(RATIO_EXEMPT_SIZE),
+        );
+        let content = match method {
+            METHOD_STORED if raw.len() > remaining => {
+                return Err(error(format!(
+                    "{} exceeds the {} byte extraction limit",
+                    name, limits.max_total_size
+                )));
+            }
+            METHOD_STORED => raw.to_vec(),
+            METHOD_DEFLATED => gzip::inflate_limited(raw, limit)
+                .map_err(|e| error(format!("Cannot inflate {}: {}", name, e)))?,
+            _ => {
+                return Err(error(format!(
+                    "{} uses unsupported compression method {}",
+                    name, method
+                )));
+            }
+        };
+        if crc32(&content) != crc {
+            return Err(error(format!("CRC check failed for {}", name)));
+        }
+        remaining -= content.len();
+        entries.push(ZipEntry {
+            name,
+            data: content,
+        });
+    }
+    Ok(entries)
+}
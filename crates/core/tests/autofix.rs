@@ -0,0 +1,45 @@
+/* tests/autofix.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::autofix::{EndMarkerIssue, check_end_marker, repair_end_marker};
+
+const BODY: &str = "%FSLAX46Y46*%\n%MOMM*%\n%ADD10C,0.1*%\nD10*\nX0Y0D02*\nX1000000Y0D01*\n";
+
+#[test]
+fn gerber_end_markers_are_repaired_to_one() {
+    assert_eq!(check_end_marker(&format!("{}M02*\n", BODY), false), None);
+    // The marker may share the last line with other statements
+    let crlf = format!("{}X2000000Y0D01*M02*\r\n", BODY.replace('\n', "\r\n"));
+    assert_eq!(check_end_marker(&crlf, false), None);
+
+    // (input, issue found)
+    let cases = [
+        (BODY.to_string(), EndMarkerIssue::Missing),
+        (format!("{}M02*\nM02*\n", BODY), EndMarkerIssue::Repeated(2)),
+        (
+            format!("{}M02*\n{}M02*\n", BODY, BODY),
+            EndMarkerIssue::Repeated(2),
+        ),
+        (format!("{}M02*\nX0Y0D02*\n", BODY), EndMarkerIssue::NotLast),
+    ];
+    for (input, expected) in cases {
+        let (repaired, issue) = repair_end_marker(&input, false).expect("an issue");
+        assert_eq!(issue, expected, "{:?}", input);
+        assert!(repaired.ends_with("\nM02*\n"), "{:?}", repaired);
+        assert_eq!(repaired.matches("M02*").count(), 1);
+        assert_eq!(check_end_marker(&repaired, false), None);
+    }
+}
+
+#[test]
+fn drill_end_markers_are_repaired_to_one() {
+    let drill = "M48\nMETRIC\nT1C0.3\n%\nT1\nX1.0Y1.0\nM30\nM30\n";
+    let (repaired, issue) = repair_end_marker(drill, true).expect("an issue");
+    assert_eq!(issue, EndMarkerIssue::Repeated(2));
+    assert_eq!(repaired, "M48\nMETRIC\nT1C0.3\n%\nT1\nX1.0Y1.0\nM30\n");
+    assert!(repair_end_marker(&repaired, true).is_none());
+}
@@ -0,0 +1,424 @@
+/* tests/dcode.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::dcode::{GerberFlavor, LineAction, decide, process_d_codes};
+
+// (line, rule expected to decide it, line after processing), in the forms
+// Altium Designer writes them, from AD9 to AD24
+const ALTIUM_CORPUS: &[(&str, &str, &str)] = &[
+    // Header and attributes
+    ("G04*", "other", "G04*"),
+    (
+        "G04 Layer_Physical_Order=1*",
+        "other",
+        "G04 Layer_Physical_Order=1*",
+    ),
+    ("G04 Layer_Color=255*", "other", "G04 Layer_Color=255*"),
+    ("%FSLAX25Y25*%", "other", "%FSLAX25Y25*%"),
+    ("%FSLAX45Y45*%", "other", "%FSLAX45Y45*%"),
+    ("%MOIN*%", "other", "%MOIN*%"),
+    ("%MOMM*%", "other", "%MOMM*%"),
+    ("G70*", "other", "G70*"),
+    ("G71*", "other", "G71*"),
+    ("G01*", "other", "G01*"),
+    ("G02*", "other", "G02*"),
+    ("G75*", "other", "G75*"),
+    (
+        "%TF.FileFunction,Copper,L1,Top,Signal*%",
+        "other",
+        "%TF.FileFunction,Copper,L1,Top,Signal*%",
+    ),
+    (
+        "%TF.GenerationSoftware,Altium Limited,Altium Designer,24.1.2*%",
+        "other",
+        "%TF.GenerationSoftware,Altium Limited,Altium Designer,24.1.2*%",
+    ),
+    // Apertures and macros
+    ("%ADD10C,0.01000*%", "other", "%ADD10C,0.01000*%"),
+    (
+        "%ADD11R,0.06000X0.05000*%",
+        "other",
+        "%ADD11R,0.06000X0.05000*%",
+    ),
+    ("%ADD27ROUNDEDRECTD27*%", "other", "%ADD27ROUNDEDRECTD27*%"),
+    (
+        "%TA.AperFunction,SMDPad,CuDef*%",
+        "other",
+        "%TA.AperFunction,SMDPad,CuDef*%",
+    ),
+    (
+        "G04:AMPARAMS|DCode=27|XSize=1mm|YSize=0.6mm|CornerRadius=0.15mm|HoleSize=0mm|Usage=FLASHONLY|Rotation=0.000|XOffset=0mm|YOffset=0mm|HoleType=Round|Shape=RoundedRectangle|*",
+        "other",
+        "G04:AMPARAMS|DCode=27|XSize=1mm|YSize=0.6mm|CornerRadius=0.15mm|HoleSize=0mm|Usage=FLASHONLY|Rotation=0.000|XOffset=0mm|YOffset=0mm|HoleType=Round|Shape=RoundedRectangle|*",
+    ),
+    ("%AMROUNDEDRECTD27*", "other", "%AMROUNDEDRECTD27*"),
+    (
+        "21,1,0.40000,0.60000,0,0,0.0*",
+        "other",
+        "21,1,0.40000,0.60000,0,0,0.0*",
+    ),
+    // Aperture selection: AD9 prefixes it already, later versions do not
+    ("G54D10*", "already_prefixed", "G54D10*"),
+    ("D10*", "aperture_select", "G54D10*"),
+    ("D27*", "aperture_select", "G54D27*"),
+    ("D100*", "aperture_select", "G54D100*"),
+    ("D1000*", "aperture_select", "G54D1000*"),
+    // Draws and flashes with the current aperture
+    (
+        "G01X12345Y67890D02*",
+        "coordinate_operation",
+        "G01X12345Y67890D02*",
+    ),
+    ("G01X23456D01*", "coordinate_operation", "G01X23456D01*"),
+    (
+        "G02X1000Y2000I500J0D01*",
+        "coordinate_operation",
+        "G02X1000Y2000I500J0D01*",
+    ),
+    ("X23456D01*", "other", "X23456D01*"),
+    ("Y67890D01*", "other", "Y67890D01*"),
+    ("X-175Y-915D03*", "other", "X-175Y-915D03*"),
+    ("X-175Y-915G54D10*", "already_prefixed", "X-175Y-915G54D10*"),
+    // Regions
+    ("G36*", "other", "G36*"),
+    ("G37*", "other", "G37*"),
+    ("G36X100Y100D02*", "coordinate_operation", "G36X100Y100D02*"),
+    // Operations on their own select nothing
+    ("D01*", "operation_code", "D01*"),
+    ("D02*", "operation_code", "D02*"),
+    ("D03*", "operation_code", "D03*"),
+    // Malformed D-codes
+    ("D3*", "other", "D3*"),
+    ("D12345*", "other", "D12345*"),
+    ("M02*", "other", "M02*"),
+    ("", "other", ""),
+];
+
+// The same, in the forms KiCad writes them
+const KICAD_CORPUS: &[(&str, &str, &str)] = &[
+    ("%FSLAX46Y46*%", "other", "%FSLAX46Y46*%"),
+    ("%MOMM*%", "other", "%MOMM*%"),
+    (
+        "%ADD10C,0.150000*%",
+        "aperture_definition",
+        "%ADD10C,0.150000*%",
+    ),
+    (
+        "%ADD11RoundRect,0.250000X-0.450000X-0.262500X0.450000X-0.262500X0.450000X0.262500X-0.450000X0.262500X0*%",
+        "aperture_definition",
+        "%ADD11RoundRect,0.250000X-0.450000X-0.262500X0.450000X-0.262500X0.450000X0.262500X-0.450000X0.262500X0*%",
+    ),
+    (
+        "%TA.AperFunction,Conductor*%",
+        "other",
+        "%TA.AperFunction,Conductor*%",
+    ),
+    ("D10*", "aperture_select", "G54D10*"),
+    ("D11*", "aperture_select", "G54D11*"),
+    ("G54D10*", "already_prefixed", "G54D10*"),
+    ("X-1125Y-965D02*", "other", "X-1125Y-965D02*"),
+    ("X100Y200D11*", "other", "X100Y200D11*"),
+    ("X50D12*Y50D13*", "other", "X50D12*Y50D13*"),
+    ("G01*", "other", "G01*"),
+    ("G36*", "other", "G36*"),
+    ("D02*", "operation_code", "D02*"),
+    ("M02*", "other", "M02*"),
+];
+
+fn check_corpus(flavor: GerberFlavor, corpus: &[(&str, &str, &str)]) {
+    for &(line, rule, expected) in corpus {
+        assert_eq!(decide(line, flavor).id, rule, "{:?}: {:?}", flavor, line);
+        assert_eq!(
+            process_d_codes(line.to_string(), flavor),
+            expected,
+            "{:?}: {:?}",
+            flavor,
+            line
+        );
+    }
+}
+
+#[test]
+fn altium_lines_follow_the_decision_table() {
+    check_corpus(GerberFlavor::Altium, ALTIUM_CORPUS);
+}
+
+#[test]
+fn kicad_lines_follow_the_decision_table() {
+    check_corpus(GerberFlavor::KiCad, KICAD_CORPUS);
+}
+
+#[test]
+fn every_table_ends_with_a_rule_keeping_any_line() {
+    for flavor in [GerberFlavor::KiCad, GerberFlavor::Altium] {
+        let last = flavor.rules().last().expect("Tables are not empty");
+        assert!((last.matches)("anything"));
+        assert_eq!(last.action, LineAction::Keep);
+    }
+}
+
+#[test]
+fn whole_layers_keep_their_line_structure() {
+    let layer = ALTIUM_CORPUS
+        .iter()
+        .map(|(line, _, _)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let expected = ALTIUM_CORPUS
+        .iter()
+        .map(|(_, _, line)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(process_d_codes(layer, GerberFlavor::Altium), expected);
+}
+
+// Excerpts of top copper layers as each Altium Designer version writes them,
+// with the layer D-code processing turns them into
+
+// AD9 (Summer 09): inch 2:5, and aperture selections already prefixed
+const AD9_TOP_LAYER: &str = "G04*
+G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%FSLAX25Y25*%
+%MOIN*%
+G70*
+G01*
+G75*
+%ADD10C,0.01000*%
+%ADD11R,0.06000X0.05000*%
+G54D10*
+G01X110000Y110000D02*
+X150000D01*
+Y130000D01*
+G54D11*
+X120000Y130000D03*
+X140000D03*
+M02*
+";
+
+const AD9_TOP_LAYER_PROCESSED: &str = AD9_TOP_LAYER;
+
+// AD17: the same units, with bare aperture selections
+const AD17_TOP_LAYER: &str = "G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%FSLAX25Y25*%
+%MOIN*%
+G70*
+G04*
+G04 #@! TF.FileFunction,Copper,L1,Top,Signal*
+G04 #@! TF.Part,Single*
+G01*
+G75*
+%ADD10C,0.01000*%
+%ADD11R,0.06000X0.05000*%
+%ADD12C,0.02400*%
+D10*
+X110000Y110000D02*
+X150000Y110000D01*
+D11*
+X120000Y130000D03*
+D12*
+X-175Y-915D03*
+M02*
+";
+
+const AD17_TOP_LAYER_PROCESSED: &str = "G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%FSLAX25Y25*%
+%MOIN*%
+G70*
+G04*
+G04 #@! TF.FileFunction,Copper,L1,Top,Signal*
+G04 #@! TF.Part,Single*
+G01*
+G75*
+%ADD10C,0.01000*%
+%ADD11R,0.06000X0.05000*%
+%ADD12C,0.02400*%
+G54D10*
+X110000Y110000D02*
+X150000Y110000D01*
+G54D11*
+X120000Y130000D03*
+G54D12*
+X-175Y-915D03*
+M02*
+";
+
+// AD22: metric 4:5, X2 attributes as comments, arcs and regions
+const AD22_TOP_LAYER: &str = "G04*
+G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%FSLAX45Y45*%
+%MOMM*%
+G71*
+G04*
+G04 #@! TF.GenerationSoftware,Altium Limited,Altium Designer,22.4.2 (48)*
+G04 #@! TF.FileFunction,Copper,L1,Top,Signal*
+G04 #@! TF.Part,Single*
+G01*
+G75*
+G04 #@! TA.AperFunction,SMDPad,CuDef*
+%ADD10R,1.50000X1.30000*%
+G04 #@! TA.AperFunction,Conductor*
+%ADD11C,0.25400*%
+G04 #@! TD*
+D10*
+X1000000Y1000000D03*
+D11*
+X1000000Y1000000D02*
+G01X2000000D01*
+G02X2500000Y1500000I0J500000D01*
+G01*
+G36*
+X3000000Y3000000D02*
+X3500000D01*
+Y3500000D01*
+X3000000D01*
+Y3000000D01*
+G37*
+M02*
+";
+
+const AD22_TOP_LAYER_PROCESSED: &str = "G04*
+G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%FSLAX45Y45*%
+%MOMM*%
+G71*
+G04*
+G04 #@! TF.GenerationSoftware,Altium Limited,Altium Designer,22.4.2 (48)*
+G04 #@! TF.FileFunction,Copper,L1,Top,Signal*
+G04 #@! TF.Part,Single*
+G01*
+G75*
+G04 #@! TA.AperFunction,SMDPad,CuDef*
+%ADD10R,1.50000X1.30000*%
+G04 #@! TA.AperFunction,Conductor*
+%ADD11C,0.25400*%
+G04 #@! TD*
+G54D10*
+X1000000Y1000000D03*
+G54D11*
+X1000000Y1000000D02*
+G01X2000000D01*
+G02X2500000Y1500000I0J500000D01*
+G01*
+G36*
+X3000000Y3000000D02*
+X3500000D01*
+Y3500000D01*
+X3000000D01*
+Y3000000D01*
+G37*
+M02*
+";
+
+// AD24: extended X2 attributes and rounded rectangle macros
+const AD24_TOP_LAYER: &str = "%TF.GenerationSoftware,Altium Limited,Altium Designer,24.1.2*%
+G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%TF.FilePolarity,Positive*%
+%TF.FileFunction,Copper,L1,Top,Signal*%
+%TF.Part,Single*%
+G01*
+G75*
+%TA.AperFunction,SMDPad,CuDef*%
+%FSLAX45Y45*%
+%MOMM*%
+G71*
+G04:AMPARAMS|DCode=27|XSize=1mm|YSize=0.6mm|CornerRadius=0.15mm|HoleSize=0mm|Usage=FLASHONLY|Rotation=0.000|XOffset=0mm|YOffset=0mm|HoleType=Round|Shape=RoundedRectangle|*
+%AMROUNDEDRECTD27*
+21,1,0.40000,0.60000,0,0,0.0*
+21,1,1.00000,0.30000,0,0,0.0*
+1,1,0.30000,0.35000,-0.15000*
+1,1,0.30000,-0.35000,-0.15000*
+1,1,0.30000,-0.35000,0.15000*
+1,1,0.30000,0.35000,0.15000*
+%
+%ADD27ROUNDEDRECTD27*%
+%TD*%
+%TA.AperFunction,Conductor*%
+%ADD28C,0.25400*%
+%TD*%
+D27*
+X1000000Y1000000D03*
+X1200000D03*
+D28*
+G01X1000000Y1000000D02*
+X1200000D01*
+%TF.MD5,0123456789abcdef0123456789abcdef*%
+M02*
+";
+
+const AD24_TOP_LAYER_PROCESSED: &str = "%TF.GenerationSoftware,Altium Limited,Altium Designer,24.1.2*%
+G04 Layer_Physical_Order=1*
+G04 Layer_Color=255*
+%TF.FilePolarity,Positive*%
+%TF.FileFunction,Copper,L1,Top,Signal*%
+%TF.Part,Single*%
+G01*
+G75*
+%TA.AperFunction,SMDPad,CuDef*%
+%FSLAX45Y45*%
+%MOMM*%
+G71*
+G04:AMPARAMS|DCode=27|XSize=1mm|YSize=0.6mm|CornerRadius=0.15mm|HoleSize=0mm|Usage=FLASHONLY|Rotation=0.000|XOffset=0mm|YOffset=0mm|HoleType=Round|Shape=RoundedRectangle|*
+%AMROUNDEDRECTD27*
+21,1,0.40000,0.60000,0,0,0.0*
+21,1,1.00000,0.30000,0,0,0.0*
+1,1,0.30000,0.35000,-0.15000*
+1,1,0.30000,-0.35000,-0.15000*
+1,1,0.30000,-0.35000,0.15000*
+1,1,0.30000,0.35000,0.15000*
+%
+%ADD27ROUNDEDRECTD27*%
+%TD*%
+%TA.AperFunction,Conductor*%
+%ADD28C,0.25400*%
+%TD*%
+G54D27*
+X1000000Y1000000D03*
+X1200000D03*
+G54D28*
+G01X1000000Y1000000D02*
+X1200000D01*
+%TF.MD5,0123456789abcdef0123456789abcdef*%
+M02*
+";
+
+#[test]
+fn ad9_layers_are_left_as_they_are() {
+    assert_eq!(
+        process_d_codes(AD9_TOP_LAYER.to_string(), GerberFlavor::Altium),
+        AD9_TOP_LAYER_PROCESSED
+    );
+}
+
+#[test]
+fn ad17_layers_get_prefixed_selections() {
+    assert_eq!(
+        process_d_codes(AD17_TOP_LAYER.to_string(), GerberFlavor::Altium),
+        AD17_TOP_LAYER_PROCESSED
+    );
+}
+
+#[test]
+fn ad22_layers_get_prefixed_selections() {
+    assert_eq!(
+        process_d_codes(AD22_TOP_LAYER.to_string(), GerberFlavor::Altium),
+        AD22_TOP_LAYER_PROCESSED
+    );
+}
+
+#[test]
+fn ad24_layers_get_prefixed_selections() {
+    assert_eq!(
+        process_d_codes(AD24_TOP_LAYER.to_string(), GerberFlavor::Altium),
+        AD24_TOP_LAYER_PROCESSED
+    );
+}
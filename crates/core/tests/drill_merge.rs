@@ -0,0 +1,455 @@
+/* tests/drill_merge.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::drill::{
+    DrillCommand, DrillOutputOptions, HoleType, ROUTE_BIT_MM, ZeroFormat, generate_jlc_excellon,
+    generate_jlc_excellon_with, merge_and_split_drills_with_dedupe, merge_drill_files,
+    merge_drill_files_with_tolerance, parse_ad_excellon, parse_excellon,
+    parse_excellon_with_report, parse_kicad_excellon, process_drill_files, route_holes,
+    take_oversized_holes,
+};
+
+// Tool number, diameter in mm and hole positions in whole mm
+type Tool<'a> = (u32, f64, &'a [(u32, u32)]);
+
+// An Altium drill file with FILE_FORMAT=2:5 metric coordinates
+fn ad_drill(plating: &str, tools: &[Tool]) -> String {
+    let mut content = format!(
+        "M48\n;Layer_Color=9474304\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE={}\n",
+        plating
+    );
+    for (tool, diameter, _) in tools {
+        content.push_str(&format!("T{}F00S00C{:.5}\n", tool, diameter));
+    }
+    content.push_str("%\n");
+    for (tool, _, holes) in tools {
+        content.push_str(&format!("T{:02}\n", tool));
+        for (x, y) in *holes {
+            content.push_str(&format!("X{:07}Y{:07}\n", x * 100000, y * 100000));
+        }
+    }
+    content.push_str("M30\n");
+    content
+}
+
+fn names(files: &[&str]) -> Vec<String> {
+    files.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn same_tool_number_with_different_diameters_stays_apart() {
+    let a = ad_drill(
+        "PLATED",
+        &[(1, 0.3, &[(1, 1), (2, 1)]), (2, 0.8, &[(3, 3)])],
+    );
+    let b = ad_drill("PLATED", &[(1, 0.8, &[(4, 4)]), (2, 3.0, &[(5, 5)])]);
+    let merged = merge_drill_files(vec![
+        ("a-RoundHoles.TXT".to_string(), parse_ad_excellon(&a)),
+        ("b-RoundHoles.TXT".to_string(), parse_ad_excellon(&b)),
+    ]);
+
+    let pth = merged.pth.expect("plated holes");
+    let sizes: Vec<(f64, usize)> = pth
+        .operations
+        .iter()
+        .map(|op| (op.diameter, op.commands.len()))
+        .collect();
+    assert_eq!(sizes, vec![(0.3, 2), (0.8, 2), (3.0, 1)]);
+    assert!(merged.npth.is_none());
+
+    let output_tool = |file: &str, tool: u32| {
+        merged
+            .tools
+            .iter()
+            .find(|t| t.file == file && t.source_tool == tool)
+            .map(|t| t.output_tool)
+    };
+    assert_eq!(output_tool("a-RoundHoles.TXT", 1), Some(1));
+    assert_eq!(output_tool("a-RoundHoles.TXT", 2), Some(2));
+    assert_eq!(output_tool("b-RoundHoles.TXT", 1), Some(2));
+    assert_eq!(output_tool("b-RoundHoles.TXT", 2), Some(3));
+    // Tool numbers clash in every multi-file export; the assignments say it all
+    assert!(merged.warnings.is_empty());
+}
+
+#[test]
+fn output_tools_are_numbered_per_hole_type() {
+    let plated = ad_drill("PLATED", &[(1, 0.4, &[(1, 1)])]);
+    let non_plated = ad_drill("NON_PLATED", &[(1, 3.2, &[(2, 2)]), (2, 0.4, &[(3, 3)])]);
+    let merged = merge_drill_files(vec![
+        ("board-Plated.TXT".to_string(), parse_ad_excellon(&plated)),
+        (
+            "board-NonPlated.TXT".to_string(),
+            parse_ad_excellon(&non_plated),
+        ),
+    ]);
+
+    assert_eq!(merged.pth.expect("plated holes").operations.len(), 1);
+    assert_eq!(merged.npth.expect("non-plated holes").operations.len(), 2);
+    let npth: Vec<(u32, u32)> = merged
+        .tools
+        .iter()
+        .filter(|t| t.hole_type == HoleType::NonPlated)
+        .map(|t| (t.source_tool, t.output_tool))
+        .collect();
+    assert_eq!(npth, vec![(2, 1), (1, 2)]);
+}
+
+#[test]
+fn multi_file_export_writes_every_hole_once() {
+    let a = ad_drill(
+        "PLATED",
+        &[(1, 0.3, &[(1, 1), (2, 1)]), (2, 0.8, &[(3, 3)])],
+    );
+    let b = ad_drill("PLATED", &[(1, 0.8, &[(4, 4)]), (2, 3.0, &[(5, 5)])]);
+    let result = process_drill_files(&[a, b], &names(&["a-RoundHoles.TXT", "b-SlotHoles.TXT"]));
+
+    let pth = result.pth_content.expect("plated output");
+    assert!(pth.contains("T01C0.30000\n"));
+    assert!(pth.contains("T02C0.80000\n"));
+    assert!(pth.contains("T03C3.00000\n"));
+    let holes = pth.lines().filter(|line| line.starts_with('X')).count();
+    assert_eq!(holes, 5);
+    assert!(pth.contains("T02\nX3.00000Y3.00000\nX4.00000Y4.00000\n"));
+    assert_eq!(result.tools.len(), 4);
+}
+
+#[test]
+fn kicad_file_is_merged_with_altium_file() {
+    let ad = ad_drill("PLATED", &[(1, 0.3, &[(1, 1)])]);
+    let kicad = "M48\n; DRILL file {KiCad 8.0.6} date 2024-11-23\nFMAT,2\nMETRIC\nT1C0.300\nT2C1.000\n%\nG90\nG05\nT1\nX10.0Y5.0\nT2\nX12.0Y5.0\nM30\n";
+    let result = process_drill_files(
+        &[ad, kicad.to_string()],
+        &names(&["board-RoundHoles.TXT", "board-PTH.drl"]),
+    );
+
+    let pth = result.pth_content.expect("plated output");
+    assert!(pth.contains("T01C0.30000\n"));
+    assert!(pth.contains("T02C1.00000\n"));
+    assert_eq!(pth.lines().filter(|line| line.starts_with('X')).count(), 3);
+}
+
+#[test]
+fn near_equal_diameters_merge_within_tolerance() {
+    let a = ad_drill("PLATED", &[(1, 0.99999, &[(1, 1)]), (2, 1.2, &[(2, 2)])]);
+    let b = ad_drill("PLATED", &[(1, 1.0, &[(3, 3), (4, 4)])]);
+    let files = || {
+        vec![
+            ("a-RoundHoles.TXT".to_string(), parse_ad_excellon(&a)),
+            ("b-RoundHoles.TXT".to_string(), parse_ad_excellon(&b)),
+        ]
+    };
+
+    let merged = merge_drill_files(files());
+    let sizes: Vec<(f64, usize)> = merged
+        .pth
+        .expect("plated holes")
+        .operations
+        .iter()
+        .map(|op| (op.diameter, op.commands.len()))
+        .collect();
+    // The merged tool takes the size of the tool with the most holes
+    assert_eq!(sizes, vec![(1.0, 3), (1.2, 1)]);
+    assert!(merged.warnings.is_empty());
+
+    let exact = merge_drill_files_with_tolerance(files(), 0.0);
+    assert_eq!(exact.pth.expect("plated holes").operations.len(), 3);
+}
+
+#[test]
+fn coincident_holes_are_removed_once() {
+    let vias = ad_drill("PLATED", &[(1, 0.3, &[(1, 1), (2, 2)])]);
+    let pth = ad_drill("PLATED", &[(1, 1.0, &[(1, 1), (5, 5)])]);
+    let files = || vec![parse_ad_excellon(&vias), parse_ad_excellon(&pth)];
+
+    let (pth, _, removed) = merge_and_split_drills_with_dedupe(files(), Some(0.01));
+    assert_eq!(removed, 1);
+    let sizes: Vec<(f64, usize)> = pth
+        .expect("plated holes")
+        .operations
+        .iter()
+        .map(|op| (op.diameter, op.commands.len()))
+        .collect();
+    // The larger hole at (1, 1) is kept
+    assert_eq!(sizes, vec![(0.3, 1), (1.0, 2)]);
+
+    let (_, _, removed) = merge_and_split_drills_with_dedupe(files(), None);
+    assert_eq!(removed, 0);
+}
+
+#[test]
+fn unmanufacturable_tools_are_reported() {
+    let drill = ad_drill(
+        "PLATED",
+        &[
+            (1, 0.1, &[(1, 1)]),
+            (2, 0.5, &[(2, 2)]),
+            (3, 8.0, &[(3, 3)]),
+        ],
+    );
+    let result = process_drill_files(&[drill], &names(&["board-RoundHoles.TXT"]));
+
+    let about = |tool: &str| {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with(&format!("PTH {}", tool)))
+            .count()
+    };
+    assert_eq!(about("T01"), 1);
+    assert_eq!(about("T02"), 0);
+    assert_eq!(about("T03"), 1);
+    assert!(
+        result
+            .warnings
+            .iter()
+            .any(|w| w.contains("maximum drill of 6.3 mm"))
+    );
+}
+
+#[test]
+fn oversized_holes_become_routed_circles() {
+    let drill = ad_drill(
+        "NON_PLATED",
+        &[(1, 3.0, &[(1, 1)]), (2, 8.0, &[(10, 10), (20, 20)])],
+    );
+    let mut drill = parse_ad_excellon(&drill);
+
+    let holes = take_oversized_holes(&mut drill, 6.3);
+    assert_eq!(holes.len(), 2);
+    assert_eq!(drill.operations.len(), 1);
+
+    route_holes(&mut drill, &holes, HoleType::NonPlated);
+    let bit = &drill.operations[0];
+    assert_eq!((bit.tool, bit.diameter), (1, ROUTE_BIT_MM));
+    let DrillCommand::Route { points } = &bit.commands[0] else {
+        panic!("expected a route");
+    };
+    // The bit's outer edge follows the 8 mm hole
+    let radius = (8.0 - ROUTE_BIT_MM) / 2.0;
+    assert!(
+        points
+            .iter()
+            .all(|p| ((p.x - 10.0).hypot(p.y - 10.0) - radius).abs() < 1e-6)
+    );
+    assert_eq!(points.first(), points.last());
+    assert_eq!(drill.operations[1].tool, 2);
+}
+
+#[test]
+fn headerless_inch_file_is_read_in_mm() {
+    // No M48, unit or format: 2:4 inch coordinates and inch tool sizes
+    let drill = parse_ad_excellon("T1C0.0310\nX012500Y003000\nX028000Y021000\nM30\n");
+
+    let op = &drill.operations[0];
+    assert!((op.diameter - 0.7874).abs() < 1e-6);
+    let DrillCommand::Hole { x, y } = op.commands[1] else {
+        panic!("expected a hole");
+    };
+    assert!((x - 71.12).abs() < 1e-6 && (y - 53.34).abs() < 1e-6);
+    assert!(drill.warnings.iter().any(|w| w.contains("read as inch")));
+}
+
+#[test]
+fn allegro_tools_are_sized_from_the_tool_report() {
+    let drill =
+        "M48\nMETRIC,LZ\n;FILE_FORMAT=3:3\n%\nT01\nX010000Y010000\nT02\nX020000Y020000\nM30\n";
+    let report = "Units: MILS\nT01  12.00  PLATED  1\nT02  125.00  NON_PLATED  1\n";
+
+    let bare = parse_ad_excellon(drill);
+    assert!(bare.operations.iter().all(|op| op.diameter == 0.0));
+    assert_eq!(bare.warnings.len(), 2);
+
+    let drill = parse_excellon_with_report(drill, report);
+    assert!((drill.operations[0].diameter - 0.3048).abs() < 1e-6);
+    assert_eq!(drill.operations[1].hole_type, HoleType::NonPlated);
+    assert!(drill.warnings.is_empty());
+}
+
+#[test]
+fn x2_attributes_give_each_tool_its_plating() {
+    // A KiCad file with plated and non-plated holes merged
+    let drill = "M48\n; DRILL file {KiCad 8.0.1} date 2024-05-01\n; FORMAT={-:-/ absolute / metric / decimal}\n\
+        ; #@! TF.FileFunction,MixedPlating,1,2\nFMAT,2\nMETRIC\n\
+        ; #@! TA.AperFunction,Plated,PTH,ViaDrill\nT1C0.300\n\
+        ; #@! TA.AperFunction,NonPlated,NPTH,ComponentDrill\nT2C3.200\n%\n\
+        G90\nG05\nT1\nX10.0Y10.0\nT2\nX20.0Y20.0\nM30\n";
+
+    let (drill, _) = parse_kicad_excellon(drill);
+    let types: Vec<HoleType> = drill.operations.iter().map(|op| op.hole_type).collect();
+    assert_eq!(types, vec![HoleType::Plated, HoleType::NonPlated]);
+}
+
+#[test]
+fn merged_kicad_file_is_split_into_pth_and_npth() {
+    let drill = "M48\n; DRILL file {KiCad 8.0.1} date 2024-05-01T10:00:00\n\
+        ; #@! TF.FileFunction,MixedPlating,1,2\nFMAT,2\nMETRIC\n\
+        ; #@! TA.AperFunction,Plated,PTH,ViaDrill\nT1C0.300\n\
+        ; #@! TA.AperFunction,Plated,PTH,ComponentDrill\nT2C1.000\n\
+        ; #@! TA.AperFunction,NonPlated,NPTH,ComponentDrill\nT3C3.200\n%\n\
+        G90\nG05\nT1\nX10.0Y10.0\nT2\nX15.0Y15.0\nT3\nX20.0Y20.0\nM30\n";
+
+    let result = process_drill_files(&[drill.to_string()], &["board.drl".to_string()]);
+    let pth = result.pth_content.expect("plated holes");
+    let npth = result.npth_content.expect("non-plated holes");
+    assert!(pth.contains("T02C1.00000") && !pth.contains("3.20000"));
+    assert!(npth.contains("T01C3.20000") && npth.contains("X20.00000Y20.00000"));
+
+    // Without tool attributes the plating cannot be told apart
+    let unmarked: String = drill
+        .lines()
+        .filter(|line| !line.contains("TA.AperFunction"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    let result = process_drill_files(&[unmarked], &["board.drl".to_string()]);
+    assert!(result.npth_content.is_none());
+    assert!(
+        result
+            .warnings
+            .iter()
+            .any(|w| w.contains("without per-tool plating"))
+    );
+}
+
+#[test]
+fn unit_switches_in_the_body_apply_to_later_coordinates() {
+    // Metric 3:3 header, then inch coordinates after M72 and metric again after M71
+    let drill = parse_ad_excellon(
+        "M48\nMETRIC,LZ,000.000\nT1C0.800\n%\nT1\nX010000Y010000\nM72\nX001000Y002000\nM71\nX020000Y020000\nM30\n",
+    );
+    let holes: Vec<(f64, f64)> = drill.operations[0]
+        .commands
+        .iter()
+        .map(|c| match c {
+            DrillCommand::Hole { x, y } => (*x, *y),
+            _ => panic!("expected holes"),
+        })
+        .collect();
+    let expected = [(10.0, 10.0), (25.4, 50.8), (20.0, 20.0)];
+    for ((x, y), (ex, ey)) in holes.iter().zip(expected) {
+        assert!(
+            (x - ex).abs() < 1e-6 && (y - ey).abs() < 1e-6,
+            "{:?}",
+            holes
+        );
+    }
+
+    let (kicad, _) = parse_kicad_excellon(
+        "M48\n; DRILL file {KiCad 8.0.1}\nINCH\nT1C0.0315\n%\nG90\nG05\nT1\nX1.0Y2.0\nM71\nX10.0Y10.0\nM30\n",
+    );
+    let op = &kicad.operations[0];
+    assert!((op.diameter - 0.8001).abs() < 1e-6);
+    assert!(matches!(op.commands[0], DrillCommand::Hole { x, .. } if (x - 25.4).abs() < 1e-6));
+    assert!(matches!(op.commands[1], DrillCommand::Hole { x, .. } if (x - 10.0).abs() < 1e-6));
+}
+
+#[test]
+fn output_format_options_are_written_and_read_back() {
+    let drill = parse_ad_excellon(&ad_drill("PLATED", &[(1, 0.8, &[(10, 2), (0, 5)])]));
+
+    let default = generate_jlc_excellon(&drill, HoleType::Plated);
+    assert!(default.contains("METRIC,LZ,0000.00000\n"));
+    assert!(default.contains("T01C0.80000\n"));
+    assert!(default.contains("T01\nX10.00000Y2.00000\n"));
+
+    for (zeros, expected) in [
+        (ZeroFormat::Leading, "X010Y002\nX000Y005\n"),
+        (ZeroFormat::Trailing, "X10000Y2000\nX0Y5000\n"),
+    ] {
+        let options = DrillOutputOptions {
+            zeros,
+            integer_digits: 3,
+            decimal_digits: 3,
+            decimal_point: false,
+        };
+        let output = generate_jlc_excellon_with(&drill, HoleType::Plated, &options);
+        assert!(output.contains(&format!("METRIC,{},000.000\n", zeros.id())));
+        assert!(output.contains("T01C0.800\n"));
+        assert!(output.contains(expected), "{}", output);
+
+        let read = parse_excellon(&output);
+        let holes: Vec<String> = read.operations[0]
+            .commands
+            .iter()
+            .map(|c| format!("{:?}", c))
+            .collect();
+        assert_eq!(
+            holes,
+            drill.operations[0]
+                .commands
+                .iter()
+                .map(|c| format!("{:?}", c))
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn drill_report_counts_holes_and_checks_tool_comments() {
+    let kicad = "M48\n; DRILL file {KiCad 8.0.1} date 2024-05-01T10:00:00\n\
+        ; #@! TF.FileFunction,MixedPlating,1,2\nFMAT,2\nMETRIC\n\
+        ; #@! TA.AperFunction,Plated,PTH,ViaDrill\nT1C0.300\n\
+        ; #@! TA.AperFunction,NonPlated,NPTH,ComponentDrill\nT2C3.200\n%\n\
+        G90\nG05\nT1\nX10.0Y10.0\nX12.0Y10.0\nT2\nX20.0Y20.0\nG00X30.0Y20.0\nM15\nG01X35.0Y20.0\nM16\nG05\nM30\n";
+    // An OrCAD file whose tool comment counts one hole more than it has
+    let orcad = "M48\n; Holesize 1. = 40.000000 Tolerance = +0.000000/-0.000000 PLATED MILS Quantity = 2\n\
+        INCH,LZ\n;FILE_FORMAT=2:4\nT1C0.0400\n%\nT1\nX003000Y003000\nM30\n";
+
+    let result = process_drill_files(
+        &[kicad.to_string(), orcad.to_string()],
+        &names(&["board.drl", "board-orcad.drl"]),
+    );
+    let report = &result.report;
+    let tools: Vec<(u32, usize, usize)> = report
+        .tools
+        .iter()
+        .map(|t| (t.tool, t.holes, t.slots))
+        .collect();
+    assert_eq!(tools, vec![(1, 2, 0), (2, 1, 0), (1, 1, 1)]);
+    assert_eq!((report.pth.holes, report.npth.holes), (3, 1));
+    assert_eq!((report.total_holes(), report.slot_count()), (4, 1));
+    assert_eq!(report.smallest_drill, Some(0.3));
+    assert_eq!(report.largest_drill, Some(3.2));
+
+    assert!(
+        result
+            .warnings
+            .iter()
+            .any(|w| w == "board-orcad.drl: T1 has 1 holes, but its tool comment lists 2")
+    );
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn drill_files_stream_into_an_archive() {
+    use nextjlc::drill::write_jlc_excellon;
+    use nextjlc::sink::{OutputSink, ZipSink};
+
+    let drill = parse_ad_excellon(&ad_drill("PLATED", &[(1, 0.8, &[(10, 2), (0, 5)])]));
+    let options = DrillOutputOptions::default();
+    let mut sink = ZipSink::new(Vec::new());
+    sink.write_file_with("Drill_PTH_Through.DRL", &mut |out| {
+        write_jlc_excellon(out, &drill, HoleType::Plated, &options)
+    })
+    .expect("streamed entry");
+    sink.finish().expect("archive");
+
+    let archive = sink.into_inner().expect("finished archive");
+    // Streaming unzippers take the size from the local header, not a data descriptor
+    let flags = u16::from_le_bytes([archive[6], archive[7]]);
+    assert_eq!(flags & (1 << 3), 0);
+    let size = u32::from_le_bytes([archive[22], archive[23], archive[24], archive[25]]);
+    let entries = nextjlc::zip::read_zip(&archive).expect("readable archive");
+    let streamed = String::from_utf8(entries[0].data.clone()).expect("text");
+    assert_eq!(size as usize, streamed.len());
+    // The header differs per call, in its generator version and time
+    let body = |content: &str| content[content.find("M48").expect("M48")..].to_string();
+    assert_eq!(
+        body(&streamed),
+        body(&generate_jlc_excellon(&drill, HoleType::Plated))
+    );
+}
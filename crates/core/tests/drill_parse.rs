@@ -0,0 +1,161 @@
+/* tests/drill_parse.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::drill::{DrillCommand, DrillFile, parse_ad_excellon, parse_kicad_excellon};
+
+// A drill file with a 1 mm tool T1 and FILE_FORMAT=3:3 metric coordinates
+fn metric_drill(header: &str, body: &str) -> String {
+    format!(
+        "M48\nMETRIC,LZ\n;FILE_FORMAT=3:3\n{}T1C1.000\n%\nT1\n{}M30\n",
+        header, body
+    )
+}
+
+fn holes(drill: &DrillFile) -> Vec<(f64, f64)> {
+    drill.operations[0]
+        .commands
+        .iter()
+        .map(|command| match command {
+            DrillCommand::Hole { x, y } => (*x, *y),
+            DrillCommand::Route { .. } => panic!("expected holes"),
+        })
+        .collect()
+}
+
+fn routes(drill: &DrillFile) -> Vec<Vec<(f64, f64)>> {
+    drill.operations[0]
+        .commands
+        .iter()
+        .map(|command| match command {
+            DrillCommand::Route { points } => points.iter().map(|p| (p.x, p.y)).collect(),
+            DrillCommand::Hole { .. } => panic!("expected routes"),
+        })
+        .collect()
+}
+
+fn assert_points(points: &[(f64, f64)], expected: &[(f64, f64)]) {
+    assert_eq!(points.len(), expected.len(), "{:?}", points);
+    for ((x, y), (ex, ey)) in points.iter().zip(expected) {
+        assert!(
+            (x - ex).abs() < 1e-6 && (y - ey).abs() < 1e-6,
+            "{:?} != {:?}",
+            points,
+            expected
+        );
+    }
+}
+
+#[test]
+fn incremental_coordinates_add_to_the_last_hole() {
+    // ICI,ON in the header, back to absolute with G90
+    let drill = parse_ad_excellon(&metric_drill(
+        "ICI,ON\n",
+        "X010000Y010000\nX005000\nY-002000\nG90\nX001000Y001000\n",
+    ));
+    assert_points(
+        &holes(&drill),
+        &[(10.0, 10.0), (15.0, 10.0), (15.0, 8.0), (1.0, 1.0)],
+    );
+
+    // G91 in the body, from that point on
+    let drill = parse_ad_excellon(&metric_drill(
+        "",
+        "X010000Y010000\nG91\nX002500Y002500\nX002500\n",
+    ));
+    assert_points(&holes(&drill), &[(10.0, 10.0), (12.5, 12.5), (15.0, 12.5)]);
+}
+
+#[test]
+fn kicad_holes_may_give_one_axis() {
+    let (drill, _) = parse_kicad_excellon(
+        "M48\n; DRILL file {KiCad 8.0.0} date 2024-01-01\nMETRIC\nT1C1.000\n%\nG90\nT1\n\
+        X10.0Y10.0\nG91\nX1.0\nY1.0\nX1.0Y0\nM30\n",
+    );
+    assert_points(
+        &holes(&drill),
+        &[(10.0, 10.0), (11.0, 10.0), (11.0, 11.0), (12.0, 11.0)],
+    );
+}
+
+#[test]
+fn routed_arcs_follow_their_circle() {
+    // A counterclockwise quarter by its center offset, and back clockwise
+    // by its radius, both around the origin
+    let drill = parse_ad_excellon(&metric_drill(
+        "",
+        "G00X010000Y000000\nM15\nG03X000000Y010000I-010000J000000\nG02X010000Y000000A010000\nM16\n",
+    ));
+    let routes = routes(&drill);
+    let points = &routes[0];
+    assert_eq!(routes.len(), 1);
+    assert!(points.len() > 4, "{:?}", points);
+    assert_points(&points[..1], &[(10.0, 0.0)]);
+    assert_points(&points[points.len() - 1..], &[(10.0, 0.0)]);
+    let turn = points
+        .iter()
+        .position(|&(x, y)| x.abs() < 1e-6 && (y - 10.0).abs() < 1e-6)
+        .expect("the arcs meet at (0, 10)");
+    assert!(0 < turn && turn < points.len() - 1);
+    for &(x, y) in points {
+        assert!((x.hypot(y) - 10.0).abs() < 0.01, "{:?}", (x, y));
+        assert!(x > -1e-6 && y > -1e-6, "{:?}", (x, y));
+    }
+}
+
+#[test]
+fn routed_polylines_keep_every_point() {
+    let drill = parse_ad_excellon(&metric_drill(
+        "",
+        "G00X010000Y010000\nM15\nG01X020000Y010000\nG01Y020000\nG01X010000\nM16\n\
+        G00X030000Y030000\nM15\nG01X040000Y030000\nM16\n",
+    ));
+    let routes = routes(&drill);
+    assert_eq!(routes.len(), 2);
+    assert_points(
+        &routes[0],
+        &[(10.0, 10.0), (20.0, 10.0), (20.0, 20.0), (10.0, 20.0)],
+    );
+    assert_points(&routes[1], &[(30.0, 30.0), (40.0, 30.0)]);
+}
+
+#[test]
+fn repeat_codes_step_from_the_last_hole() {
+    let drill = parse_ad_excellon(&metric_drill(
+        "",
+        "X010000Y010000\nR3X002540\nY020000\nR2Y-001000\n",
+    ));
+    assert_points(
+        &holes(&drill),
+        &[
+            (10.0, 10.0),
+            (12.54, 10.0),
+            (15.08, 10.0),
+            (17.62, 10.0),
+            (17.62, 20.0),
+            (17.62, 19.0),
+            (17.62, 18.0),
+        ],
+    );
+}
+
+#[test]
+fn three_three_format_is_inferred_from_the_coordinates() {
+    // No FILE_FORMAT: 6 digits fit 80 mm as 3:3, but 800 mm as 4:2
+    let drill =
+        parse_ad_excellon("M48\nMETRIC,LZ\nT1C1.000\n%\nT1\nX050000Y080000\nX025000Y040000\nM30\n");
+    assert_points(&holes(&drill), &[(50.0, 80.0), (25.0, 40.0)]);
+}
+
+#[test]
+fn headerless_metric_file_is_read_from_its_numbers() {
+    // No M48, unit or format: the tool size says mm, the coordinates 3:3
+    let drill = parse_ad_excellon("T1C0.800\nX012500Y030000\nX040250Y010500\nM30\n");
+    assert!((drill.operations[0].diameter - 0.8).abs() < 1e-6);
+    assert_points(&holes(&drill), &[(12.5, 30.0), (40.25, 10.5)]);
+    assert!(drill.warnings.iter().any(|w| w.contains("no M48 header")));
+    assert!(drill.warnings.iter().any(|w| w.contains("read as mm")));
+}
@@ -0,0 +1,35 @@
+/* tests/fingerprint.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::fingerprint::{FingerprintMode, add_fingerprint_with_mode};
+
+// An AD9-style layer whose sixth aperture, the one the fingerprint is
+// based on, is an octagon with a signed modifier
+const AD9_LAYER: &str = "G04*\n%FSLAX25Y25*%\n%MOIN*%\n\
+    %ADD10C,0.0100*%\n%ADD11C,0.0120*%\n%ADD12R,0.0600X0.0500*%\n%ADD13C,0.0200*%\n\
+    %ADD14O,0.0600X0.0300*%\n%ADD15OCT,0.0600X+0.0100*%\n%ADD16C,0.0300*%\n\
+    G54D10*\nX100Y100D02*\nX200Y100D01*\nG54D15*\nX300Y300D03*\nM02*\n";
+
+fn fingerprint_line(mode: FingerprintMode) -> String {
+    add_fingerprint_with_mode(AD9_LAYER, false, mode)
+        .lines()
+        .find(|line| line.starts_with("%ADD15"))
+        .expect("The fingerprint takes the sixth aperture number")
+        .to_string()
+}
+
+#[test]
+fn legacy_mode_appends_a_circle_for_nonstandard_apertures() {
+    assert!(fingerprint_line(FingerprintMode::Standard).starts_with("%ADD15OCT,"));
+
+    let line = fingerprint_line(FingerprintMode::Legacy);
+    assert!(line.starts_with("%ADD15C,0.42"), "{}", line);
+    // The original aperture moved up one number, unchanged
+    let output = add_fingerprint_with_mode(AD9_LAYER, false, FingerprintMode::Legacy);
+    assert!(output.contains("%ADD16OCT,0.0600X+0.0100*%\n"));
+    assert!(output.contains("G54D16*\n"));
+}
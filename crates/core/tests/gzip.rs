@@ -0,0 +1,82 @@
+/* tests/gzip.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+#![cfg(feature = "archive")]
+
+use nextjlc::gzip::{deflate, gunzip, gzip, inflate, inflate_limited};
+
+// Ten drawn points, deflated by zlib at level 9 into one dynamic Huffman block
+const REFERENCE_DEFLATE: [u8; 61] = [
+    0x3D, 0xCC, 0xBB, 0x11, 0xC0, 0x30, 0x0C, 0x02, 0xD0, 0x3E, 0xA3, 0xA4, 0x02, 0xFD, 0xAC, 0xF4,
+    0x19, 0xC2, 0xDA, 0x7F, 0x11, 0x17, 0x8A, 0xD2, 0xBD, 0xE3, 0x80, 0x8D, 0xC2, 0x0B, 0xDE, 0xD7,
+    0xA6, 0xAC, 0x62, 0x53, 0xDC, 0xCA, 0x9A, 0x9A, 0x2C, 0x69, 0x3A, 0x72, 0x18, 0xEA, 0x53, 0x58,
+    0x21, 0x33, 0xCB, 0x7C, 0xFE, 0x33, 0x30, 0x26, 0x26, 0x4D, 0xBF, 0xF6, 0x01,
+];
+
+fn reference_text() -> String {
+    (0..10)
+        .map(|i| format!("X{}Y{}D01*\n", i * 127, i * i % 7))
+        .collect()
+}
+
+// Bytes from a fixed xorshift sequence, which deflate cannot shrink
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn gzip_round_trips_any_input() {
+    // Longer than the 32 KiB window, so matches reach across its edge
+    let long: Vec<u8> = (0..20_000u32)
+        .flat_map(|i| format!("X{}Y{}D01*\n", i % 5000, i % 37).into_bytes())
+        .collect();
+    for data in [Vec::new(), vec![b'X'], long, noise(70_000)] {
+        assert_eq!(gunzip(&gzip(&data)).expect("valid gzip"), data);
+        assert_eq!(inflate(&deflate(&data)).expect("valid deflate"), data);
+    }
+}
+
+#[test]
+fn dynamic_huffman_blocks_are_inflated() {
+    assert_eq!((REFERENCE_DEFLATE[0] >> 1) & 3, 2, "a dynamic block");
+    let text = inflate(&REFERENCE_DEFLATE).expect("valid deflate");
+    assert_eq!(String::from_utf8(text).expect("text"), reference_text());
+}
+
+#[test]
+fn inflating_past_the_limit_is_refused() {
+    let data = deflate(&[0; 10_000]);
+    assert_eq!(
+        inflate_limited(&data, 10_000).expect("at the limit").len(),
+        10_000
+    );
+    assert!(inflate_limited(&data, 9_999).is_err());
+    assert!(inflate_limited(&REFERENCE_DEFLATE, 100).is_err());
+}
+
+#[test]
+fn gunzip_checks_the_trailer() {
+    let mut data = gzip(b"G04 checked*\nM02*\n");
+    let crc = data.len() - 8;
+    data[crc] ^= 1;
+    let error = gunzip(&data).unwrap_err();
+    assert!(error.message.contains("CRC"), "{}", error);
+
+    let mut data = gzip(b"G04 checked*\nM02*\n");
+    let size = data.len() - 4;
+    data[size] ^= 1;
+    assert!(gunzip(&data).is_err());
+    assert!(gunzip(&data[..data.len() - 1]).is_err());
+}
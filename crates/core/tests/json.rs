@@ -0,0 +1,189 @@
+/* tests/json.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::drill::{DrillCommand, DrillFile, DrillOperation, HoleType, LayerSpan};
+use nextjlc::geometry::Point;
+use nextjlc::gerber::{Command, GerberLayer, parse_gerber};
+use nextjlc::json::{JsonValue, parse_json};
+use nextjlc::prelude::*;
+
+// Through the text form, as a cache stores it
+fn reparse(value: &JsonValue) -> JsonValue {
+    parse_json(&value.to_json_string()).expect("valid JSON")
+}
+
+#[test]
+fn values_survive_writing_and_parsing() {
+    let value = JsonValue::Object(vec![
+        (
+            "text".to_string(),
+            "quote \" backslash \\ tab \t newline \n bell \u{7} µm ✓ 🛠".into(),
+        ),
+        (
+            "numbers".to_string(),
+            JsonValue::Array(vec![
+                0.0.into(),
+                (-12.5).into(),
+                0.1.into(),
+                1e-9.into(),
+                123456789.0.into(),
+                2.5e20.into(),
+            ]),
+        ),
+        (
+            "nested".to_string(),
+            JsonValue::Array(vec![
+                JsonValue::Null,
+                true.into(),
+                JsonValue::Object(Vec::new()),
+                JsonValue::Array(Vec::new()),
+            ]),
+        ),
+    ]);
+    assert_eq!(reparse(&value), value);
+    assert_eq!(
+        parse_json(&value.to_pretty_string()).expect("valid JSON"),
+        value
+    );
+    assert_eq!(
+        parse_json(r#""\ud83d\udee0 \u00b5""#).expect("valid JSON"),
+        "🛠 µ".into()
+    );
+}
+
+#[test]
+fn malformed_documents_are_refused() {
+    for input in [
+        "",
+        "{",
+        "[1,]",
+        "{\"a\" 1}",
+        "\"open",
+        "\"\\x\"",
+        "nul",
+        "1 2",
+        "-",
+    ] {
+        assert!(parse_json(input).is_err(), "{:?}", input);
+    }
+    let deep = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+    let error = parse_json(&deep).unwrap_err();
+    assert!(error.message.contains("too deep"), "{}", error);
+}
+
+#[test]
+fn gerber_layers_round_trip_as_parsed() {
+    let layer = parse_gerber(
+        "G04 Round trip*\n%TF.FileFunction,Copper,L1,Top*%\n%FSLAX46Y46*%\n%MOIN*%\n\
+        %AMTHERMAL*7,0,0,0.08,0.06,0.01,0*%\n%TA.AperFunction,SMDPad,CuDef*%\n\
+        %ADD10C,0.010*%\n%TD*%\n%ADD11R,0.050X0.025*%\n%ADD12THERMAL*%\n\
+        D10*\nX0Y0D02*\nX1000000Y0D01*\nG75*\nG03X0Y1000000I-1000000J0D01*\n\
+        %TO.N,GND*%\nD11*\nX500000Y500000D03*\n%TD*%\n%LPC*%\nD12*\nX250000Y250000D03*\n\
+        %LPD*%\nG36*\nX0Y0D02*\nG01X100000Y0D01*\nX100000Y100000D01*\nX0Y0D01*\nG37*\nM02*\n",
+    );
+    assert_eq!(layer.apertures.len(), 3);
+    assert_eq!(layer.macros.len(), 1);
+    let json = layer.to_json();
+    let commands = json.get("commands").and_then(JsonValue::as_array).unwrap();
+    for kind in [
+        "flash",
+        "draw",
+        "region",
+        "polarity",
+        "comment",
+        "attribute",
+    ] {
+        assert!(
+            commands.iter().any(|c| c.get(kind).is_some()),
+            "no {} command",
+            kind
+        );
+    }
+    assert!(layer.commands.iter().any(|c| matches!(
+        c,
+        Command::Draw {
+            center: Some(_),
+            ..
+        }
+    )));
+
+    let restored = GerberLayer::from_json(&reparse(&json)).expect("restored layer");
+    assert_eq!(restored, layer);
+}
+
+#[test]
+fn drill_files_round_trip_as_parsed() {
+    let drill = DrillFile {
+        operations: vec![
+            DrillOperation {
+                tool: 1,
+                diameter: 0.3,
+                hole_type: HoleType::Plated,
+                commands: vec![
+                    DrillCommand::Hole { x: 10.0, y: -2.54 },
+                    DrillCommand::Hole {
+                        x: 0.000_01,
+                        y: 123.456_78,
+                    },
+                ],
+                span: None,
+            },
+            DrillOperation {
+                tool: 2,
+                diameter: 1.1,
+                hole_type: HoleType::NonPlated,
+                commands: vec![DrillCommand::Route {
+                    points: vec![
+                        Point::new(0.0, 0.0),
+                        Point::new(5.0, 0.0),
+                        Point::new(5.0, 2.5),
+                    ],
+                }],
+                span: None,
+            },
+            DrillOperation {
+                tool: 3,
+                diameter: 0.2,
+                hole_type: HoleType::Plated,
+                commands: vec![DrillCommand::Hole { x: 1.0, y: 1.0 }],
+                span: Some(LayerSpan::new(1, 2)),
+            },
+        ],
+        warnings: vec!["T3 has no size".to_string()],
+    };
+    let restored = DrillFile::from_json(&reparse(&drill.to_json())).expect("restored drill");
+    assert_eq!(restored, drill);
+
+    let mut truncated = drill.to_json();
+    if let JsonValue::Object(entries) = &mut truncated {
+        entries.retain(|(key, _)| key != "warnings");
+    }
+    assert!(DrillFile::from_json(&truncated).is_none());
+}
+
+#[test]
+fn merged_drills_are_restored_without_reading_excellon() {
+    let outline = "G04 Altium Designer*\n%FSLAX25Y25*%\n%MOMM*%\n%ADD10C,0.10000*%\nD10*\n\
+        X0Y0D02*\nX5000000Y0D01*\nX5000000Y3000000D01*\nX0Y3000000D01*\nX0Y0D01*\nM02*\n";
+    let drill = "M48\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE=PLATED\nT1F00S00C0.80000\n\
+        T2F00S00C1.00000\n%\nT01\nX1000000Y1000000\nT02\nG00X2000000Y1000000\nM15\n\
+        G01X3000000Y1000000\nM16\nM30\n";
+    let files = vec![
+        ("board.GM1".to_string(), outline.to_string()),
+        ("board-RoundHoles.TXT".to_string(), drill.to_string()),
+    ];
+    let result = Pipeline::new(ConvertOptions {
+        eda_type: Some(EdaType::Ad),
+        ..ConvertOptions::default()
+    })
+    .run(&files);
+    assert_eq!(result.set.merged_drills.len(), 1);
+
+    let restored = LayerSet::from_json(&reparse(&result.set.to_json())).expect("restored set");
+    assert_eq!(restored.merged_drills, result.set.merged_drills);
+    assert_eq!(restored.layers, result.set.layers);
+}
@@ -0,0 +1,316 @@
+/* tests/pipeline.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::prelude::*;
+
+// An Altium layer of a 50 x 30 mm board outline, with FILE_FORMAT 2:5 metric coordinates
+const AD_OUTLINE: &str = "G04 Altium Designer*\n%FSLAX25Y25*%\n%MOMM*%\n%ADD10C,0.10000*%\nD10*\n\
+    X0Y0D02*\nX5000000Y0D01*\nX5000000Y3000000D01*\nX0Y3000000D01*\nX0Y0D01*\nM02*\n";
+
+fn output(result: &ConvertResult, name: &str) -> String {
+    result
+        .set
+        .clone()
+        .into_outputs()
+        .into_iter()
+        .find(|(output, _)| output == name)
+        .map(|(_, content)| content)
+        .unwrap_or_else(|| panic!("no {} in the output", name))
+}
+
+#[test]
+fn drill_files_with_decimal_commas_are_read_as_decimals() {
+    let drill = "M48\n;Layer_Color=9474304\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE=PLATED\n\
+        T1F00S00C0,80\n%\nT01\nX1000000Y1000000\nM30\n";
+    let files = vec![
+        ("board.GM1".to_string(), AD_OUTLINE.to_string()),
+        ("board-RoundHoles.TXT".to_string(), drill.to_string()),
+    ];
+    let options = ConvertOptions {
+        eda_type: Some(EdaType::Ad),
+        ..ConvertOptions::default()
+    };
+    let result = Pipeline::new(options).run(&files);
+
+    assert!(output(&result, "Drill_PTH_Through.DRL").contains("T01C0.80000\n"));
+    let warnings = &result.set.metadata.warnings;
+    assert!(
+        warnings
+            .iter()
+            .any(|w| w.contains("decimal comma") && w.contains("RoundHoles")),
+        "{:?}",
+        warnings
+    );
+    assert!(!warnings.iter().any(|w| w.contains("never defined")));
+}
+
+#[test]
+fn coincident_holes_of_separate_drill_files_are_removed() {
+    let drill = |diameter: &str, holes: &str| {
+        format!(
+            "M48\n;Layer_Color=9474304\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE=PLATED\n\
+            T1F00S00C{}\n%\nT01\n{}M30\n",
+            diameter, holes
+        )
+    };
+    let files = vec![
+        ("board.GM1".to_string(), AD_OUTLINE.to_string()),
+        (
+            "board-RoundHoles.TXT".to_string(),
+            drill("1.00000", "X1000000Y1000000\nX2000000Y1000000\n"),
+        ),
+        (
+            "board-Vias.TXT".to_string(),
+            drill("0.30000", "X1000000Y1000000\nX3000000Y1000000\n"),
+        ),
+    ];
+    let run = |drill_dedupe_epsilon| {
+        Pipeline::new(ConvertOptions {
+            eda_type: Some(EdaType::Ad),
+            drill_dedupe_epsilon,
+            ..ConvertOptions::default()
+        })
+        .run(&files)
+    };
+
+    let result = run(Some(0.01));
+    let pth = output(&result, "Drill_PTH_Through.DRL");
+    // The larger hole at (10, 10) is kept
+    assert_eq!(pth.matches("X10.00000Y10.00000").count(), 1);
+    assert!(pth.contains("T02\nX10.00000Y10.00000\n"), "{}", pth);
+    assert!(
+        result
+            .fixes
+            .iter()
+            .any(|f| f.pass == "drill-dedupe" && f.description.contains("1 duplicate"))
+    );
+
+    let result = run(None);
+    let pth = output(&result, "Drill_PTH_Through.DRL");
+    assert_eq!(pth.matches("X10.00000Y10.00000").count(), 2);
+    assert!(!result.fixes.iter().any(|f| f.pass == "drill-dedupe"));
+}
+
+#[test]
+fn drill_units_are_only_rescaled_when_not_declared() {
+    let run = |drill: &str| {
+        let files = vec![
+            ("board.GM1".to_string(), AD_OUTLINE.to_string()),
+            ("board-RoundHoles.TXT".to_string(), drill.to_string()),
+        ];
+        Pipeline::new(ConvertOptions {
+            eda_type: Some(EdaType::Ad),
+            ..ConvertOptions::default()
+        })
+        .run(&files)
+    };
+
+    // Holes near the origin fit the board in the declared unit
+    let declared = "M48\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE=PLATED\nT1F00S00C0.80000\n%\n\
+        T01\nX0100000Y0100000\nX0200000Y0100000\nM30\n";
+    let result = run(declared);
+    let pth = output(&result, "Drill_PTH_Through.DRL");
+    assert!(
+        pth.contains("X1.00000Y1.00000\nX2.00000Y1.00000\n"),
+        "{}",
+        pth
+    );
+    assert!(!result.fixes.iter().any(|f| f.pass == "drill-units"));
+    assert!(
+        result
+            .set
+            .metadata
+            .warnings
+            .iter()
+            .any(|w| w.contains("RoundHoles") && w.contains("check the declared drill unit")),
+        "{:?}",
+        result.set.metadata.warnings
+    );
+
+    // Without a unit statement, the same holes are read as inches
+    let undeclared = "M48\nT1C0.8\n%\nT01\nX1.0Y1.0\nX2.0Y1.0\nM30\n";
+    let result = run(undeclared);
+    let pth = output(&result, "Drill_PTH_Through.DRL");
+    assert!(
+        pth.contains("X25.40000Y25.40000\nX50.80000Y25.40000\n"),
+        "{}",
+        pth
+    );
+    assert!(result.fixes.iter().any(|f| f.pass == "drill-units"));
+}
+
+#[test]
+fn merged_drill_files_are_written_from_the_parsed_holes() {
+    let drill = "M48\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE=PLATED\nT1F00S00C0.80000\n%\n\
+        T01\nX1000000Y1000000\nX2000000Y1500000\nM30\n";
+    let files = vec![
+        ("board.GM1".to_string(), AD_OUTLINE.to_string()),
+        ("board-RoundHoles.TXT".to_string(), drill.to_string()),
+    ];
+    let result = Pipeline::new(ConvertOptions {
+        eda_type: Some(EdaType::Ad),
+        ..ConvertOptions::default()
+    })
+    .run(&files);
+    assert!(result.set.drills.is_empty());
+    assert_eq!(result.set.merged_drills.len(), 1);
+
+    let mut sink = MemorySink::new();
+    let count = result.set.write_outputs(&mut sink).expect("written");
+    assert_eq!(count, result.set.output_count());
+    let (_, written) = sink
+        .files
+        .iter()
+        .find(|(name, _)| name == "Drill_PTH_Through.DRL")
+        .expect("merged drill file");
+    let written = String::from_utf8(written.clone()).expect("text");
+    assert!(written.contains("T01\nX10.00000Y10.00000\nX20.00000Y15.00000\n"));
+
+    // The header carries the time of writing, so only the bodies are compared
+    let body = |content: &str| content[content.find("M48").expect("M48")..].to_string();
+    let restored = LayerSet::from_json(&result.set.to_json()).expect("restored set");
+    assert_eq!(
+        body(&restored.merged_drills[0].content()),
+        body(&output(&result, "Drill_PTH_Through.DRL"))
+    );
+}
+
+#[test]
+fn drill_tools_are_checked_after_oversized_holes_are_routed() {
+    let drill = "M48\n;FILE_FORMAT=2:5\nMETRIC,LZ\n;TYPE=PLATED\nT1F00S00C0.10000\nT2F00S00C8.00000\n%\n\
+        T01\nX1000000Y1000000\nT02\nX2500000Y1500000\nM30\n";
+    let files = vec![
+        ("board.GM1".to_string(), AD_OUTLINE.to_string()),
+        ("board-RoundHoles.TXT".to_string(), drill.to_string()),
+    ];
+    let run = |oversized_holes| {
+        Pipeline::new(ConvertOptions {
+            eda_type: Some(EdaType::Ad),
+            oversized_holes,
+            ..ConvertOptions::default()
+        })
+        .run(&files)
+        .set
+        .metadata
+        .warnings
+    };
+    let about = |warnings: &[String], text: &str| warnings.iter().any(|w| w.contains(text));
+
+    let kept = run(OversizedHoles::Keep);
+    assert!(about(
+        &kept,
+        "PTH T02 (1 x 8.000 mm): above JLC's maximum drill"
+    ));
+    assert!(about(&kept, "PTH T01 (1 x 0.100 mm): below JLC's"));
+
+    let routed = run(OversizedHoles::Slots);
+    assert!(!about(&routed, "maximum drill"), "{:?}", routed);
+    assert!(about(&routed, "PTH T01 (1 x 0.100 mm): below JLC's"));
+}
+
+#[test]
+fn a_cancelled_stage_leaves_the_session_to_run_it_again() {
+    let files = vec![("board.GM1".to_string(), AD_OUTLINE.to_string())];
+    let options = ConvertOptions {
+        eda_type: Some(EdaType::Ad),
+        ..ConvertOptions::default()
+    };
+    let mut session = Session::new(options.clone(), &files);
+    while session.next_stage() != Some("dcodes") {
+        session.step();
+    }
+    let before = session.set().clone();
+    let timings = session.clone().finish().stats.stage_timings.len();
+
+    assert_eq!(session.step_cancellable(&|| true), Err(Cancelled));
+    assert_eq!(session.next_stage(), Some("dcodes"));
+    assert_eq!(session.set().layers, before.layers);
+    assert_eq!(session.clone().finish().stats.stage_timings.len(), timings);
+
+    assert_eq!(session.step_cancellable(&NeverCancel), Ok(Some("dcodes")));
+    assert_ne!(session.set().layers, before.layers);
+
+    let pipeline = Pipeline::new(options);
+    assert!(pipeline.run_cancellable(&files, &|| true).is_err());
+    assert!(pipeline.run_cancellable(&files, &NeverCancel).is_ok());
+}
+
+#[test]
+fn the_time_budget_stops_a_stage_from_inside() {
+    let files = vec![("board.GM1".to_string(), AD_OUTLINE.to_string())];
+    let options = ConvertOptions {
+        eda_type: Some(EdaType::Ad),
+        ..ConvertOptions::default()
+    };
+    let mut session = Session::new(options.clone(), &files);
+    while session.next_stage() != Some("dcodes") {
+        session.step();
+    }
+
+    // Resumed with the budget already spent by the earlier stages
+    let options = ConvertOptions {
+        limits: ResourceLimits {
+            time_budget_ms: Some(0.0),
+            ..ResourceLimits::default()
+        },
+        ..options
+    };
+    let mut session = Session::resume(&session.to_json(), options).unwrap();
+    let before = session.set().layers.clone();
+    assert_eq!(session.step(), Some("dcodes"));
+
+    assert!(session.is_finished());
+    assert_eq!(session.set().layers, before);
+    assert!(matches!(
+        session.limit_exceeded(),
+        Some(LimitExceeded::TimeBudget {
+            stage: "dcodes",
+            ..
+        })
+    ));
+    assert!(session.finish().report.is_err());
+}
+
+#[test]
+fn v_cut_layers_are_written_as_a_note_by_default() {
+    // Score lines at Y = 15 and X = 25, and a diagonal that cannot be scored
+    let v_cut = "G04 Altium Designer*\n%FSLAX25Y25*%\n%MOMM*%\n%ADD10C,0.10000*%\nD10*\n\
+        X0Y1500000D02*\nX5000000Y1500000D01*\nX2500000Y0D02*\nX2500000Y3000000D01*\n\
+        X0Y0D02*\nX1000000Y1000000D01*\nM02*\n";
+    let files = vec![
+        ("board.GM1".to_string(), AD_OUTLINE.to_string()),
+        ("board.GM2".to_string(), v_cut.to_string()),
+    ];
+    let options = ConvertOptions {
+        eda_type: Some(EdaType::Ad),
+        layer_overrides: vec![("board.GM2".to_string(), LogicalLayer::VCut)],
+        ..ConvertOptions::default()
+    };
+    let result = Pipeline::new(options.clone()).run(&files);
+
+    assert_eq!(
+        output(&result, "V-CUT.txt"),
+        "V-CUT\nScore lines from board.GM2, in mm\nHorizontal at Y = 15.00\nVertical at X = 25.00\n"
+    );
+    assert!(!result.set.file_names().iter().any(|n| n.ends_with(".GML")));
+    assert!(
+        result
+            .set
+            .metadata
+            .warnings
+            .iter()
+            .any(|w| w.contains("1 object of board.GM2"))
+    );
+
+    let options = ConvertOptions {
+        v_cut: VCutOutput::Layer,
+        ..options
+    };
+    let result = Pipeline::new(options).run(&files);
+    assert!(output(&result, "Gerber_VCutLayer.GML").contains("X2500000Y3000000D01*"));
+    assert!(!result.set.file_names().iter().any(|n| n == "V-CUT.txt"));
+}
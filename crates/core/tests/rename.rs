@@ -0,0 +1,434 @@
+/* tests/rename.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+use nextjlc::drill::{is_drill_file, is_through_drill};
+use nextjlc::rename::{EdaType, MAX_INNER_LAYERS, map_filenames_detailed};
+use std::collections::BTreeMap;
+
+fn names(files: &[&str]) -> Vec<String> {
+    files.iter().map(|f| f.to_string()).collect()
+}
+
+// Maps the files of `expected` as one export, and checks every new name
+fn assert_renames(eda_type: EdaType, expected: &[(&str, &str)]) {
+    let files: Vec<&str> = expected.iter().map(|(original, _)| *original).collect();
+    let expected: BTreeMap<String, String> = expected
+        .iter()
+        .map(|(original, new)| (original.to_string(), new.to_string()))
+        .collect();
+    assert_eq!(
+        map_filenames_detailed(&names(&files), eda_type).to_map(),
+        expected
+    );
+}
+
+fn new_name(eda_type: EdaType, files: &[&str], file: &str) -> String {
+    map_filenames_detailed(&names(files), eda_type)
+        .new_name(file)
+        .expect("file in the result")
+        .to_string()
+}
+
+#[test]
+fn altium_designer_export() {
+    assert_renames(
+        EdaType::Ad,
+        &[
+            ("PCB1.GTL", "Gerber_TopLayer.GTL"),
+            ("PCB1.GBL", "Gerber_BottomLayer.GBL"),
+            ("PCB1.G1", "Gerber_InnerLayer1.G1"),
+            ("PCB1.G2", "Gerber_InnerLayer2.G2"),
+            ("PCB1.GTO", "Gerber_TopSilkscreenLayer.GTO"),
+            ("PCB1.GBO", "Gerber_BottomSilkscreenLayer.GBO"),
+            ("PCB1.GTS", "Gerber_TopSolderMaskLayer.GTS"),
+            ("PCB1.GBS", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("PCB1.GTP", "Gerber_TopPasteMaskLayer.GTP"),
+            ("PCB1.GBP", "Gerber_BottomPasteMaskLayer.GBP"),
+            ("PCB1.GM1", "Gerber_BoardOutlineLayer.GKO"),
+            ("PCB1.GD1", "Drill_PTH_Through_GBR.GBR"),
+            ("PCB1.GG1", "Drill_PTH_Through_Via_GBR.GBR"),
+            ("PCB1-RoundHoles.TXT", "Drill_Source.DRL"),
+            ("PCB1-SlotHoles.TXT", "Drill_Source.DRL"),
+            ("PCB1.DRR", "Drill_MAP_GBR.DRR"),
+            ("PCB1.LDP", "Gerber_GBR_JOB.LDP"),
+            ("PCB1.EXTREP", "PCB1.EXTREP"),
+            ("PCB1.REP", "PCB1.REP"),
+            ("Status Report.Txt", "Status Report.Txt"),
+        ],
+    );
+}
+
+#[test]
+fn protel_99_se_export() {
+    // Two mid layers and two internal planes: the planes come after G2
+    assert_renames(
+        EdaType::Ad,
+        &[
+            ("BOARD.GTL", "Gerber_TopLayer.GTL"),
+            ("BOARD.G1", "Gerber_InnerLayer1.G1"),
+            ("BOARD.G2", "Gerber_InnerLayer2.G2"),
+            ("BOARD.GP1", "Gerber_InnerLayer3.G3"),
+            ("BOARD.GP2", "Gerber_InnerLayer4.G4"),
+            ("BOARD.GBL", "Gerber_BottomLayer.GBL"),
+            ("BOARD.GTO", "Gerber_TopSilkscreenLayer.GTO"),
+            ("BOARD.GTS", "Gerber_TopSolderMaskLayer.GTS"),
+            ("BOARD.GBS", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("BOARD.GKO", "Gerber_BoardOutlineLayer.GKO"),
+            ("BOARD.GM", "Gerber_DocumentLayer.GBR"),
+            ("BOARD.DRR", "Drill_MAP_GBR.DRR"),
+            ("BOARD.TX1", "Drill_Source.DRL"),
+        ],
+    );
+}
+
+#[test]
+fn planes_are_numbered_after_the_mid_layers() {
+    let files = ["board.GTL", "board.GP1", "board.GP2", "board.GBL"];
+    assert_eq!(
+        new_name(EdaType::Ad, &files, "board.GP1"),
+        "Gerber_InnerLayer1.G1"
+    );
+    assert_eq!(
+        new_name(EdaType::Ad, &files, "board.GP2"),
+        "Gerber_InnerLayer2.G2"
+    );
+
+    // Mid layers with a gap: planes follow the highest one
+    let files = ["board.G1", "board.G4", "board.GP1"];
+    assert_eq!(
+        new_name(EdaType::Ad, &files, "board.GP1"),
+        "Gerber_InnerLayer5.G5"
+    );
+}
+
+#[test]
+fn inner_layers_reach_thirty() {
+    let files: Vec<String> = (1..=MAX_INNER_LAYERS)
+        .map(|n| format!("PCB1.G{}", n))
+        .collect();
+    let result = map_filenames_detailed(&files, EdaType::Ad);
+    for n in 1..=MAX_INNER_LAYERS {
+        assert_eq!(
+            result.new_name(&format!("PCB1.G{}", n)),
+            Some(format!("Gerber_InnerLayer{0}.G{0}", n).as_str())
+        );
+    }
+    assert_eq!(MAX_INNER_LAYERS, 30);
+
+    let files: Vec<String> = (1..=MAX_INNER_LAYERS)
+        .map(|n| format!("board-In{}_Cu.gbr", n))
+        .collect();
+    let result = map_filenames_detailed(&files, EdaType::KiCad);
+    for n in 1..=MAX_INNER_LAYERS {
+        assert_eq!(
+            result.new_name(&format!("board-In{}_Cu.gbr", n)),
+            Some(format!("Gerber_InnerLayer{0}.G{0}", n).as_str())
+        );
+    }
+}
+
+#[test]
+fn mechanical_outline_depends_on_a_keep_out_layer() {
+    let without = ["PCB1.GTL", "PCB1.GM1"];
+    assert_eq!(
+        new_name(EdaType::Ad, &without, "PCB1.GM1"),
+        "Gerber_BoardOutlineLayer.GKO"
+    );
+
+    let with = ["PCB1.GTL", "PCB1.GM1", "PCB1.GKO"];
+    assert_eq!(new_name(EdaType::Ad, &with, "PCB1.GM1"), "PCB1.GM1");
+    assert_eq!(
+        new_name(EdaType::Ad, &with, "PCB1.GKO"),
+        "Gerber_BoardOutlineLayer.GKO"
+    );
+}
+
+#[test]
+fn holes_text_is_a_drill_unless_there_is_a_drl() {
+    let without = ["PCB1.GTL", "PCB1-RoundHoles.TXT"];
+    assert_eq!(
+        new_name(EdaType::Ad, &without, "PCB1-RoundHoles.TXT"),
+        "Drill_Source.DRL"
+    );
+
+    let with = ["PCB1.GTL", "PCB1-RoundHoles.TXT", "PCB1.DRL"];
+    assert_eq!(
+        new_name(EdaType::Ad, &with, "PCB1-RoundHoles.TXT"),
+        "PCB1-RoundHoles.TXT"
+    );
+}
+
+#[test]
+fn blind_and_buried_drills_are_known_by_every_check() {
+    let files: Vec<String> = (1..=9).map(|n| format!("PCB1.TX{}", n)).collect();
+    let result = map_filenames_detailed(&files, EdaType::Ad);
+    for file in &files {
+        assert_eq!(result.new_name(file), Some("Drill_Source.DRL"));
+        assert!(is_drill_file(file), "{}", file);
+        assert!(!is_through_drill(file), "{}", file);
+    }
+    assert!(is_through_drill("PCB1.DRL"));
+    assert!(!is_drill_file("PCB1.TX10"));
+}
+
+#[test]
+fn kicad_export() {
+    assert_renames(
+        EdaType::KiCad,
+        &[
+            ("blinky-F_Cu.gbr", "Gerber_TopLayer.GTL"),
+            ("blinky-In1_Cu.gbr", "Gerber_InnerLayer1.G1"),
+            ("blinky-In2_Cu.gbr", "Gerber_InnerLayer2.G2"),
+            ("blinky-B_Cu.gbr", "Gerber_BottomLayer.GBL"),
+            ("blinky-F_Mask.gbr", "Gerber_TopSolderMaskLayer.GTS"),
+            ("blinky-B_Mask.gbr", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("blinky-F_Paste.gbr", "Gerber_TopPasteMaskLayer.GTP"),
+            ("blinky-B_Paste.gbr", "Gerber_BottomPasteMaskLayer.GBP"),
+            ("blinky-F_Silkscreen.gbr", "Gerber_TopSilkscreenLayer.GTO"),
+            (
+                "blinky-B_Silkscreen.gbr",
+                "Gerber_BottomSilkscreenLayer.GBO",
+            ),
+            ("blinky-Edge_Cuts.gbr", "Gerber_BoardOutlineLayer.GKO"),
+            ("blinky-F_Courtyard.gbr", "blinky-F_Courtyard.gbr"),
+            ("blinky-job.gbrjob", "Gerber_GBR_JOB.GBRJOB"),
+            ("blinky-PTH.drl", "Drill_Source.DRL"),
+            ("blinky-NPTH.drl", "Drill_Source.DRL"),
+            ("blinky-PTH-drl_map.gbr", "Drill_MAP_GBR.GBR"),
+            ("blinky-NPTH-drl_map.gbr", "Drill_MAP_GBR.GBR"),
+        ],
+    );
+}
+
+#[test]
+fn kicad_5_export() {
+    assert_renames(
+        EdaType::KiCad,
+        &[
+            ("blinky-F.Cu.gbr", "Gerber_TopLayer.GTL"),
+            ("blinky-In1.Cu.gbr", "Gerber_InnerLayer1.G1"),
+            ("blinky-B.Cu.gbr", "Gerber_BottomLayer.GBL"),
+            ("blinky-F.Mask.gbr", "Gerber_TopSolderMaskLayer.GTS"),
+            ("blinky-B.Mask.gbr", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("blinky-F.Paste.gbr", "Gerber_TopPasteMaskLayer.GTP"),
+            ("blinky-F.SilkS.gbr", "Gerber_TopSilkscreenLayer.GTO"),
+            ("blinky-B.SilkS.gbr", "Gerber_BottomSilkscreenLayer.GBO"),
+            ("blinky-Edge.Cuts.gbr", "Gerber_BoardOutlineLayer.GKO"),
+            ("blinky.drl", "Drill_Source.DRL"),
+            ("blinky-NPTH.drl", "Drill_Source.DRL"),
+            ("blinky-drl_map.gbr", "Drill_MAP_GBR.GBR"),
+        ],
+    );
+}
+
+#[test]
+fn kicad_inner_layer_ten_is_not_layer_one() {
+    let files = ["board-In1_Cu.gbr", "board-In10_Cu.gbr", "board-In11.Cu.gbr"];
+    assert_eq!(
+        new_name(EdaType::KiCad, &files, "board-In1_Cu.gbr"),
+        "Gerber_InnerLayer1.G1"
+    );
+    assert_eq!(
+        new_name(EdaType::KiCad, &files, "board-In10_Cu.gbr"),
+        "Gerber_InnerLayer10.G10"
+    );
+    assert_eq!(
+        new_name(EdaType::KiCad, &files, "board-In11.Cu.gbr"),
+        "Gerber_InnerLayer11.G11"
+    );
+}
+
+#[test]
+fn eagle_and_fusion_export() {
+    assert_renames(
+        EdaType::Eagle,
+        &[
+            ("board_copper_top.gbr", "Gerber_TopLayer.GTL"),
+            ("board_copper_l2.gbr", "Gerber_InnerLayer1.G1"),
+            ("board_copper_l3.gbr", "Gerber_InnerLayer2.G2"),
+            ("board_copper_bottom.gbr", "Gerber_BottomLayer.GBL"),
+            ("board_soldermask_top.gbr", "Gerber_TopSolderMaskLayer.GTS"),
+            (
+                "board_soldermask_bottom.gbr",
+                "Gerber_BottomSolderMaskLayer.GBS",
+            ),
+            ("board_silkscreen_top.gbr", "Gerber_TopSilkscreenLayer.GTO"),
+            (
+                "board_silkscreen_bottom.gbr",
+                "Gerber_BottomSilkscreenLayer.GBO",
+            ),
+            ("board_solderpaste_top.gbr", "Gerber_TopPasteMaskLayer.GTP"),
+            ("board_profile.gbr", "Gerber_BoardOutlineLayer.GKO"),
+            ("board_drill_1_16.xln", "Drill_Source.DRL"),
+            ("board.gbrjob", "Gerber_GBR_JOB.GBRJOB"),
+        ],
+    );
+
+    // The legacy eagle.cam job
+    assert_renames(
+        EdaType::Eagle,
+        &[
+            ("board.cmp", "Gerber_TopLayer.GTL"),
+            ("board.ly2", "Gerber_InnerLayer1.G1"),
+            ("board.sol", "Gerber_BottomLayer.GBL"),
+            ("board.stc", "Gerber_TopSolderMaskLayer.GTS"),
+            ("board.sts", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("board.plc", "Gerber_TopSilkscreenLayer.GTO"),
+            ("board.pls", "Gerber_BottomSilkscreenLayer.GBO"),
+            ("board.crc", "Gerber_TopPasteMaskLayer.GTP"),
+            ("board.dim", "Gerber_BoardOutlineLayer.GKO"),
+            ("board.drd", "Drill_Source.DRL"),
+            ("board.dri", "board.dri"),
+            ("board.gpi", "board.gpi"),
+        ],
+    );
+}
+
+#[test]
+fn pads_export() {
+    assert_renames(
+        EdaType::Pads,
+        &[
+            ("top.pho", "Gerber_TopLayer.GTL"),
+            ("inner1.pho", "Gerber_InnerLayer1.G1"),
+            ("inner2.pho", "Gerber_InnerLayer2.G2"),
+            ("bot.pho", "Gerber_BottomLayer.GBL"),
+            ("smt.pho", "Gerber_TopSolderMaskLayer.GTS"),
+            ("smb.pho", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("sst.pho", "Gerber_TopSilkscreenLayer.GTO"),
+            ("ssb.pho", "Gerber_BottomSilkscreenLayer.GBO"),
+            ("spt.pho", "Gerber_TopPasteMaskLayer.GTP"),
+            ("brd.pho", "Gerber_BoardOutlineLayer.GKO"),
+            ("drilldrawing.pho", "Drill_MAP_GBR.GBR"),
+            ("drl001.drl", "Drill_Source.DRL"),
+            ("drl001.lst", "Drill_MAP_GBR.DRR"),
+            ("art001.pho", "art001.pho"),
+        ],
+    );
+}
+
+#[test]
+fn proteus_export() {
+    assert_renames(
+        EdaType::Proteus,
+        &[
+            ("Board - CADCAM Top Copper.TXT", "Gerber_TopLayer.GTL"),
+            ("Board - CADCAM Inner 1.TXT", "Gerber_InnerLayer1.G1"),
+            ("Board - CADCAM Bottom Copper.TXT", "Gerber_BottomLayer.GBL"),
+            (
+                "Board - CADCAM Top Silk Screen.TXT",
+                "Gerber_TopSilkscreenLayer.GTO",
+            ),
+            (
+                "Board - CADCAM Top Solder Resist.TXT",
+                "Gerber_TopSolderMaskLayer.GTS",
+            ),
+            (
+                "Board - CADCAM Bottom Solder Resist.TXT",
+                "Gerber_BottomSolderMaskLayer.GBS",
+            ),
+            (
+                "Board - CADCAM Top SMT Paste.TXT",
+                "Gerber_TopPasteMaskLayer.GTP",
+            ),
+            (
+                "Board - CADCAM Board Edge.TXT",
+                "Gerber_BoardOutlineLayer.GKO",
+            ),
+            // Only the outline when there is no board edge layer
+            (
+                "Board - CADCAM Mechanical 1.TXT",
+                "Board - CADCAM Mechanical 1.TXT",
+            ),
+            ("Board - CADCAM Drill.TXT", "Drill_Source.DRL"),
+            ("Board - CADCAM READ-ME.TXT", "Board - CADCAM READ-ME.TXT"),
+        ],
+    );
+}
+
+#[test]
+fn librepcb_export() {
+    assert_renames(
+        EdaType::LibrePcb,
+        &[
+            ("board_v1_COPPER-TOP.gbr", "Gerber_TopLayer.GTL"),
+            ("board_v1_COPPER-IN1.gbr", "Gerber_InnerLayer1.G1"),
+            ("board_v1_COPPER-BOTTOM.gbr", "Gerber_BottomLayer.GBL"),
+            (
+                "board_v1_SOLDERMASK-TOP.gbr",
+                "Gerber_TopSolderMaskLayer.GTS",
+            ),
+            (
+                "board_v1_SOLDERMASK-BOTTOM.gbr",
+                "Gerber_BottomSolderMaskLayer.GBS",
+            ),
+            (
+                "board_v1_SILKSCREEN-TOP.gbr",
+                "Gerber_TopSilkscreenLayer.GTO",
+            ),
+            (
+                "board_v1_SILKSCREEN-BOTTOM.gbr",
+                "Gerber_BottomSilkscreenLayer.GBO",
+            ),
+            (
+                "board_v1_SOLDERPASTE-TOP.gbr",
+                "Gerber_TopPasteMaskLayer.GTP",
+            ),
+            ("board_v1_OUTLINES.gbr", "Gerber_BoardOutlineLayer.GKO"),
+            ("board_v1_DRILLS-PTH.drl", "Drill_Source.DRL"),
+            ("board_v1_DRILLS-NPTH.drl", "Drill_Source.DRL"),
+        ],
+    );
+}
+
+#[test]
+fn horizon_export() {
+    assert_renames(
+        EdaType::Horizon,
+        &[
+            ("board.gtl", "Gerber_TopLayer.GTL"),
+            ("board.g1", "Gerber_InnerLayer1.G1"),
+            ("board.g2", "Gerber_InnerLayer2.G2"),
+            ("board.gbl", "Gerber_BottomLayer.GBL"),
+            ("board.gts", "Gerber_TopSolderMaskLayer.GTS"),
+            ("board.gbs", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("board.gto", "Gerber_TopSilkscreenLayer.GTO"),
+            ("board.gbo", "Gerber_BottomSilkscreenLayer.GBO"),
+            ("board.gtp", "Gerber_TopPasteMaskLayer.GTP"),
+            ("board.gko", "Gerber_BoardOutlineLayer.GKO"),
+            ("board_pth.drl", "Drill_Source.DRL"),
+            ("board_npth.drl", "Drill_Source.DRL"),
+        ],
+    );
+
+    let files = ["board.gtl", "board.gm1"];
+    assert_eq!(
+        new_name(EdaType::Horizon, &files, "board.gm1"),
+        "Gerber_BoardOutlineLayer.GKO"
+    );
+}
+
+#[test]
+fn diptrace_export() {
+    assert_renames(
+        EdaType::DipTrace,
+        &[
+            ("Top.gbr", "Gerber_TopLayer.GTL"),
+            ("Inner1.gbr", "Gerber_InnerLayer1.G1"),
+            ("Bottom.gbr", "Gerber_BottomLayer.GBL"),
+            ("TopMask.gbr", "Gerber_TopSolderMaskLayer.GTS"),
+            ("BottomMask.gbr", "Gerber_BottomSolderMaskLayer.GBS"),
+            ("TopSilk.gbr", "Gerber_TopSilkscreenLayer.GTO"),
+            ("BottomSilk.gbr", "Gerber_BottomSilkscreenLayer.GBO"),
+            ("TopPaste.gbr", "Gerber_TopPasteMaskLayer.GTP"),
+            ("BoardOutline.gbr", "Gerber_BoardOutlineLayer.GKO"),
+            ("TopAssy.gbr", "Gerber_TopAssemblyLayer.GBR"),
+            ("Through.drl", "Drill_Source.DRL"),
+            ("NonPlated.drl", "Drill_Source.DRL"),
+        ],
+    );
+}
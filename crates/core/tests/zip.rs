@@ -0,0 +1,134 @@
+/* tests/zip.rs */
+
+/* SPDX-License-Identifier: MIT */
+/*
+ * Author Canmi <t@canmi.icu>
+ */
+
+#![cfg(feature = "archive")]
+
+use nextjlc::gzip::deflate;
+use nextjlc::zip::{ZipLimits, ZipWriter, read_zip, read_zip_with_limits};
+
+fn stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new();
+    for (name, content) in files {
+        writer.add_file(name, content);
+    }
+    writer.finish()
+}
+
+// One deflated entry; the CRC is left 0, as the limits are checked before it
+fn deflated_zip(name: &str, content: &[u8]) -> Vec<u8> {
+    let compressed = deflate(content);
+    let fields = |out: &mut Vec<u8>| {
+        out.extend_from_slice(&20u16.to_le_bytes()); // Version needed to extract
+        out.extend_from_slice(&0u16.to_le_bytes()); // Flags
+        out.extend_from_slice(&8u16.to_le_bytes()); // Deflated
+        out.extend_from_slice(&[0; 8]); // Time, date and CRC
+        out.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // Extra field length
+    };
+
+    let mut archive = 0x0403_4B50u32.to_le_bytes().to_vec();
+    fields(&mut archive);
+    archive.extend_from_slice(name.as_bytes());
+    archive.extend_from_slice(&compressed);
+
+    let directory = archive.len() as u32;
+    archive.extend_from_slice(&0x0201_4B50u32.to_le_bytes());
+    archive.extend_from_slice(&20u16.to_le_bytes()); // Version made by
+    fields(&mut archive);
+    archive.extend_from_slice(&[0; 14]); // Comment, disk, attributes and offset 0
+    archive.extend_from_slice(name.as_bytes());
+    let directory_size = archive.len() as u32 - directory;
+
+    archive.extend_from_slice(&0x0605_4B50u32.to_le_bytes());
+    archive.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    archive.extend_from_slice(&directory_size.to_le_bytes());
+    archive.extend_from_slice(&directory.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive
+}
+
+#[test]
+fn entry_names_cannot_leave_the_archive() {
+    let archive = stored_zip(&[
+        ("../../escape.gbr", b"a"),
+        ("/etc/passwd.gbr", b"b"),
+        ("C:\\Windows\\board.gbr", b"c"),
+        ("gerber/./../top.gtl", b"d"),
+    ]);
+    let names: Vec<String> = read_zip(&archive)
+        .expect("readable archive")
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "escape.gbr",
+            "etc/passwd.gbr",
+            "Windows/board.gbr",
+            "gerber/top.gtl"
+        ]
+    );
+}
+
+#[test]
+fn archives_with_too_many_entries_are_refused() {
+    let archive = stored_zip(&[("a.gbr", b"a"), ("b.gbr", b"b"), ("c.gbr", b"c")]);
+    let limits = ZipLimits {
+        max_entries: 2,
+        ..ZipLimits::default()
+    };
+    let error = read_zip_with_limits(&archive, &limits).unwrap_err();
+    assert!(error.message.contains("3 entries"), "{}", error);
+    assert!(read_zip(&archive).is_ok());
+}
+
+#[test]
+fn entries_inflating_past_the_ratio_are_refused() {
+    // 4 MiB of zeros deflate to some 26 KiB, a ratio of about 160
+    let archive = deflated_zip("bomb.gbr", &vec![0; 4 << 20]);
+    let limits = ZipLimits {
+        max_ratio: 50,
+        ..ZipLimits::default()
+    };
+    let error = read_zip_with_limits(&archive, &limits).unwrap_err();
+    assert!(
+        error.message.contains("Cannot inflate bomb.gbr"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn archives_extracting_past_the_total_size_are_refused() {
+    let archive = stored_zip(&[("a.gbr", &[b'a'; 600]), ("b.gbr", &[b'b'; 600])]);
+    let limits = ZipLimits {
+        max_total_size: 1000,
+        ..ZipLimits::default()
+    };
+    let error = read_zip_with_limits(&archive, &limits).unwrap_err();
+    assert!(error.message.contains("b.gbr exceeds"), "{}", error);
+
+    let archive = deflated_zip("big.gbr", &[b'x'; 2000]);
+    assert!(read_zip_with_limits(&archive, &limits).is_err());
+}
+
+#[test]
+fn entry_sizes_past_the_end_of_the_archive_are_refused() {
+    let mut archive = stored_zip(&[("top.gtl", b"G04*")]);
+    let end = archive.len() - 22;
+    let directory = u32::from_le_bytes(archive[end + 16..end + 20].try_into().unwrap()) as usize;
+    archive[directory + 20..directory + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+    let error = read_zip(&archive).unwrap_err();
+    assert!(
+        error.message.contains("Truncated data for top.gtl"),
+        "{}",
+        error
+    );
+}
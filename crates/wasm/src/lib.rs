@@ -0,0 +1,657 @@
+/* src/lib.rs */
+
+#[cfg(target_arch = "wasm32")]
+use nextjlc::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// Times the conversion stages with `Date.now`, as wasm32 has no other clock.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(start)]
+fn start() {
+    pipeline::set_clock(js_sys::Date::now);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_d_codes(gerber_data: String, use_altium: bool) -> String {
+    use nextjlc::dcode::GerberFlavor;
+
+    let flavor = if use_altium {
+        GerberFlavor::Altium
+    } else {
+        GerberFlavor::KiCad
+    };
+
+    dcode::process_d_codes(gerber_data, flavor)
+}
+
+/// Polls a JavaScript callback as a cancellation source.
+/// The callback is expected to return a truthy value once the user cancelled.
+#[cfg(target_arch = "wasm32")]
+fn js_cancellation(should_cancel: &js_sys::Function) -> impl Fn() -> bool + '_ {
+    move || {
+        should_cancel
+            .call0(&JsValue::NULL)
+            .map(|v| v.is_truthy())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_d_codes_cancellable(
+    gerber_data: String,
+    use_altium: bool,
+    should_cancel: &js_sys::Function,
+) -> Result<String, JsValue> {
+    use nextjlc::dcode::GerberFlavor;
+
+    let flavor = if use_altium {
+        GerberFlavor::Altium
+    } else {
+        GerberFlavor::KiCad
+    };
+
+    dcode::process_d_codes_cancellable(gerber_data, flavor, &js_cancellation(should_cancel))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn identify_software(content: &str) -> Option<String> {
+    file_type::identify_software(content).map(|s| s.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn add_fingerprint(gerber_content: &str, is_foreign_board_file: bool) -> String {
+    fingerprint::add_fingerprint(gerber_content, is_foreign_board_file)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn add_fingerprint_cancellable(
+    gerber_content: &str,
+    is_foreign_board_file: bool,
+    should_cancel: &js_sys::Function,
+) -> Result<String, JsValue> {
+    fingerprint::add_fingerprint_cancellable(
+        gerber_content,
+        is_foreign_board_file,
+        &js_cancellation(should_cancel),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn get_gerber_header() -> String {
+    header::get_gerber_header()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn get_order_guide_text() -> String {
+    header::get_order_guide_text().to_string()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn sort_gerber_files(files: Vec<String>) -> Vec<String> {
+    let mut mutable_files = files;
+    outline::sort_gerber_files(&mut mutable_files)
+}
+
+/// The original to new name pairs of `rename_map`, for the frontend.
+#[cfg(target_arch = "wasm32")]
+fn to_js_map(rename_map: &rename::RenameResult) -> js_sys::Map {
+    let js_map = js_sys::Map::new();
+    for (original, renamed) in rename_map.to_map() {
+        js_map.set(&JsValue::from(original), &JsValue::from(renamed));
+    }
+    js_map
+}
+
+#[cfg(target_arch = "wasm32")]
+fn eda_type_from_id(eda: &str) -> Result<rename::EdaType, JsValue> {
+    rename::EdaType::from_id(eda)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown EDA '{}'", eda)))
+}
+
+/// Maps file names with the rules for `eda` ("ad", "kicad", "eagle", "pads",
+/// "proteus", "librepcb", "horizon", "diptrace" or "easyeda").
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn map_filenames(files: Vec<String>, eda: &str) -> Result<js_sys::Map, JsValue> {
+    let eda_type = eda_type_from_id(eda)?;
+    Ok(to_js_map(&rename::map_filenames_detailed(&files, eda_type)))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn map_filenames_ad(files: Vec<String>) -> js_sys::Map {
+    to_js_map(&rename::map_filenames_detailed(&files, rename::EdaType::Ad))
+}
+
+/// Maps Altium file names with `outline` ("gm13", "gko", "profile", a file name)
+/// choosing the board outline.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn map_filenames_ad_with_outline(files: Vec<String>, outline: &str) -> js_sys::Map {
+    let selection = rename::OutlineSelection::from_id(outline);
+    to_js_map(&rename::map_filenames_with_outline(
+        &files,
+        rename::EdaType::Ad,
+        &selection,
+    ))
+}
+
+/// Maps file names with the user rules in `rules_json` merged into the rules
+/// for `eda` ("ad", "kicad", ...).
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn map_filenames_with_rules(
+    files: Vec<String>,
+    eda: &str,
+    rules_json: &str,
+) -> Result<js_sys::Map, JsValue> {
+    let eda_type = eda_type_from_id(eda)?;
+    let rules = custom_rules::CustomRules::from_json(rules_json)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(to_js_map(&rules.map_filenames_detailed(&files, eda_type)))
+}
+
+/// Maps file names for `eda` ("ad", "kicad", ...) and names the output by
+/// `template`, e.g. "{stem}_{layer}.{ext}", instead of the JLC names.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn map_filenames_with_template(
+    files: Vec<String>,
+    eda: &str,
+    template: &str,
+) -> Result<js_sys::Map, JsValue> {
+    let eda_type = eda_type_from_id(eda)?;
+    let mut rename_map = rename::map_filenames_detailed(&files, eda_type);
+    rename_map.apply_profile(&rename::NamingProfile::Template(template.to_string()));
+    Ok(to_js_map(&rename_map))
+}
+
+/// Classifies each file by the software that made it, as an EDA id ("ad",
+/// "kicad", ...) or an empty string when unknown, so mixed sets can be shown.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn detect_file_eda_types(filenames: Vec<String>, contents: Vec<String>) -> js_sys::Map {
+    let files: Vec<(String, String)> = filenames.into_iter().zip(contents).collect();
+    let js_map = js_sys::Map::new();
+    for (name, eda_type) in LayerSet::from_files(&files).file_eda_types() {
+        let id = eda_type.as_ref().map_or("", EdaType::id);
+        js_map.set(&JsValue::from(name), &JsValue::from(id));
+    }
+    js_map
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn map_filenames_kicad(files: Vec<String>) -> js_sys::Map {
+    to_js_map(&rename::map_filenames_detailed(
+        &files,
+        rename::EdaType::KiCad,
+    ))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct ValidationResult {
+    pub is_valid: bool,
+    pub layer_count: u32,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl ValidationResult {
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.clone()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn validate_gerber_files(files: Vec<String>) -> ValidationResult {
+    match validation::validate_gerber_files(&files) {
+        Ok(report) => ValidationResult {
+            is_valid: true,
+            layer_count: report.layer_count,
+            warnings: report.warnings,
+            errors: Vec::new(),
+        },
+        Err(errors) => ValidationResult {
+            is_valid: false,
+            layer_count: 0,
+            warnings: Vec::new(),
+            errors,
+        },
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct DrillProcessResult {
+    pth_content: Option<String>,
+    npth_content: Option<String>,
+    warnings: Vec<String>,
+    report: drill::DrillReport,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl DrillProcessResult {
+    #[wasm_bindgen(getter)]
+    pub fn pth_content(&self) -> Option<String> {
+        self.pth_content.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn npth_content(&self) -> Option<String> {
+        self.npth_content.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn has_pth(&self) -> bool {
+        self.pth_content.is_some()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn has_npth(&self) -> bool {
+        self.npth_content.is_some()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_holes(&self) -> usize {
+        self.report.total_holes()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn slot_count(&self) -> usize {
+        self.report.slot_count()
+    }
+
+    /// Per-tool hole counts and the PTH/NPTH breakdown, as JSON
+    #[wasm_bindgen(getter)]
+    pub fn report_json(&self) -> String {
+        self.report.to_json().to_json_string()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_drill_files(contents: Vec<String>, filenames: Vec<String>) -> DrillProcessResult {
+    let result = drill::process_drill_files(&contents, &filenames);
+    DrillProcessResult {
+        pth_content: result.pth_content,
+        npth_content: result.npth_content,
+        warnings: result.warnings,
+        report: result.report,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn is_drill_file(filename: &str) -> bool {
+    drill::is_drill_file(filename)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn is_drill_file_with_content(filename: &str, content: &str) -> bool {
+    drill::is_drill_file_with_content(filename, content)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn is_through_drill(filename: &str) -> bool {
+    drill::is_through_drill(filename)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn find_outline_file(files: Vec<String>) -> Option<String> {
+    outline::find_outline_file(&files)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct BoardSizeResult {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn compute_board_size(outline_content: &str) -> Option<BoardSizeResult> {
+    outline::compute_board_size(outline_content).map(|size| BoardSizeResult {
+        width: size.width,
+        height: size.height,
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct LayerStatsResult {
+    pub is_metric: bool,
+    pub aperture_count: u32,
+    pub macro_count: u32,
+    pub flash_count: u32,
+    pub draw_count: u32,
+    pub arc_count: u32,
+    pub region_count: u32,
+    pub has_bounds: bool,
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn get_layer_stats(content: &str) -> LayerStatsResult {
+    let stats = gerber::layer_stats(content);
+    let bounds = stats.bounds;
+    LayerStatsResult {
+        is_metric: stats.unit == gerber::Unit::Millimeter,
+        aperture_count: stats.aperture_count as u32,
+        macro_count: stats.macro_count as u32,
+        flash_count: stats.flash_count as u32,
+        draw_count: stats.draw_count as u32,
+        arc_count: stats.arc_count as u32,
+        region_count: stats.region_count as u32,
+        has_bounds: bounds.is_some(),
+        min_x: bounds.map_or(0.0, |b| b.min_x),
+        min_y: bounds.map_or(0.0, |b| b.min_y),
+        max_x: bounds.map_or(0.0, |b| b.max_x),
+        max_y: bounds.map_or(0.0, |b| b.max_y),
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "render"))]
+#[wasm_bindgen]
+pub struct LayerDiffResult {
+    pub area_a: f64,
+    pub area_b: f64,
+    pub xor_area: f64,
+    pub similarity: f64,
+    pub missing_count: u32,
+    pub extra_count: u32,
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "render"))]
+impl From<compare::LayerDiff> for LayerDiffResult {
+    fn from(diff: compare::LayerDiff) -> Self {
+        LayerDiffResult {
+            area_a: diff.area_a,
+            area_b: diff.area_b,
+            xor_area: diff.xor_area,
+            similarity: diff.similarity,
+            missing_count: diff.missing_in_b.len() as u32,
+            extra_count: diff.extra_in_b.len() as u32,
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "render"))]
+#[wasm_bindgen]
+pub fn compare_layers(content_a: &str, content_b: &str) -> LayerDiffResult {
+    compare::layers(
+        &gerber::parse_gerber(content_a),
+        &gerber::parse_gerber(content_b),
+    )
+    .into()
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "render"))]
+#[wasm_bindgen]
+pub fn compare_layers_cancellable(
+    content_a: &str,
+    content_b: &str,
+    should_cancel: &js_sys::Function,
+) -> Result<LayerDiffResult, JsValue> {
+    compare::layers_cancellable(
+        &gerber::parse_gerber(content_a),
+        &gerber::parse_gerber(content_b),
+        &compare::CompareOptions::default(),
+        &js_cancellation(should_cancel),
+    )
+    .map(LayerDiffResult::from)
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Renders a layer as SVG with every aperture in its own color.
+#[cfg(all(target_arch = "wasm32", feature = "render"))]
+#[wasm_bindgen]
+pub fn render_aperture_heat_map(content: &str) -> String {
+    render::render_svg(
+        &gerber::parse_gerber(content),
+        render::RenderMode::ApertureHeatMap,
+    )
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "render"))]
+#[wasm_bindgen]
+pub fn render_aperture_heat_map_cancellable(
+    content: &str,
+    should_cancel: &js_sys::Function,
+) -> Result<String, JsValue> {
+    render::render_svg_cancellable(
+        &gerber::parse_gerber(content),
+        render::RenderMode::ApertureHeatMap,
+        &js_cancellation(should_cancel),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Output of a full conversion run with the default options.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct ConversionResult {
+    pub is_valid: bool,
+    filenames: Vec<String>,
+    contents: Vec<String>,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<pipeline::ConvertResult> for ConversionResult {
+    fn from(result: pipeline::ConvertResult) -> Self {
+        let (is_valid, errors) = match &result.report {
+            Ok(_) => (true, Vec::new()),
+            Err(errors) => (false, errors.clone()),
+        };
+        let warnings = result.set.metadata.warnings.clone();
+        let (filenames, contents) = result.set.into_outputs().into_iter().unzip();
+        ConversionResult {
+            is_valid,
+            filenames,
+            contents,
+            warnings,
+            errors,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl ConversionResult {
+    #[wasm_bindgen(getter)]
+    pub fn filenames(&self) -> Vec<String> {
+        self.filenames.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn contents(&self) -> Vec<String> {
+        self.contents.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.clone()
+    }
+}
+
+/// Converts (filename, content) pairs into a JLC-ready set.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn convert_files(filenames: Vec<String>, contents: Vec<String>) -> ConversionResult {
+    let files: Vec<(String, String)> = filenames.into_iter().zip(contents).collect();
+    pipeline::Pipeline::default().run(&files).into()
+}
+
+/// Same as `convert_files`, but stops with an error as soon as
+/// `should_cancel` returns a truthy value, also from inside the longer stages.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn convert_files_cancellable(
+    filenames: Vec<String>,
+    contents: Vec<String>,
+    should_cancel: &js_sys::Function,
+) -> Result<ConversionResult, JsValue> {
+    let files: Vec<(String, String)> = filenames.into_iter().zip(contents).collect();
+    pipeline::Pipeline::default()
+        .run_cancellable(&files, &js_cancellation(should_cancel))
+        .map(ConversionResult::from)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// A conversion run with the default options, stepped one stage at a time so
+/// the frontend can show progress, and saved between stages as JSON so it can
+/// resume after a page reload.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct ConversionSession {
+    inner: pipeline::Session,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl ConversionSession {
+    /// Starts a run by loading the files.
+    pub fn create(filenames: Vec<String>, contents: Vec<String>) -> ConversionSession {
+        let files: Vec<(String, String)> = filenames.into_iter().zip(contents).collect();
+        ConversionSession {
+            inner: pipeline::Session::new(pipeline::ConvertOptions::default(), &files),
+        }
+    }
+
+    /// Runs the next stage and returns its name, or `undefined` once all have run.
+    pub fn step(&mut self) -> Option<String> {
+        self.inner.step().map(str::to_string)
+    }
+
+    /// Same as `step`, but stops with an error as soon as `should_cancel`
+    /// returns a truthy value; the stage then runs again on the next step.
+    pub fn step_cancellable(
+        &mut self,
+        should_cancel: &js_sys::Function,
+    ) -> Result<Option<String>, JsValue> {
+        self.inner
+            .step_cancellable(&js_cancellation(should_cancel))
+            .map(|stage| stage.map(str::to_string))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn next_stage(&self) -> Option<String> {
+        self.inner.next_stage().map(str::to_string)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    pub fn to_json(&self) -> String {
+        self.inner.to_json().to_json_string()
+    }
+
+    /// Restores a run saved with `to_json`.
+    pub fn from_json(json: &str) -> Result<ConversionSession, JsValue> {
+        let value = json::parse_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        pipeline::Session::resume(&value, pipeline::ConvertOptions::default())
+            .map(|inner| ConversionSession { inner })
+            .ok_or_else(|| JsValue::from_str("Invalid conversion session"))
+    }
+
+    /// Runs the remaining stages.
+    pub fn finish(self) -> ConversionResult {
+        self.inner.finish().into()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn generate_gbrjob(filenames: Vec<String>, contents: Vec<String>) -> String {
+    let files: Vec<(String, String)> = filenames.into_iter().zip(contents).collect();
+    gbrjob::generate_gbrjob(&files)
+}
+
+/// Lints a Gerber layer for the viewer profiles given by id ("jlc", "jlc-assistant",
+/// "gerbv", "altium"). Unknown ids are ignored; an empty list checks all viewers.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn lint_for_viewers(content: &str, viewer_ids: Vec<String>) -> Vec<String> {
+    let mut viewers: Vec<lint::Viewer> = viewer_ids
+        .iter()
+        .filter_map(|id| lint::Viewer::from_id(id))
+        .collect();
+    if viewer_ids.is_empty() {
+        viewers = lint::Viewer::ALL.to_vec();
+    }
+    lint::lint_gerber(content, &viewers)
+        .into_iter()
+        .map(|finding| finding.message)
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn get_altium_outjob() -> String {
+    templates::altium_outjob()
+}
+
+/// Shrinks a Gerber layer before it is sent to the preview renderer. The
+/// result is for display only; order output uses the original content.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn minify_layer_for_preview(content: &str) -> String {
+    writer::minify_for_preview(content)
+}
+
+/// Compresses a converted layer with gzip, e.g. before storing it in IndexedDB.
+#[cfg(all(target_arch = "wasm32", feature = "archive"))]
+#[wasm_bindgen]
+pub fn compress_layer(content: &str) -> Vec<u8> {
+    gzip::compress_layer(content)
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "archive"))]
+#[wasm_bindgen]
+pub fn decompress_layer(data: &[u8]) -> Result<String, JsValue> {
+    gzip::decompress_layer(data).map_err(|e| JsValue::from_str(&e.to_string()))
+}